@@ -0,0 +1,151 @@
+//! Benchmarks of the pipes, run with `cargo bench --features bench`.
+//!
+//! The backend is chosen at compile time, so to compare them, save a baseline with one and compare against it with the other:
+//!
+//! ```text
+//! cargo bench --features bench -- --save-baseline buffer
+//! cargo +nightly bench --features bench,fringe -- --baseline buffer
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_pipe::{deserialize_from_slice, pipe, serialize_to_vec, Deserializer, Serializer};
+use std::{convert::TryFrom, hint::black_box};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Quote {
+	instrument: u32,
+	bid: f64,
+	ask: f64,
+	size: u64,
+}
+const QUOTE: Quote = Quote {
+	instrument: 42,
+	bid: 99.5,
+	ask: 100.5,
+	size: 1000,
+};
+
+/// Push `t` through `serializer` and `deserializer` a byte at a time, returning it.
+fn transfer<T: Serialize + DeserializeOwned + 'static>(
+	serializer: &mut Serializer, deserializer: &mut Deserializer, t: T,
+) -> T {
+	serializer.push().unwrap()(t);
+	let _ = deserializer.pull::<T>();
+	while let Some(pull) = serializer.pull() {
+		deserializer.push().unwrap()(pull());
+	}
+	deserializer.pull::<T>().unwrap()()
+}
+
+fn small_messages(c: &mut Criterion) {
+	let mut group = c.benchmark_group("small_messages");
+	let _ = group.throughput(Throughput::Elements(1));
+	let _ = group.bench_function("u64", |b| {
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		b.iter(|| {
+			transfer(
+				&mut serializer,
+				&mut deserializer,
+				black_box(0x0102_0304_u64),
+			)
+		});
+	});
+	let _ = group.bench_function("struct", |b| {
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		b.iter(|| transfer(&mut serializer, &mut deserializer, black_box(QUOTE.clone())));
+	});
+	let _ = group.bench_function("string", |b| {
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		b.iter_batched(
+			|| String::from("all systems nominal"),
+			|t| transfer(&mut serializer, &mut deserializer, t),
+			BatchSize::SmallInput,
+		);
+	});
+	group.finish();
+}
+
+fn large_frames(c: &mut Criterion) {
+	let mut group = c.benchmark_group("large_frames");
+	let _ = group.sample_size(10);
+	for &len in &[1_usize << 10, 1 << 16, 1 << 20] {
+		let _ = group.throughput(Throughput::Bytes(u64::try_from(len * 8).unwrap()));
+		let _ = group.bench_with_input(BenchmarkId::new("vec_u64", len), &len, |b, &len| {
+			let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+			b.iter_batched(
+				|| (0..u64::try_from(len).unwrap()).collect::<Vec<_>>(),
+				|t| transfer(&mut serializer, &mut deserializer, t),
+				BatchSize::LargeInput,
+			);
+		});
+	}
+	group.finish();
+}
+
+fn apis(c: &mut Criterion) {
+	let mut group = c.benchmark_group("apis");
+	let _ = group.throughput(Throughput::Elements(1));
+	let _ = group.bench_function("push_pull", |b| {
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		b.iter(|| transfer(&mut serializer, &mut deserializer, black_box(QUOTE.clone())));
+	});
+	let _ = group.throughput(Throughput::Elements(4));
+	let _ = group.bench_function("push_or_queue", |b| {
+		let mut serializer = Serializer::new();
+		b.iter(|| {
+			for _ in 0..4 {
+				serializer.push_or_queue(black_box(QUOTE.clone())).unwrap();
+			}
+			let mut sum = 0_u64;
+			while let Some(pull) = serializer.pull() {
+				sum += u64::from(pull());
+			}
+			sum
+		});
+	});
+	let _ = group.throughput(Throughput::Elements(1));
+	let _ = group.bench_function("pull_chunk", |b| {
+		let mut serializer = Serializer::new();
+		b.iter(|| {
+			serializer.push().unwrap()(black_box(QUOTE.clone()));
+			let mut sum = 0_u64;
+			while let Some(pull_chunk) = serializer.pull_chunk() {
+				sum += pull_chunk().iter().map(|&byte| u64::from(byte)).sum::<u64>();
+			}
+			sum
+		});
+	});
+	#[cfg(not(feature = "fringe"))]
+	let _ = group.bench_function("pull_buf", |b| {
+		let mut serializer = Serializer::new();
+		b.iter(|| {
+			serializer.push().unwrap()(black_box(QUOTE.clone()));
+			let mut sum = 0_u64;
+			while let Some(buf) = serializer.pull_buf() {
+				let len = buf.len();
+				sum += buf.iter().map(|&byte| u64::from(byte)).sum::<u64>();
+				serializer.consume(len);
+			}
+			sum
+		});
+	});
+	let _ = group.bench_function("pipe", |b| {
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		b.iter(|| {
+			serializer.push().unwrap()(black_box(QUOTE.clone()));
+			let _ = deserializer.pull::<Quote>();
+			let _ = pipe(&mut serializer, &mut deserializer);
+			deserializer.pull::<Quote>().unwrap()()
+		});
+	});
+	let _ = group.bench_function("to_vec_from_slice", |b| {
+		b.iter(|| {
+			deserialize_from_slice::<Quote>(&serialize_to_vec(black_box(QUOTE.clone()))).unwrap()
+		});
+	});
+	group.finish();
+}
+
+criterion_group!(benches, small_messages, large_frames, apis);
+criterion_main!(benches);
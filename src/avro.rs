@@ -0,0 +1,1476 @@
+//! [Avro](https://avro.apache.org) pipes: the stream is an Avro object container, with the schema written once at the start and each value encoded against it as a block of its own.
+//!
+//! These have the same push/pull discipline as [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer), but the stream can be ingested directly by Kafka, Hadoop and other tooling that expects Avro object container semantics.
+//!
+//! The schema is supplied by the user, and must describe `T` according to the following mapping from the serde data model:
+//!
+//! * `bool` → `boolean`; integers → `int` or `long`; `f32` → `float`; `f64` → `double`
+//! * `char`, `String` → `string`; bytes → `bytes`
+//! * `Option<T>` → `["null", T]`
+//! * sequences → `array`; maps (with string keys) → `map`
+//! * structs and tuples → `record`
+//! * enums of only unit variants → `enum`; other enums → a union with, per variant, `null` if unit, the inner type if newtype, and a `record` otherwise
+//!
+//! Values are encoded against the schema, and decoded against the one read from the start of the stream, so that, for example, an `f32` is written as a `double` if the schema says so, and an `Option`'s `null` is found wherever it lies in its union. A value that doesn't fit the schema fails to encode rather than producing a stream other tooling misreads.
+//!
+//! Only the `null` codec is supported.
+//!
+//! Requires the `avro` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::avro::{AvroDeserializer, AvroSerializer};
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Reading {
+//!     sensor: String,
+//!     value: Option<f64>,
+//! }
+//!
+//! let schema = r#"{"type":"record","name":"Reading","fields":[
+//!     {"name":"sensor","type":"string"},
+//!     {"name":"value","type":["null","double"]}
+//! ]}"#;
+//! let mut serializer = AvroSerializer::new(schema).unwrap();
+//! let mut deserializer = AvroDeserializer::new();
+//! for value in vec![Some(1.5), None] {
+//!     serializer.push().unwrap()(Reading { sensor: String::from("a"), value });
+//!     while let Some(pull) = serializer.pull() {
+//!         deserializer.push().unwrap()(pull());
+//!     }
+//!     let reading = deserializer.pull::<Reading>().unwrap()();
+//!     assert_eq!(reading, Reading { sensor: String::from("a"), value });
+//! }
+//! assert_eq!(deserializer.schema(), Some(schema));
+//! ```
+
+use serde::{de, ser};
+use std::{
+	collections::{hash_map::RandomState, HashMap}, convert::{TryFrom, TryInto}, error, fmt, hash::{BuildHasher, Hasher}, slice
+};
+
+const MAGIC: [u8; 4] = *b"Obj\x01";
+const SYNC_LEN: usize = 16;
+
+/// Avro serializer pipe: push `T`; pull `u8`.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`AvroSerializer::empty()`] before dropping it.
+pub struct AvroSerializer {
+	buffer: Option<(Box<[u8]>, usize)>,
+	schema: Option<String>,
+	parsed: Schema,
+	sync: [u8; SYNC_LEN],
+}
+impl AvroSerializer {
+	/// Construct a new `AvroSerializer` pipe, writing `schema` at the start of the stream. `schema` is the JSON representation of the Avro schema of the values to be pushed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `schema` isn't a valid Avro schema.
+	pub fn new(schema: &str) -> Result<Self, SchemaError> {
+		let parsed = Schema::parse(schema)?;
+		let mut sync = [0; SYNC_LEN];
+		for chunk in sync.chunks_mut(8) {
+			let random = RandomState::new().build_hasher().finish();
+			chunk.copy_from_slice(&random.to_le_bytes());
+		}
+		Ok(Self {
+			buffer: None,
+			schema: Some(schema.to_owned()),
+			parsed,
+			sync,
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.buffer.is_none()
+	}
+	/// Push a `T`. [`None`] denotes that the `AvroSerializer` is instead awaiting a [`pull`](AvroSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if `T` can't be encoded against the schema, for example a `String` where it has a `long`, or an integer too large for an `int`.
+	pub fn push<T: ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.buffer.is_none() {
+			Some(move |t: T| {
+				let mut vec = Vec::new();
+				if let Some(schema) = self.schema.take() {
+					vec.extend_from_slice(&MAGIC);
+					write_long(&mut vec, 2);
+					write_bytes(&mut vec, b"avro.schema");
+					write_bytes(&mut vec, schema.as_bytes());
+					write_bytes(&mut vec, b"avro.codec");
+					write_bytes(&mut vec, b"null");
+					write_long(&mut vec, 0);
+					vec.extend_from_slice(&self.sync);
+				}
+				let mut encoder = Encoder::new(&self.parsed.nodes, self.parsed.root);
+				t.serialize(&mut encoder).unwrap();
+				write_long(&mut vec, 1);
+				write_bytes(&mut vec, &encoder.out);
+				vec.extend_from_slice(&self.sync);
+				self.buffer = Some((vec.into_boxed_slice(), 0));
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Pull a `u8`. [`None`] denotes that the `AvroSerializer` is instead awaiting a [`push`](AvroSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((buffer, index)) = &mut self.buffer else {
+					unreachable!()
+				};
+				let ret = buffer[*index];
+				*index += 1;
+				if *index == buffer.len() {
+					self.buffer = None;
+				}
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				self.buffer = None;
+			})
+		} else {
+			None
+		}
+	}
+}
+impl Drop for AvroSerializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_none() || std::thread::panicking(),
+			"AvroSerializer dropped with a frame unpulled; empty() it first to discard it"
+		);
+	}
+}
+impl fmt::Debug for AvroSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AvroSerializer")
+			.field("buffer", &self.buffer)
+			.field("schema", &self.schema)
+			.field("parsed", &self.parsed)
+			.field("sync", &self.sync)
+			.finish()
+	}
+}
+
+/// Avro deserializer pipe: push `u8`; pull `T`.
+///
+/// As blocks are delimited independently of their contents, bytes can be pushed before the type to be deserialized to has been specified. Blocks containing multiple values, as written by other Avro implementations, are pulled one value at a time.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`AvroDeserializer::empty()`] before dropping it.
+pub struct AvroDeserializer {
+	buffer: Vec<u8>,
+	header: Option<Header>,
+	progress: HeaderProgress,
+	block: Option<(usize, usize)>,
+	remaining: usize,
+	offset: usize,
+}
+impl AvroDeserializer {
+	/// Construct a new `AvroDeserializer` pipe.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self {
+			buffer: Vec::new(),
+			header: None,
+			progress: HeaderProgress::new(),
+			block: None,
+			remaining: 0,
+			offset: 0,
+		}
+	}
+
+	/// The schema read from the start of the stream, or [`None`] if it hasn't yet been received.
+	#[must_use]
+	pub fn schema(&self) -> Option<&str> {
+		self.header.as_ref().map(|header| &*header.schema)
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.remaining != 0
+	}
+	/// Pull a `T`. [`None`] denotes that the `AvroDeserializer` is instead awaiting a [`push`](AvroDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the value isn't a valid encoding of `T` against the schema read from the start of the stream.
+	pub fn pull<T: de::DeserializeOwned + 'static>(&mut self) -> Option<impl FnOnce() -> T + '_> {
+		if self.remaining != 0 {
+			Some(move || {
+				let schema = &self.header.as_ref().unwrap().parsed;
+				let mut decoder =
+					Decoder::new(&self.buffer[self.offset..], &schema.nodes, schema.root);
+				let ret = T::deserialize(&mut decoder).unwrap();
+				self.offset = self.buffer.len() - decoder.input.len();
+				self.remaining -= 1;
+				if self.remaining == 0 {
+					assert_eq!(
+						self.offset,
+						self.buffer.len(),
+						"trailing bytes in Avro block"
+					);
+					self.buffer.clear();
+					self.block = None;
+				}
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.remaining == 0
+	}
+	/// Push a `u8`. [`None`] denotes that the `AvroDeserializer` is instead awaiting a [`pull`](AvroDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the stream isn't a valid Avro object container.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.remaining == 0 {
+			Some(move |x| {
+				self.buffer.push(x);
+				let sync = match &self.header {
+					None => {
+						if let Some(header) = self.progress.advance(&self.buffer) {
+							self.header = Some(header);
+							self.progress = HeaderProgress::new();
+							self.buffer.clear();
+						}
+						return;
+					}
+					Some(header) => &header.sync,
+				};
+				if self.block.is_none() {
+					if let Some((count, offset)) = read_block_header(&self.buffer) {
+						self.block = Some((count, offset));
+					}
+				}
+				if let Some((count, offset)) = self.block {
+					if self.buffer.len() == offset {
+						assert_eq!(
+							self.buffer[offset - SYNC_LEN..],
+							sync[..],
+							"Avro sync marker mismatch"
+						);
+						self.buffer.truncate(offset - SYNC_LEN);
+						if count == 0 {
+							self.buffer.clear();
+							self.block = None;
+						} else {
+							self.offset = read_long(&self.buffer).unwrap().1;
+							self.offset += read_long(&self.buffer[self.offset..]).unwrap().1;
+							self.remaining = count;
+						}
+					}
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.buffer.is_empty()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_empty() {
+			None
+		} else {
+			Some(move || {
+				self.buffer.clear();
+				self.block = None;
+				self.remaining = 0;
+			})
+		}
+	}
+}
+impl Drop for AvroDeserializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_empty() || std::thread::panicking(),
+			"AvroDeserializer dropped with {} bytes of a frame buffered; empty() it first to discard them",
+			self.buffer.len()
+		);
+	}
+}
+impl fmt::Debug for AvroDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AvroDeserializer")
+			.field("buffer", &self.buffer)
+			.field("header", &self.header)
+			.field("progress", &self.progress)
+			.field("block", &self.block)
+			.field("remaining", &self.remaining)
+			.field("offset", &self.offset)
+			.finish()
+	}
+}
+
+/// The object container header: the schema, as written and parsed, and the sync marker ending each block.
+#[derive(Debug)]
+struct Header {
+	schema: String,
+	parsed: Schema,
+	sync: [u8; SYNC_LEN],
+}
+
+/// How far the object container header has been read, so that each byte pushed resumes reading it rather than starting again.
+#[derive(Debug)]
+struct HeaderProgress {
+	/// The length of the header read so far.
+	offset: usize,
+	/// The metadata entries left to read in the current block of them.
+	entries: u64,
+	schema: Option<String>,
+}
+impl HeaderProgress {
+	const fn new() -> Self {
+		Self {
+			offset: 0,
+			entries: 0,
+			schema: None,
+		}
+	}
+
+	/// Read as much more of the header as `input` holds, returning it once complete, or [`None`] if `input` is incomplete.
+	fn advance(&mut self, input: &[u8]) -> Option<Header> {
+		if self.offset == 0 {
+			assert!(
+				input.iter().zip(&MAGIC).all(|(a, b)| a == b),
+				"not an Avro object container"
+			);
+			if input.len() < MAGIC.len() {
+				return None;
+			}
+			self.offset = MAGIC.len();
+		}
+		loop {
+			if self.entries == 0 {
+				let (count, len) = read_long(&input[self.offset..])?;
+				let mut offset = self.offset + len;
+				if count == 0 {
+					let sync = input.get(offset..offset + SYNC_LEN)?.try_into().unwrap();
+					assert_eq!(offset + SYNC_LEN, input.len());
+					let schema = self.schema.take().expect("missing Avro schema");
+					let parsed = Schema::parse(&schema).expect("invalid Avro schema");
+					return Some(Header {
+						schema,
+						parsed,
+						sync,
+					});
+				}
+				if count < 0 {
+					offset += read_long(&input[offset..])?.1;
+				}
+				self.offset = offset;
+				self.entries = count.unsigned_abs();
+			}
+			let (key, a) = read_bytes(&input[self.offset..])?;
+			let (value, b) = read_bytes(&input[self.offset + a..])?;
+			match key {
+				b"avro.schema" => {
+					self.schema =
+						Some(String::from_utf8(value.to_owned()).expect("invalid Avro schema"));
+				}
+				b"avro.codec" => assert_eq!(value, b"null", "unsupported Avro codec"),
+				_ => (),
+			}
+			self.offset += a + b;
+			self.entries -= 1;
+		}
+	}
+}
+
+/// Read the object count and size prefixing a block, returning the count and the total length of the block including its sync marker, or [`None`] if `input` is incomplete.
+fn read_block_header(input: &[u8]) -> Option<(usize, usize)> {
+	let (count, a) = read_long(input)?;
+	let (size, b) = read_long(&input[a..])?;
+	let count = usize::try_from(count).expect("invalid Avro block count");
+	let size = usize::try_from(size).expect("invalid Avro block size");
+	Some((count, a + b + size + SYNC_LEN))
+}
+
+/// Read a zig-zag varint, returning it and its length, or [`None`] if `input` is incomplete.
+fn read_long(input: &[u8]) -> Option<(i64, usize)> {
+	let mut n: u64 = 0;
+	for (i, &byte) in input.iter().enumerate() {
+		assert!(i < 10, "invalid Avro long");
+		n |= u64::from(byte & 0x7f) << (7 * i);
+		if byte & 0x80 == 0 {
+			#[allow(clippy::cast_possible_wrap)]
+			return Some(((n >> 1) as i64 ^ -((n & 1) as i64), i + 1));
+		}
+	}
+	None
+}
+
+/// Read length-prefixed bytes, returning them and the total length, or [`None`] if `input` is incomplete.
+fn read_bytes(input: &[u8]) -> Option<(&[u8], usize)> {
+	let (len, offset) = read_long(input)?;
+	let len = usize::try_from(len).expect("invalid Avro length");
+	Some((input.get(offset..offset + len)?, offset + len))
+}
+
+fn write_long(out: &mut Vec<u8>, n: i64) {
+	#[allow(clippy::cast_sign_loss)]
+	let mut n = ((n << 1) ^ (n >> 63)) as u64;
+	while n >= 0x80 {
+		#[allow(clippy::cast_possible_truncation)]
+		out.push(n as u8 | 0x80);
+		n >>= 7;
+	}
+	#[allow(clippy::cast_possible_truncation)]
+	out.push(n as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+	write_long(out, i64::try_from(bytes.len()).unwrap());
+	out.extend_from_slice(bytes);
+}
+
+/// Why a schema couldn't be used.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SchemaError(String);
+impl fmt::Display for SchemaError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid Avro schema: {}", self.0)
+	}
+}
+impl error::Error for SchemaError {}
+
+/// An Avro schema, parsed into the kinds of its nodes, as far as they determine the encoding. Nodes refer to each other by index, so named types can be referred to recursively.
+#[derive(Debug)]
+struct Schema {
+	nodes: Vec<Node>,
+	root: usize,
+}
+impl Schema {
+	fn parse(schema: &str) -> Result<Self, SchemaError> {
+		let json: serde_json::Value =
+			serde_json::from_str(schema).map_err(|err| SchemaError(err.to_string()))?;
+		let mut parser = Parser {
+			nodes: vec![
+				Node::Null,
+				Node::Boolean,
+				Node::Int,
+				Node::Long,
+				Node::Float,
+				Node::Double,
+				Node::Bytes,
+				Node::String,
+			],
+			names: HashMap::new(),
+		};
+		let root = parser.parse(&json, None)?;
+		Ok(Self {
+			nodes: parser.nodes,
+			root,
+		})
+	}
+}
+
+#[derive(Debug)]
+enum Node {
+	Null,
+	Boolean,
+	Int,
+	Long,
+	Float,
+	Double,
+	Bytes,
+	String,
+	/// The items.
+	Array(usize),
+	/// The values, whose keys are strings.
+	Map(usize),
+	/// The fields, in order.
+	Record(Vec<usize>),
+	/// The number of symbols.
+	Enum(usize),
+	/// The size.
+	Fixed(usize),
+	/// The branches, in order.
+	Union(Vec<usize>),
+}
+impl Node {
+	fn kind(&self) -> &'static str {
+		match self {
+			Self::Null => "null",
+			Self::Boolean => "boolean",
+			Self::Int => "int",
+			Self::Long => "long",
+			Self::Float => "float",
+			Self::Double => "double",
+			Self::Bytes => "bytes",
+			Self::String => "string",
+			Self::Array(_) => "array",
+			Self::Map(_) => "map",
+			Self::Record(_) => "record",
+			Self::Enum(_) => "enum",
+			Self::Fixed(_) => "fixed",
+			Self::Union(_) => "union",
+		}
+	}
+}
+
+/// The names of the primitive types, whose nodes are the first of every [`Schema`], in this order.
+const PRIMITIVES: [&str; 8] = [
+	"null", "boolean", "int", "long", "float", "double", "bytes", "string",
+];
+/// The index of the `string` node, of which map keys are.
+const STRING: usize = 7;
+
+struct Parser {
+	nodes: Vec<Node>,
+	/// The full names of the named types defined so far.
+	names: HashMap<String, usize>,
+}
+impl Parser {
+	/// Parse `json`, within `namespace`, returning the index of its node.
+	fn parse(
+		&mut self, json: &serde_json::Value, namespace: Option<&str>,
+	) -> Result<usize, SchemaError> {
+		let object = match json {
+			serde_json::Value::String(name) => return self.reference(name, namespace),
+			serde_json::Value::Array(branches) => {
+				let branches = branches
+					.iter()
+					.map(|branch| self.parse(branch, namespace))
+					.collect::<Result<_, _>>()?;
+				return Ok(self.push(Node::Union(branches)));
+			}
+			serde_json::Value::Object(object) => object,
+			_ => return Err(SchemaError(format!("expected a type, not {json}"))),
+		};
+		let type_ = object
+			.get("type")
+			.ok_or_else(|| SchemaError(String::from("missing type")))?;
+		match type_.as_str() {
+			Some("record" | "error") => {
+				let (index, namespace) =
+					self.define(object, namespace, Node::Record(Vec::new()))?;
+				let fields = object
+					.get("fields")
+					.and_then(serde_json::Value::as_array)
+					.ok_or_else(|| SchemaError(String::from("record missing fields")))?
+					.iter()
+					.map(|field| {
+						let type_ = field
+							.get("type")
+							.ok_or_else(|| SchemaError(String::from("field missing type")))?;
+						self.parse(type_, namespace.as_deref())
+					})
+					.collect::<Result<_, _>>()?;
+				self.nodes[index] = Node::Record(fields);
+				Ok(index)
+			}
+			Some("enum") => {
+				let symbols = object
+					.get("symbols")
+					.and_then(serde_json::Value::as_array)
+					.ok_or_else(|| SchemaError(String::from("enum missing symbols")))?
+					.len();
+				Ok(self.define(object, namespace, Node::Enum(symbols))?.0)
+			}
+			Some("fixed") => {
+				let size = object
+					.get("size")
+					.and_then(serde_json::Value::as_u64)
+					.and_then(|size| usize::try_from(size).ok())
+					.ok_or_else(|| SchemaError(String::from("fixed missing size")))?;
+				Ok(self.define(object, namespace, Node::Fixed(size))?.0)
+			}
+			Some("array") => {
+				let items = object
+					.get("items")
+					.ok_or_else(|| SchemaError(String::from("array missing items")))?;
+				let items = self.parse(items, namespace)?;
+				Ok(self.push(Node::Array(items)))
+			}
+			Some("map") => {
+				let values = object
+					.get("values")
+					.ok_or_else(|| SchemaError(String::from("map missing values")))?;
+				let values = self.parse(values, namespace)?;
+				Ok(self.push(Node::Map(values)))
+			}
+			// a primitive with attributes, such as a logical type, or a reference
+			_ => self.parse(type_, namespace),
+		}
+	}
+
+	/// Add the named type `object`, defined within `namespace`, as `node`, returning its index and the namespace its own definitions are within.
+	fn define(
+		&mut self, object: &serde_json::Map<String, serde_json::Value>, namespace: Option<&str>,
+		node: Node,
+	) -> Result<(usize, Option<String>), SchemaError> {
+		let name = object
+			.get("name")
+			.and_then(serde_json::Value::as_str)
+			.ok_or_else(|| SchemaError(String::from("named type missing name")))?;
+		let namespace = object
+			.get("namespace")
+			.and_then(serde_json::Value::as_str)
+			.or(namespace);
+		let full_name = match (name.rfind('.'), namespace) {
+			(None, Some(namespace)) if !namespace.is_empty() => format!("{namespace}.{name}"),
+			_ => name.to_owned(),
+		};
+		let index = self.push(node);
+		if self.names.insert(full_name.clone(), index).is_some() {
+			return Err(SchemaError(format!("{full_name} defined twice")));
+		}
+		let namespace = full_name.rfind('.').map(|dot| full_name[..dot].to_owned());
+		Ok((index, namespace))
+	}
+
+	/// Resolve `name`, referred to within `namespace`, to the index of its node.
+	fn reference(&self, name: &str, namespace: Option<&str>) -> Result<usize, SchemaError> {
+		if let Some(index) = PRIMITIVES.iter().position(|&primitive| primitive == name) {
+			return Ok(index);
+		}
+		namespace
+			.filter(|_| !name.contains('.'))
+			.and_then(|namespace| self.names.get(&format!("{namespace}.{name}")))
+			.or_else(|| self.names.get(name))
+			.copied()
+			.ok_or_else(|| SchemaError(format!("unknown type {name}")))
+	}
+
+	fn push(&mut self, node: Node) -> usize {
+		self.nodes.push(node);
+		self.nodes.len() - 1
+	}
+}
+
+#[derive(Debug)]
+struct Error(String);
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+impl error::Error for Error {}
+impl ser::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self(msg.to_string())
+	}
+}
+impl de::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self(msg.to_string())
+	}
+}
+
+/// The error for a value that doesn't fit the schema node it's encoded against or decoded from.
+fn mismatch(value: &str, node: &Node) -> Error {
+	Error(format!("{} doesn't match Avro {}", value, node.kind()))
+}
+
+/// Write a union's branch `index`.
+fn write_index(out: &mut Vec<u8>, index: usize) {
+	write_long(out, i64::try_from(index).unwrap());
+}
+
+struct Encoder<'s> {
+	out: Vec<u8>,
+	nodes: &'s [Node],
+	/// The schema node the next value is encoded against.
+	node: usize,
+}
+impl<'s> Encoder<'s> {
+	fn new(nodes: &'s [Node], node: usize) -> Self {
+		Self {
+			out: Vec::new(),
+			nodes,
+			node,
+		}
+	}
+	/// The node the next value is encoded against, if it `matches`. A union is resolved to its first branch that does, whose index is written.
+	fn resolve(&mut self, value: &str, matches: impl Fn(&Node) -> bool) -> Result<&'s Node, Error> {
+		let nodes = self.nodes;
+		if let Node::Union(branches) = &nodes[self.node] {
+			if let Some(index) = branches.iter().position(|&branch| matches(&nodes[branch])) {
+				write_index(&mut self.out, index);
+				self.node = branches[index];
+			}
+		}
+		let node = &nodes[self.node];
+		if matches(node) {
+			Ok(node)
+		} else {
+			Err(mismatch(value, node))
+		}
+	}
+	/// Resolve the union the next value is encoded against to the branch of variant `index`, writing it, if that branch `matches`.
+	fn variant(
+		&mut self, index: u32, value: &str, matches: impl Fn(&Node) -> bool,
+	) -> Result<&'s Node, Error> {
+		let nodes = self.nodes;
+		let index = usize::try_from(index).unwrap();
+		match &nodes[self.node] {
+			Node::Union(branches) if index < branches.len() && matches(&nodes[branches[index]]) => {
+				write_index(&mut self.out, index);
+				self.node = branches[index];
+				Ok(&nodes[self.node])
+			}
+			node => Err(mismatch(value, node)),
+		}
+	}
+	/// Start a `record` of `len` fields.
+	fn record<'a>(&'a mut self, value: &str, len: usize) -> Result<Record<'a, 's>, Error> {
+		match self.resolve(
+			value,
+			|node| matches!(node, Node::Record(fields) if fields.len() == len),
+		)? {
+			Node::Record(fields) => Ok(Record {
+				encoder: self,
+				fields: fields.iter(),
+			}),
+			_ => unreachable!(),
+		}
+	}
+}
+impl<'a, 's> ser::Serializer for &'a mut Encoder<'s> {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = Block<'a, 's>;
+	type SerializeTuple = Record<'a, 's>;
+	type SerializeTupleStruct = Record<'a, 's>;
+	type SerializeTupleVariant = Record<'a, 's>;
+	type SerializeMap = Block<'a, 's>;
+	type SerializeStruct = Record<'a, 's>;
+	type SerializeStructVariant = Record<'a, 's>;
+
+	fn serialize_bool(self, v: bool) -> Result<(), Error> {
+		let _ = self.resolve("a bool", |node| matches!(node, Node::Boolean))?;
+		self.out.push(u8::from(v));
+		Ok(())
+	}
+	fn serialize_i8(self, v: i8) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_i16(self, v: i16) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_i32(self, v: i32) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_i64(self, v: i64) -> Result<(), Error> {
+		if let Node::Int =
+			self.resolve("an integer", |node| matches!(node, Node::Int | Node::Long))?
+		{
+			let _ = i32::try_from(v).map_err(ser::Error::custom)?;
+		}
+		write_long(&mut self.out, v);
+		Ok(())
+	}
+	fn serialize_u8(self, v: u8) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_u16(self, v: u16) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_u32(self, v: u32) -> Result<(), Error> {
+		self.serialize_i64(v.into())
+	}
+	fn serialize_u64(self, v: u64) -> Result<(), Error> {
+		self.serialize_i64(i64::try_from(v).map_err(ser::Error::custom)?)
+	}
+	fn serialize_f32(self, v: f32) -> Result<(), Error> {
+		match self.resolve("an f32", |node| matches!(node, Node::Float | Node::Double))? {
+			Node::Float => self.out.extend_from_slice(&v.to_le_bytes()),
+			_ => self.out.extend_from_slice(&f64::from(v).to_le_bytes()),
+		}
+		Ok(())
+	}
+	fn serialize_f64(self, v: f64) -> Result<(), Error> {
+		let _ = self.resolve("an f64", |node| matches!(node, Node::Double))?;
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_char(self, v: char) -> Result<(), Error> {
+		self.serialize_str(v.encode_utf8(&mut [0; 4]))
+	}
+	fn serialize_str(self, v: &str) -> Result<(), Error> {
+		let _ = self.resolve("a string", |node| matches!(node, Node::String))?;
+		write_bytes(&mut self.out, v.as_bytes());
+		Ok(())
+	}
+	fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+		let fits = |node: &Node| match node {
+			Node::Bytes => true,
+			Node::Fixed(size) => *size == v.len(),
+			_ => false,
+		};
+		match self.resolve("bytes", fits)? {
+			Node::Bytes => write_bytes(&mut self.out, v),
+			_ => self.out.extend_from_slice(v),
+		}
+		Ok(())
+	}
+	fn serialize_none(self) -> Result<(), Error> {
+		let _ = self.resolve("None", |node| matches!(node, Node::Null))?;
+		Ok(())
+	}
+	fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<(), Error> {
+		let nodes = self.nodes;
+		match &nodes[self.node] {
+			Node::Union(branches)
+				if branches
+					.iter()
+					.any(|&branch| matches!(nodes[branch], Node::Null)) =>
+			{
+				// the other branch of a two-branch union, whatever the value; otherwise the value's type selects it
+				if let [a, b] = branches[..] {
+					let index = usize::from(matches!(nodes[a], Node::Null));
+					write_index(&mut self.out, index);
+					self.node = [a, b][index];
+				}
+				value.serialize(self)
+			}
+			node => Err(mismatch("Some", node)),
+		}
+	}
+	fn serialize_unit(self) -> Result<(), Error> {
+		let _ = self.resolve("()", |node| matches!(node, Node::Null))?;
+		Ok(())
+	}
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+		let _ = self.resolve("a unit struct", |node| match node {
+			Node::Null => true,
+			Node::Record(fields) => fields.is_empty(),
+			_ => false,
+		})?;
+		Ok(())
+	}
+	fn serialize_unit_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str,
+	) -> Result<(), Error> {
+		match self.nodes[self.node] {
+			Node::Enum(symbols) if usize::try_from(variant_index).unwrap() < symbols => {
+				write_long(&mut self.out, variant_index.into());
+			}
+			_ => {
+				let _ = self.variant(variant_index, "a unit variant", |node| {
+					matches!(node, Node::Null)
+				})?;
+			}
+		}
+		Ok(())
+	}
+	fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+		self, _name: &'static str, value: &T,
+	) -> Result<(), Error> {
+		value.serialize(self)
+	}
+	fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T,
+	) -> Result<(), Error> {
+		let _ = self.variant(variant_index, "a newtype variant", |_| true)?;
+		value.serialize(self)
+	}
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Block<'a, 's>, Error> {
+		match self.resolve("a sequence", |node| matches!(node, Node::Array(_)))? {
+			&Node::Array(items) => Ok(Block::new(self, items)),
+			_ => unreachable!(),
+		}
+	}
+	fn serialize_tuple(self, len: usize) -> Result<Record<'a, 's>, Error> {
+		self.record("a tuple", len)
+	}
+	fn serialize_tuple_struct(
+		self, _name: &'static str, len: usize,
+	) -> Result<Record<'a, 's>, Error> {
+		self.record("a tuple struct", len)
+	}
+	fn serialize_tuple_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, len: usize,
+	) -> Result<Record<'a, 's>, Error> {
+		let _ = self.variant(variant_index, "a tuple variant", |node| {
+			matches!(node, Node::Record(_))
+		})?;
+		self.record("a tuple variant", len)
+	}
+	fn serialize_map(self, _len: Option<usize>) -> Result<Block<'a, 's>, Error> {
+		match self.resolve("a map", |node| matches!(node, Node::Map(_)))? {
+			&Node::Map(values) => Ok(Block::new(self, values)),
+			_ => unreachable!(),
+		}
+	}
+	fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Record<'a, 's>, Error> {
+		self.record("a struct", len)
+	}
+	fn serialize_struct_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, len: usize,
+	) -> Result<Record<'a, 's>, Error> {
+		let _ = self.variant(variant_index, "a struct variant", |node| {
+			matches!(node, Node::Record(_))
+		})?;
+		self.record("a struct variant", len)
+	}
+	fn is_human_readable(&self) -> bool {
+		false
+	}
+}
+
+/// A `record`, whose fields are each encoded against their own node.
+struct Record<'a, 's> {
+	encoder: &'a mut Encoder<'s>,
+	fields: slice::Iter<'s, usize>,
+}
+impl Record<'_, '_> {
+	fn field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.encoder.node = *self
+			.fields
+			.next()
+			.ok_or_else(|| Error(String::from("more fields than the Avro record")))?;
+		value.serialize(&mut *self.encoder)
+	}
+	fn finish(&self) -> Result<(), Error> {
+		if self.fields.len() == 0 {
+			Ok(())
+		} else {
+			Err(Error(String::from("fewer fields than the Avro record")))
+		}
+	}
+}
+impl ser::SerializeTuple for Record<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.field(value)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+impl ser::SerializeTupleStruct for Record<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.field(value)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+impl ser::SerializeTupleVariant for Record<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.field(value)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+impl ser::SerializeStruct for Record<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(
+		&mut self, _key: &'static str, value: &T,
+	) -> Result<(), Error> {
+		self.field(value)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+impl ser::SerializeStructVariant for Record<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(
+		&mut self, _key: &'static str, value: &T,
+	) -> Result<(), Error> {
+		self.field(value)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish()
+	}
+}
+
+/// An `array` or `map`, written as a single block once its length is known.
+struct Block<'a, 's> {
+	encoder: &'a mut Encoder<'s>,
+	items: Encoder<'s>,
+	/// The node of the items, or of the values of a `map`.
+	item: usize,
+	count: i64,
+}
+impl<'a, 's> Block<'a, 's> {
+	fn new(encoder: &'a mut Encoder<'s>, item: usize) -> Self {
+		let items = Encoder::new(encoder.nodes, item);
+		Self {
+			encoder,
+			items,
+			item,
+			count: 0,
+		}
+	}
+	fn finish(self) {
+		if self.count != 0 {
+			write_long(&mut self.encoder.out, self.count);
+			self.encoder.out.extend(self.items.out);
+		}
+		write_long(&mut self.encoder.out, 0);
+	}
+}
+impl ser::SerializeSeq for Block<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.count += 1;
+		self.items.node = self.item;
+		value.serialize(&mut self.items)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish();
+		Ok(())
+	}
+}
+impl ser::SerializeMap for Block<'_, '_> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<(), Error> {
+		self.count += 1;
+		self.items.node = STRING;
+		key.serialize(&mut self.items)
+	}
+	fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		self.items.node = self.item;
+		value.serialize(&mut self.items)
+	}
+	fn end(self) -> Result<(), Error> {
+		self.finish();
+		Ok(())
+	}
+}
+
+struct Decoder<'de, 's> {
+	input: &'de [u8],
+	nodes: &'s [Node],
+	/// The schema node the next value was encoded against.
+	node: usize,
+}
+impl<'de, 's> Decoder<'de, 's> {
+	fn new(input: &'de [u8], nodes: &'s [Node], node: usize) -> Self {
+		Self { input, nodes, node }
+	}
+	/// The node the next value was encoded against, if it `matches`. A union is resolved to the branch whose index is read.
+	fn resolve(&mut self, value: &str, matches: impl Fn(&Node) -> bool) -> Result<&'s Node, Error> {
+		let nodes = self.nodes;
+		if let Node::Union(branches) = &nodes[self.node] {
+			self.node = self.branch(branches)?;
+		}
+		let node = &nodes[self.node];
+		if matches(node) {
+			Ok(node)
+		} else {
+			Err(mismatch(value, node))
+		}
+	}
+	/// Read the index of one of `branches`, returning its node.
+	fn branch(&mut self, branches: &[usize]) -> Result<usize, Error> {
+		let index: usize = self.int()?;
+		branches
+			.get(index)
+			.copied()
+			.ok_or_else(|| de::Error::custom("invalid Avro union index"))
+	}
+	/// The fields of a `record` of `len` fields.
+	fn record(&mut self, value: &str, len: usize) -> Result<slice::Iter<'s, usize>, Error> {
+		match self.resolve(
+			value,
+			|node| matches!(node, Node::Record(fields) if fields.len() == len),
+		)? {
+			Node::Record(fields) => Ok(fields.iter()),
+			_ => unreachable!(),
+		}
+	}
+	fn long(&mut self) -> Result<i64, Error> {
+		let (n, len) = read_long(self.input).ok_or_else(eof)?;
+		self.input = &self.input[len..];
+		Ok(n)
+	}
+	fn take(&mut self, len: usize) -> Result<&'de [u8], Error> {
+		if len > self.input.len() {
+			return Err(eof());
+		}
+		let (taken, rest) = self.input.split_at(len);
+		self.input = rest;
+		Ok(taken)
+	}
+	fn bytes(&mut self) -> Result<&'de [u8], Error> {
+		let len = self.long()?;
+		self.take(usize::try_from(len).map_err(de::Error::custom)?)
+	}
+	fn str(&mut self) -> Result<&'de str, Error> {
+		let _ = self.resolve("a string", |node| matches!(node, Node::String))?;
+		std::str::from_utf8(self.bytes()?).map_err(de::Error::custom)
+	}
+	fn int<T: TryFrom<i64>>(&mut self) -> Result<T, Error>
+	where
+		T::Error: fmt::Display,
+	{
+		T::try_from(self.long()?).map_err(de::Error::custom)
+	}
+	/// Read an integer, encoded against an `int` or `long`.
+	fn integer<T: TryFrom<i64>>(&mut self) -> Result<T, Error>
+	where
+		T::Error: fmt::Display,
+	{
+		let _ = self.resolve("an integer", |node| matches!(node, Node::Int | Node::Long))?;
+		self.int()
+	}
+	/// Read the count of the next block of an `array` or `map`.
+	fn block(&mut self) -> Result<usize, Error> {
+		let count = self.long()?;
+		if count < 0 {
+			let _size = self.long()?;
+		}
+		usize::try_from(count.unsigned_abs()).map_err(de::Error::custom)
+	}
+}
+fn eof() -> Error {
+	Error(String::from("unexpected end of Avro value"))
+}
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de, '_> {
+	type Error = Error;
+
+	fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+		Err(de::Error::custom("Avro is not self-describing"))
+	}
+	fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let _ = self.resolve("a bool", |node| matches!(node, Node::Boolean))?;
+		match self.take(1)?[0] {
+			0 => visitor.visit_bool(false),
+			1 => visitor.visit_bool(true),
+			_ => Err(de::Error::custom("invalid Avro boolean")),
+		}
+	}
+	fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i8(self.integer()?)
+	}
+	fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i16(self.integer()?)
+	}
+	fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i32(self.integer()?)
+	}
+	fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i64(self.integer()?)
+	}
+	fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u8(self.integer()?)
+	}
+	fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u16(self.integer()?)
+	}
+	fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u32(self.integer()?)
+	}
+	fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u64(self.integer()?)
+	}
+	fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let _ = self.resolve("an f32", |node| matches!(node, Node::Float))?;
+		visitor.visit_f32(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+	fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match self.resolve("an f64", |node| matches!(node, Node::Float | Node::Double))? {
+			Node::Float => {
+				visitor.visit_f64(f32::from_le_bytes(self.take(4)?.try_into().unwrap()).into())
+			}
+			_ => visitor.visit_f64(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+		}
+	}
+	fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let mut chars = self.str()?.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => visitor.visit_char(c),
+			_ => Err(de::Error::custom("invalid char")),
+		}
+	}
+	fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_borrowed_str(self.str()?)
+	}
+	fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_str(visitor)
+	}
+	fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match *self.resolve("bytes", |node| matches!(node, Node::Bytes | Node::Fixed(_)))? {
+			Node::Fixed(size) => visitor.visit_borrowed_bytes(self.take(size)?),
+			_ => visitor.visit_borrowed_bytes(self.bytes()?),
+		}
+	}
+	fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_bytes(visitor)
+	}
+	fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let nodes = self.nodes;
+		match &nodes[self.node] {
+			Node::Union(branches)
+				if branches
+					.iter()
+					.any(|&branch| matches!(nodes[branch], Node::Null)) =>
+			{
+				self.node = self.branch(branches)?;
+				if let Node::Null = nodes[self.node] {
+					visitor.visit_none()
+				} else {
+					visitor.visit_some(self)
+				}
+			}
+			node => Err(mismatch("an Option", node)),
+		}
+	}
+	fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let _ = self.resolve("()", |node| matches!(node, Node::Null))?;
+		visitor.visit_unit()
+	}
+	fn deserialize_unit_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, visitor: V,
+	) -> Result<V::Value, Error> {
+		let _ = self.resolve("a unit struct", |node| match node {
+			Node::Null => true,
+			Node::Record(fields) => fields.is_empty(),
+			_ => false,
+		})?;
+		visitor.visit_unit()
+	}
+	fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+	fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match *self.resolve("a sequence", |node| matches!(node, Node::Array(_)))? {
+			Node::Array(item) => visitor.visit_seq(Blocks::new(self, item)),
+			_ => unreachable!(),
+		}
+	}
+	fn deserialize_tuple<V: de::Visitor<'de>>(
+		self, len: usize, visitor: V,
+	) -> Result<V::Value, Error> {
+		let fields = self.record("a tuple", len)?;
+		visitor.visit_seq(Fields {
+			decoder: self,
+			fields,
+		})
+	}
+	fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, len: usize, visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_tuple(len, visitor)
+	}
+	fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		match *self.resolve("a map", |node| matches!(node, Node::Map(_)))? {
+			Node::Map(item) => visitor.visit_map(Blocks::new(self, item)),
+			_ => unreachable!(),
+		}
+	}
+	fn deserialize_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_tuple(fields.len(), visitor)
+	}
+	fn deserialize_enum<V: de::Visitor<'de>>(
+		self, _name: &'static str, _variants: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_enum(self)
+	}
+	fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_any(visitor)
+	}
+	fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_any(visitor)
+	}
+	fn is_human_readable(&self) -> bool {
+		false
+	}
+}
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de, '_> {
+	type Error = Error;
+	type Variant = Self;
+
+	fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+		let nodes = self.nodes;
+		let index: u32 = match &nodes[self.node] {
+			&Node::Enum(symbols) => {
+				let index = self.int()?;
+				if usize::try_from(index).unwrap() >= symbols {
+					return Err(de::Error::custom("invalid Avro enum index"));
+				}
+				index
+			}
+			// each variant is a branch, so the index read is the variant's
+			Node::Union(branches) => {
+				let index = self.int()?;
+				self.node = *branches
+					.get(usize::try_from(index).unwrap())
+					.ok_or_else(|| de::Error::custom("invalid Avro union index"))?;
+				index
+			}
+			node => return Err(mismatch("an enum", node)),
+		};
+		let value = seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(index))?;
+		Ok((value, self))
+	}
+}
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de, '_> {
+	type Error = Error;
+
+	fn unit_variant(self) -> Result<(), Error> {
+		match &self.nodes[self.node] {
+			Node::Null | Node::Enum(_) => Ok(()),
+			node => Err(mismatch("a unit variant", node)),
+		}
+	}
+	fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+		seed.deserialize(self)
+	}
+	fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_tuple(self, len, visitor)
+	}
+	fn struct_variant<V: de::Visitor<'de>>(
+		self, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+	}
+}
+
+/// The items of an `array` or `map`, which may span multiple blocks.
+struct Blocks<'a, 'de, 's> {
+	decoder: &'a mut Decoder<'de, 's>,
+	/// The node of the items, or of the values of a `map`.
+	item: usize,
+	remaining: usize,
+	done: bool,
+}
+impl<'a, 'de, 's> Blocks<'a, 'de, 's> {
+	fn new(decoder: &'a mut Decoder<'de, 's>, item: usize) -> Self {
+		Self {
+			decoder,
+			item,
+			remaining: 0,
+			done: false,
+		}
+	}
+	fn next(&mut self) -> Result<bool, Error> {
+		if self.remaining == 0 && !self.done {
+			self.remaining = self.decoder.block()?;
+			self.done = self.remaining == 0;
+		}
+		if self.remaining != 0 {
+			self.remaining -= 1;
+			Ok(true)
+		} else {
+			Ok(false)
+		}
+	}
+}
+impl<'de> de::SeqAccess<'de> for Blocks<'_, 'de, '_> {
+	type Error = Error;
+
+	fn next_element_seed<T: de::DeserializeSeed<'de>>(
+		&mut self, seed: T,
+	) -> Result<Option<T::Value>, Error> {
+		if self.next()? {
+			self.decoder.node = self.item;
+			seed.deserialize(&mut *self.decoder).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+}
+impl<'de> de::MapAccess<'de> for Blocks<'_, 'de, '_> {
+	type Error = Error;
+
+	fn next_key_seed<K: de::DeserializeSeed<'de>>(
+		&mut self, seed: K,
+	) -> Result<Option<K::Value>, Error> {
+		if self.next()? {
+			self.decoder.node = STRING;
+			seed.deserialize(&mut *self.decoder).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+	fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+		self.decoder.node = self.item;
+		seed.deserialize(&mut *self.decoder)
+	}
+}
+
+/// The fields of a `record`, each decoded from its own node.
+struct Fields<'a, 'de, 's> {
+	decoder: &'a mut Decoder<'de, 's>,
+	fields: slice::Iter<'s, usize>,
+}
+impl<'de> de::SeqAccess<'de> for Fields<'_, 'de, '_> {
+	type Error = Error;
+
+	fn next_element_seed<T: de::DeserializeSeed<'de>>(
+		&mut self, seed: T,
+	) -> Result<Option<T::Value>, Error> {
+		match self.fields.next() {
+			Some(&node) => {
+				self.decoder.node = node;
+				seed.deserialize(&mut *self.decoder).map(Some)
+			}
+			None => Ok(None),
+		}
+	}
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.fields.len())
+	}
+}
@@ -0,0 +1,261 @@
+//! Batching many small values into each frame.
+//!
+//! Each frame carries a length header, and often a checksum or other per-frame overhead downstream, which for small values can be a large fraction of the bytes sent. [`BatchSerializer`] coalesces values pushed while a frame is in flight into a single frame, encoded as a `Vec<T>`, up to a limit on the number of values or their encoded size. [`BatchDeserializer`] unpacks each such frame, yielding the values one at a time.
+//!
+//! Values aren't held back waiting for a batch to fill: whenever the underlying [`Serializer`] is idle, whatever has been pushed is sent straight away. So batches grow only while the reader is falling behind.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{batch::{BatchDeserializer, BatchSerializer}, serialize_to_vec, Deserializer, Serializer};
+//!
+//! let mut serializer = BatchSerializer::new(Serializer::new(), 100, 4096);
+//! for i in 0..10_u32 {
+//!     serializer.push().unwrap()(i);
+//! }
+//! let mut deserializer = BatchDeserializer::new(Deserializer::new());
+//! let mut bytes = 0;
+//! while let (Some(pull), Some(push)) = (serializer.pull(), deserializer.push()) {
+//!     push(pull());
+//!     bytes += 1;
+//! }
+//! // one frame of a count and ten u32s, as long as one of a Vec of them, rather than ten frames
+//! assert_eq!(bytes, serialize_to_vec((0..10_u32).collect::<Vec<_>>()).len());
+//! let values = std::iter::from_fn(|| deserializer.pull().map(|pull| pull())).collect::<Vec<u32>>();
+//! assert_eq!(values, (0..10).collect::<Vec<_>>());
+//! ```
+
+use crate::{wire, Deserializer, Serializer};
+use std::{convert::TryFrom, fmt, mem, vec};
+
+/// A [`Serializer`] that sends values of type `T` in batches of up to `max_len` values or `max_bytes` encoded bytes, whichever is reached first.
+///
+/// # Panics
+///
+/// Will panic if dropped while values are batched, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`BatchSerializer::empty()`] before dropping it.
+pub struct BatchSerializer<T> {
+	serializer: Serializer,
+	batch: Vec<T>,
+	bytes: usize,
+	max_len: usize,
+	max_bytes: usize,
+}
+impl<T: serde::ser::Serialize + 'static> BatchSerializer<T> {
+	/// Wrap `serializer`, batching up to `max_len` values or `max_bytes` encoded bytes per frame. A single value larger than `max_bytes` is sent in a batch of its own.
+	///
+	/// # Panics
+	///
+	/// Will panic if `max_len` is zero.
+	#[must_use]
+	pub fn new(serializer: Serializer, max_len: usize, max_bytes: usize) -> Self {
+		assert_ne!(max_len, 0, "max_len must be non-zero");
+		Self {
+			serializer,
+			batch: Vec::new(),
+			bytes: 0,
+			max_len,
+			max_bytes,
+		}
+	}
+
+	/// The number of values batched, not including any frame in flight.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.batch.len()
+	}
+
+	/// Whether no values are batched, not including any frame in flight.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.batch.is_empty()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.batch.len() < self.max_len && (self.batch.is_empty() || self.bytes < self.max_bytes)
+	}
+	/// Push a `T`. [`None`] denotes that the batch is full, and is instead awaiting a [`pull`](BatchSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error.
+	pub fn push(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				self.bytes += usize::try_from(wire::serialized_size(&t).unwrap()).unwrap();
+				self.batch.push(t);
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail() || !self.batch.is_empty()
+	}
+	/// Pull a `u8`. [`None`] denotes that nothing is batched, and it's instead awaiting a [`push`](BatchSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				// there's a frame in flight, or advance just started one
+				self.advance();
+				let Some(pull) = self.serializer.pull() else {
+					unreachable!()
+				};
+				pull()
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.batch.is_empty() || self.serializer.empty_avail()
+	}
+	/// Empty the batch and the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				self.batch.clear();
+				self.bytes = 0;
+				if let Some(empty) = self.serializer.empty() {
+					empty();
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Start sending the batch, if the [`Serializer`] is idle.
+	fn advance(&mut self) {
+		if self.serializer.push_avail() && !self.batch.is_empty() {
+			self.bytes = 0;
+			self.serializer.push().unwrap()(mem::take(&mut self.batch));
+		}
+	}
+}
+impl<T> Drop for BatchSerializer<T> {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.batch.is_empty() || std::thread::panicking(),
+			"BatchSerializer dropped with {} values batched; empty() it first to discard them",
+			self.batch.len()
+		);
+	}
+}
+impl<T> fmt::Debug for BatchSerializer<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BatchSerializer")
+			.field("serializer", &self.serializer)
+			.field("batch", &self.batch.len())
+			.field("bytes", &self.bytes)
+			.field("max_len", &self.max_len)
+			.field("max_bytes", &self.max_bytes)
+			.finish()
+	}
+}
+
+/// A [`Deserializer`] that receives the batches sent by a [`BatchSerializer`], yielding their values individually.
+///
+/// # Panics
+///
+/// Will panic if dropped while values of a batch remain to be pulled, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`BatchDeserializer::empty()`] before dropping it.
+pub struct BatchDeserializer<T> {
+	deserializer: Deserializer,
+	batch: vec::IntoIter<T>,
+}
+impl<T: serde::de::DeserializeOwned + 'static> BatchDeserializer<T> {
+	/// Wrap `deserializer`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `deserializer` is part way through a frame of another type.
+	#[must_use]
+	pub fn new(mut deserializer: Deserializer) -> Self {
+		let _ = deserializer.pull::<Vec<T>>();
+		Self {
+			deserializer,
+			batch: Vec::new().into_iter(),
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that a batch is awaiting a [`pull`](BatchDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		self.deserializer.push()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.batch.len() != 0 || self.deserializer.pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the current batch is exhausted, and it's instead awaiting a [`push`](BatchDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if a batch is empty, which [`BatchSerializer`] never sends.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> T + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				if self.batch.len() == 0 {
+					self.batch = self.deserializer.pull::<Vec<T>>().unwrap()().into_iter();
+					// supply the type of the next batch, so it can be pushed
+					let _ = self.deserializer.pull::<Vec<T>>();
+				}
+				self.batch.next().expect("received an empty batch")
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.batch.len() != 0 || self.deserializer.empty_avail()
+	}
+	/// Empty the current batch and the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				self.batch = Vec::new().into_iter();
+				if let Some(empty) = self.deserializer.empty() {
+					empty();
+				}
+				let _ = self.deserializer.pull::<Vec<T>>();
+			})
+		} else {
+			None
+		}
+	}
+}
+impl<T> Drop for BatchDeserializer<T> {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.batch.len() == 0 || std::thread::panicking(),
+			"BatchDeserializer dropped with {} values of a batch remaining; empty() it first to discard them",
+			self.batch.len()
+		);
+	}
+}
+impl<T> fmt::Debug for BatchDeserializer<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BatchDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("batch", &self.batch.len())
+			.finish()
+	}
+}
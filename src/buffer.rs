@@ -1,7 +1,42 @@
+use crate::{
+	pretty, telemetry::{Direction, StallTimer, Stalls, Telemetry, RAW_FRAME}, wire
+};
+use bincode::Options;
+use either::Either;
 use std::{
-	any::TypeId, fmt, io::{self, Read}
+	any::{self, Any, TypeId}, collections::VecDeque, convert::TryFrom, error, fmt, io::{self, Read, Write}, iter, mem, time::{Duration, Instant}
 };
 
+/// The default number of frames [`Serializer::push_or_queue()`] queues.
+const QUEUE_LIMIT: usize = 16;
+/// The most bytes [`Deserializer::fill_from()`] reads at once.
+const FILL_LEN: usize = 8 * 1024;
+/// The most a Deserializer reserves for a frame ahead of its bytes arriving.
+const RESERVE_LEN: usize = 64 * 1024;
+
+/// A frame queued by [`Serializer::push_or_queue()`], from the index it starts at, with its priority, and when it expires, if it does.
+type Queued = ((Vec<u8>, usize), i32, Option<Instant>);
+
+/// Wipe a frame's bytes before they're freed, with the `zeroize` feature.
+#[cfg(feature = "zeroize")]
+fn wipe(bytes: &mut [u8]) {
+	zeroize::Zeroize::zeroize(bytes);
+}
+#[cfg(not(feature = "zeroize"))]
+#[inline(always)]
+fn wipe(_: &mut [u8]) {}
+
+/// Box a frame's bytes, without leaving a copy behind in a freed allocation with the `zeroize` feature.
+fn into_boxed(mut vec: Vec<u8>) -> Box<[u8]> {
+	if cfg!(feature = "zeroize") && vec.capacity() != vec.len() {
+		let boxed = Box::from(&*vec);
+		wipe(&mut vec);
+		boxed
+	} else {
+		vec.into_boxed_slice()
+	}
+}
+
 struct ReadCounter<T: Read>(T, usize);
 impl<T: Read> ReadCounter<T> {
 	fn new(t: T) -> Self {
@@ -21,9 +56,168 @@ impl<T: Read> Read for ReadCounter<T> {
 	}
 }
 
+/// Context for a frame that failed to decode: the type, how far decoding got, and a bounded hex snippet of the surrounding bytes, with the byte at `offset` bracketed.
+#[cfg(not(feature = "terse-errors"))]
+struct FrameError<'a> {
+	type_name: &'static str,
+	frame: &'a [u8],
+	offset: usize,
+}
+#[cfg(not(feature = "terse-errors"))]
+impl fmt::Display for FrameError<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		const CONTEXT: usize = 16;
+		let (start, end) = (
+			self.offset.saturating_sub(CONTEXT),
+			(self.offset + CONTEXT).min(self.frame.len()),
+		);
+		write!(
+			f,
+			"deserializing {} from a {} byte frame, at byte offset {}: ",
+			self.type_name,
+			self.frame.len(),
+			self.offset
+		)?;
+		if start != 0 {
+			f.write_str("..")?;
+		}
+		for (i, byte) in self.frame[start..end].iter().enumerate() {
+			let sep = if i == 0 { "" } else { " " };
+			if start + i == self.offset {
+				write!(f, "{sep}[{byte:02x}]")?;
+			} else {
+				write!(f, "{sep}{byte:02x}")?;
+			}
+		}
+		if end != self.frame.len() {
+			f.write_str("..")?;
+		}
+		Ok(())
+	}
+}
+
 /// As documented <https://github.com/servo/bincode>
 const USIZE_BINCODE_LEN: usize = 8;
 
+/// How frames are delimited on the wire: the length prefix preceding each frame.
+///
+/// Both ends of a pipe must use the same framing. With the `fringe` feature, whose wire format has no frame header, [`Serializer::with_framing()`] and [`Deserializer::with_framing()`] panic.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Framing {
+	/// An 8-byte little-endian length prefix, as bincode encodes a `u64`. This is the default.
+	Bincode,
+	/// A 4-byte big-endian length prefix, as read by Java's `DataInputStream.readInt()`. Frames are limited to `i32::MAX` bytes, and [`Control`] frames aren't supported.
+	BigEndianU32,
+	/// gRPC's 5-byte message prefix: a 1-byte compressed flag, always `0`, then a 4-byte big-endian length. Frames are limited to `u32::MAX` bytes, compressed messages aren't supported, and [`Control`] frames aren't supported.
+	///
+	/// Zero-length messages, such as `google.protobuf.Empty`, are valid payloads: a zero-length encoding is sent as one rather than padded, and one received is pulled rather than absorbed as a heartbeat. Heartbeats therefore aren't supported.
+	Grpc,
+}
+impl Framing {
+	#[inline(always)]
+	const fn header_len(self) -> usize {
+		match self {
+			Framing::Bincode => USIZE_BINCODE_LEN,
+			Framing::BigEndianU32 => 4,
+			Framing::Grpc => 5,
+		}
+	}
+	fn write_header(self, header: u64, buf: &mut [u8]) {
+		match self {
+			Framing::Bincode => buf.copy_from_slice(&header.to_le_bytes()),
+			Framing::BigEndianU32 => {
+				let header = i32::try_from(header)
+					.unwrap_or_else(|_| fail!("frame too long for Framing::BigEndianU32"));
+				buf.copy_from_slice(&header.to_be_bytes());
+			}
+			Framing::Grpc => {
+				let header = u32::try_from(header)
+					.unwrap_or_else(|_| fail!("frame too long for Framing::Grpc"));
+				buf[0] = 0;
+				buf[1..].copy_from_slice(&header.to_be_bytes());
+			}
+		}
+	}
+	fn read_header(self, buf: &[u8]) -> u64 {
+		match self {
+			Framing::Bincode => u64::from_le_bytes(<[u8; 8]>::try_from(buf).unwrap()),
+			Framing::BigEndianU32 => {
+				u64::from(u32::from_be_bytes(<[u8; 4]>::try_from(buf).unwrap()))
+			}
+			Framing::Grpc => {
+				ensure_eq!(buf[0], 0, "compressed gRPC messages aren't supported");
+				u64::from(u32::from_be_bytes(<[u8; 4]>::try_from(&buf[1..]).unwrap()))
+			}
+		}
+	}
+}
+
+/// Stands in for the type supplied by [`Deserializer::pull_with()`], whose frames aren't of any Rust type.
+struct RawFrame;
+
+/// Set in a frame header to denote a control frame rather than a data frame.
+const CONTROL_BIT: u64 = 1 << 63;
+
+/// An out-of-band control frame, sent with [`Serializer::push_control()`] and received with [`Deserializer::pull_control()`].
+///
+/// These are distinguished from data frames by their header, and are delivered in order with respect to them. Their meaning is up to the application; the pipes attach no behaviour to them. With the `fringe` feature, whose wire format has no frame header, [`Serializer::push_control()`] panics, and so none are received.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Control {
+	/// Request that the receiver flush.
+	Flush,
+	/// Notify the receiver that the sender is closing.
+	Close,
+	/// Notify the receiver of an error, with an application-defined code.
+	Error(u32),
+	/// An application-defined code.
+	App(u32),
+}
+impl Control {
+	fn into_header(self) -> u64 {
+		CONTROL_BIT
+			| match self {
+				Control::Flush => 0,
+				Control::Close => 1 << 32,
+				Control::Error(code) => 2 << 32 | u64::from(code),
+				Control::App(code) => 3 << 32 | u64::from(code),
+			}
+	}
+	fn from_header(header: u64) -> Self {
+		let code = u32::try_from(header & u64::from(u32::MAX)).unwrap();
+		match (header & !CONTROL_BIT) >> 32 {
+			0 => Control::Flush,
+			1 => Control::Close,
+			2 => Control::Error(code),
+			3 => Control::App(code),
+			_ => fail!(
+				"invalid control frame header",
+				"invalid control frame header {:#x}",
+				header
+			),
+		}
+	}
+}
+
+/// A frame nested too deeply for its coroutine stack, which poisons the pipe. Returned by [`Serializer::poisoned()`] and [`Deserializer::poisoned()`]; only with the `fringe` feature, as otherwise frames are serialized and deserialized on the caller's own stack.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StackOverflow {
+	/// The type being serialized or deserialized.
+	pub type_name: &'static str,
+	/// The size of the stack it overflowed.
+	pub stack_len: usize,
+}
+impl fmt::Display for StackOverflow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} nested too deeply for its {} byte stack",
+			self.type_name, self.stack_len
+		)
+	}
+}
+impl error::Error for StackOverflow {}
+
 /// Serializer pipe: push `T`; pull `u8`.
 ///
 /// The [`push`](Serializer::push()) and [`pull`](Serializer::pull()) calls can signify "blocking" – i.e. they're awaiting the other call – by returning [`None`].
@@ -47,15 +241,109 @@ const USIZE_BINCODE_LEN: usize = 8;
 ///
 /// # Panics
 ///
-/// Will panic if dropped while non-empty. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Serializer::empty()`] before dropping it.
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Serializer::empty()`] before dropping it.
+///
+/// With the `fringe` feature, will also panic if pushed to or pulled from while [`poisoned`](Serializer::poisoned()).
 pub struct Serializer {
-	buffer: Option<(Box<[u8]>, usize)>,
+	buffer: Option<(Vec<u8>, usize)>,
+	/// The index in `buffer` set by [`mark`](Serializer::mark()), until the frame ends.
+	mark: Option<usize>,
+	/// The frame last pulled by [`pull_chunk`](Serializer::pull_chunk()), kept so the bytes it returned outlive the frame's end.
+	chunk: Vec<u8>,
+	/// Frames queued by [`push_or_queue`](Serializer::push_or_queue()) to be pulled after `buffer`. Kept in order of priority, highest first.
+	queue: VecDeque<Queued>,
+	queue_limit: usize,
+	expired: usize,
+	alignment: usize,
+	framing: Framing,
+	telemetry: Option<Telemetry>,
+	stalls: Option<StallTimer>,
 }
 impl Serializer {
 	/// Construct a new Serializer pipe.
+	///
+	/// This doesn't allocate, and is a `const fn` so it can be used to initialise a `static`.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self::with_framing(Framing::Bincode)
+	}
+
+	/// Construct a new Serializer pipe that delimits frames with the given [`Framing`].
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend's wire format has no frame header.
+	#[must_use]
 	#[inline(always)]
-	pub fn new() -> Self {
-		Self { buffer: None }
+	pub const fn with_framing(framing: Framing) -> Self {
+		Self {
+			buffer: None,
+			mark: None,
+			chunk: Vec::new(),
+			queue: VecDeque::new(),
+			queue_limit: QUEUE_LIMIT,
+			expired: 0,
+			alignment: 1,
+			framing,
+			telemetry: None,
+			stalls: None,
+		}
+	}
+
+	/// Construct a new Serializer pipe with `t` already pushed, ready to be [`pull`](Serializer::pull())ed.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn of<T: serde::ser::Serialize + 'static>(t: T) -> Self {
+		let mut serializer = Self::new();
+		serializer.push().unwrap()(t);
+		serializer
+	}
+
+	/// Set a callback to be invoked as each frame is serialized, or [`None`] to clear it. See [`telemetry`](crate::telemetry).
+	pub fn set_telemetry(&mut self, telemetry: Option<Telemetry>) {
+		self.telemetry = telemetry;
+	}
+
+	/// Enable or disable timing how long this pipe spends awaiting a push versus a pull, returned by [`stalls`](Serializer::stalls()). Enabling it restarts the timing. See [`telemetry`](crate::telemetry).
+	pub fn set_stall_timing(&mut self, enabled: bool) {
+		self.stalls = enabled.then(|| StallTimer::new(self.pull_avail()));
+	}
+
+	/// The time spent awaiting a push versus a pull since [`set_stall_timing`](Serializer::set_stall_timing()) was enabled, or [`None`] if it isn't.
+	pub fn stalls(&self) -> Option<Stalls> {
+		self.stalls.as_ref().map(StallTimer::stalls)
+	}
+
+	/// Allocate `count` coroutine stacks of `len` bytes now, touching every page, for use as inner pipelines are created for new types or grown, so that latency-critical code doesn't take page faults mid-frame.
+	///
+	/// Every stack, preallocated or not, has a guard page beneath it, so an overflow faults rather than corrupting memory. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if the stacks can't be allocated.
+	#[allow(clippy::unused_self)]
+	pub fn preallocate_stacks(&mut self, count: usize, len: usize) {
+		let _ = (count, len);
+	}
+
+	/// Pin the number of bytes serialized between each switch into and out of the coroutine, or [`None`] to adapt it, as by default. Adapted, each frame starts with a block of 32 bytes, so short frames are staged in little, and each block after is twice the last, up to 64 KiB, so large frames take few context switches. Takes effect from the next block. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if `block_len` is zero.
+	#[allow(clippy::unused_self)]
+	pub fn set_block_len(&mut self, block_len: Option<usize>) {
+		assert!(block_len != Some(0), "block_len must be at least 1");
+	}
+
+	/// The overflow, if a frame nested too deeply for its stack, in which case it was cut short, and this pipe is poisoned until it's [`empty`](Serializer::empty())ed: [`push`](Serializer::push()) and [`pull`](Serializer::pull()) panic with it, and [`drain_to`](Serializer::drain_to()) returns it as an error. Frames are abandoned once they near the end of their stack, so the process isn't aborted by the guard page, provided they write between each level of nesting, as bincode does. The value is leaked rather than dropped, as dropping it would likely overflow too. Always [`None`] without the `fringe` feature.
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub fn poisoned(&self) -> Option<StackOverflow> {
+		None
 	}
 
 	#[doc(hidden)]
@@ -68,19 +356,275 @@ impl Serializer {
 	) -> Option<impl FnOnce(T) + 'a> {
 		if self.buffer.is_none() {
 			Some(move |t| {
-				// TODO: reuse vec
-				let mut vec = vec![0; USIZE_BINCODE_LEN + 1];
-				let _ = vec.pop().unwrap();
-				bincode::serialize_into(&mut vec, &t).unwrap();
-				let mut len = vec.len() - USIZE_BINCODE_LEN;
-				if len == 0 {
-					len += 1;
-					vec.push(0);
+				self.buffer = Some(self.frame(&t));
+				self.time_stalls();
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Push an `A` or a `B` to the Serializer pipe, tagged so that the receiving end can [`pull_either`](Deserializer::pull_either()) it without declaring a wrapper enum. Nest [`Either`] for more types, for example `push_tagged::<A, Either<B, C>>()`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce(Either<A, B>)` that can be called to perform the `push`.
+	pub fn push_tagged<A: serde::ser::Serialize + 'static, B: serde::ser::Serialize + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce(Either<A, B>) + '_> {
+		self.push()
+	}
+
+	/// Push a `T` to the Serializer pipe if it's awaiting a push, otherwise queue it to be pulled after the frames already pushed. [`Err`] returns `t` if [`queue_limit`](Serializer::set_queue_limit()) values are already queued.
+	///
+	/// Queued values are serialized straight away, so their frames are held in memory until pulled.
+	///
+	/// # Errors
+	///
+	/// Returns `t` if the queue is full.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_or_queue<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T,
+	) -> Result<(), T> {
+		self.push_or_queue_until(t, 0, None)
+	}
+
+	/// Push each of `values` in turn as with [`push_or_queue`](Serializer::push_or_queue()), so a burst of values needn't be interleaved with checks of whether each can be pushed. The first is pushed if the Serializer is awaiting a push, and the rest are queued behind it.
+	///
+	/// # Errors
+	///
+	/// Returns the values not pushed, starting with the first that didn't fit, if the queue fills. [`set_queue_limit`](Serializer::set_queue_limit()) can make room for larger bursts.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_batch<T: serde::ser::Serialize + Send + 'static, I: IntoIterator<Item = T>>(
+		&mut self, values: I,
+	) -> Result<(), Vec<T>> {
+		let mut values = values.into_iter();
+		while let Some(t) = values.next() {
+			if let Err(t) = self.push_or_queue(t) {
+				return Err(iter::once(t).chain(values).collect());
+			}
+		}
+		Ok(())
+	}
+
+	/// Push a `T` as with [`push_or_queue`](Serializer::push_or_queue()), but if it's queued, and hasn't started to be pulled within `ttl`, drop it unsent, counting it in [`expired`](Serializer::expired()). For values worthless once stale, which would otherwise delay fresher ones behind them.
+	///
+	/// # Errors
+	///
+	/// Returns `t` if the queue is full of unexpired values.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_or_queue_with_ttl<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T, ttl: Duration,
+	) -> Result<(), T> {
+		self.push_or_queue_until(t, 0, Some(Instant::now() + ttl))
+	}
+
+	/// Push or queue a `T`, queueing it ahead of those of lower `priority`, and behind those of equal or higher. Shared with [`QueuedSerializer`](crate::queue::QueuedSerializer).
+	pub(crate) fn push_or_queue_until<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T, priority: i32, deadline: Option<Instant>,
+	) -> Result<(), T> {
+		if self.buffer.is_none() {
+			self.push().unwrap()(t);
+			return Ok(());
+		}
+		self.expire();
+		if self.queue.len() < self.queue_limit {
+			let frame = self.frame(&t);
+			let index = self
+				.queue
+				.iter()
+				.position(|&(_, queued, _)| queued < priority)
+				.unwrap_or(self.queue.len());
+			self.queue.insert(index, (frame, priority, deadline));
+			Ok(())
+		} else {
+			Err(t)
+		}
+	}
+
+	/// Whether [`push_or_queue_until`](Serializer::push_or_queue_until()) would push or queue rather than return the value.
+	pub(crate) fn queue_avail(&self) -> bool {
+		if self.buffer.is_none() || self.queue.len() < self.queue_limit {
+			return true;
+		}
+		let now = Instant::now();
+		self.queue
+			.iter()
+			.any(|(_, _, deadline)| matches!(deadline, Some(deadline) if *deadline <= now))
+	}
+
+	/// The number of values queued behind the frame being pulled.
+	pub(crate) fn queued(&self) -> usize {
+		self.queue.len()
+	}
+
+	/// Drop queued frames whose TTL has passed.
+	fn expire(&mut self) {
+		if self.queue.iter().any(|(_, _, deadline)| deadline.is_some()) {
+			let now = Instant::now();
+			let len = self.queue.len();
+			self.queue.retain_mut(|(frame, _, deadline)| {
+				let expired = matches!(deadline, Some(deadline) if *deadline <= now);
+				if expired {
+					wipe(&mut frame.0);
 				}
-				let mut len_vec = Vec::with_capacity(USIZE_BINCODE_LEN);
-				bincode::serialize_into::<_, usize>(&mut len_vec, &len).unwrap();
-				vec[..USIZE_BINCODE_LEN].copy_from_slice(&len_vec);
-				self.buffer = Some((vec.into_boxed_slice(), 0));
+				!expired
+			});
+			self.expired += len - self.queue.len();
+		}
+	}
+
+	/// The number of values queued by [`push_or_queue_with_ttl`](Serializer::push_or_queue_with_ttl()) that were dropped unsent as their TTL passed.
+	#[must_use]
+	pub fn expired(&self) -> usize {
+		self.expired
+	}
+
+	/// Set how many values [`push_or_queue`](Serializer::push_or_queue()) queues behind the frame being pulled before returning them. Defaults to 16.
+	pub fn set_queue_limit(&mut self, queue_limit: usize) {
+		self.queue_limit = queue_limit;
+	}
+
+	/// Push a frame whose payload is written by `f`, for example bytes produced by a different encoder. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()), in which case `f` isn't called. [`Some`] contains the result of `f`; if it's an error, no frame is pushed.
+	///
+	/// With the `fringe` feature, whose wire format has no frame header to delimit such a payload, `f` isn't called, and [`Some`] contains an error of kind [`io::ErrorKind::Unsupported`].
+	pub fn push_with<F: FnOnce(&mut dyn Write) -> io::Result<()>>(
+		&mut self, f: F,
+	) -> Option<io::Result<()>> {
+		if self.buffer.is_none() {
+			let mut vec = self.frame_vec();
+			Some(f(&mut vec).map(|()| {
+				self.buffer = Some(self.frame_finish(vec, RAW_FRAME));
+				self.time_stalls();
+			}))
+		} else {
+			None
+		}
+	}
+
+	/// A buffer with space reserved for the frame header.
+	fn frame_vec(&self) -> Vec<u8> {
+		let header_len = self.framing.header_len();
+		let mut vec = vec![0; header_len + 1];
+		let _ = vec.pop().unwrap();
+		vec
+	}
+	/// Serialize `t` to a whole frame, header included.
+	#[cfg_attr(feature = "terse-errors", allow(unused_variables))]
+	fn frame<T: serde::ser::Serialize + 'static>(&self, t: &T) -> (Vec<u8>, usize) {
+		// TODO: reuse vec
+		let mut vec = self.frame_vec();
+		#[cfg(feature = "zeroize")]
+		vec.reserve_exact(usize::try_from(wire::serialized_size(t).unwrap()).unwrap());
+		wire::serialize_into(&mut vec, t).unwrap_or_else(|err| {
+			fail!(
+				"serializing failed",
+				"serializing {}: {}",
+				any::type_name::<T>(),
+				err
+			)
+		});
+		self.frame_finish(vec, any::type_name::<T>())
+	}
+	/// Write the frame header into `vec`, returning it ready to be pulled.
+	fn frame_finish(&self, mut vec: Vec<u8>, type_name: &'static str) -> (Vec<u8>, usize) {
+		let header_len = self.framing.header_len();
+		let mut len = vec.len() - header_len;
+		// zero-length gRPC messages are valid, so needn't be told apart from heartbeats
+		if len == 0 && self.framing != Framing::Grpc {
+			len += 1;
+			vec.push(0);
+		}
+		self.framing
+			.write_header(u64::try_from(len).unwrap(), &mut vec[..header_len]);
+		trace!("serializer: frame start, {=usize} bytes", len);
+		if let Some(telemetry) = self.telemetry {
+			telemetry(type_name, len, Direction::Serialized);
+		}
+		self.aligned(vec)
+	}
+	/// Shrink a frame's bytes, returning them along with the index they start at, which is a multiple of the [alignment](Serializer::set_alignment()).
+	fn aligned(&self, vec: Vec<u8>) -> (Vec<u8>, usize) {
+		let mut boxed = into_boxed(vec);
+		if boxed.as_ptr().align_offset(self.alignment) == 0 {
+			return (boxed.into_vec(), 0);
+		}
+		// truncating rather than shrinking, so the padded allocation isn't moved
+		let mut aligned = vec![0; boxed.len() + self.alignment - 1];
+		let start = aligned.as_ptr().align_offset(self.alignment);
+		aligned.truncate(start + boxed.len());
+		aligned[start..].copy_from_slice(&boxed);
+		wipe(&mut boxed);
+		(aligned, start)
+	}
+
+	/// Set the alignment of the start of frames pushed after this, so that the slices returned by [`pull_buf`](Serializer::pull_buf()) can be handed directly to DMA engines or `io_uring` registered buffers. Defaults to 1.
+	///
+	/// With the `fringe` feature, the slices are cut from its coroutine's blocks as they're generated, so the alignment is checked but has no effect.
+	///
+	/// # Panics
+	///
+	/// Will panic if `alignment` isn't a power of two.
+	#[track_caller]
+	pub fn set_alignment(&mut self, alignment: usize) {
+		ensure!(
+			alignment.is_power_of_two(),
+			"alignment must be a power of two",
+			"alignment must be a power of two, not {}",
+			alignment
+		);
+		self.alignment = alignment;
+	}
+
+	/// Push a heartbeat frame to the Serializer pipe. This is an empty frame that the receiving [`Deserializer`] absorbs, counting it in [`Deserializer::heartbeats()`], so that idle connections can signify liveness. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if called on a Serializer using [`Framing::Grpc`], under which an empty frame is an empty message, or with the `fringe` feature, as that backend's wire format has no frame header to mark one with.
+	#[track_caller]
+	pub fn push_heartbeat(&mut self) -> Option<impl FnOnce() + '_> {
+		ensure!(
+			self.framing != Framing::Grpc,
+			"heartbeats aren't supported with Framing::Grpc",
+			"heartbeats aren't supported with Framing::Grpc, under which an empty frame is an empty message"
+		);
+		if self.buffer.is_none() {
+			Some(move || {
+				trace!("serializer: heartbeat");
+				self.buffer = Some(self.aligned(vec![0; self.framing.header_len()]));
+				self.time_stalls();
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Push a [`Control`] frame to the Serializer pipe. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce(Control)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if called on a Serializer that isn't using [`Framing::Bincode`], or with the `fringe` feature, as that backend's wire format has no frame header to mark one with.
+	#[track_caller]
+	pub fn push_control(&mut self) -> Option<impl FnOnce(Control) + '_> {
+		ensure_eq!(
+			self.framing,
+			Framing::Bincode,
+			"control frames require Framing::Bincode",
+			"control frames require Framing::Bincode, but this Serializer uses {:?}",
+			self.framing
+		);
+		if self.buffer.is_none() {
+			Some(move |control: Control| {
+				trace!("serializer: control frame {}", control);
+				let mut vec = vec![0; self.framing.header_len()];
+				self.framing.write_header(control.into_header(), &mut vec);
+				self.buffer = Some(self.aligned(vec));
+				self.time_stalls();
 			})
 		} else {
 			None
@@ -95,12 +639,9 @@ impl Serializer {
 	pub fn pull<'a>(&'a mut self) -> Option<impl FnOnce() -> u8 + 'a> {
 		if self.buffer.is_some() {
 			Some(move || {
-				let (buffer, index) = self.buffer.as_mut().unwrap();
+				let (buffer, index) = self.buffer.as_ref().unwrap();
 				let ret = buffer[*index];
-				*index += 1;
-				if *index == buffer.len() {
-					self.buffer = None;
-				}
+				self.consume(1);
 				ret
 			})
 		} else {
@@ -108,6 +649,171 @@ impl Serializer {
 		}
 	}
 
+	/// Pull the remaining bytes of the frame being pulled, as a `Vec`. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). [`Some`] contains an `impl FnOnce() -> Vec<u8>` that can be called to perform the `pull`, so that serializing a value to its framed bytes is a `push` and a single `pull_frame`.
+	///
+	/// The frame's buffer is handed over rather than copied.
+	pub fn pull_frame(&mut self) -> Option<impl FnOnce() -> Vec<u8> + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((mut buffer, index)) = self.buffer.take() else {
+					unreachable!()
+				};
+				let _ = buffer.drain(..index);
+				self.frame_end();
+				buffer
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Pull the remaining bytes of the frame being pulled. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). [`Some`] contains an `impl FnOnce() -> &[u8]` that can be called to perform the `pull`, so the bytes can be copied out at once rather than a byte at a time.
+	///
+	/// The bytes returned never span two frames. With the `fringe` feature, at most 4 KiB are returned at once, so its memory use stays bounded.
+	pub fn pull_chunk<'a>(&'a mut self) -> Option<impl FnOnce() -> &'a [u8] + 'a> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((buffer, index)) = &mut self.buffer else {
+					unreachable!()
+				};
+				let start = *index;
+				// keep the frame, as the bytes returned are borrowed from it
+				mem::swap(buffer, &mut self.chunk);
+				self.frame_end();
+				let this = self;
+				&this.chunk[start..]
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Pull pending bytes into `buf`, returning how many were written, which is less than its length only if the Serializer runs out of bytes and is awaiting a [`push`](Serializer::push()). Unlike [`pull_chunk`](Serializer::pull_chunk()), the bytes written can span frames, filling `buf` from those queued by [`push_or_queue`](Serializer::push_or_queue()).
+	pub fn pull_into(&mut self, buf: &mut [u8]) -> usize {
+		let mut pulled = 0;
+		while let Some(bytes) = self.pull_buf() {
+			let len = bytes.len().min(buf.len() - pulled);
+			if len == 0 {
+				break;
+			}
+			buf[pulled..pulled + len].copy_from_slice(&bytes[..len]);
+			pulled += len;
+			self.consume(len);
+		}
+		pulled
+	}
+
+	/// Pull pending bytes into each of `bufs` in turn, as [`Read::read_vectored()`](io::Read::read_vectored()) does, returning how many were written in total. As with [`pull_into`](Serializer::pull_into()), this is less than their total length only if the Serializer runs out of bytes, and the bytes written can span frames.
+	///
+	/// To hand the bytes of the frame being pulled to a transport without copying them, use [`pull_buf`](Serializer::pull_buf()).
+	pub fn pull_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> usize {
+		let mut pulled = 0;
+		for buf in bufs {
+			let len = self.pull_into(buf);
+			pulled += len;
+			if len < buf.len() {
+				break;
+			}
+		}
+		pulled
+	}
+
+	/// The remaining bytes of the frame being pulled, without pulling them. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). A frame's first slice starts at a multiple of the [alignment](Serializer::set_alignment()). Call [`consume`](Serializer::consume()) to mark bytes as pulled.
+	///
+	/// With the `fringe` feature, only the bytes its coroutine has generated so far are returned, up to 64 KiB, and once they're consumed the next call returns those that follow.
+	#[must_use]
+	pub fn pull_buf(&self) -> Option<&[u8]> {
+		self.buffer
+			.as_ref()
+			.map(|(buffer, index)| &buffer[*index..])
+	}
+
+	/// Pull a byte, along with whether it was the last of its frame.
+	pub(crate) fn pull_byte(&mut self) -> Option<(u8, bool)> {
+		let last = self.pull_buf()?.len() == 1;
+		Some((self.pull().unwrap()(), last))
+	}
+
+	/// Write the remaining bytes of the frame being pulled to `writer`, returning how many were written, stopping at the end of the frame rather than continuing into those queued by [`push_or_queue`](Serializer::push_or_queue()). `Ok(0)` denotes that the Serializer is awaiting a [`push`](Serializer::push()).
+	///
+	/// Bytes are only pulled as `writer` accepts them, so after an error the call can be retried, resuming where it left off. With the `fringe` feature, the bytes are instead pulled up to 4 KiB at a time and written with [`write_all`](Write::write_all()), so after an error up to that many may have been pulled but not written.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to `writer` other than [`io::ErrorKind::Interrupted`], which is retried, or [`io::ErrorKind::WriteZero`] if it accepts no bytes. With the `fringe` feature, returns the [`StackOverflow`] as an error of kind [`io::ErrorKind::Other`] if the Serializer is, or becomes, [`poisoned`](Serializer::poisoned()).
+	pub fn drain_to<W: Write>(&mut self, mut writer: W) -> io::Result<usize> {
+		let remaining = self.pull_buf().map_or(0, <[u8]>::len);
+		let mut drained = 0;
+		while drained < remaining {
+			let Some(buf) = self.pull_buf() else {
+				unreachable!()
+			};
+			match writer.write(buf) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(len) => {
+					self.consume(len);
+					drained += len;
+				}
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(drained)
+	}
+
+	/// Mark `amt` bytes returned by [`pull_buf`](Serializer::pull_buf()) as pulled, as if by calling [`pull`](Serializer::pull()) `amt` times.
+	///
+	/// # Panics
+	///
+	/// Will panic if `amt` exceeds the length of the slice returned by [`pull_buf`](Serializer::pull_buf()).
+	#[track_caller]
+	pub fn consume(&mut self, amt: usize) {
+		if amt == 0 {
+			return;
+		}
+		let (buffer, index) = self
+			.buffer
+			.as_mut()
+			.filter(|(buffer, index)| amt <= buffer.len() - index)
+			.expect("consumed more bytes than pull_buf() returned");
+		*index += amt;
+		if *index == buffer.len() {
+			self.frame_end();
+		}
+	}
+
+	/// Note the end of the frame being pulled, and move on to the next queued one, if any.
+	fn frame_end(&mut self) {
+		trace!("serializer: frame end");
+		if let Some((buffer, _)) = &mut self.buffer {
+			wipe(buffer);
+		}
+		self.mark = None;
+		self.expire();
+		self.buffer = self.queue.pop_front().map(|(frame, _, _)| frame);
+		self.time_stalls();
+	}
+
+	/// Remember the position in the frame being pulled, so that [`rewind`](Serializer::rewind()) can return to it, for example to pull the same bytes again if a write of them failed partway. The mark is forgotten as the frame's last byte is pulled, so bytes can be retransmitted up to the end of the frame.
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend doesn't keep the bytes it has generated.
+	pub fn mark(&mut self) {
+		self.mark = self.buffer.as_ref().map(|&(_, index)| index);
+	}
+
+	/// Return to the position last [`mark`](Serializer::mark())ed, so the bytes pulled since are pulled again. Returns `false` if there's no mark, as the frame it was in has ended. Always `false` with the `fringe` feature, as nothing can be marked.
+	pub fn rewind(&mut self) -> bool {
+		match (&mut self.buffer, self.mark) {
+			(Some((_, index)), Some(mark)) => {
+				*index = mark;
+				true
+			}
+			_ => false,
+		}
+	}
+
 	#[doc(hidden)]
 	pub fn empty_avail(&self) -> bool {
 		self.buffer.is_some()
@@ -116,26 +822,108 @@ impl Serializer {
 	pub fn empty<'a>(&'a mut self) -> Option<impl FnOnce() + 'a> {
 		if self.buffer.is_some() {
 			Some(move || {
+				if let Some((buffer, _)) = &mut self.buffer {
+					wipe(buffer);
+				}
 				self.buffer = None;
+				self.mark = None;
+				wipe(&mut self.chunk);
+				for ((frame, _), _, _) in &mut self.queue {
+					wipe(frame);
+				}
+				self.queue.clear();
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
+
+	/// Note a possible transition between awaiting a push and a pull, if stall timing is enabled.
+	#[inline(always)]
+	fn time_stalls(&mut self) {
+		let awaiting_pull = self.pull_avail();
+		if let Some(stalls) = &mut self.stalls {
+			stalls.update(awaiting_pull);
+		}
+	}
+
+	/// Consume this pipe, returning the bytes remaining to be pulled. Unlike dropping, this doesn't panic if it's non-empty, so the unsent tail of a torn-down connection can be kept for retransmission.
+	#[must_use]
+	pub fn into_vec(mut self) -> Vec<u8> {
+		self.expire();
+		let mut ret = Vec::new();
+		for (mut buffer, index) in self
+			.buffer
+			.take()
+			.into_iter()
+			.chain(self.queue.drain(..).map(|(frame, _, _)| frame))
+		{
+			ret.extend_from_slice(&buffer[index..]);
+			wipe(&mut buffer);
+		}
+		ret
+	}
 }
 impl Drop for Serializer {
 	#[inline(always)]
 	fn drop(&mut self) {
-		assert!(self.buffer.is_none());
+		ensure!(
+			self.buffer.is_none() || std::thread::panicking(),
+			"Serializer dropped with a frame unpulled; empty() it first to discard it",
+			"Serializer dropped with {} bytes of a frame unpulled; empty() it first to discard them",
+			self.buffer
+				.as_ref()
+				.map_or(0, |(buffer, index)| buffer.len() - index)
+		);
+		if let Some((buffer, _)) = &mut self.buffer {
+			wipe(buffer);
+		}
+		wipe(&mut self.chunk);
+		for ((frame, _), _, _) in &mut self.queue {
+			wipe(frame);
+		}
 	}
 }
 impl fmt::Debug for Serializer {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("Serializer")
 			.field("buffer", &self.buffer)
+			.field("mark", &self.mark)
+			.field("queue", &self.queue.len())
+			.field("expired", &self.expired)
+			.field("alignment", &self.alignment)
+			.field("framing", &self.framing)
+			.field("telemetry", &self.telemetry)
+			.field("stalls", &self.stalls())
 			.finish()
 	}
 }
+/// The bytes remaining are those of the frame being pulled. Once they've all been advanced past, the next queued frame's, if any, become remaining. With the `fringe` feature they're those of the frame generated so far, which grow a block at a time as they're advanced past.
+#[cfg(feature = "bytes")]
+impl bytes::Buf for Serializer {
+	fn remaining(&self) -> usize {
+		self.pull_buf().map_or(0, <[u8]>::len)
+	}
+	fn chunk(&self) -> &[u8] {
+		self.pull_buf().unwrap_or(&[])
+	}
+	fn advance(&mut self, cnt: usize) {
+		self.consume(cnt);
+	}
+}
+#[cfg(feature = "defmt")]
+impl defmt::Format for Serializer {
+	fn format(&self, f: defmt::Formatter) {
+		defmt::write!(
+			f,
+			"Serializer {{ buffer: {} }}",
+			self.buffer
+				.as_ref()
+				.map(|(buffer, index)| (&**buffer, *index))
+		);
+	}
+}
 
 /// Deserializer pipe: push `u8`; pull `T`.
 ///
@@ -167,51 +955,146 @@ impl fmt::Debug for Serializer {
 ///
 /// # Panics
 ///
-/// Will panic if dropped while non-empty. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Deserializer::empty()`] before dropping it.
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Deserializer::empty()`] before dropping it.
+///
+/// With the `fringe` feature, will also panic if pushed to or pulled from while [`poisoned`](Deserializer::poisoned()).
 pub struct Deserializer {
 	buffer: Vec<u8>,
+	/// The frame, if it was held within a chunk pushed by [`push_chunk`](Deserializer::push_chunk()) rather than copied into `buffer`.
+	#[cfg(feature = "bytes")]
+	held: Option<bytes::Bytes>,
 	len: usize,
+	/// Whether a zero-length frame has been received, which only [`Framing::Grpc`] sends, as `len` alone doesn't tell it apart from awaiting a header.
+	empty_frame: bool,
 	deserializer: Option<TypeId>,
+	render: Option<pretty::Render>,
+	/// The buffered frame, decoded by [`peek`](Deserializer::peek()).
+	peeked: Option<Box<dyn Any + Send + Sync>>,
+	heartbeats: usize,
+	control: Option<Control>,
+	framing: Framing,
+	telemetry: Option<Telemetry>,
+	size_limit: Option<u64>,
+	stalls: Option<StallTimer>,
 }
 impl Deserializer {
 	/// Construct a new Deserializer pipe.
+	///
+	/// This doesn't allocate, and is a `const fn` so it can be used to initialise a `static`.
+	#[must_use]
 	#[inline(always)]
-	pub fn new() -> Self {
+	pub const fn new() -> Self {
+		Self::with_framing(Framing::Bincode)
+	}
+
+	/// Construct a new Deserializer pipe that expects frames delimited with the given [`Framing`].
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend's wire format has no frame header.
+	#[must_use]
+	#[inline(always)]
+	pub const fn with_framing(framing: Framing) -> Self {
 		Self {
-			buffer: Vec::with_capacity(USIZE_BINCODE_LEN + 1),
+			buffer: Vec::new(),
+			#[cfg(feature = "bytes")]
+			held: None,
 			len: 0,
+			empty_frame: false,
 			deserializer: None,
+			render: None,
+			peeked: None,
+			heartbeats: 0,
+			control: None,
+			framing,
+			telemetry: None,
+			size_limit: None,
+			stalls: None,
 		}
 	}
 
+	/// Set a callback to be invoked as each frame is pulled, or [`None`] to clear it. See [`telemetry`](crate::telemetry).
+	pub fn set_telemetry(&mut self, telemetry: Option<Telemetry>) {
+		self.telemetry = telemetry;
+	}
+
+	/// Enable or disable timing how long this pipe spends awaiting a push versus a pull, returned by [`stalls`](Deserializer::stalls()). Enabling it restarts the timing. See [`telemetry`](crate::telemetry).
+	pub fn set_stall_timing(&mut self, enabled: bool) {
+		self.stalls =
+			enabled.then(|| StallTimer::new(self.pull_avail() || self.pull_control_avail()));
+	}
+
+	/// The time spent awaiting a push versus a pull since [`set_stall_timing`](Deserializer::set_stall_timing()) was enabled, or [`None`] if it isn't.
+	pub fn stalls(&self) -> Option<Stalls> {
+		self.stalls.as_ref().map(StallTimer::stalls)
+	}
+
+	/// Limit the bytes each frame may decode to `size_limit`, or [`None`] to clear it. Decoding reads no further than the frame, so a hostile length within it can't trigger an allocation larger than the frame before failing; this limit can tighten that further. A frame whose header claims more than the limit is rejected as soon as the header arrives.
+	pub fn set_size_limit(&mut self, size_limit: Option<u64>) {
+		self.size_limit = size_limit;
+	}
+
+	/// Allocate `count` coroutine stacks of `len` bytes now, touching every page, for use as inner pipelines are created for new types or grown, so that latency-critical code doesn't take page faults mid-frame.
+	///
+	/// Every stack, preallocated or not, has a guard page beneath it, so an overflow faults rather than corrupting memory. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if the stacks can't be allocated.
+	#[allow(clippy::unused_self)]
+	pub fn preallocate_stacks(&mut self, count: usize, len: usize) {
+		let _ = (count, len);
+	}
+
+	/// The overflow, if a frame nested too deeply for its stack, in which case it was abandoned, and this pipe is poisoned until it's [`empty`](Deserializer::empty())ed: [`push`](Deserializer::push()) and [`pull`](Deserializer::pull()) panic with it, [`fill_from`](Deserializer::fill_from()) returns it as an error, and [`push_bytes`](Deserializer::push_bytes()) and [`pull_all`](Deserializer::pull_all()) stop short. Frames are abandoned once they near the end of their stack, so the process isn't aborted by the guard page, provided they read between each level of nesting, as bincode does. Always [`None`] without the `fringe` feature.
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub fn poisoned(&self) -> Option<StackOverflow> {
+		None
+	}
+
+	/// The number of heartbeat frames, pushed by [`Serializer::push_heartbeat()`], that this Deserializer has absorbed. Always zero with the `fringe` feature, as that backend can't send them.
+	#[must_use]
+	pub fn heartbeats(&self) -> usize {
+		self.heartbeats
+	}
+
 	#[doc(hidden)]
 	pub fn pull_avail(&self) -> bool {
-		self.len != 0 && self.buffer.len() == self.len
+		(self.len != 0 || self.empty_frame) && self.frame().len() == self.len
 	}
 	/// Pull a `T` from the Deserializer pipe. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
 	///
 	/// Note that [`push`](Deserializer::push()) will return [`None`] until [`pull`](Deserializer::pull()) has been called, as it's necessary to supply the type of the value being seserialized.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
 	pub fn pull<'a, T: serde::de::DeserializeOwned + 'static>(
 		&'a mut self,
 	) -> Option<impl FnOnce() -> T + 'a> {
 		let deserializer = TypeId::of::<T>();
 		if self.deserializer.is_none() {
 			self.deserializer = Some(deserializer);
+			self.render = Some(pretty::render::<T>);
 		}
-		assert_eq!(self.deserializer.unwrap(), deserializer);
-		if self.len != 0 && self.buffer.len() == self.len {
+		ensure_eq!(
+			self.deserializer.unwrap(),
+			deserializer,
+			"pull() called on a Deserializer awaiting a different type",
+			"pull::<{}>() called on a Deserializer awaiting a different type ({} of {} bytes buffered)",
+			any::type_name::<T>(),
+			self.frame().len(),
+			self.len
+		);
+		if self.pull_avail() {
 			Some(move || {
-				let mut counter = ReadCounter::new(&*self.buffer);
-				let ret = bincode::deserialize_from(&mut counter).unwrap();
-				let mut len = counter.count();
-				if len == 0 {
-					len += 1;
-					assert_eq!(self.buffer[0], 0);
-				}
-				assert_eq!(len, self.len);
-				self.len = 0;
-				self.deserializer = None;
-				self.buffer.clear();
+				let ret = match self.peeked.take() {
+					Some(peeked) => *peeked.downcast().unwrap(),
+					None => self.decode(None).unwrap(),
+				};
+				self.frame_end(any::type_name::<T>());
 				ret
 			})
 		} else {
@@ -219,58 +1102,471 @@ impl Deserializer {
 		}
 	}
 
+	/// Pull a `T` from the Deserializer pipe into an existing `T`, so that allocations it holds, like the buffers of `Vec`s and `String`s, are reused rather than reallocated. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce(&mut T)` that can be called to perform the `pull`.
+	///
+	/// This uses serde's [`deserialize_in_place`](serde::Deserialize::deserialize_in_place()), which falls back to overwriting the place with a newly deserialized value for types that don't implement it. Types deriving `Deserialize` only implement it if `serde_derive`'s `deserialize_in_place` feature is enabled.
+	///
+	/// Like [`pull`](Deserializer::pull()), this needs to have been called for [`push`](Deserializer::push()) to return [`Some`].
+	///
+	/// With the `fringe` feature the place is always overwritten with a newly deserialized value, as that backend decodes each frame as it's pushed, before the `T` to decode into is supplied.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull_in_place<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce(&mut T) + '_> {
+		let _ = self.pull::<T>()?;
+		Some(move |place: &mut T| {
+			match self.peeked.take() {
+				Some(peeked) => *place = *peeked.downcast().unwrap(),
+				None => {
+					let _ = self.decode(Some(place));
+				}
+			}
+			self.frame_end(any::type_name::<T>());
+		})
+	}
+
+	/// Reset for the next frame, having pulled one of `type_name`.
+	fn frame_end(&mut self, type_name: &'static str) {
+		trace!("deserializer: frame end");
+		if let Some(telemetry) = self.telemetry {
+			telemetry(type_name, self.len, Direction::Deserialized);
+		}
+		self.len = 0;
+		self.empty_frame = false;
+		self.deserializer = None;
+		self.render = None;
+		self.clear();
+		self.time_stalls();
+	}
+
+	/// The bytes of the frame received so far.
+	fn frame(&self) -> &[u8] {
+		#[cfg(feature = "bytes")]
+		if let Some(held) = &self.held {
+			return held;
+		}
+		&self.buffer
+	}
+
+	/// Discard the bytes of the frame received so far.
+	fn clear(&mut self) {
+		wipe(&mut self.buffer);
+		self.buffer.clear();
+		#[cfg(feature = "bytes")]
+		{
+			self.held = None;
+		}
+	}
+
+	/// Decode the buffered frame as a `T` without pulling it, so its contents can be inspected before committing to take it. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). The value is kept, and returned by the next [`pull`](Deserializer::pull()) rather than decoded again.
+	///
+	/// Like [`pull`](Deserializer::pull()), this supplies the type of the value being deserialized, so [`push`](Deserializer::push()) returns [`Some`] once it has been called.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame, or if the frame doesn't decode as a `T`.
+	#[track_caller]
+	pub fn peek<T: serde::de::DeserializeOwned + Send + Sync + 'static>(&mut self) -> Option<&T> {
+		let _ = self.pull::<T>()?;
+		if self.peeked.is_none() {
+			self.peeked = Some(Box::new(self.decode::<T>(None).unwrap()));
+		}
+		self.peeked.as_ref().unwrap().downcast_ref()
+	}
+
+	/// Push `bytes`, pulling each `T` they complete, so that a chunk holding several frames can be decoded in one call rather than alternating between [`push`](Deserializer::push()) and [`pull`](Deserializer::pull()) for each. Returns the values pulled, and how many of `bytes` were accepted, which is fewer than all of them only if the Deserializer stops accepting pushes, as a control frame awaits [`pull_control`](Deserializer::pull_control()), or, with the `fringe` feature, it's [`poisoned`](Deserializer::poisoned()). A frame left incomplete at the end of `bytes` stays buffered, to be completed by those that follow.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull_all<T: serde::de::DeserializeOwned + 'static>(
+		&mut self, bytes: &[u8],
+	) -> (Vec<T>, usize) {
+		let (mut values, mut pushed) = (Vec::new(), 0);
+		loop {
+			// pulling again supplies the type of the next frame
+			if let Some(pull) = self.pull::<T>() {
+				values.push(pull());
+				continue;
+			}
+			let len = self.push_bytes(&bytes[pushed..]);
+			if len == 0 {
+				break;
+			}
+			pushed += len;
+		}
+		(values, pushed)
+	}
+
+	/// Decode the buffered frame as a `T`, into `place` if given, else returning it.
+	#[track_caller]
+	#[cfg_attr(feature = "terse-errors", allow(unused_variables))]
+	fn decode<T: serde::de::DeserializeOwned + 'static>(&self, place: Option<&mut T>) -> Option<T> {
+		let mut counter = ReadCounter::new(self.frame());
+		// lengths within the frame can't exceed it, so reject them before allocating
+		let limit = self
+			.size_limit
+			.map_or(self.len as u64, |limit| limit.min(self.len as u64));
+		let options = wire::options().with_limit(limit);
+		let mut deserializer = bincode::Deserializer::with_reader(&mut counter, options);
+		let ret = match place {
+			Some(place) => T::deserialize_in_place(&mut deserializer, place).map(|()| None),
+			None => T::deserialize(&mut deserializer).map(Some),
+		};
+		#[cfg(not(feature = "terse-errors"))]
+		let error = |offset| FrameError {
+			type_name: any::type_name::<T>(),
+			frame: self.frame(),
+			offset,
+		};
+		let ret = ret.unwrap_or_else(|err| {
+			// the byte at fault is the last one read, if any were
+			fail!(
+				"deserializing failed",
+				"{}: {}",
+				error(counter.count().saturating_sub(1)),
+				err
+			)
+		});
+		let mut len = counter.count();
+		if len == 0 && !self.empty_frame {
+			len += 1;
+			ensure_eq!(
+				self.frame()[0],
+				0,
+				"deserializing failed: expected padding",
+				"{}: expected padding",
+				error(0)
+			);
+		}
+		ensure_eq!(
+			len,
+			self.len,
+			"deserializing failed: trailing bytes",
+			"{}: trailing bytes",
+			error(len)
+		);
+		ret
+	}
+
+	/// Pull an `A` or a `B` pushed with [`push_tagged`](Serializer::push_tagged()). [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce() -> Either<A, B>` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `A` and `B` differ from the types supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull_either<
+		A: serde::de::DeserializeOwned + 'static,
+		B: serde::de::DeserializeOwned + 'static,
+	>(
+		&mut self,
+	) -> Option<impl FnOnce() -> Either<A, B> + '_> {
+		self.pull()
+	}
+
+	/// Pull a frame, decoding its payload with `f`, for example with a different decoder. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()), in which case `f` isn't called. [`Some`] contains the result of `f`.
+	///
+	/// `f` is handed a reader scoped to exactly one frame. The whole frame is consumed regardless of how much of it `f` reads, or whether it returns an error, so the stream stays aligned.
+	///
+	/// Like [`pull`](Deserializer::pull()), this needs to have been called for [`push`](Deserializer::push()) to return [`Some`].
+	///
+	/// With the `fringe` feature, whose wire format has no frame header to delimit such a payload, `f` isn't called, and [`Some`] contains an error of kind [`io::ErrorKind::Unsupported`].
+	pub fn pull_with<R, F: FnOnce(&mut dyn Read) -> io::Result<R>>(
+		&mut self, f: F,
+	) -> Option<io::Result<R>> {
+		if self.deserializer.is_none() {
+			self.deserializer = Some(TypeId::of::<RawFrame>());
+		}
+		if self.pull_avail() {
+			let ret = f(&mut self.frame());
+			self.peeked = None;
+			self.frame_end(RAW_FRAME);
+			Some(ret)
+		} else {
+			None
+		}
+	}
+
+	/// Render the buffered frame as JSON-ish text, for logging. [`None`] denotes that no complete frame is buffered, or that its type hasn't been supplied by a [`pull`](Deserializer::pull()), or that it doesn't decode as that type.
+	///
+	/// bincode isn't self-describing, so the frame is decoded as the type supplied, recording the values, field names and variant names it yields. The frame isn't consumed. The alternate [`Debug`](fmt::Debug) format, `{:#?}`, shows this in place of the raw bytes.
+	///
+	/// Always [`None`] with the `fringe` feature, as that backend decodes frames as their bytes arrive rather than buffering them.
+	#[must_use]
+	pub fn dump(&self) -> Option<String> {
+		if self.pull_avail() {
+			self.render.and_then(|render| render(self.frame()))
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_control_avail(&self) -> bool {
+		self.control.is_some()
+	}
+	/// Pull a [`Control`] frame from the Deserializer pipe. [`None`] denotes that the next frame isn't a control frame, or hasn't yet been pushed. [`Some`] contains an `impl FnOnce() -> Control` that can be called to perform the `pull`.
+	///
+	/// [`push`](Deserializer::push()) will return [`None`] while a received control frame is awaiting this pull. Always [`None`] with the `fringe` feature, as that backend can't send them.
+	pub fn pull_control(&mut self) -> Option<impl FnOnce() -> Control + '_> {
+		let control = self.control?;
+		Some(move || {
+			self.control = None;
+			self.time_stalls();
+			control
+		})
+	}
+
 	#[doc(hidden)]
 	pub fn push_avail(&self) -> bool {
-		self.deserializer.is_some() && (self.buffer.len() != self.len || self.len == 0)
+		self.deserializer.is_some()
+			&& self.control.is_none()
+			&& !self.empty_frame
+			&& (self.frame().len() != self.len || self.len == 0)
 	}
 	/// Push a `u8` to the Deserializer pipe. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](Deserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
 	///
 	/// Note that [`push`](Deserializer::push()) will return [`None`] until [`pull`](Deserializer::pull()) has been called, as it's necessary to supply the type of the value being seserialized.
 	pub fn push<'a>(&'a mut self) -> Option<impl FnOnce(u8) + 'a> {
-		if self.deserializer.is_some() && (self.buffer.len() != self.len || self.len == 0) {
+		if self.push_avail() {
 			Some(move |x| {
 				self.buffer.push(x);
-				if self.len == 0 && self.buffer.len() == USIZE_BINCODE_LEN {
-					let mut counter = ReadCounter::new(&*self.buffer);
-					self.len = bincode::deserialize_from::<_, usize>(&mut counter).unwrap();
-					assert_eq!(counter.count(), USIZE_BINCODE_LEN);
+				if self.len == 0 && self.buffer.len() == self.framing.header_len() {
+					let header = self.framing.read_header(&self.buffer);
+					if header & CONTROL_BIT != 0 {
+						let control = Control::from_header(header);
+						trace!("deserializer: control frame {}", control);
+						self.control = Some(control);
+					} else {
+						// reject a hostile length before it's trusted
+						if let Some(limit) = self.size_limit {
+							ensure!(
+								header <= limit,
+								"deserializing failed",
+								"deserializing failed: frame of {} bytes: the size limit has been reached",
+								header
+							);
+						}
+						self.len = usize::try_from(header).unwrap();
+					}
+					if header == 0 && self.framing == Framing::Grpc {
+						trace!("deserializer: frame start, 0 bytes");
+						self.empty_frame = true;
+					} else if header == 0 {
+						trace!("deserializer: heartbeat");
+						self.heartbeats += 1;
+					} else if self.len != 0 {
+						trace!("deserializer: frame start, {=usize} bytes", self.len);
+					}
 					self.buffer.clear();
-					self.buffer.reserve(self.len);
+					// the length is untrusted, so grow into it as bytes arrive
+					self.buffer.reserve(self.len.min(RESERVE_LEN));
 				}
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
 
+	/// Push as many of `bytes` as the Deserializer pipe accepts before it awaits a [`pull`](Deserializer::pull()), returning how many it accepted. This is equivalent to calling [`push`](Deserializer::push()) for each byte until it returns [`None`], so the bytes accepted never extend beyond the end of a frame, and the rest can be pushed once it's pulled. With the `fringe` feature, it also stops once the Deserializer is [`poisoned`](Deserializer::poisoned()).
+	///
+	/// Each frame's payload is copied in at once. With the `fringe` feature the bytes are still handed to the decoder one at a time, as it decodes them as they arrive.
+	pub fn push_bytes(&mut self, bytes: &[u8]) -> usize {
+		let mut pushed = 0;
+		while pushed < bytes.len() && self.push_avail() {
+			if self.len == 0 {
+				// the header, a byte at a time
+				let Some(push) = self.push() else {
+					unreachable!()
+				};
+				push(bytes[pushed]);
+				pushed += 1;
+			} else {
+				let len = (self.len - self.buffer.len()).min(bytes.len() - pushed);
+				self.buffer.extend_from_slice(&bytes[pushed..pushed + len]);
+				pushed += len;
+				self.time_stalls();
+			}
+		}
+		pushed
+	}
+
+	/// Read bytes from `reader` into the Deserializer pipe until it awaits a [`pull`](Deserializer::pull()), `reader` ends, or a read would block, returning how many were read and whether a value can now be pulled. Bytes are never read beyond the end of a frame, so those of the next frame are left in `reader` for the next call.
+	///
+	/// As with [`push`](Deserializer::push()), [`pull`](Deserializer::pull()) must have been called first, to supply the type of the value being deserialized. With the `fringe` feature the length of a frame isn't known until it's decoded, so bytes are read one at a time; wrap an unbuffered `reader` in a [`BufReader`](io::BufReader) to avoid a system call per byte.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading from `reader` other than [`io::ErrorKind::WouldBlock`], which ends the fill, or [`io::ErrorKind::Interrupted`], which is retried. With the `fringe` feature, returns the [`StackOverflow`] as an error of kind [`io::ErrorKind::Other`] if the Deserializer is, or becomes, [`poisoned`](Deserializer::poisoned()).
+	pub fn fill_from<R: Read>(&mut self, mut reader: R) -> io::Result<(usize, bool)> {
+		let mut buf = [0; FILL_LEN];
+		let mut filled = 0;
+		while self.push_avail() {
+			// the rest of the header, or of the payload
+			let end = if self.len == 0 {
+				self.framing.header_len()
+			} else {
+				self.len
+			};
+			let wanted = (end - self.frame().len()).min(FILL_LEN);
+			match reader.read(&mut buf[..wanted]) {
+				Ok(0) => break,
+				Ok(len) => filled += self.push_bytes(&buf[..len]),
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) => return Err(err),
+			}
+		}
+		Ok((filled, self.pull_avail()))
+	}
+
+	/// Push as much of `chunk` as [`push_bytes`](Deserializer::push_bytes()) would, returning the rest. A frame whose bytes lie wholly within `chunk` is held as a slice of it rather than copied, so frames arriving in [`Bytes`](bytes::Bytes) from the network are decoded straight out of them. Frames straddling chunks are stitched together by copying, as with [`push_bytes`](Deserializer::push_bytes()).
+	///
+	/// A held frame shares the chunk's memory, so it isn't wiped with the `zeroize` feature.
+	///
+	/// Requires the `bytes` feature. With the `fringe` feature every byte is copied, as that backend has no frame buffers to hold a slice in.
+	#[cfg(feature = "bytes")]
+	pub fn push_chunk(&mut self, mut chunk: bytes::Bytes) -> bytes::Bytes {
+		use bytes::Buf;
+		while !chunk.is_empty() && self.push_avail() {
+			if self.len == 0 {
+				// the header, a byte at a time
+				let Some(push) = self.push() else {
+					unreachable!()
+				};
+				push(chunk[0]);
+				chunk.advance(1);
+			} else if self.buffer.is_empty() && chunk.len() >= self.len {
+				self.held = Some(chunk.split_to(self.len));
+				self.time_stalls();
+			} else {
+				let len = self.push_bytes(&chunk[..chunk.len().min(self.len - self.buffer.len())]);
+				chunk.advance(len);
+			}
+		}
+		chunk
+	}
+
 	#[doc(hidden)]
 	pub fn empty_avail(&self) -> bool {
-		!self.buffer.is_empty() || self.len != 0
+		!self.frame().is_empty() || self.len != 0 || self.empty_frame || self.control.is_some()
 	}
 	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
 	pub fn empty<'a>(&'a mut self) -> Option<impl FnOnce() + 'a> {
-		if !self.buffer.is_empty() || self.len != 0 {
+		if self.empty_avail() {
 			Some(move || {
-				self.buffer.clear();
+				self.clear();
 				self.len = 0;
+				self.empty_frame = false;
+				self.peeked = None;
+				self.control = None;
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
+
+	/// Note a possible transition between awaiting a push and a pull, if stall timing is enabled.
+	#[inline(always)]
+	fn time_stalls(&mut self) {
+		let awaiting_pull = self.pull_avail() || self.pull_control_avail();
+		if let Some(stalls) = &mut self.stalls {
+			stalls.update(awaiting_pull);
+		}
+	}
+
+	/// Consume this pipe, returning the bytes that have been pushed but not yet pulled, as they were on the wire, and whether they end partway through a frame. Unlike dropping, this doesn't panic if it's non-empty, so a connection can be handed off along with the bytes sitting in the pipe.
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature if a frame has been partly or wholly pushed but not pulled, as that backend decodes bytes as they arrive rather than retaining them.
+	#[must_use]
+	pub fn into_remaining(mut self) -> (Vec<u8>, bool) {
+		let header_len = self.framing.header_len();
+		#[cfg(feature = "bytes")]
+		if let Some(held) = self.held.take() {
+			self.buffer = held.to_vec();
+		}
+		let mut buffer = mem::take(&mut self.buffer);
+		let header = if let Some(control) = self.control.take() {
+			Some(control.into_header())
+		} else if self.len != 0 || self.empty_frame {
+			Some(u64::try_from(self.len).unwrap())
+		} else {
+			None
+		};
+		let len = mem::replace(&mut self.len, 0);
+		self.empty_frame = false;
+		if let Some(header) = header {
+			let mut vec = vec![0; header_len];
+			self.framing.write_header(header, &mut vec);
+			vec.extend(&buffer);
+			wipe(&mut buffer);
+			(vec, buffer.len() != len)
+		} else {
+			let partial = !buffer.is_empty();
+			(buffer, partial)
+		}
+	}
 }
 impl Drop for Deserializer {
 	#[inline(always)]
 	fn drop(&mut self) {
-		assert!(self.buffer.is_empty() && self.len == 0);
+		ensure!(
+			(self.frame().is_empty() && self.len == 0 && !self.empty_frame && self.control.is_none()) || std::thread::panicking(),
+			"Deserializer dropped with a partial or unpulled frame; empty() it first to discard it",
+			"Deserializer dropped with a partial or unpulled frame ({} of {} bytes, control frame {:?}); empty() it first to discard it",
+			self.frame().len(),
+			self.len,
+			self.control
+		);
+		wipe(&mut self.buffer);
 	}
 }
 impl fmt::Debug for Deserializer {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if f.alternate() {
+			if let Some(frame) = self.dump() {
+				return f
+					.debug_struct("Deserializer")
+					.field("frame", &format_args!("{frame}"))
+					.field("heartbeats", &self.heartbeats)
+					.field("framing", &self.framing)
+					.finish();
+			}
+		}
 		f.debug_struct("Deserializer")
-			.field("buffer", &self.buffer)
+			.field("buffer", &self.frame())
 			.field("len", &self.len)
+			.field("empty_frame", &self.empty_frame)
 			.field("deserializer", &self.deserializer)
+			.field("peeked", &self.peeked.is_some())
+			.field("heartbeats", &self.heartbeats)
+			.field("control", &self.control)
+			.field("framing", &self.framing)
+			.field("telemetry", &self.telemetry)
+			.field("size_limit", &self.size_limit)
+			.field("stalls", &self.stalls())
 			.finish()
 	}
 }
+#[cfg(feature = "defmt")]
+impl defmt::Format for Deserializer {
+	fn format(&self, f: defmt::Formatter) {
+		defmt::write!(
+			f,
+			"Deserializer {{ buffer: {=[u8]}, len: {=usize}, deserializer: {=bool} }}",
+			self.frame(),
+			self.len,
+			self.deserializer.is_some()
+		);
+	}
+}
@@ -0,0 +1,275 @@
+//! Replacing frames identical to a recently sent one with a short back-reference, for workloads that repeatedly send the same configuration or state objects.
+//!
+//! [`CacheSerializer`] hashes each value pushed, and remembers the last `capacity` distinct values sent, of any type, in a ring of slots. If a value is the same as one still in the ring, it sends the index of its slot in its place. [`CacheDeserializer`] keeps the same ring, filling and overwriting slots in the same order, and returns the value in the slot again on receiving a back-reference, so needs `T: Clone`. Both ends must be created with the same capacity.
+//!
+//! Each value is sent as an [`Either<T, u32>`](Either), with [`Right`](Either::Right) as the back-reference, so a plain [`Deserializer`] can also receive them by pulling `Either<T, u32>`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{cache::{CacheDeserializer, CacheSerializer}, serialize_to_vec, Deserializer, Either, Serializer};
+//!
+//! let mut serializer = CacheSerializer::new(Serializer::new(), 16);
+//! let mut deserializer = CacheDeserializer::new(Deserializer::new(), 16);
+//! let mut bytes = 0;
+//! for config in &["mode=fast", "mode=safe", "mode=fast", "mode=safe"] {
+//!     serializer.push().unwrap()(String::from(*config));
+//!     let _ = deserializer.pull::<String>();
+//!     while let Some(pull) = serializer.pull() {
+//!         deserializer.push().unwrap()(pull());
+//!         bytes += 1;
+//!     }
+//!     assert_eq!(deserializer.pull::<String>().unwrap()(), *config);
+//! }
+//! // each config once, then two back-references
+//! let config = serialize_to_vec(Either::<String, u32>::Left(String::from("mode=fast")));
+//! let back_reference = serialize_to_vec(Either::<String, u32>::Right(0));
+//! assert_eq!(bytes, 2 * config.len() + 2 * back_reference.len());
+//! assert_eq!(serializer.hits(), 2);
+//! ```
+
+use crate::{wire, Deserializer, Either, Serializer};
+use std::{
+	any::{self, Any, TypeId}, collections::{hash_map::DefaultHasher, HashMap}, convert::TryFrom, fmt, hash::Hasher, io
+};
+
+/// A [`Serializer`] that sends a back-reference in place of a value equal to one recently sent.
+pub struct CacheSerializer {
+	serializer: Serializer,
+	/// The type and hash of the value in each slot.
+	slots: Vec<Option<(TypeId, u64)>>,
+	/// The slot holding each type and hash.
+	index: HashMap<(TypeId, u64), u32>,
+	/// The slot the next value missing from the cache is put in.
+	next: usize,
+	hits: u64,
+}
+impl CacheSerializer {
+	/// Wrap `serializer`, remembering the last `capacity` distinct values sent.
+	///
+	/// # Panics
+	///
+	/// Will panic if `capacity` is zero or doesn't fit in a `u32`.
+	#[must_use]
+	pub fn new(serializer: Serializer, capacity: usize) -> Self {
+		assert!(
+			capacity != 0 && u32::try_from(capacity).is_ok(),
+			"capacity must be between 1 and u32::MAX"
+		);
+		Self {
+			serializer,
+			slots: vec![None; capacity],
+			index: HashMap::new(),
+			next: 0,
+			hits: 0,
+		}
+	}
+
+	/// The number of values sent as a back-reference.
+	#[must_use]
+	pub fn hits(&self) -> u64 {
+		self.hits
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](CacheSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				let mut hasher = HashWriter(DefaultHasher::new());
+				wire::serialize_into(&mut hasher, &t).unwrap();
+				let key = (TypeId::of::<T>(), hasher.0.finish());
+				if let Some(&slot) = self.index.get(&key) {
+					self.hits += 1;
+					self.serializer.push().unwrap()(Either::<T, u32>::Right(slot));
+				} else {
+					if let Some(evicted) = self.slots[self.next].replace(key) {
+						let _ = self.index.remove(&evicted);
+					}
+					let _ = self.index.insert(key, u32::try_from(self.next).unwrap());
+					self.next = (self.next + 1) % self.slots.len();
+					self.serializer.push().unwrap()(Either::<T, u32>::Left(t));
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](CacheSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the underlying pipe, and forget the values sent, so that the next of each is sent in full. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		let (slots, index, next) = (&mut self.slots, &mut self.index, &mut self.next);
+		self.serializer.empty().map(move |empty| {
+			move || {
+				slots.fill(None);
+				index.clear();
+				*next = 0;
+				empty();
+			}
+		})
+	}
+
+	/// Unwrap, returning the underlying Serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl fmt::Debug for CacheSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CacheSerializer")
+			.field("serializer", &self.serializer)
+			.field("capacity", &self.slots.len())
+			.field("cached", &self.index.len())
+			.field("hits", &self.hits)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A [`Deserializer`] that receives the values sent by a [`CacheSerializer`], returning a recently received value again in place of a back-reference to it.
+pub struct CacheDeserializer {
+	deserializer: Deserializer,
+	/// The value in each slot.
+	slots: Vec<Option<Box<dyn Any + Send>>>,
+	/// The slot the next value received in full is put in.
+	next: usize,
+}
+impl CacheDeserializer {
+	/// Wrap `deserializer`, remembering the last `capacity` distinct values received. This must match the capacity of the [`CacheSerializer`].
+	///
+	/// # Panics
+	///
+	/// Will panic if `capacity` is zero or doesn't fit in a `u32`.
+	#[must_use]
+	pub fn new(deserializer: Deserializer, capacity: usize) -> Self {
+		assert!(
+			capacity != 0 && u32::try_from(capacity).is_ok(),
+			"capacity must be between 1 and u32::MAX"
+		);
+		Self {
+			deserializer,
+			slots: (0..capacity).map(|_| None).collect(),
+			next: 0,
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](CacheDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame, or if a back-reference is received to a slot that doesn't hold a `T`, which signifies mismatched capacities.
+	#[track_caller]
+	pub fn pull<T: serde::de::DeserializeOwned + Clone + Send + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		let (slots, next) = (&mut self.slots, &mut self.next);
+		self.deserializer.pull::<Either<T, u32>>().map(move |pull| {
+			move || match pull() {
+				Either::Left(t) => {
+					slots[*next] = Some(Box::new(t.clone()));
+					*next = (*next + 1) % slots.len();
+					t
+				}
+				Either::Right(slot) => slots
+					.get(usize::try_from(slot).unwrap())
+					.and_then(Option::as_ref)
+					.and_then(|t| t.downcast_ref::<T>())
+					.unwrap_or_else(|| {
+						panic!(
+							"received a back-reference to slot {} that doesn't hold a {}",
+							slot,
+							any::type_name::<T>()
+						)
+					})
+					.clone(),
+			}
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](CacheDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		self.deserializer.push()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.deserializer.empty_avail()
+	}
+	/// Empty the underlying pipe, and forget the values received, as the [`CacheSerializer`] should also be emptied. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		let (slots, next) = (&mut self.slots, &mut self.next);
+		self.deserializer.empty().map(move |empty| {
+			move || {
+				for slot in slots.iter_mut() {
+					*slot = None;
+				}
+				*next = 0;
+				empty();
+			}
+		})
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
+impl fmt::Debug for CacheDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CacheDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("capacity", &self.slots.len())
+			.field(
+				"cached",
+				&self.slots.iter().filter(|slot| slot.is_some()).count(),
+			)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Feeds the bytes written to a hasher.
+struct HashWriter(DefaultHasher);
+impl io::Write for HashWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.write(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
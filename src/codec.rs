@@ -0,0 +1,505 @@
+//! Composing compression, checksum and encryption layers in a defined order.
+//!
+//! Stacking wrapper pipes by hand, the two ends must wrap in mirror-image order, and get it wrong silently. Instead [`Layers`] is a builder of the layers wanted, which are always applied in the same order regardless of the order they're added in: each value is serialized, then [compressed](Layers::compress()), then [checksummed](Layers::checksum()), then [encrypted](Layers::encrypt()), and the receiving end reverses this. The same builder then makes either end, with [`serializer`](Layers::serializer()) or [`deserializer`](Layers::deserializer()), so building both from one shared function yields matched configurations.
+//!
+//! Compression and checksums are pluggable, with the [`Compressor`] and [`Checksum`] traits, as encryption is with [`Cipher`]; [`Crc32`] is provided.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{codec::{Crc32, Layers}, crypto::Cipher, Deserializer, Serializer};
+//!
+//! /// For illustration only: not secure. Use a real AEAD.
+//! struct Xor(u8);
+//! impl Cipher for Xor {
+//!     fn seal(&self, nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) {
+//!         buffer.iter_mut().for_each(|byte| *byte ^= self.0 ^ nonce[0]);
+//!     }
+//!     fn open(&self, nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+//!         buffer.iter_mut().for_each(|byte| *byte ^= self.0 ^ nonce[0]);
+//!         true
+//!     }
+//! }
+//!
+//! // the order the layers are added in doesn't matter
+//! let layers = || Layers::new().encrypt(1, Xor(0x5a)).checksum(Crc32);
+//! let mut serializer = layers().serializer(Serializer::new());
+//! let mut deserializer = layers().deserializer(Deserializer::new());
+//! serializer.push().unwrap()(String::from("hello"));
+//! while let Some(pull) = serializer.pull() {
+//!     deserializer.push().unwrap()(pull());
+//! }
+//! assert_eq!(deserializer.pull::<String>().unwrap()().unwrap(), "hello");
+//! ```
+
+use crate::{
+	crypto::{Cipher, OpenError, OpeningDeserializer, SealingSerializer}, wire, Deserializer, Serializer
+};
+use std::{convert::TryFrom, error, fmt, time::Duration};
+
+/// How long an [`OpeningDeserializer`] keeps superseded keys by default.
+const GRACE: Duration = Duration::from_secs(10);
+
+/// A lossless compression codec.
+pub trait Compressor {
+	/// Compress `bytes`.
+	fn compress(&self, bytes: &[u8]) -> Vec<u8>;
+	/// Decompress `bytes`, returning [`None`] if they're invalid.
+	fn decompress(&self, bytes: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// A checksum, for detecting corruption.
+pub trait Checksum {
+	/// The checksum of `bytes`.
+	fn checksum(&self, bytes: &[u8]) -> u32;
+}
+
+/// The CRC-32 used by Ethernet, zlib and PNG, among others.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Crc32;
+impl Checksum for Crc32 {
+	fn checksum(&self, bytes: &[u8]) -> u32 {
+		let mut crc = !0_u32;
+		for &byte in bytes {
+			crc ^= u32::from(byte);
+			for _ in 0..8 {
+				crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+			}
+		}
+		!crc
+	}
+}
+
+/// A builder of the layers to apply to each value.
+#[derive(Default)]
+pub struct Layers {
+	compressor: Option<Box<dyn Compressor + Send>>,
+	checksum: Option<Box<dyn Checksum + Send>>,
+	cipher: Option<(u32, Box<dyn Cipher + Send>)>,
+	grace: Option<Duration>,
+}
+impl Layers {
+	/// No layers: values are sent as serialized.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Compress each value, once serialized, with `compressor`, replacing any compressor already added.
+	#[must_use]
+	pub fn compress<C: Compressor + Send + 'static>(mut self, compressor: C) -> Self {
+		self.compressor = Some(Box::new(compressor));
+		self
+	}
+
+	/// Append a checksum of each value, once compressed, with `checksum`, replacing any checksum already added.
+	#[must_use]
+	pub fn checksum<C: Checksum + Send + 'static>(mut self, checksum: C) -> Self {
+		self.checksum = Some(Box::new(checksum));
+		self
+	}
+
+	/// Encrypt each value, once checksummed, with `cipher`, identified by `key_id`, as a [`SealingSerializer`] does, replacing any cipher already added. The receiving end can add further keys to rotate to with [`CodecDeserializer::add_key()`].
+	#[must_use]
+	pub fn encrypt<C: Cipher + Send + 'static>(mut self, key_id: u32, cipher: C) -> Self {
+		self.cipher = Some((key_id, Box::new(cipher)));
+		self
+	}
+
+	/// Keep superseded keys for `grace` at the receiving end, as an [`OpeningDeserializer`] does. Defaults to 10 seconds.
+	#[must_use]
+	pub fn grace(mut self, grace: Duration) -> Self {
+		self.grace = Some(grace);
+		self
+	}
+
+	/// Make the sending end, wrapping `serializer`.
+	#[must_use]
+	pub fn serializer(self, serializer: Serializer) -> CodecSerializer {
+		CodecSerializer {
+			serializer: match self.cipher {
+				Some((key_id, cipher)) => {
+					Inner::Sealed(SealingSerializer::new(serializer, key_id, cipher))
+				}
+				None => Inner::Plain(serializer),
+			},
+			compressor: self.compressor,
+			checksum: self.checksum,
+		}
+	}
+
+	/// Make the receiving end, wrapping `deserializer`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `deserializer` is part way through a frame of another type.
+	#[must_use]
+	pub fn deserializer(self, mut deserializer: Deserializer) -> CodecDeserializer {
+		CodecDeserializer {
+			deserializer: if let Some((key_id, cipher)) = self.cipher {
+				let mut deserializer =
+					OpeningDeserializer::new(deserializer, self.grace.unwrap_or(GRACE));
+				deserializer.add_key(key_id, cipher);
+				Inner::Sealed(deserializer)
+			} else {
+				let _ = deserializer.pull::<Vec<u8>>();
+				Inner::Plain(deserializer)
+			},
+			compressor: self.compressor,
+			checksum: self.checksum,
+		}
+	}
+}
+impl fmt::Debug for Layers {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Layers")
+			.field("compress", &self.compressor.is_some())
+			.field("checksum", &self.checksum.is_some())
+			.field("encrypt", &self.cipher.as_ref().map(|(key_id, _)| key_id))
+			.field("grace", &self.grace)
+			.finish()
+	}
+}
+
+/// The innermost pipe, encrypting or not.
+#[derive(Debug)]
+enum Inner<P, S> {
+	Plain(P),
+	Sealed(S),
+}
+
+/// A [`Serializer`] that applies the [`Layers`] it was made with to each value pushed.
+pub struct CodecSerializer {
+	serializer: Inner<Serializer, SealingSerializer>,
+	compressor: Option<Box<dyn Compressor + Send>>,
+	checksum: Option<Box<dyn Checksum + Send>>,
+}
+impl CodecSerializer {
+	/// Encrypt subsequent values with `cipher`, identified by `key_id`, as with [`SealingSerializer::rotate()`].
+	///
+	/// # Panics
+	///
+	/// Will panic if this wasn't made with [`encrypt`](Layers::encrypt()), or if `key_id` isn't greater than the current key's.
+	#[track_caller]
+	pub fn rotate<C: Cipher + Send + 'static>(&mut self, key_id: u32, cipher: C) {
+		match &mut self.serializer {
+			Inner::Sealed(serializer) => serializer.rotate(key_id, cipher),
+			Inner::Plain(_) => panic!("rotate() called on a CodecSerializer that doesn't encrypt"),
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		match &self.serializer {
+			Inner::Plain(serializer) => serializer.push_avail(),
+			Inner::Sealed(serializer) => serializer.push_avail(),
+		}
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](CodecSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, or as [`SealingSerializer::push()`] does.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				let mut bytes = wire::serialize(&t).unwrap();
+				if let Some(compressor) = &self.compressor {
+					bytes = compressor.compress(&bytes);
+				}
+				if let Some(checksum) = &self.checksum {
+					let checksum = checksum.checksum(&bytes);
+					bytes.extend_from_slice(&checksum.to_le_bytes());
+				}
+				match &mut self.serializer {
+					Inner::Plain(serializer) => serializer.push().unwrap()(bytes),
+					Inner::Sealed(serializer) => serializer.push().unwrap()(bytes),
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		match &self.serializer {
+			Inner::Plain(serializer) => serializer.pull_avail(),
+			Inner::Sealed(serializer) => serializer.pull_avail(),
+		}
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](CodecSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.pull_avail() {
+			Some(move || match &mut self.serializer {
+				Inner::Plain(serializer) => {
+					let Some(pull) = serializer.pull() else {
+						unreachable!()
+					};
+					pull()
+				}
+				Inner::Sealed(serializer) => {
+					let Some(pull) = serializer.pull() else {
+						unreachable!()
+					};
+					pull()
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		match &self.serializer {
+			Inner::Plain(serializer) => serializer.empty_avail(),
+			Inner::Sealed(serializer) => serializer.empty_avail(),
+		}
+	}
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || match &mut self.serializer {
+				Inner::Plain(serializer) => {
+					let Some(empty) = serializer.empty() else {
+						unreachable!()
+					};
+					empty();
+				}
+				Inner::Sealed(serializer) => {
+					let Some(empty) = serializer.empty() else {
+						unreachable!()
+					};
+					empty();
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Unwrap, returning the underlying Serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		match self.serializer {
+			Inner::Plain(serializer) => serializer,
+			Inner::Sealed(serializer) => serializer.into_inner(),
+		}
+	}
+}
+impl fmt::Debug for CodecSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CodecSerializer")
+			.field("serializer", &self.serializer)
+			.field("compress", &self.compressor.is_some())
+			.field("checksum", &self.checksum.is_some())
+			.finish()
+	}
+}
+
+/// A [`Deserializer`] that reverses the [`Layers`] it was made with on each value pulled.
+pub struct CodecDeserializer {
+	deserializer: Inner<Deserializer, OpeningDeserializer>,
+	compressor: Option<Box<dyn Compressor + Send>>,
+	checksum: Option<Box<dyn Checksum + Send>>,
+}
+impl CodecDeserializer {
+	/// Add `cipher`, identified by `key_id`, to the keys frames can be decrypted with, as with [`OpeningDeserializer::add_key()`].
+	///
+	/// # Panics
+	///
+	/// Will panic if this wasn't made with [`encrypt`](Layers::encrypt()).
+	#[track_caller]
+	pub fn add_key<C: Cipher + Send + 'static>(&mut self, key_id: u32, cipher: C) {
+		match &mut self.deserializer {
+			Inner::Sealed(deserializer) => deserializer.add_key(key_id, cipher),
+			Inner::Plain(_) => {
+				panic!("add_key() called on a CodecDeserializer that doesn't decrypt")
+			}
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		match &self.deserializer {
+			Inner::Plain(deserializer) => deserializer.pull_avail(),
+			Inner::Sealed(deserializer) => deserializer.pull_avail(),
+		}
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](CodecDeserializer::push()). [`Some`] contains an `impl FnOnce() -> Result<T, CodecError>` that can be called to perform the `pull`.
+	///
+	/// A frame that fails to decode is consumed, and the error returned; subsequent frames can still be pulled. With the `fringe` feature, whose frames aren't delimited, a frame too malformed to find the end of will instead panic.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> Result<T, CodecError> + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let mut bytes = match &mut self.deserializer {
+					Inner::Plain(deserializer) => {
+						let Some(payload) = wire::pull_payload::<Vec<u8>>(deserializer) else {
+							unreachable!()
+						};
+						let _ = deserializer.pull::<Vec<u8>>();
+						match &self.checksum {
+							// verify the bytes before decoding the length preceding them, so a corrupted length fails the checksum too
+							Some(checksum) => {
+								let (len, bytes) = payload.split_at(payload.len().min(8));
+								let bytes = verify(&**checksum, bytes)?;
+								if wire::deserialize_exact::<u64>(len).ok()
+									!= Some(bytes.len() as u64 + 4)
+								{
+									return Err(CodecError::Checksum);
+								}
+								bytes.to_vec()
+							}
+							None => wire::deserialize_exact(&payload)
+								.map_err(|_| CodecError::Malformed)?,
+						}
+					}
+					Inner::Sealed(deserializer) => {
+						let Some(pull) = deserializer.pull::<Vec<u8>>() else {
+							unreachable!()
+						};
+						let bytes = pull()?;
+						match &self.checksum {
+							Some(checksum) => verify(&**checksum, &bytes)?.to_vec(),
+							None => bytes,
+						}
+					}
+				};
+				if let Some(compressor) = &self.compressor {
+					bytes = compressor
+						.decompress(&bytes)
+						.ok_or(CodecError::Decompress)?;
+				}
+				wire::deserialize(&bytes).map_err(|_| CodecError::Malformed)
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		match &self.deserializer {
+			Inner::Plain(deserializer) => deserializer.push_avail(),
+			Inner::Sealed(deserializer) => deserializer.push_avail(),
+		}
+	}
+	/// Push a `u8`. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](CodecDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.push_avail() {
+			Some(move |byte| match &mut self.deserializer {
+				Inner::Plain(deserializer) => {
+					let Some(push) = deserializer.push() else {
+						unreachable!()
+					};
+					push(byte);
+				}
+				Inner::Sealed(deserializer) => {
+					let Some(push) = deserializer.push() else {
+						unreachable!()
+					};
+					push(byte);
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		match &self.deserializer {
+			Inner::Plain(deserializer) => deserializer.empty_avail(),
+			Inner::Sealed(deserializer) => deserializer.empty_avail(),
+		}
+	}
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || match &mut self.deserializer {
+				Inner::Plain(deserializer) => {
+					let Some(empty) = deserializer.empty() else {
+						unreachable!()
+					};
+					empty();
+					let _ = deserializer.pull::<Vec<u8>>();
+				}
+				Inner::Sealed(deserializer) => {
+					let Some(empty) = deserializer.empty() else {
+						unreachable!()
+					};
+					empty();
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		match self.deserializer {
+			Inner::Plain(deserializer) => deserializer,
+			Inner::Sealed(deserializer) => deserializer.into_inner(),
+		}
+	}
+}
+impl fmt::Debug for CodecDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CodecDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("compress", &self.compressor.is_some())
+			.field("checksum", &self.checksum.is_some())
+			.finish()
+	}
+}
+
+/// Strip the checksum appended to `bytes`, verifying it.
+fn verify<'a>(checksum: &dyn Checksum, bytes: &'a [u8]) -> Result<&'a [u8], CodecError> {
+	let len = bytes.len().checked_sub(4).ok_or(CodecError::Checksum)?;
+	let (bytes, expected) = bytes.split_at(len);
+	if checksum.checksum(bytes) == u32::from_le_bytes(<[u8; 4]>::try_from(expected).unwrap()) {
+		Ok(bytes)
+	} else {
+		Err(CodecError::Checksum)
+	}
+}
+
+/// Why a frame couldn't be decoded.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CodecError {
+	/// It couldn't be decrypted.
+	Open(OpenError),
+	/// Its checksum didn't match.
+	Checksum,
+	/// It couldn't be decompressed.
+	Decompress,
+	/// It was decoded, but isn't a valid encoding of the type pulled.
+	Malformed,
+}
+impl From<OpenError> for CodecError {
+	fn from(err: OpenError) -> Self {
+		Self::Open(err)
+	}
+}
+impl fmt::Display for CodecError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Open(err) => err.fmt(f),
+			Self::Checksum => f.write_str("frame checksum mismatch"),
+			Self::Decompress => f.write_str("frame decompression failed"),
+			Self::Malformed => f.write_str("frame malformed"),
+		}
+	}
+}
+impl error::Error for CodecError {}
@@ -0,0 +1,368 @@
+//! Encrypting frames, with key rotation and nonce management.
+//!
+//! [`SealingSerializer`] encrypts each value pushed with an AEAD [`Cipher`], such as AES-GCM or ChaCha20-Poly1305 from the `RustCrypto` crates, and [`OpeningDeserializer`] decrypts and authenticates them. Each frame carries the id of the key it was sealed with, and the nonce it was sealed under, which is authenticated along with it.
+//!
+//! Nonces are a per-key counter, managed internally: they start at zero for each key, and increment with each frame. So a key must never be given to more than one [`SealingSerializer`], including across restarts, and each direction of a connection needs its own keys. The [`OpeningDeserializer`] rejects a frame whose nonce isn't greater than that of the last opened with its key, so frames can't be replayed.
+//!
+//! Keys are rotated mid-stream with [`SealingSerializer::rotate()`], once the new key has been [`add`](OpeningDeserializer::add_key())ed at the receiving end. Key ids must increase with each rotation. Once the receiving end has opened a frame sealed with a key, older keys are kept for a grace period, for frames in flight on other paths, then discarded.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{crypto::{Cipher, OpeningDeserializer, SealingSerializer}, Deserializer, Serializer};
+//! use std::time::Duration;
+//!
+//! /// For illustration only: not secure. Use a real AEAD.
+//! struct Xor(u8);
+//! impl Cipher for Xor {
+//!     fn seal(&self, nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) {
+//!         buffer.iter_mut().for_each(|byte| *byte ^= self.0 ^ nonce[0]);
+//!         buffer.push(self.0);
+//!     }
+//!     fn open(&self, nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+//!         if buffer.pop() != Some(self.0) {
+//!             return false;
+//!         }
+//!         buffer.iter_mut().for_each(|byte| *byte ^= self.0 ^ nonce[0]);
+//!         true
+//!     }
+//! }
+//!
+//! let mut serializer = SealingSerializer::new(Serializer::new(), 1, Xor(0x5a));
+//! let mut deserializer = OpeningDeserializer::new(Deserializer::new(), Duration::from_secs(10));
+//! deserializer.add_key(1, Xor(0x5a));
+//! deserializer.add_key(2, Xor(0xc3));
+//! for (i, message) in ["hello", "rotated"].iter().enumerate() {
+//!     if i == 1 {
+//!         serializer.rotate(2, Xor(0xc3));
+//!     }
+//!     serializer.push().unwrap()(String::from(*message));
+//!     let _ = deserializer.pull::<String>();
+//!     while let Some(pull) = serializer.pull() {
+//!         deserializer.push().unwrap()(pull());
+//!     }
+//!     assert_eq!(deserializer.pull::<String>().unwrap()().unwrap(), *message);
+//! }
+//! ```
+
+use crate::{wire, Deserializer, Serializer};
+use std::{
+	collections::BTreeMap, error, fmt, time::{Duration, Instant}
+};
+
+/// The frame sent on the wire: the key id, the nonce counter, and the ciphertext.
+type Sealed = (u32, u64, Vec<u8>);
+
+/// An AEAD cipher, keyed with a single key.
+pub trait Cipher {
+	/// Encrypt `buffer` in place under `nonce`, appending the authentication tag, which also authenticates `aad`.
+	fn seal(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>);
+	/// Decrypt `buffer` in place under `nonce`, verifying and removing the authentication tag, which also authenticates `aad`. Returns `false` if verification fails.
+	fn open(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>) -> bool;
+}
+impl<C: Cipher + ?Sized> Cipher for Box<C> {
+	fn seal(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>) {
+		(**self).seal(nonce, aad, buffer);
+	}
+	fn open(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+		(**self).open(nonce, aad, buffer)
+	}
+}
+
+/// The nonce for the `counter`th frame sealed with a key, and the associated data authenticated with it.
+fn nonce_aad(key_id: u32, counter: u64) -> ([u8; 12], [u8; 12]) {
+	let mut nonce = [0; 12];
+	nonce[..8].copy_from_slice(&counter.to_le_bytes());
+	let mut aad = [0; 12];
+	aad[..4].copy_from_slice(&key_id.to_le_bytes());
+	aad[4..].copy_from_slice(&counter.to_le_bytes());
+	(nonce, aad)
+}
+
+/// A [`Serializer`] that encrypts each value pushed.
+pub struct SealingSerializer {
+	serializer: Serializer,
+	key_id: u32,
+	cipher: Box<dyn Cipher + Send>,
+	/// The nonce counter for the next frame sealed with the current key.
+	counter: u64,
+}
+impl SealingSerializer {
+	/// Wrap `serializer`, sealing with `cipher`, identified by `key_id`. The key must not have been used to seal before.
+	pub fn new<C: Cipher + Send + 'static>(serializer: Serializer, key_id: u32, cipher: C) -> Self {
+		Self {
+			serializer,
+			key_id,
+			cipher: Box::new(cipher),
+			counter: 0,
+		}
+	}
+
+	/// Seal subsequent values with `cipher`, identified by `key_id`. The key must not have been used to seal before. A frame in flight continues to be sent as sealed with the previous key.
+	///
+	/// # Panics
+	///
+	/// Will panic if `key_id` isn't greater than the current key's.
+	#[track_caller]
+	pub fn rotate<C: Cipher + Send + 'static>(&mut self, key_id: u32, cipher: C) {
+		assert!(
+			key_id > self.key_id,
+			"key ids must increase with each rotation, but {} follows {}",
+			key_id,
+			self.key_id
+		);
+		self.key_id = key_id;
+		self.cipher = Box::new(cipher);
+		self.counter = 0;
+	}
+
+	/// The id of the key values are sealed with.
+	#[must_use]
+	pub fn key_id(&self) -> u32 {
+		self.key_id
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](SealingSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, or if 2<sup>64</sup> values have been sealed with the current key.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				let mut buffer = wire::serialize(&t).unwrap();
+				let (nonce, aad) = nonce_aad(self.key_id, self.counter);
+				self.cipher.seal(&nonce, &aad, &mut buffer);
+				let sealed: Sealed = (self.key_id, self.counter, buffer);
+				self.counter = self
+					.counter
+					.checked_add(1)
+					.expect("nonces exhausted; rotate the key");
+				self.serializer.push().unwrap()(sealed);
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](SealingSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the underlying pipe. The nonce of a discarded frame isn't reused. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.serializer.empty()
+	}
+
+	/// Unwrap, returning the underlying Serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl fmt::Debug for SealingSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SealingSerializer")
+			.field("serializer", &self.serializer)
+			.field("key_id", &self.key_id)
+			.field("counter", &self.counter)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A key held by an [`OpeningDeserializer`].
+struct Key {
+	cipher: Box<dyn Cipher + Send>,
+	/// The lowest nonce counter not yet seen.
+	counter: u64,
+	/// When it's to be discarded, once superseded.
+	retire: Option<Instant>,
+}
+
+/// A [`Deserializer`] that decrypts and authenticates the values sent by a [`SealingSerializer`].
+pub struct OpeningDeserializer {
+	deserializer: Deserializer,
+	keys: BTreeMap<u32, Key>,
+	grace: Duration,
+}
+impl OpeningDeserializer {
+	/// Wrap `deserializer`, keeping keys for `grace` after a frame sealed with a newer key is opened. Keys are added with [`add_key`](OpeningDeserializer::add_key()).
+	///
+	/// # Panics
+	///
+	/// Will panic if `deserializer` is part way through a frame of another type.
+	#[must_use]
+	pub fn new(mut deserializer: Deserializer, grace: Duration) -> Self {
+		let _ = deserializer.pull::<Sealed>();
+		Self {
+			deserializer,
+			keys: BTreeMap::new(),
+			grace,
+		}
+	}
+
+	/// Add `cipher`, identified by `key_id`, to the keys frames can be opened with, ahead of the sending end rotating to it.
+	///
+	/// # Panics
+	///
+	/// Will panic if `key_id` has already been added and not yet discarded, as replacing it would forget the nonces it has seen.
+	#[track_caller]
+	pub fn add_key<C: Cipher + Send + 'static>(&mut self, key_id: u32, cipher: C) {
+		assert!(
+			!self.keys.contains_key(&key_id),
+			"key {} has already been added",
+			key_id
+		);
+		let _ = self.keys.insert(
+			key_id,
+			Key {
+				cipher: Box::new(cipher),
+				counter: 0,
+				retire: None,
+			},
+		);
+	}
+
+	/// The ids of the keys frames can currently be opened with.
+	pub fn key_ids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.keys.keys().copied()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](OpeningDeserializer::push()). [`Some`] contains an `impl FnOnce() -> Result<T, OpenError>` that can be called to perform the `pull`.
+	///
+	/// A frame that fails to open is consumed, and the error returned; subsequent frames can still be pulled. With the `fringe` feature, whose frames aren't delimited, a frame too malformed to find the end of will instead panic.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> Result<T, OpenError> + '_> {
+		self.pull_at(Instant::now())
+	}
+
+	/// Pull a `T`, as at `now`, which determines which superseded keys have outlived their grace period. [`None`] denotes that the Deserializer is instead awaiting a [`push`](OpeningDeserializer::push()). [`Some`] contains an `impl FnOnce() -> Result<T, OpenError>` that can be called to perform the `pull`.
+	///
+	/// A frame that fails to open is consumed, and the error returned; subsequent frames can still be pulled. With the `fringe` feature, whose frames aren't delimited, a frame too malformed to find the end of will instead panic.
+	pub fn pull_at<T: serde::de::DeserializeOwned + 'static>(
+		&mut self, now: Instant,
+	) -> Option<impl FnOnce() -> Result<T, OpenError> + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let Some(payload) = wire::pull_payload::<Sealed>(&mut self.deserializer) else {
+					unreachable!()
+				};
+				let _ = self.deserializer.pull::<Sealed>();
+				let (key_id, counter, mut buffer) =
+					wire::deserialize_exact::<Sealed>(&payload).map_err(|_| OpenError::Malformed)?;
+				self.keys
+					.retain(|_, key| !matches!(key.retire, Some(retire) if retire <= now));
+				let key = self
+					.keys
+					.get_mut(&key_id)
+					.ok_or(OpenError::UnknownKey(key_id))?;
+				if counter < key.counter {
+					return Err(OpenError::Replayed);
+				}
+				let (nonce, aad) = nonce_aad(key_id, counter);
+				if !key.cipher.open(&nonce, &aad, &mut buffer) {
+					return Err(OpenError::Forged);
+				}
+				key.counter = counter + 1;
+				let retire = now + self.grace;
+				for (_, key) in self.keys.range_mut(..key_id) {
+					let _ = key.retire.get_or_insert(retire);
+				}
+				wire::deserialize_exact(&buffer).map_err(|_| OpenError::Malformed)
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](OpeningDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		self.deserializer.push()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.deserializer.empty_avail()
+	}
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				let Some(empty) = self.deserializer.empty() else {
+					unreachable!()
+				};
+				empty();
+				let _ = self.deserializer.pull::<Sealed>();
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
+impl fmt::Debug for OpeningDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("OpeningDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("keys", &self.keys.keys())
+			.field("grace", &self.grace)
+			.finish()
+	}
+}
+
+/// Why a frame couldn't be opened.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum OpenError {
+	/// It was sealed with a key that hasn't been added, or has been discarded.
+	UnknownKey(u32),
+	/// Its nonce isn't greater than that of the last frame opened with its key.
+	Replayed,
+	/// It failed authentication.
+	Forged,
+	/// Its envelope of key id, nonce and ciphertext isn't exactly a valid encoding of one, or it was authenticated, but its plaintext isn't exactly a valid encoding of the type pulled, without trailing bytes.
+	Malformed,
+}
+impl fmt::Display for OpenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::UnknownKey(key_id) => write!(f, "frame sealed with unknown key {key_id}"),
+			Self::Replayed => f.write_str("frame replayed"),
+			Self::Forged => f.write_str("frame failed authentication"),
+			Self::Malformed => f.write_str("frame malformed"),
+		}
+	}
+}
+impl error::Error for OpenError {}
@@ -0,0 +1,293 @@
+//! CSV pipes: each value is one CSV record, optionally preceded by a header row at the start of the stream.
+//!
+//! These have the same push/pull discipline as [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer), and are intended for flat struct types, so that the stream can be consumed directly by existing CSV tooling.
+//!
+//! Requires the `csv` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::csv::{CsvDeserializer, CsvSerializer};
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Reading {
+//!     sensor: String,
+//!     value: f64,
+//! }
+//!
+//! let mut serializer = CsvSerializer::new(true);
+//! let mut deserializer = CsvDeserializer::new(true);
+//! let mut output = Vec::new();
+//! for value in &[1.5, 2.5] {
+//!     let reading = Reading { sensor: String::from("a, \"b\""), value: *value };
+//!     serializer.push().unwrap()(reading);
+//!     while let Some(pull) = serializer.pull() {
+//!         let byte = pull();
+//!         output.push(byte);
+//!         deserializer.push().unwrap()(byte);
+//!     }
+//!     let reading = deserializer.pull::<Reading>().unwrap()();
+//!     assert_eq!(reading.value, *value);
+//! }
+//! assert_eq!(output, &b"sensor,value\n\"a, \"\"b\"\"\",1.5\n\"a, \"\"b\"\"\",2.5\n"[..]);
+//! ```
+
+use std::fmt;
+
+/// CSV serializer pipe: push `T`; pull `u8`.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`CsvSerializer::empty()`] before dropping it.
+pub struct CsvSerializer {
+	buffer: Option<(Box<[u8]>, usize)>,
+	headers: bool,
+}
+impl CsvSerializer {
+	/// Construct a new `CsvSerializer` pipe. If `headers` is true, a header row derived from the field names of the first value pushed is written at the start of the stream.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new(headers: bool) -> Self {
+		Self {
+			buffer: None,
+			headers,
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.buffer.is_none()
+	}
+	/// Push a `T`. [`None`] denotes that the `CsvSerializer` is instead awaiting a [`pull`](CsvSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if `T` can't be represented as a CSV record, for example if it contains a map.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.buffer.is_none() {
+			Some(move |t| {
+				let mut writer = ::csv::WriterBuilder::new()
+					.has_headers(self.headers)
+					.from_writer(Vec::new());
+				writer.serialize(t).unwrap();
+				let vec = writer.into_inner().unwrap();
+				self.headers = false;
+				self.buffer = Some((vec.into_boxed_slice(), 0));
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Pull a `u8`. [`None`] denotes that the `CsvSerializer` is instead awaiting a [`push`](CsvSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((buffer, index)) = &mut self.buffer else {
+					unreachable!()
+				};
+				let ret = buffer[*index];
+				*index += 1;
+				if *index == buffer.len() {
+					self.buffer = None;
+				}
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				self.buffer = None;
+			})
+		} else {
+			None
+		}
+	}
+}
+impl Drop for CsvSerializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_none() || std::thread::panicking(),
+			"CsvSerializer dropped with a frame unpulled; empty() it first to discard it"
+		);
+	}
+}
+impl fmt::Debug for CsvSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CsvSerializer")
+			.field("buffer", &self.buffer)
+			.field("headers", &self.headers)
+			.finish()
+	}
+}
+
+/// CSV deserializer pipe: push `u8`; pull `T`.
+///
+/// Records are delimited by unquoted newlines, so bytes can be pushed before the type to be deserialized to has been specified. Empty lines are skipped.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`CsvDeserializer::empty()`] before dropping it.
+pub struct CsvDeserializer {
+	buffer: Vec<u8>,
+	quoted: bool,
+	done: bool,
+	headers: Headers,
+}
+impl CsvDeserializer {
+	/// Construct a new `CsvDeserializer` pipe. If `headers` is true, the first record of the stream is taken to be a header row, and used to match fields by name rather than position.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new(headers: bool) -> Self {
+		Self {
+			buffer: Vec::new(),
+			quoted: false,
+			done: false,
+			headers: if headers {
+				Headers::Awaited
+			} else {
+				Headers::Absent
+			},
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.done
+	}
+	/// Pull a `T`. [`None`] denotes that the `CsvDeserializer` is instead awaiting a [`push`](CsvDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the record isn't a valid CSV encoding of `T`.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		if self.done {
+			Some(move || {
+				let record = record(&self.buffer);
+				let ret = record.deserialize(self.headers.record()).unwrap();
+				self.buffer.clear();
+				self.done = false;
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		!self.done
+	}
+	/// Push a `u8`. [`None`] denotes that the `CsvDeserializer` is instead awaiting a [`pull`](CsvDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.done {
+			None
+		} else {
+			Some(move |x| {
+				self.buffer.push(x);
+				if x == b'"' {
+					self.quoted = !self.quoted;
+				} else if x == b'\n' && !self.quoted {
+					if self.buffer.iter().all(u8::is_ascii_whitespace) {
+						self.buffer.clear();
+					} else if let Headers::Awaited = self.headers {
+						self.headers = Headers::Received(record(&self.buffer));
+						self.buffer.clear();
+					} else {
+						self.done = true;
+					}
+				}
+			})
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.buffer.is_empty()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_empty() {
+			None
+		} else {
+			Some(move || {
+				self.buffer.clear();
+				self.quoted = false;
+				self.done = false;
+			})
+		}
+	}
+}
+impl Drop for CsvDeserializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_empty() || std::thread::panicking(),
+			"CsvDeserializer dropped with {} bytes of a frame buffered; empty() it first to discard them",
+			self.buffer.len()
+		);
+	}
+}
+impl fmt::Debug for CsvDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CsvDeserializer")
+			.field("buffer", &self.buffer)
+			.field("quoted", &self.quoted)
+			.field("done", &self.done)
+			.field("headers", &self.headers)
+			.finish()
+	}
+}
+
+/// The header row a [`CsvDeserializer`] matches fields by.
+#[derive(Debug)]
+enum Headers {
+	/// The stream has no header row.
+	Absent,
+	/// The stream's first record is its header row, and hasn't yet been received.
+	Awaited,
+	/// The header row received.
+	Received(::csv::ByteRecord),
+}
+impl Headers {
+	fn record(&self) -> Option<&::csv::ByteRecord> {
+		match self {
+			Headers::Received(record) => Some(record),
+			Headers::Absent | Headers::Awaited => None,
+		}
+	}
+}
+
+/// Parse a single complete CSV record.
+fn record(buffer: &[u8]) -> ::csv::ByteRecord {
+	let mut record = ::csv::ByteRecord::new();
+	let read = ::csv::ReaderBuilder::new()
+		.has_headers(false)
+		.from_reader(buffer)
+		.read_byte_record(&mut record)
+		.unwrap();
+	assert!(read);
+	record
+}
@@ -0,0 +1,232 @@
+//! Suppressing consecutive duplicate frames, for status broadcasts that rarely change.
+//!
+//! [`DedupSerializer`] hashes each value pushed, and if it's the same as the previous value of that type, sends a one-byte repeat marker in its place. [`DedupDeserializer`] returns the previous value of that type again on receiving the marker, so needs `T: Clone`.
+//!
+//! Each value is sent as an `Option<T>`, with [`None`] as the marker, so a plain [`Deserializer`] can also receive them by pulling `Option<T>`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{dedup::{DedupDeserializer, DedupSerializer}, serialize_to_vec, Deserializer, Serializer};
+//!
+//! let mut serializer = DedupSerializer::new(Serializer::new());
+//! let mut deserializer = DedupDeserializer::new(Deserializer::new());
+//! let mut bytes = 0;
+//! for _ in 0..3 {
+//!     serializer.push().unwrap()(String::from("all systems nominal"));
+//!     let _ = deserializer.pull::<String>();
+//!     while let Some(pull) = serializer.pull() {
+//!         deserializer.push().unwrap()(pull());
+//!         bytes += 1;
+//!     }
+//!     assert_eq!(deserializer.pull::<String>().unwrap()(), "all systems nominal");
+//! }
+//! // the status once, then two markers
+//! let status = serialize_to_vec(Some(String::from("all systems nominal")));
+//! let marker = serialize_to_vec(None::<String>);
+//! assert_eq!(bytes, status.len() + 2 * marker.len());
+//! ```
+
+use crate::{wire, Deserializer, Serializer};
+use std::{
+	any::{self, Any, TypeId}, collections::{hash_map::DefaultHasher, HashMap}, fmt, hash::Hasher, io
+};
+
+/// A [`Serializer`] that sends a marker in place of a value equal to the previous one of its type.
+pub struct DedupSerializer {
+	serializer: Serializer,
+	/// The hash of the last value of each type pushed.
+	last: HashMap<TypeId, u64>,
+	repeats: u64,
+}
+impl DedupSerializer {
+	/// Wrap `serializer`.
+	#[must_use]
+	pub fn new(serializer: Serializer) -> Self {
+		Self {
+			serializer,
+			last: HashMap::new(),
+			repeats: 0,
+		}
+	}
+
+	/// The number of values sent as a repeat marker.
+	#[must_use]
+	pub fn repeats(&self) -> u64 {
+		self.repeats
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](DedupSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				let mut hasher = HashWriter(DefaultHasher::new());
+				wire::serialize_into(&mut hasher, &t).unwrap();
+				let hash = hasher.0.finish();
+				if self.last.insert(TypeId::of::<T>(), hash) == Some(hash) {
+					self.repeats += 1;
+					self.serializer.push().unwrap()(None::<T>);
+				} else {
+					self.serializer.push().unwrap()(Some(t));
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](DedupSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the underlying pipe, and forget the values sent, so that the next of each type is sent in full. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		let last = &mut self.last;
+		self.serializer.empty().map(move |empty| {
+			move || {
+				last.clear();
+				empty();
+			}
+		})
+	}
+
+	/// Unwrap, returning the underlying Serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl fmt::Debug for DedupSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DedupSerializer")
+			.field("serializer", &self.serializer)
+			.field("last", &self.last.len())
+			.field("repeats", &self.repeats)
+			.finish()
+	}
+}
+
+/// A [`Deserializer`] that receives the values sent by a [`DedupSerializer`], returning the previous value of a type again in place of a repeat marker.
+pub struct DedupDeserializer {
+	deserializer: Deserializer,
+	/// The last value of each type pulled.
+	last: HashMap<TypeId, Box<dyn Any + Send>>,
+}
+impl DedupDeserializer {
+	/// Wrap `deserializer`.
+	#[must_use]
+	pub fn new(deserializer: Deserializer) -> Self {
+		Self {
+			deserializer,
+			last: HashMap::new(),
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](DedupDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame, or if a repeat marker is received for a type that hasn't been received since this was created or last emptied.
+	#[track_caller]
+	pub fn pull<T: serde::de::DeserializeOwned + Clone + Send + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		let last = &mut self.last;
+		self.deserializer.pull::<Option<T>>().map(move |pull| {
+			move || match pull() {
+				Some(t) => {
+					let _ = last.insert(TypeId::of::<T>(), Box::new(t.clone()));
+					t
+				}
+				None => last
+					.get(&TypeId::of::<T>())
+					.unwrap_or_else(|| {
+						panic!(
+							"received a repeat of a {} that hasn't been received",
+							any::type_name::<T>()
+						)
+					})
+					.downcast_ref::<T>()
+					.unwrap()
+					.clone(),
+			}
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](DedupDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		self.deserializer.push()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.deserializer.empty_avail()
+	}
+	/// Empty the underlying pipe, and forget the values received, as the [`DedupSerializer`] should also be emptied. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		let last = &mut self.last;
+		self.deserializer.empty().map(move |empty| {
+			move || {
+				last.clear();
+				empty();
+			}
+		})
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
+impl fmt::Debug for DedupDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DedupDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("last", &self.last.len())
+			.finish()
+	}
+}
+
+/// Feeds the bytes written to a hasher.
+struct HashWriter(DefaultHasher);
+impl io::Write for HashWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.write(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
@@ -0,0 +1,70 @@
+//! [`embedded-io`](https://docs.rs/embedded-io) trait implementations, so the pipes can be used wherever an embedded-io reader or writer is expected.
+//!
+//! [`Serializer`] implements `Read`, yielding the serialized bytes; [`Deserializer`] implements `Write`, consuming them. As the pipes never block, a read or write that can't make progress – i.e. the pipe is instead awaiting a push or a pull – returns [`NotReady`], rather than `Ok(0)`, which embedded-io reserves for end-of-file and forbids of writes. `ReadReady` and `WriteReady` report whether it would.
+//!
+//! The `embedded-io-async` traits are implemented by [`task::AsyncSerializer`](crate::task::AsyncSerializer) and [`task::AsyncDeserializer`](crate::task::AsyncDeserializer) instead, as awaiting readiness needs another task to push or pull through a shared handle.
+
+use crate::{Deserializer, Serializer};
+use std::{error, fmt};
+
+/// The error returned by an embedded-io read of a [`Serializer`] that's awaiting a push, or write to a [`Deserializer`] that's awaiting a pull. Its kind is [`Other`](embedded_io::ErrorKind::Other).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NotReady;
+impl fmt::Display for NotReady {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("the pipe is awaiting a push or a pull")
+	}
+}
+impl error::Error for NotReady {}
+impl embedded_io::Error for NotReady {
+	fn kind(&self) -> embedded_io::ErrorKind {
+		embedded_io::ErrorKind::Other
+	}
+}
+
+impl embedded_io::ErrorType for Serializer {
+	type Error = NotReady;
+}
+impl embedded_io::ErrorType for Deserializer {
+	type Error = NotReady;
+}
+
+impl embedded_io::Read for Serializer {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, NotReady> {
+		let mut len = 0;
+		while let (Some(byte), Some(pull)) = (buf.get_mut(len), self.pull()) {
+			*byte = pull();
+			len += 1;
+		}
+		if len == 0 && !buf.is_empty() {
+			return Err(NotReady);
+		}
+		Ok(len)
+	}
+}
+impl embedded_io::ReadReady for Serializer {
+	fn read_ready(&mut self) -> Result<bool, NotReady> {
+		Ok(self.pull_avail())
+	}
+}
+impl embedded_io::Write for Deserializer {
+	fn write(&mut self, buf: &[u8]) -> Result<usize, NotReady> {
+		let mut len = 0;
+		while let (Some(&byte), Some(push)) = (buf.get(len), self.push()) {
+			push(byte);
+			len += 1;
+		}
+		if len == 0 && !buf.is_empty() {
+			return Err(NotReady);
+		}
+		Ok(len)
+	}
+	fn flush(&mut self) -> Result<(), NotReady> {
+		Ok(())
+	}
+}
+impl embedded_io::WriteReady for Deserializer {
+	fn write_ready(&mut self) -> Result<bool, NotReady> {
+		Ok(self.push_avail())
+	}
+}
@@ -0,0 +1,392 @@
+//! The subset of libfringe's API that the `fringe` backend uses, implemented with fibers for the targets libfringe doesn't support: Windows fibers on Windows, and `ucontext` on RISC-V.
+//!
+//! Each [`OsStack`] is a fiber, created with its stack fully committed, that runs the closure of each [`Generator`] given it in turn. A generator's closure can't be abandoned part way through without leaking whatever it holds, so as with libfringe, the backend only drops generators once they've returned.
+//!
+//! On Windows, a thread must itself be a fiber to switch to one, so each thread that first resumes a generator is converted with `ConvertThreadToFiber`. This can't be undone: the thread is never converted back, as a generator suspended on it could otherwise be resumed into a thread that's no longer a fiber, so it remains a fiber for the rest of its life. Code that converts threads itself, or converts them back with `ConvertFiberToThread`, mustn't share threads with the backend.
+
+use std::{
+	any::Any, hint, io, marker::PhantomData, mem, panic::{self, AssertUnwindSafe}, ptr
+};
+
+/// Extra stack committed beyond that requested, for the frames of the fiber's entry point above the stack's [`base`](Stack::base()).
+const SLACK: usize = 16 * 1024;
+/// Stack left between the fiber's entry point and the stack's [`base`](Stack::base()), for its frames while suspended between closures, as the backend writes to everything below the base.
+const IDLE: usize = 8 * 1024;
+
+#[cfg(windows)]
+mod sys {
+	use super::{run, Context};
+	use std::{cell::Cell, ffi::c_void, io, ptr};
+	use windows_sys::Win32::System::Threading::{
+		ConvertThreadToFiber, CreateFiberEx, DeleteFiber, IsThreadAFiber, SwitchToFiber, FIBER_FLAG_FLOAT_SWITCH
+	};
+
+	thread_local! {
+		/// The fiber running on this thread, if it's been converted into one.
+		static CURRENT: Cell<*mut c_void> = Cell::new(ptr::null_mut());
+	}
+
+	/// The state for switching between a fiber and whoever resumed it.
+	pub struct Switch {
+		caller: *mut c_void,
+	}
+	impl Switch {
+		pub fn new() -> Self {
+			Self {
+				caller: ptr::null_mut(),
+			}
+		}
+	}
+
+	/// A Windows fiber, with its stack and guard page.
+	pub struct Fiber(*mut c_void);
+	impl Fiber {
+		pub fn new(size: usize, context: *mut Context) -> io::Result<Self> {
+			// SAFETY: entry is a valid fiber routine, and context is boxed in the OsStack alongside the fiber, so outlives it
+			let fiber = unsafe {
+				CreateFiberEx(
+					size,
+					size,
+					FIBER_FLAG_FLOAT_SWITCH,
+					Some(entry),
+					context.cast(),
+				)
+			};
+			if fiber.is_null() {
+				return Err(io::Error::last_os_error());
+			}
+			Ok(Self(fiber))
+		}
+
+		/// Switch from the fiber running on this thread to this one, returning once it suspends. The thread is converted to a fiber if it isn't already, and stays one for the rest of its life.
+		///
+		/// # Safety
+		///
+		/// `context` must be the one this fiber was created with, and the fiber mustn't be running, i.e. this mustn't be called from within it.
+		pub unsafe fn resume(&self, context: *mut Context) {
+			let current = CURRENT.with(|current| {
+				if current.get().is_null() {
+					assert!(
+						IsThreadAFiber() == 0,
+						"the fringe backend can't run on a thread converted to a fiber by other code"
+					);
+					let fiber = ConvertThreadToFiber(ptr::null());
+					assert!(!fiber.is_null(), "{}", io::Error::last_os_error());
+					current.set(fiber);
+				}
+				current.replace(self.0)
+			});
+			(*context).switch.caller = current;
+			SwitchToFiber(self.0);
+			CURRENT.with(|current_| current_.set(current));
+		}
+	}
+	impl Drop for Fiber {
+		fn drop(&mut self) {
+			// SAFETY: the fiber isn't running, as resume only returns once it's switched away from it, so deleting it doesn't delete the running fiber and exit the thread
+			unsafe { DeleteFiber(self.0) };
+		}
+	}
+
+	/// Switch from the fiber back to whoever resumed it.
+	///
+	/// # Safety
+	///
+	/// Must be called from within the fiber, with the `context` it was resumed with.
+	pub unsafe fn suspend(context: *mut Context) {
+		SwitchToFiber((*context).switch.caller);
+	}
+
+	unsafe extern "system" fn entry(context: *mut c_void) {
+		run(context.cast())
+	}
+}
+
+#[cfg(not(windows))]
+mod sys {
+	use super::{run, Context};
+	use std::{
+		convert::TryFrom, ffi::c_void, io, mem, os::raw::{c_int, c_uint}, ptr
+	};
+
+	// glibc provides these on RISC-V, though the libc crate doesn't bind them there
+	extern "C" {
+		fn getcontext(ucp: *mut libc::ucontext_t) -> c_int;
+		fn makecontext(ucp: *mut libc::ucontext_t, func: extern "C" fn(), argc: c_int, ...);
+		fn swapcontext(oucp: *mut libc::ucontext_t, ucp: *const libc::ucontext_t) -> c_int;
+	}
+
+	/// The state for switching between a fiber and whoever resumed it.
+	pub struct Switch {
+		caller: libc::ucontext_t,
+		fiber: libc::ucontext_t,
+	}
+	impl Switch {
+		pub fn new() -> Self {
+			// SAFETY: ucontext_t is plain C data, for which all zeroes is valid; getcontext initialises the fiber's before it's used
+			unsafe { mem::zeroed() }
+		}
+	}
+
+	/// A mapped stack, with a guard page at its bottom.
+	pub struct Fiber {
+		stack: *mut c_void,
+		size: usize,
+	}
+	impl Fiber {
+		pub fn new(size: usize, context: *mut Context) -> io::Result<Self> {
+			// SAFETY: sysconf has no preconditions
+			let page = usize::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).unwrap();
+			let size = size.div_ceil(page) * page + page;
+			// SAFETY: an anonymous mapping at an address of the kernel's choosing aliases no existing memory
+			let stack = unsafe {
+				libc::mmap(
+					ptr::null_mut(),
+					size,
+					libc::PROT_READ | libc::PROT_WRITE,
+					libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_STACK,
+					-1,
+					0,
+				)
+			};
+			if stack == libc::MAP_FAILED {
+				return Err(io::Error::last_os_error());
+			}
+			let fiber = Self { stack, size };
+			// SAFETY: the guard page and stack are within the mapping just created, which only this fiber uses. context is boxed in the OsStack alongside the fiber, so outlives it. entry takes the two c_uints makecontext is told to pass it; makecontext's signature requires it be cast to take none, as C's would
+			unsafe {
+				let ucontext = ptr::addr_of_mut!((*context).switch.fiber);
+				if libc::mprotect(stack, page, libc::PROT_NONE) != 0 || getcontext(ucontext) != 0 {
+					return Err(io::Error::last_os_error());
+				}
+				(*ucontext).uc_stack.ss_sp = stack;
+				(*ucontext).uc_stack.ss_size = size;
+				(*ucontext).uc_link = ptr::null_mut();
+				// makecontext passes int arguments, so the pointer is split in two
+				let context = context as u64;
+				#[allow(clippy::cast_possible_truncation)]
+				makecontext(
+					ucontext,
+					mem::transmute::<unsafe extern "C" fn(c_uint, c_uint), extern "C" fn()>(entry),
+					2,
+					(context >> 32) as c_uint,
+					context as c_uint,
+				);
+			}
+			Ok(fiber)
+		}
+
+		/// Switch to this fiber, returning once it suspends.
+		///
+		/// # Safety
+		///
+		/// `context` must be the one this fiber was created with, and the fiber mustn't be running, i.e. this mustn't be called from within it.
+		#[allow(clippy::unused_self)]
+		pub unsafe fn resume(&self, context: *mut Context) {
+			let ret = swapcontext(
+				ptr::addr_of_mut!((*context).switch.caller),
+				ptr::addr_of!((*context).switch.fiber),
+			);
+			assert_eq!(ret, 0, "{}", io::Error::last_os_error());
+		}
+	}
+	impl Drop for Fiber {
+		fn drop(&mut self) {
+			// SAFETY: the mapping was created by new, and isn't in use, as the fiber only runs within resume
+			let ret = unsafe { libc::munmap(self.stack, self.size) };
+			assert_eq!(ret, 0, "{}", io::Error::last_os_error());
+		}
+	}
+
+	/// Switch from the fiber back to whoever resumed it.
+	///
+	/// # Safety
+	///
+	/// Must be called from within the fiber, with the `context` it was resumed with.
+	pub unsafe fn suspend(context: *mut Context) {
+		let ret = swapcontext(
+			ptr::addr_of_mut!((*context).switch.fiber),
+			ptr::addr_of!((*context).switch.caller),
+		);
+		assert_eq!(ret, 0, "{}", io::Error::last_os_error());
+	}
+
+	unsafe extern "C" fn entry(high: c_uint, low: c_uint) {
+		run(((u64::from(high) << 32) | u64::from(low)) as *mut Context)
+	}
+}
+
+/// A stack, with the addresses it spans.
+pub trait Stack {
+	/// The highest address of the stack, which it grows down from.
+	fn base(&self) -> *mut u8;
+	/// The lowest address of the stack.
+	fn limit(&self) -> *mut u8;
+}
+
+/// What an [`OsStack`]'s fiber shares with whoever resumes it.
+struct Context {
+	/// The next closure to run.
+	task: Option<Box<dyn FnOnce()>>,
+	switch: sys::Switch,
+	/// The stack's base, as measured on entry.
+	base: usize,
+}
+
+/// A fiber, with its own stack.
+pub struct OsStack {
+	fiber: sys::Fiber,
+	context: Box<Context>,
+	len: usize,
+}
+impl OsStack {
+	/// Create a fiber with a stack of at least `len` bytes, all committed up front.
+	pub fn new(len: usize) -> io::Result<Self> {
+		let mut context = Box::new(Context {
+			task: None,
+			switch: sys::Switch::new(),
+			base: 0,
+		});
+		let fiber = sys::Fiber::new(len + SLACK, ptr::addr_of_mut!(*context))?;
+		// run it up to its first suspension, to measure its base
+		// SAFETY: context is the one the fiber was created with, and is boxed so doesn't move as it's returned
+		unsafe { fiber.resume(ptr::addr_of_mut!(*context)) };
+		Ok(Self {
+			fiber,
+			context,
+			len,
+		})
+	}
+}
+impl Stack for OsStack {
+	fn base(&self) -> *mut u8 {
+		self.context.base as *mut u8
+	}
+	fn limit(&self) -> *mut u8 {
+		(self.context.base - self.len) as *mut u8
+	}
+}
+
+/// The body of each [`OsStack`]'s fiber: run each closure given it, suspending after each.
+///
+/// # Safety
+///
+/// Must only be the entry point of the fiber created with `context`.
+unsafe fn run(context: *mut Context) -> ! {
+	let marker = 0_u8;
+	(*context).base = ptr::addr_of!(marker) as usize - IDLE;
+	loop {
+		sys::suspend(context);
+		if let Some(task) = (*context).task.take() {
+			below(task);
+		}
+	}
+}
+
+/// Run `task` with its frames at least [`IDLE`] bytes below the caller's.
+#[inline(never)]
+fn below(task: Box<dyn FnOnce()>) {
+	let pad = [0_u8; IDLE];
+	let _ = hint::black_box(&pad);
+	task();
+}
+
+/// What a [`Generator`] shares with its [`Yielder`].
+struct Shared<I, O> {
+	input: Option<I>,
+	output: Option<O>,
+	done: bool,
+	panic: Option<Box<dyn Any + Send>>,
+}
+
+pub mod generator {
+	//! Generators, as in libfringe.
+
+	use super::{mem, panic, ptr, sys, AssertUnwindSafe, Context, OsStack, PhantomData, Shared};
+
+	/// A closure running on its own fiber, that can suspend itself, yielding an `O`, and be resumed with an `I`.
+	pub struct Generator<'a, I, O, S> {
+		stack: Option<S>,
+		shared: Box<Shared<I, O>>,
+		marker: PhantomData<&'a ()>,
+	}
+	impl<'a, I: 'a, O: 'a> Generator<'a, I, O, OsStack> {
+		/// Create a generator that runs `f` on `stack`, once first resumed.
+		pub fn new<F: FnOnce(&Yielder<I, O>, I) + 'a>(mut stack: OsStack, f: F) -> Self {
+			let mut shared = Box::new(Shared {
+				input: None,
+				output: None,
+				done: false,
+				panic: None,
+			});
+			let (shared_, context) = (
+				ptr::addr_of_mut!(*shared),
+				ptr::addr_of_mut!(*stack.context),
+			);
+			let task: Box<dyn FnOnce() + 'a> = Box::new(move || {
+				let yielder = Yielder { shared: shared_, context };
+				let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+					// SAFETY: the task only runs while the generator, which owns the boxed shared_, is being resumed
+					let input = unsafe { (*shared_).input.take().unwrap() };
+					f(&yielder, input);
+				}));
+				// SAFETY: as above
+				unsafe {
+					(*shared_).panic = ret.err();
+					(*shared_).done = true;
+				}
+			});
+			// SAFETY: erasing 'a is sound as the task can only run within resume, which borrows this generator, and the generator can't outlive 'a. If the generator is instead dropped before resuming it, the task is dropped unrun along with the stack. The task's frames can't outlive the generator either: unwrap, the only way to reclaim the stack for another generator, requires the task have returned
+			stack.context.task = Some(unsafe {
+				mem::transmute::<Box<dyn FnOnce() + 'a>, Box<dyn FnOnce()>>(task)
+			});
+			Self {
+				stack: Some(stack),
+				shared,
+				marker: PhantomData,
+			}
+		}
+
+		/// Resume the closure with `input`, returning what it next yields, or [`None`] if it returns instead. A panic in the closure is propagated.
+		pub fn resume(&mut self, input: I) -> Option<O> {
+			if self.shared.done {
+				return None;
+			}
+			self.shared.input = Some(input);
+			let stack = self.stack.as_mut().unwrap();
+			// SAFETY: the stack's context is the one its fiber was created with, and a generator isn't resumed from within its own closure, as that only has the Yielder
+			unsafe { stack.fiber.resume(ptr::addr_of_mut!(*stack.context)) };
+			if let Some(panic) = self.shared.panic.take() {
+				panic::resume_unwind(panic);
+			}
+			self.shared.output.take()
+		}
+
+		/// Return the stack, for reuse.
+		///
+		/// # Panics
+		///
+		/// Will panic if the closure hasn't returned.
+		pub fn unwrap(mut self) -> OsStack {
+			assert!(self.shared.done, "generator unwrapped before returning");
+			self.stack.take().unwrap()
+		}
+	}
+
+	/// Given to a [`Generator`]'s closure, to suspend it.
+	pub struct Yielder<I, O> {
+		shared: *mut Shared<I, O>,
+		context: *mut Context,
+	}
+	impl<I, O> Yielder<I, O> {
+		/// Suspend the closure, yielding `output` from [`Generator::resume()`], and returning the input it's next resumed with.
+		pub fn suspend(&self, output: O) -> I {
+			// SAFETY: the Yielder is only reachable within the generator's closure, which runs on the fiber while the generator, owning shared and the stack's context, is being resumed
+			unsafe {
+				(*self.shared).output = Some(output);
+				sys::suspend(self.context);
+				(*self.shared).input.take().unwrap()
+			}
+		}
+	}
+}
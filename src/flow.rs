@@ -0,0 +1,198 @@
+//! Credit-based flow control between a [`Serializer`] and a [`Deserializer`].
+//!
+//! The receiving side grants [`Credit`]s – a number of bytes and a number of frames – which the sending side spends as it pushes frames and pulls bytes. Once either is exhausted the sender reports not-ready, so a slow consumer can't cause unbounded buffering between the two.
+//!
+//! [`CreditDeserializer`] tallies the credit freed up as values are consumed, which the application returns to the sender by whatever means suits – e.g. a control frame or a pipe in the opposite direction – to be [`grant`](CreditSerializer::grant())ed.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{flow::{Credit, CreditDeserializer, CreditSerializer}, Deserializer, Serializer};
+//!
+//! let mut serializer = CreditSerializer::new(Serializer::new(), Credit { bytes: 64, frames: 1 });
+//! let mut deserializer = CreditDeserializer::new(Deserializer::new());
+//!
+//! serializer.push().unwrap()(String::from("hello"));
+//! assert!(serializer.push::<String>().is_none()); // out of frame credit
+//!
+//! let _ = deserializer.pull::<String>();
+//! while let Some(pull) = serializer.pull() {
+//!     deserializer.push().unwrap()(pull());
+//! }
+//! assert_eq!(deserializer.pull::<String>().unwrap()(), "hello");
+//!
+//! serializer.grant(deserializer.take_credit());
+//! assert!(serializer.push::<String>().is_some());
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{mem, ops};
+
+/// An amount of flow-control credit: the number of bytes and of frames the sender may send.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Credit {
+	/// The number of bytes that may be sent.
+	pub bytes: usize,
+	/// The number of frames that may be sent.
+	pub frames: usize,
+}
+impl ops::Add for Credit {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self {
+			bytes: self.bytes + other.bytes,
+			frames: self.frames + other.frames,
+		}
+	}
+}
+impl ops::AddAssign for Credit {
+	fn add_assign(&mut self, other: Self) {
+		*self = *self + other;
+	}
+}
+
+/// A [`Serializer`] that only sends as much as it has been granted [`Credit`] for.
+///
+/// Each [`push`](CreditSerializer::push()) spends a frame of credit, and each [`pull`](CreditSerializer::pull()) a byte.
+#[derive(Debug)]
+pub struct CreditSerializer {
+	serializer: Serializer,
+	credit: Credit,
+}
+impl CreditSerializer {
+	/// Wrap `serializer`, with `credit` initially granted.
+	#[must_use]
+	pub fn new(serializer: Serializer, credit: Credit) -> Self {
+		Self { serializer, credit }
+	}
+
+	/// Grant additional credit, typically as returned by [`CreditDeserializer::take_credit()`] on the receiving side.
+	pub fn grant(&mut self, credit: Credit) {
+		self.credit += credit;
+	}
+
+	/// The credit remaining.
+	#[must_use]
+	pub fn credit(&self) -> Credit {
+		self.credit
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.credit.frames != 0 && self.serializer.push_avail()
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](CreditSerializer::pull()), or that there's no frame credit remaining. [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.credit.frames != 0 {
+			let credit = &mut self.credit;
+			self.serializer.push().map(move |push| {
+				move |t| {
+					credit.frames -= 1;
+					push(t);
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.credit.bytes != 0 && self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](CreditSerializer::push()), or that there's no byte credit remaining. [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.credit.bytes != 0 {
+			let credit = &mut self.credit;
+			self.serializer.pull().map(move |pull| {
+				move || {
+					credit.bytes -= 1;
+					pull()
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.serializer.empty()
+	}
+
+	/// Unwrap, returning the underlying [`Serializer`].
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+
+/// A [`Deserializer`] that tallies the [`Credit`] freed up by consuming frames, for returning to the sender.
+#[derive(Debug)]
+pub struct CreditDeserializer {
+	deserializer: Deserializer,
+	credit: Credit,
+}
+impl CreditDeserializer {
+	/// Wrap `deserializer`.
+	#[must_use]
+	pub fn new(deserializer: Deserializer) -> Self {
+		Self {
+			deserializer,
+			credit: Credit::default(),
+		}
+	}
+
+	/// Take the credit freed up since this was last called, to be [`grant`](CreditSerializer::grant())ed to the sender.
+	pub fn take_credit(&mut self) -> Credit {
+		mem::take(&mut self.credit)
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T`, freeing up a frame of credit. [`None`] denotes that the Deserializer is instead awaiting a [`push`](CreditDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		let credit = &mut self.credit;
+		self.deserializer.pull().map(move |pull| {
+			move || {
+				credit.frames += 1;
+				pull()
+			}
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`, freeing up a byte of credit. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](CreditDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		let credit = &mut self.credit;
+		self.deserializer.push().map(move |push| {
+			move |x| {
+				credit.bytes += 1;
+				push(x);
+			}
+		})
+	}
+
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.deserializer.empty()
+	}
+
+	/// Unwrap, returning the underlying [`Deserializer`].
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
@@ -1,18 +1,156 @@
+use crate::{
+	telemetry::{Direction, StallTimer, Stalls, Telemetry}, wire
+};
+use bincode::Options;
 use either::Either;
+#[cfg(any(windows, target_arch = "riscv64"))]
+use crate::fiber as fringe;
+use fringe::Stack;
 use std::{
-	any::Any, fmt, io::{self, Read, Write}, marker
+	any::{self, Any}, collections::VecDeque, error, fmt, io::{self, Read, Write}, iter, marker, ptr, sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Arc
+	}, time::{Duration, Instant}
 };
 
+/// The number of idle inner pipelines, each with its own stack, kept for reuse when alternating between types.
+const CACHE_LEN: usize = 4;
+/// The default number of values [`Serializer::push_or_queue()`] queues.
+const QUEUE_LIMIT: usize = 16;
+/// The initial size of each inner pipeline's stack. It's doubled once a frame uses more than half of it.
+const STACK_LEN: usize = 64 * 1024;
+/// The most bytes [`Serializer::pull_chunk()`] returns at once.
+const CHUNK_LEN: usize = 4 * 1024;
+/// The number of bytes each inner serializing pipeline writes before first yielding them in a frame. Unless pinned by [`Serializer::set_block_len()`], each block is then twice the last, up to [`MAX_BLOCK_LEN`].
+const BLOCK_LEN: usize = 32;
+/// The most bytes each inner serializing pipeline writes before yielding them, unless pinned higher.
+const MAX_BLOCK_LEN: usize = 64 * 1024;
+
+/// A stack allocated ahead of time by [`Serializer::preallocate_stacks()`] or [`Deserializer::preallocate_stacks()`].
+struct Preallocated(fringe::OsStack);
+/// Safe as the stack is unused until it's handed to a generator, which is itself Send.
+unsafe impl Send for Preallocated {}
+unsafe impl Sync for Preallocated {}
+impl Preallocated {
+	/// Allocate a stack of `len` bytes, touching every page so that none fault in later.
+	fn new(len: usize) -> Self {
+		let stack = fringe::OsStack::new(len).unwrap();
+		let limit = stack.limit();
+		unsafe { ptr::write_bytes(limit, 0, stack.base() as usize - limit as usize) };
+		Self(stack)
+	}
+
+	fn len(&self) -> usize {
+		self.0.base() as usize - self.0.limit() as usize
+	}
+}
+
+/// Take a preallocated stack of at least `len` bytes from `stacks`, if there is one.
+fn take_stack(stacks: &mut Vec<Preallocated>, len: usize) -> Option<fringe::OsStack> {
+	let i = stacks.iter().position(|stack| stack.len() >= len)?;
+	Some(stacks.swap_remove(i).0)
+}
+
+/// A value queued by [`Serializer::push_or_queue()`], pushing it when called, with its priority, and when it expires, if it does.
+struct Queued(Box<dyn FnOnce(&mut Serializer) + Send>, i32, Option<Instant>);
+/// Safe as the closure can only be called, and so accessed, through `&mut self`.
+unsafe impl Sync for Queued {}
+
+/// The depth of the stack at the point of calling, measured down from `base`.
+#[inline(never)]
+fn stack_depth(base: usize) -> usize {
+	let marker = 0_u8;
+	base - std::ptr::addr_of!(marker) as usize
+}
+
+/// The deepest a frame may reach into a stack of `stack_len` bytes before it's abandoned, leaving an eighth of it for the frames between one read or write and the next.
+fn max_depth(stack_len: usize) -> usize {
+	stack_len - stack_len / 8
+}
+
+/// A frame nested too deeply for its coroutine stack, which poisons the pipe. Returned by [`Serializer::poisoned()`] and [`Deserializer::poisoned()`]; only with the `fringe` feature, as otherwise frames are serialized and deserialized on the caller's own stack.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StackOverflow {
+	/// The type being serialized or deserialized.
+	pub type_name: &'static str,
+	/// The size of the stack it overflowed.
+	pub stack_len: usize,
+}
+impl fmt::Display for StackOverflow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} nested too deeply for its {} byte stack",
+			self.type_name, self.stack_len
+		)
+	}
+}
+impl error::Error for StackOverflow {}
+
+/// Panic if a pipe is poisoned by `overflow`, as it can't be pushed to or pulled from until it's emptied.
+#[track_caller]
+#[cfg_attr(feature = "terse-errors", allow(unused_variables))]
+fn check_poisoned(overflow: Option<StackOverflow>) {
+	if let Some(overflow) = overflow {
+		fail!(
+			"pipe poisoned by a stack overflow; empty() it to reset it",
+			"pipe poisoned: {}; empty() it to reset it",
+			overflow
+		);
+	}
+}
+
+/// How frames are delimited on the wire: the length prefix preceding each frame.
+///
+/// Both ends of a pipe must use the same framing. With the `fringe` feature, whose wire format has no frame header, [`Serializer::with_framing()`] and [`Deserializer::with_framing()`] panic.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Framing {
+	/// An 8-byte little-endian length prefix, as bincode encodes a `u64`. This is the default.
+	Bincode,
+	/// A 4-byte big-endian length prefix, as read by Java's `DataInputStream.readInt()`. Frames are limited to `i32::MAX` bytes, and [`Control`] frames aren't supported.
+	BigEndianU32,
+	/// gRPC's 5-byte message prefix: a 1-byte compressed flag, always `0`, then a 4-byte big-endian length. Frames are limited to `u32::MAX` bytes, compressed messages aren't supported, and [`Control`] frames aren't supported.
+	///
+	/// Zero-length messages, such as `google.protobuf.Empty`, are valid payloads: a zero-length encoding is sent as one rather than padded, and one received is pulled rather than absorbed as a heartbeat. Heartbeats therefore aren't supported.
+	Grpc,
+}
+
+/// An out-of-band control frame, sent with [`Serializer::push_control()`] and received with [`Deserializer::pull_control()`].
+///
+/// These are distinguished from data frames by their header, and are delivered in order with respect to them. Their meaning is up to the application; the pipes attach no behaviour to them. With the `fringe` feature, whose wire format has no frame header, [`Serializer::push_control()`] panics, and so none are received.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Control {
+	/// Request that the receiver flush.
+	Flush,
+	/// Notify the receiver that the sender is closing.
+	Close,
+	/// Notify the receiver of an error, with an application-defined code.
+	Error(u32),
+	/// An application-defined code.
+	App(u32),
+}
+
 #[derive(Debug)]
 enum SerializerMsg<T> {
 	Kill,
-	Next,
+	/// Resume serializing into the given block, emptied for reuse.
+	Next(Vec<u8>),
 	New(T),
 }
 struct SerializerInner<T: serde::ser::Serialize + 'static> {
 	generator: Option<
-		fringe::generator::Generator<'static, SerializerMsg<T>, Option<u8>, fringe::OsStack>,
+		fringe::generator::Generator<'static, SerializerMsg<T>, Option<Vec<u8>>, fringe::OsStack>,
 	>,
+	/// The block last yielded by the generator, and how much of it has been pulled.
+	block: Vec<u8>,
+	index: usize,
+	/// The length of the next block, if it isn't pinned, growing as the frame streams.
+	block_len: usize,
+	stack_len: usize,
+	/// The deepest the generator has reached into its stack.
+	high_water: Arc<AtomicUsize>,
+	/// Whether a frame was abandoned as it neared the end of the stack.
+	overflowed: Arc<AtomicBool>,
 	_marker: marker::PhantomData<fn(T)>,
 }
 /// These are I believe safe, as there's almost certainly nothing !Send on the stack, at least nothing that crosses the boundary; and all access is mediated through &mut self
@@ -21,36 +159,73 @@ unsafe impl<T: serde::ser::Serialize + 'static> Sync for SerializerInner<T> {}
 impl<T: serde::ser::Serialize + 'static> SerializerInner<T> {
 	#[inline(always)]
 	fn new(stack: Option<fringe::OsStack>) -> Self {
-		let stack = stack.unwrap_or_else(|| fringe::OsStack::new(64 * 1024).unwrap());
-		let generator = fringe::generator::Generator::<SerializerMsg<T>, Option<u8>, _>::new(
+		let stack = stack.unwrap_or_else(|| fringe::OsStack::new(STACK_LEN).unwrap());
+		let base = stack.base() as usize;
+		let stack_len = base - stack.limit() as usize;
+		let high_water = Arc::new(AtomicUsize::new(0));
+		let high_water_ = high_water.clone();
+		let overflowed = Arc::new(AtomicBool::new(false));
+		let overflowed_ = overflowed.clone();
+		let generator = fringe::generator::Generator::<SerializerMsg<T>, Option<Vec<u8>>, _>::new(
 			stack,
 			move |yielder, t| {
 				let mut x = Some(t);
-				while let Some(t) = match x.take().unwrap_or_else(|| yielder.suspend(None)) {
+				// the empty block that ends each frame, yielded once the rest of it has been pulled
+				let mut block = None;
+				while let Some(t) = match x.take().unwrap_or_else(|| yielder.suspend(block.take())) {
 					SerializerMsg::New(t) => Some(t),
 					SerializerMsg::Kill => None,
 					_ => panic!(),
 				} {
-					if let SerializerMsg::Next = yielder.suspend(None) {
-					} else {
-						panic!()
-					}
+					let block_ = match yielder.suspend(None) {
+						SerializerMsg::Next(block) => block,
+						// abandon the frame if killed while unwinding, rather than panicking again
+						SerializerMsg::Kill if std::thread::panicking() => break,
+						_ => panic!(),
+					};
 					struct Writer<'a, T: 'a>(
-						&'a fringe::generator::Yielder<SerializerMsg<T>, Option<u8>>,
+						&'a fringe::generator::Yielder<SerializerMsg<T>, Option<Vec<u8>>>,
+						usize,
+						&'a AtomicUsize,
+						usize,
+						&'a AtomicBool,
+						Vec<u8>,
+						bool,
 					);
 					impl<'a, T: 'a> Write for Writer<'a, T> {
 						#[inline(always)]
 						fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-							for byte in buf {
-								if let SerializerMsg::Next = self.0.suspend(Some(*byte)) {
-								} else {
-									panic!()
-								};
+							let depth = stack_depth(self.1);
+							let _ = self.2.fetch_max(depth, Ordering::Relaxed);
+							if depth > self.3 {
+								self.4.store(true, Ordering::Relaxed);
+								return Err(io::Error::other("stack overflow"));
+							}
+							let mut rest = buf;
+							while !rest.is_empty() {
+								let len = rest.len().min(self.5.capacity() - self.5.len());
+								self.5.extend_from_slice(&rest[..len]);
+								rest = &rest[len..];
+								if self.5.len() == self.5.capacity() {
+									self.flush()?;
+								}
 							}
 							Ok(buf.len())
 						}
+						/// Yield the block, if it's not empty, awaiting the next.
 						#[inline(always)]
 						fn flush(&mut self) -> io::Result<()> {
+							if !self.5.is_empty() {
+								let block = std::mem::take(&mut self.5);
+								match self.0.suspend(Some(block)) {
+									SerializerMsg::Next(block) => self.5 = block,
+									SerializerMsg::Kill if std::thread::panicking() => {
+										self.6 = true;
+										return Err(io::Error::new(io::ErrorKind::BrokenPipe, ""));
+									}
+									_ => panic!(),
+								}
+							}
 							Ok(())
 						}
 					}
@@ -74,23 +249,51 @@ impl<T: serde::ser::Serialize + 'static> SerializerInner<T> {
 							self.0.flush()
 						}
 					}
-					let mut writer = Writer(yielder);
+					let mut writer = Writer(
+						yielder,
+						base,
+						&high_water_,
+						max_depth(stack_len),
+						&overflowed_,
+						block_,
+						false,
+					);
 					let mut counter = Counter::new(&mut writer);
-					bincode::serialize_into(&mut counter, &t).unwrap();
-					if counter.count() == 0 {
-						let _ = writer.write(&[0]).unwrap();
+					let ret = wire::serialize_into(&mut counter, &t);
+					let count = counter.count();
+					if overflowed_.load(Ordering::Relaxed) {
+						// end the frame where it is, leaking the value, as dropping it would likely overflow too
+						std::mem::forget(t);
+					} else if !writer.6 {
+						ret.unwrap();
+						if count == 0 && writer.write(&[0]).is_err() {
+							assert!(writer.6);
+						}
+					}
+					if writer.flush().is_err() || writer.6 {
+						// killed while unwinding, so abandon the frame
+						break;
 					}
+					block = Some(writer.5);
 				}
 			},
 		);
 		Self {
 			generator: Some(generator),
+			block: Vec::new(),
+			index: 0,
+			block_len: BLOCK_LEN,
+			stack_len,
+			high_water,
+			overflowed,
 			_marker: marker::PhantomData,
 		}
 	}
 
 	#[inline(always)]
 	fn push(&mut self, t: T) {
+		// start each frame with a small block, so short frames aren't staged in a large one
+		self.block_len = BLOCK_LEN;
 		let x = self
 			.generator
 			.as_mut()
@@ -100,13 +303,48 @@ impl<T: serde::ser::Serialize + 'static> SerializerInner<T> {
 		assert!(x.is_none());
 	}
 
+	/// The next byte of the frame, or [`None`] at its end. Once the block is pulled, the generator is resumed with it, reallocated to `block_len` bytes if need be, to fill the next. If `block_len` is [`None`], each block in the frame is twice the last, up to [`MAX_BLOCK_LEN`].
 	#[inline(always)]
-	fn next(&mut self) -> Option<u8> {
-		self.generator
-			.as_mut()
-			.unwrap()
-			.resume(SerializerMsg::Next)
-			.unwrap()
+	fn next(&mut self, block_len: Option<usize>) -> Option<u8> {
+		if self.index == self.block.len() {
+			let block_len = block_len.unwrap_or_else(|| {
+				let block_len = self.block_len;
+				self.block_len = (block_len * 2).min(MAX_BLOCK_LEN);
+				block_len
+			});
+			let mut block = std::mem::take(&mut self.block);
+			block.clear();
+			if block.capacity() != block_len {
+				block = Vec::with_capacity(block_len);
+			}
+			self.block = self
+				.generator
+				.as_mut()
+				.unwrap()
+				.resume(SerializerMsg::Next(block))
+				.unwrap()
+				.unwrap();
+			self.index = 0;
+			if self.block.is_empty() {
+				return None;
+			}
+		}
+		let byte = self.block[self.index];
+		self.index += 1;
+		Some(byte)
+	}
+
+	/// The bytes of the block yet to be pulled, starting with the one last returned by [`next`](SerializerInner::next()).
+	#[inline(always)]
+	fn pending(&self) -> &[u8] {
+		&self.block[self.index - 1..]
+	}
+
+	/// Skip `len` bytes of the block, returning the last one skipped to, as [`next`](SerializerInner::next()) would have.
+	#[inline(always)]
+	fn skip(&mut self, len: usize) -> u8 {
+		self.index += len;
+		self.block[self.index - 1]
 	}
 
 	#[inline(always)]
@@ -116,31 +354,79 @@ impl<T: serde::ser::Serialize + 'static> SerializerInner<T> {
 		assert!(x.is_none());
 		generator.unwrap()
 	}
+
+	/// Whether the frames so far have used more than half the stack.
+	#[inline(always)]
+	fn outgrown(&self) -> bool {
+		self.high_water.load(Ordering::Relaxed) > self.stack_len / 2
+	}
+
+	/// The overflow, if a frame was abandoned as it neared the end of the stack.
+	#[inline(always)]
+	fn overflow(&self) -> Option<StackOverflow> {
+		self.overflowed
+			.load(Ordering::Relaxed)
+			.then(|| StackOverflow {
+				type_name: any::type_name::<T>(),
+				stack_len: self.stack_len,
+			})
+	}
+
+	/// Between frames, respawn onto a stack of twice the size, preferring one from `stacks`.
+	#[inline(always)]
+	fn regrow(self, stacks: &mut Vec<Preallocated>) -> Self {
+		let stack_len = self.stack_len * 2;
+		trace!("growing stack to {} bytes", stack_len);
+		drop(self.into_stack());
+		let stack = take_stack(stacks, stack_len)
+			.unwrap_or_else(|| fringe::OsStack::new(stack_len).unwrap());
+		Self::new(Some(stack))
+	}
 }
 impl<T: serde::ser::Serialize + 'static> Drop for SerializerInner<T> {
 	#[inline(always)]
 	fn drop(&mut self) {
 		if let Some(mut generator) = self.generator.take() {
 			let x = generator.resume(SerializerMsg::Kill);
-			assert!(x.is_none());
+			ensure!(x.is_none(), "serializer coroutine didn't finish when killed");
 			let _ = generator.unwrap();
 		}
 	}
 }
 trait SerializerInnerBox: Send + Sync {
-	fn next_box(&mut self) -> Option<u8>;
+	fn next_box(&mut self, block_len: Option<usize>) -> Option<u8>;
+	fn pending_box(&self) -> &[u8];
+	fn skip_box(&mut self, len: usize) -> u8;
 	fn into_stack_box(self: Box<Self>) -> fringe::OsStack;
+	fn outgrown_box(&self) -> bool;
+	fn overflow_box(&self) -> Option<StackOverflow>;
+	fn regrow_box(self: Box<Self>, stacks: &mut Vec<Preallocated>) -> Box<dyn SerializerInnerBox>;
 	fn as_any_ref(&self) -> &dyn Any;
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 	fn as_any_box(self: Box<Self>) -> Box<dyn Any>;
 }
 impl<T: serde::ser::Serialize + 'static> SerializerInnerBox for SerializerInner<T> {
-	fn next_box(&mut self) -> Option<u8> {
-		self.next()
+	fn next_box(&mut self, block_len: Option<usize>) -> Option<u8> {
+		self.next(block_len)
+	}
+	fn pending_box(&self) -> &[u8] {
+		self.pending()
+	}
+	fn skip_box(&mut self, len: usize) -> u8 {
+		self.skip(len)
 	}
 	fn into_stack_box(self: Box<Self>) -> fringe::OsStack {
 		self.into_stack()
 	}
+	fn outgrown_box(&self) -> bool {
+		self.outgrown()
+	}
+	fn overflow_box(&self) -> Option<StackOverflow> {
+		self.overflow()
+	}
+	fn regrow_box(self: Box<Self>, stacks: &mut Vec<Preallocated>) -> Box<dyn SerializerInnerBox> {
+		Box::new(self.regrow(stacks))
+	}
 	fn as_any_ref(&self) -> &dyn Any {
 		self as &dyn Any
 	}
@@ -175,34 +461,135 @@ impl<T: serde::ser::Serialize + 'static> SerializerInnerBox for SerializerInner<
 ///
 /// # Panics
 ///
-/// Will panic if dropped while non-empty. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Serializer::empty()`] before dropping it.
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Serializer::empty()`] before dropping it.
+///
+/// With the `fringe` feature, will also panic if pushed to or pulled from while [`poisoned`](Serializer::poisoned()).
 pub struct Serializer {
 	serializer: Option<Box<dyn SerializerInnerBox>>,
+	/// Idle inner pipelines for other types, least recently used first.
+	cache: Vec<Box<dyn SerializerInnerBox>>,
+	/// Stacks allocated ahead of time, for new or grown inner pipelines.
+	stacks: Vec<Preallocated>,
+	/// Values queued by [`push_or_queue`](Serializer::push_or_queue()) to be pushed once the current frame is pulled. Kept in order of priority, highest first.
+	queue: VecDeque<Queued>,
+	queue_limit: usize,
+	block_len: Option<usize>,
+	expired: usize,
+	overflow: Option<StackOverflow>,
 	done: bool,
 	pull: Option<u8>,
+	/// The bytes last pulled by [`pull_chunk`](Serializer::pull_chunk()).
+	chunk: Vec<u8>,
+	telemetry: Option<Telemetry>,
+	stalls: Option<StallTimer>,
+	frame: (&'static str, usize),
 }
 impl Serializer {
 	/// Construct a new Serializer pipe.
+	///
+	/// This doesn't allocate, and is a `const fn` so it can be used to initialise a `static`.
+	#[must_use]
 	#[inline(always)]
-	pub fn new() -> Self {
+	pub const fn new() -> Self {
 		Self {
 			serializer: None,
+			cache: Vec::new(),
+			stacks: Vec::new(),
+			queue: VecDeque::new(),
+			queue_limit: QUEUE_LIMIT,
+			block_len: None,
+			expired: 0,
+			overflow: None,
 			done: true,
 			pull: None,
+			chunk: Vec::new(),
+			telemetry: None,
+			stalls: None,
+			frame: ("", 0),
 		}
 	}
 
+	/// Construct a new Serializer pipe that delimits frames with the given [`Framing`].
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend's wire format has no frame header.
+	#[must_use]
+	#[inline(always)]
+	pub const fn with_framing(framing: Framing) -> Self {
+		let _ = framing;
+		fail!("framing isn't supported with the fringe feature, as its wire format has no frame header")
+	}
+
+	/// Construct a new Serializer pipe with `t` already pushed, ready to be [`pull`](Serializer::pull())ed.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn of<T: serde::ser::Serialize + 'static>(t: T) -> Self {
+		let mut serializer = Self::new();
+		serializer.push().unwrap()(t);
+		serializer
+	}
+
+	/// Set a callback to be invoked as each frame is serialized, or [`None`] to clear it. See [`telemetry`](crate::telemetry).
+	pub fn set_telemetry(&mut self, telemetry: Option<Telemetry>) {
+		self.telemetry = telemetry;
+	}
+
+	/// Enable or disable timing how long this pipe spends awaiting a push versus a pull, returned by [`stalls`](Serializer::stalls()). Enabling it restarts the timing. See [`telemetry`](crate::telemetry).
+	pub fn set_stall_timing(&mut self, enabled: bool) {
+		self.stalls = enabled.then(|| StallTimer::new(self.pull_avail()));
+	}
+
+	/// The time spent awaiting a push versus a pull since [`set_stall_timing`](Serializer::set_stall_timing()) was enabled, or [`None`] if it isn't.
+	pub fn stalls(&self) -> Option<Stalls> {
+		self.stalls.as_ref().map(StallTimer::stalls)
+	}
+
+	/// Allocate `count` coroutine stacks of `len` bytes now, touching every page, for use as inner pipelines are created for new types or grown, so that latency-critical code doesn't take page faults mid-frame.
+	///
+	/// Every stack, preallocated or not, has a guard page beneath it, so an overflow faults rather than corrupting memory. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if the stacks can't be allocated.
+	pub fn preallocate_stacks(&mut self, count: usize, len: usize) {
+		self.stacks
+			.extend((0..count).map(|_| Preallocated::new(len)));
+	}
+
+	/// Pin the number of bytes serialized between each switch into and out of the coroutine, or [`None`] to adapt it, as by default. Adapted, each frame starts with a block of 32 bytes, so short frames are staged in little, and each block after is twice the last, up to 64 KiB, so large frames take few context switches. Takes effect from the next block. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if `block_len` is zero.
+	pub fn set_block_len(&mut self, block_len: Option<usize>) {
+		assert!(block_len != Some(0), "block_len must be at least 1");
+		self.block_len = block_len;
+	}
+
+	/// The overflow, if a frame nested too deeply for its stack, in which case it was cut short, and this pipe is poisoned until it's [`empty`](Serializer::empty())ed: [`push`](Serializer::push()) and [`pull`](Serializer::pull()) panic with it, and [`drain_to`](Serializer::drain_to()) returns it as an error. Frames are abandoned once they near the end of their stack, so the process isn't aborted by the guard page, provided they write between each level of nesting, as bincode does. The value is leaked rather than dropped, as dropping it would likely overflow too. Always [`None`] without the `fringe` feature.
+	#[must_use]
+	pub fn poisoned(&self) -> Option<StackOverflow> {
+		self.overflow
+	}
+
 	#[doc(hidden)]
 	pub fn push_avail(&self) -> bool {
 		self.done
 	}
 	/// Push a `T` to the Serializer pipe. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	#[track_caller]
 	pub fn push<'a, T: serde::ser::Serialize + 'static>(
 		&'a mut self,
 	) -> Option<impl FnOnce(T) + 'a> {
-		if self.done {
+		check_poisoned(self.overflow);
+		if self.push_avail() {
 			Some(move |t| {
+				trace!("serializer: frame start");
 				self.done = false;
+				self.frame = (any::type_name::<T>(), 0);
 				if self.serializer.is_none()
 					|| !self
 						.serializer
@@ -211,9 +598,23 @@ impl Serializer {
 						.as_any_ref()
 						.is::<SerializerInner<T>>()
 				{
-					self.serializer = Some(Box::new(SerializerInner::<T>::new(
-						self.serializer.take().map(|x| x.into_stack_box()),
-					)));
+					let cached = self
+						.cache
+						.iter()
+						.position(|x| x.as_any_ref().is::<SerializerInner<T>>());
+					let cached = cached.map(|i| self.cache.remove(i));
+					self.cache.extend(self.serializer.take());
+					self.serializer = Some(if let Some(cached) = cached {
+						cached
+					} else {
+						// evict the least recently used inner pipeline if the cache is full, reusing its stack, else take a preallocated one
+						let stack = if self.cache.len() > CACHE_LEN {
+							Some(self.cache.remove(0).into_stack_box())
+						} else {
+							take_stack(&mut self.stacks, STACK_LEN)
+						};
+						Box::new(SerializerInner::<T>::new(stack))
+					});
 				}
 				self.serializer
 					.as_mut()
@@ -222,30 +623,216 @@ impl Serializer {
 					.downcast_mut::<SerializerInner<T>>()
 					.unwrap()
 					.push(t);
-				let ret = self.serializer.as_mut().unwrap().next_box();
-				self.pull = Some(ret.unwrap());
+				let _ = self.advance();
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
 
+	/// Push an `A` or a `B` to the Serializer pipe, tagged so that the receiving end can [`pull_either`](Deserializer::pull_either()) it without declaring a wrapper enum. Nest [`Either`] for more types, for example `push_tagged::<A, Either<B, C>>()`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce(Either<A, B>)` that can be called to perform the `push`.
+	pub fn push_tagged<A: serde::ser::Serialize + 'static, B: serde::ser::Serialize + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce(Either<A, B>) + '_> {
+		self.push()
+	}
+
+	/// Push a `T` to the Serializer pipe if it's awaiting a push, otherwise queue it to be pulled after the frames already pushed. [`Err`] returns `t` if [`queue_limit`](Serializer::set_queue_limit()) values are already queued.
+	///
+	/// Queued values are held unserialized until their turn, so memory usage remains bounded.
+	///
+	/// # Errors
+	///
+	/// Returns `t` if the queue is full.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_or_queue<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T,
+	) -> Result<(), T> {
+		self.push_or_queue_until(t, 0, None)
+	}
+
+	/// Push each of `values` in turn as with [`push_or_queue`](Serializer::push_or_queue()), so a burst of values needn't be interleaved with checks of whether each can be pushed. The first is pushed if the Serializer is awaiting a push, and the rest are queued behind it.
+	///
+	/// # Errors
+	///
+	/// Returns the values not pushed, starting with the first that didn't fit, if the queue fills. [`set_queue_limit`](Serializer::set_queue_limit()) can make room for larger bursts.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_batch<T: serde::ser::Serialize + Send + 'static, I: IntoIterator<Item = T>>(
+		&mut self, values: I,
+	) -> Result<(), Vec<T>> {
+		let mut values = values.into_iter();
+		while let Some(t) = values.next() {
+			if let Err(t) = self.push_or_queue(t) {
+				return Err(iter::once(t).chain(values).collect());
+			}
+		}
+		Ok(())
+	}
+
+	/// Push a `T` as with [`push_or_queue`](Serializer::push_or_queue()), but if it's queued, and hasn't started to be pulled within `ttl`, drop it unsent, counting it in [`expired`](Serializer::expired()). For values worthless once stale, which would otherwise delay fresher ones behind them.
+	///
+	/// # Errors
+	///
+	/// Returns `t` if the queue is full of unexpired values.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`push`](Serializer::push()).
+	pub fn push_or_queue_with_ttl<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T, ttl: Duration,
+	) -> Result<(), T> {
+		self.push_or_queue_until(t, 0, Some(Instant::now() + ttl))
+	}
+
+	/// Push or queue a `T`, queueing it ahead of those of lower `priority`, and behind those of equal or higher. Shared with [`QueuedSerializer`](crate::queue::QueuedSerializer).
+	pub(crate) fn push_or_queue_until<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, t: T, priority: i32, deadline: Option<Instant>,
+	) -> Result<(), T> {
+		if self.push_avail() {
+			self.push().unwrap()(t);
+			return Ok(());
+		}
+		self.expire();
+		if self.queue.len() < self.queue_limit {
+			let index = self
+				.queue
+				.iter()
+				.position(|queued| queued.1 < priority)
+				.unwrap_or(self.queue.len());
+			self.queue.insert(
+				index,
+				Queued(
+					Box::new(move |serializer: &mut Self| {
+						serializer.push().unwrap()(t);
+					}),
+					priority,
+					deadline,
+				),
+			);
+			Ok(())
+		} else {
+			Err(t)
+		}
+	}
+
+	/// Whether [`push_or_queue_until`](Serializer::push_or_queue_until()) would push or queue rather than return the value.
+	pub(crate) fn queue_avail(&self) -> bool {
+		if self.push_avail() || self.queue.len() < self.queue_limit {
+			return true;
+		}
+		let now = Instant::now();
+		self.queue
+			.iter()
+			.any(|queued| matches!(queued.2, Some(deadline) if deadline <= now))
+	}
+
+	/// The number of values queued behind the frame being pulled.
+	pub(crate) fn queued(&self) -> usize {
+		self.queue.len()
+	}
+
+	/// Drop queued values whose TTL has passed.
+	fn expire(&mut self) {
+		if self.queue.iter().any(|queued| queued.2.is_some()) {
+			let now = Instant::now();
+			let len = self.queue.len();
+			self.queue
+				.retain(|queued| !matches!(queued.2, Some(deadline) if deadline <= now));
+			self.expired += len - self.queue.len();
+		}
+	}
+
+	/// The number of values queued by [`push_or_queue_with_ttl`](Serializer::push_or_queue_with_ttl()) that were dropped unsent as their TTL passed.
+	#[must_use]
+	pub fn expired(&self) -> usize {
+		self.expired
+	}
+
+	/// Set how many values [`push_or_queue`](Serializer::push_or_queue()) queues behind the frame being pulled before returning them. Defaults to 16.
+	pub fn set_queue_limit(&mut self, queue_limit: usize) {
+		self.queue_limit = queue_limit;
+	}
+
+	/// Push a frame whose payload is written by `f`, for example bytes produced by a different encoder. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()), in which case `f` isn't called. [`Some`] contains the result of `f`; if it's an error, no frame is pushed.
+	///
+	/// With the `fringe` feature, whose wire format has no frame header to delimit such a payload, `f` isn't called, and [`Some`] contains an error of kind [`io::ErrorKind::Unsupported`].
+	pub fn push_with<F: FnOnce(&mut dyn Write) -> io::Result<()>>(
+		&mut self, f: F,
+	) -> Option<io::Result<()>> {
+		let _ = f;
+		self.push_avail().then(|| {
+			Err(io::Error::new(
+				io::ErrorKind::Unsupported,
+				"raw frames aren't supported with the fringe feature, as its wire format has no frame header",
+			))
+		})
+	}
+
+	/// Set the alignment of the start of frames pushed after this, so that the slices returned by [`pull_buf`](Serializer::pull_buf()) can be handed directly to DMA engines or `io_uring` registered buffers. Defaults to 1.
+	///
+	/// With the `fringe` feature, the slices are cut from its coroutine's blocks as they're generated, so the alignment is checked but has no effect.
+	///
+	/// # Panics
+	///
+	/// Will panic if `alignment` isn't a power of two.
+	#[track_caller]
+	#[allow(clippy::unused_self)]
+	pub fn set_alignment(&mut self, alignment: usize) {
+		ensure!(
+			alignment.is_power_of_two(),
+			"alignment must be a power of two",
+			"alignment must be a power of two, not {}",
+			alignment
+		);
+	}
+
+	/// Push a heartbeat frame to the Serializer pipe. This is an empty frame that the receiving [`Deserializer`] absorbs, counting it in [`Deserializer::heartbeats()`], so that idle connections can signify liveness. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if called on a Serializer using [`Framing::Grpc`], under which an empty frame is an empty message, or with the `fringe` feature, as that backend's wire format has no frame header to mark one with.
+	#[track_caller]
+	#[allow(unreachable_code, clippy::unused_self)]
+	pub fn push_heartbeat(&mut self) -> Option<impl FnOnce()> {
+		fail!("heartbeats aren't supported with the fringe feature, as its wire format has no frame header");
+		Some(|| ())
+	}
+
+	/// Push a [`Control`] frame to the Serializer pipe. [`None`] denotes that the Serializer is instead awaiting a [`pull`](Serializer::pull()). [`Some`] contains an `impl FnOnce(Control)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if called on a Serializer that isn't using [`Framing::Bincode`], or with the `fringe` feature, as that backend's wire format has no frame header to mark one with.
+	#[track_caller]
+	#[allow(unreachable_code, clippy::unused_self)]
+	pub fn push_control(&mut self) -> Option<impl FnOnce(Control)> {
+		fail!("control frames aren't supported with the fringe feature, as its wire format has no frame header");
+		Some(|_: Control| ())
+	}
+
 	#[doc(hidden)]
 	pub fn pull_avail(&self) -> bool {
-		self.pull.is_some()
+		self.pull.is_some() || self.overflow.is_some()
 	}
 	/// Pull a `T` from the Serializer pipe. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	#[track_caller]
 	pub fn pull<'a>(&'a mut self) -> Option<impl FnOnce() -> u8 + 'a> {
+		check_poisoned(self.overflow);
 		if self.pull.is_some() {
 			Some(move || {
 				let ret = self.pull.take().unwrap();
+				self.frame.1 += 1;
 				if !self.done {
-					let ret = self.serializer.as_mut().unwrap().next_box();
-					if ret.is_none() {
-						self.done = true;
-					}
-					self.pull = ret;
+					let _ = self.advance();
 				}
+				self.time_stalls();
 				ret
 			})
 		} else {
@@ -253,29 +840,263 @@ impl Serializer {
 		}
 	}
 
+	/// Pull the remaining bytes of the frame being pulled. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). [`Some`] contains an `impl FnOnce() -> &[u8]` that can be called to perform the `pull`, so the bytes can be copied out at once rather than a byte at a time.
+	///
+	/// The bytes returned never span two frames. With the `fringe` feature, at most 4 KiB are returned at once, so its memory use stays bounded.
+	#[track_caller]
+	pub fn pull_chunk<'a>(&'a mut self) -> Option<impl FnOnce() -> &'a [u8] + 'a> {
+		check_poisoned(self.overflow);
+		if self.pull.is_some() {
+			Some(move || {
+				let mut chunk = std::mem::take(&mut self.chunk);
+				chunk.clear();
+				let _ = self.pull_frame_into(&mut chunk, CHUNK_LEN);
+				self.chunk = chunk;
+				let this = self;
+				&this.chunk[..]
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Pull the remaining bytes of the frame being pulled, as a `Vec`. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). [`Some`] contains an `impl FnOnce() -> Vec<u8>` that can be called to perform the `pull`, so that serializing a value to its framed bytes is a `push` and a single `pull_frame`.
+	#[track_caller]
+	pub fn pull_frame(&mut self) -> Option<impl FnOnce() -> Vec<u8> + '_> {
+		check_poisoned(self.overflow);
+		if self.pull.is_some() {
+			Some(move || {
+				let mut frame = Vec::new();
+				let _ = self.pull_frame_into(&mut frame, usize::MAX);
+				frame
+			})
+		} else {
+			None
+		}
+	}
+
+	/// The remaining bytes of the frame being pulled, without pulling them. [`None`] denotes that the Serializer is instead awaiting a [`push`](Serializer::push()). A frame's first slice starts at a multiple of the [alignment](Serializer::set_alignment()). Call [`consume`](Serializer::consume()) to mark bytes as pulled.
+	///
+	/// With the `fringe` feature, only the bytes its coroutine has generated so far are returned, up to 64 KiB, and once they're consumed the next call returns those that follow.
+	#[must_use]
+	#[track_caller]
+	pub fn pull_buf(&self) -> Option<&[u8]> {
+		check_poisoned(self.overflow);
+		let _ = self.pull?;
+		self.serializer
+			.as_deref()
+			.map(SerializerInnerBox::pending_box)
+	}
+
+	/// Mark `amt` bytes returned by [`pull_buf`](Serializer::pull_buf()) as pulled, as if by calling [`pull`](Serializer::pull()) `amt` times.
+	///
+	/// # Panics
+	///
+	/// Will panic if `amt` exceeds the length of the slice returned by [`pull_buf`](Serializer::pull_buf()).
+	#[track_caller]
+	pub fn consume(&mut self, amt: usize) {
+		if amt == 0 {
+			return;
+		}
+		ensure!(
+			amt <= self.pull_buf().map_or(0, <[u8]>::len),
+			"consumed more bytes than pull_buf() returned"
+		);
+		if amt > 1 {
+			// skip to the last byte consumed, then pull it as usual, so a frame ending is handled in one place
+			self.pull = Some(self.serializer.as_mut().unwrap().skip_box(amt - 1));
+			self.frame.1 += amt - 1;
+		}
+		let _ = self.pull().unwrap()();
+	}
+
+	/// Pull a byte, along with whether it was the last of its frame.
+	pub(crate) fn pull_byte(&mut self) -> Option<(u8, bool)> {
+		let byte = self.pull()?();
+		// either nothing follows, or the next queued frame has started
+		Some((byte, self.pull.is_none() || self.frame.1 == 0))
+	}
+
+	/// Write the remaining bytes of the frame being pulled to `writer`, returning how many were written, stopping at the end of the frame rather than continuing into those queued by [`push_or_queue`](Serializer::push_or_queue()). `Ok(0)` denotes that the Serializer is awaiting a [`push`](Serializer::push()).
+	///
+	/// Bytes are only pulled as `writer` accepts them, so after an error the call can be retried, resuming where it left off. With the `fringe` feature, the bytes are instead pulled up to 4 KiB at a time and written with [`write_all`](Write::write_all()), so after an error up to that many may have been pulled but not written.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to `writer` other than [`io::ErrorKind::Interrupted`], which is retried, or [`io::ErrorKind::WriteZero`] if it accepts no bytes. With the `fringe` feature, returns the [`StackOverflow`] as an error of kind [`io::ErrorKind::Other`] if the Serializer is, or becomes, [`poisoned`](Serializer::poisoned()).
+	pub fn drain_to<W: Write>(&mut self, mut writer: W) -> io::Result<usize> {
+		let mut chunk = std::mem::take(&mut self.chunk);
+		let mut drained = 0;
+		let mut ended = self.pull.is_none();
+		while !ended && self.overflow.is_none() {
+			chunk.clear();
+			ended = self.pull_frame_into(&mut chunk, CHUNK_LEN);
+			if let Err(err) = writer.write_all(&chunk) {
+				self.chunk = chunk;
+				return Err(err);
+			}
+			drained += chunk.len();
+		}
+		self.chunk = chunk;
+		match self.overflow {
+			Some(overflow) => Err(io::Error::other(overflow)),
+			None => Ok(drained),
+		}
+	}
+
+	/// Pull bytes of the frame being pulled onto `bytes`, until it ends or `limit` have been pulled, returning whether it ended.
+	fn pull_frame_into(&mut self, bytes: &mut Vec<u8>, limit: usize) -> bool {
+		let start = bytes.len();
+		while let Some(block) = self.pull_buf() {
+			// copy a block at a time, rather than a byte
+			let len = block.len().min(limit - (bytes.len() - start));
+			bytes.extend_from_slice(&block[..len]);
+			self.consume(len);
+			// either nothing follows, or the next queued frame has started
+			if self.pull.is_none() || self.frame.1 == 0 {
+				break;
+			}
+			if bytes.len() - start == limit {
+				return false;
+			}
+		}
+		true
+	}
+
+	/// Pull pending bytes into `buf`, returning how many were written, which is less than its length only if the Serializer runs out of bytes and is awaiting a [`push`](Serializer::push()). Unlike [`pull_chunk`](Serializer::pull_chunk()), the bytes written can span frames, filling `buf` from those queued by [`push_or_queue`](Serializer::push_or_queue()).
+	pub fn pull_into(&mut self, buf: &mut [u8]) -> usize {
+		let mut pulled = 0;
+		while pulled < buf.len() {
+			let Some(block) = self.pull_buf() else {
+				break;
+			};
+			let len = block.len().min(buf.len() - pulled);
+			buf[pulled..pulled + len].copy_from_slice(&block[..len]);
+			self.consume(len);
+			pulled += len;
+		}
+		pulled
+	}
+
+	/// Pull pending bytes into each of `bufs` in turn, as [`Read::read_vectored()`](io::Read::read_vectored()) does, returning how many were written in total. As with [`pull_into`](Serializer::pull_into()), this is less than their total length only if the Serializer runs out of bytes, and the bytes written can span frames.
+	pub fn pull_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> usize {
+		let mut pulled = 0;
+		for buf in bufs {
+			let len = self.pull_into(buf);
+			pulled += len;
+			if len < buf.len() {
+				break;
+			}
+		}
+		pulled
+	}
+
+	/// Move on to the next byte of the frame being pulled, ending it if there are none, returning whether it ended. If the frame has overflowed its stack, the rest of it is discarded, and the pipe poisoned, as soon as the generator yields.
+	fn advance(&mut self) -> bool {
+		let serializer = self.serializer.as_mut().unwrap();
+		self.pull = serializer.next_box(self.block_len);
+		if self.pull.is_some() && serializer.overflow_box().is_some() {
+			while serializer.next_box(self.block_len).is_some() {}
+			self.pull = None;
+		}
+		let ended = self.pull.is_none();
+		if ended {
+			self.frame_end();
+		}
+		ended
+	}
+
+	/// Note the end of a frame, and push the next queued value, if any.
+	fn frame_end(&mut self) {
+		trace!("serializer: frame end");
+		self.done = true;
+		let overflow = self.serializer.as_ref().unwrap().overflow_box();
+		if let Some(overflow) = overflow {
+			trace!("serializer: stack overflow");
+			self.overflow = Some(overflow);
+			self.queue.clear();
+		}
+		// respawn an overflowed generator too, so its stack is bigger and its flag cleared
+		if overflow.is_some() || self.serializer.as_ref().unwrap().outgrown_box() {
+			self.serializer = Some(self.serializer.take().unwrap().regrow_box(&mut self.stacks));
+		}
+		if let Some(telemetry) = self.telemetry {
+			telemetry(self.frame.0, self.frame.1, Direction::Serialized);
+		}
+		self.expire();
+		if let Some(queued) = self.queue.pop_front() {
+			(queued.0)(self);
+		}
+	}
+
+	/// Remember the position in the frame being pulled, so that [`rewind`](Serializer::rewind()) can return to it, for example to pull the same bytes again if a write of them failed partway. The mark is forgotten as the frame's last byte is pulled, so bytes can be retransmitted up to the end of the frame.
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend doesn't keep the bytes it has generated.
+	#[track_caller]
+	#[allow(clippy::unused_self)]
+	pub fn mark(&mut self) {
+		fail!("mark isn't supported with the fringe feature, as it doesn't keep the bytes it has generated");
+	}
+
+	/// Return to the position last [`mark`](Serializer::mark())ed, so the bytes pulled since are pulled again. Returns `false` if there's no mark, as the frame it was in has ended. Always `false` with the `fringe` feature, as nothing can be marked.
+	#[allow(clippy::unused_self)]
+	pub fn rewind(&mut self) -> bool {
+		false
+	}
+
 	#[doc(hidden)]
 	pub fn empty_avail(&self) -> bool {
-		!self.done || self.pull.is_some()
+		!self.done || self.pull.is_some() || self.overflow.is_some()
 	}
 	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	///
+	/// This also resets a pipe that's [`poisoned`](Serializer::poisoned()), so it can be pushed to again.
 	pub fn empty<'a>(&'a mut self) -> Option<impl FnOnce() + 'a> {
-		if !self.done || self.pull.is_some() {
+		if self.empty_avail() {
 			Some(move || {
 				if !self.done {
-					while self.serializer.as_mut().unwrap().next_box().is_some() {}
+					while self.serializer.as_mut().unwrap().next_box(self.block_len).is_some() {}
 					self.done = true;
 				}
+				self.overflow = None;
 				self.pull = None;
+				self.queue.clear();
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
+
+	/// Note a possible transition between awaiting a push and a pull, if stall timing is enabled.
+	#[inline(always)]
+	fn time_stalls(&mut self) {
+		let awaiting_pull = self.pull_avail();
+		if let Some(stalls) = &mut self.stalls {
+			stalls.update(awaiting_pull);
+		}
+	}
+
+	/// Consume this pipe, returning the bytes remaining to be pulled. Unlike dropping, this doesn't panic if it's non-empty, so the unsent tail of a torn-down connection can be kept for retransmission.
+	#[must_use]
+	pub fn into_vec(mut self) -> Vec<u8> {
+		self.overflow = None;
+		let mut vec = Vec::new();
+		while let Some(pull) = self.pull() {
+			vec.push(pull());
+		}
+		vec
+	}
 }
 impl Drop for Serializer {
 	#[inline(always)]
 	fn drop(&mut self) {
-		assert!(self.done && self.pull.is_none());
+		ensure!(
+			(self.done && self.pull.is_none()) || std::thread::panicking(),
+			"Serializer dropped mid-frame; empty() it first to discard the rest"
+		);
 	}
 }
 impl Unpin for Serializer {}
@@ -284,9 +1105,39 @@ impl fmt::Debug for Serializer {
 		f.debug_struct("Serializer")
 			.field("done", &self.done)
 			.field("pull", &self.pull.is_some())
+			.field("queue", &self.queue.len())
+			.field("stacks", &self.stacks.len())
+			.field("expired", &self.expired)
+			.field("overflow", &self.overflow)
+			.field("telemetry", &self.telemetry)
+			.field("stalls", &self.stalls())
 			.finish()
 	}
 }
+/// The bytes remaining are those of the frame being pulled. Once they've all been advanced past, the next queued frame's, if any, become remaining. With the `fringe` feature they're those of the frame generated so far, which grow a block at a time as they're advanced past.
+#[cfg(feature = "bytes")]
+impl bytes::Buf for Serializer {
+	fn remaining(&self) -> usize {
+		self.pull_buf().map_or(0, <[u8]>::len)
+	}
+	fn chunk(&self) -> &[u8] {
+		self.pull_buf().unwrap_or(&[])
+	}
+	fn advance(&mut self, cnt: usize) {
+		self.consume(cnt);
+	}
+}
+#[cfg(feature = "defmt")]
+impl defmt::Format for Serializer {
+	fn format(&self, f: defmt::Formatter) {
+		defmt::write!(
+			f,
+			"Serializer {{ done: {=bool}, pull: {=bool} }}",
+			self.done,
+			self.pull.is_some()
+		);
+	}
+}
 
 #[derive(Debug)]
 enum DeserializerMsg {
@@ -299,6 +1150,13 @@ struct DeserializerInner<T: serde::de::DeserializeOwned + 'static> {
 	generator: Option<
 		fringe::generator::Generator<'static, DeserializerMsg, Either<bool, T>, fringe::OsStack>,
 	>,
+	stack_len: usize,
+	/// The deepest the generator has reached into its stack.
+	high_water: Arc<AtomicUsize>,
+	/// Whether a frame was abandoned as it neared the end of the stack, returning the generator.
+	overflowed: Arc<AtomicBool>,
+	/// The bytes the next frame may decode to, read by the generator as the frame starts.
+	size_limit: Arc<AtomicU64>,
 	_marker: marker::PhantomData<fn() -> T>,
 }
 /// These are I believe safe, as there's almost certainly nothing !Send on the stack, at least nothing that crosses the boundary; and all access is mediated through &mut self
@@ -306,8 +1164,17 @@ unsafe impl<T: serde::de::DeserializeOwned + 'static> Send for DeserializerInner
 unsafe impl<T: serde::de::DeserializeOwned + 'static> Sync for DeserializerInner<T> {}
 impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 	#[inline(always)]
+	#[cfg_attr(feature = "terse-errors", allow(unused_variables))]
 	fn new(stack: Option<fringe::OsStack>) -> Self {
-		let stack = stack.unwrap_or_else(|| fringe::OsStack::new(64 * 1024).unwrap());
+		let stack = stack.unwrap_or_else(|| fringe::OsStack::new(STACK_LEN).unwrap());
+		let base = stack.base() as usize;
+		let stack_len = base - stack.limit() as usize;
+		let high_water = Arc::new(AtomicUsize::new(0));
+		let high_water_ = high_water.clone();
+		let overflowed = Arc::new(AtomicBool::new(false));
+		let overflowed_ = overflowed.clone();
+		let size_limit = Arc::new(AtomicU64::new(u64::MAX));
+		let size_limit_ = size_limit.clone();
 		let generator = fringe::generator::Generator::new(stack, move |yielder, t| {
 			let mut x = Some(t);
 			loop {
@@ -330,10 +1197,20 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 					Option<u8>,
 					usize,
 					Option<bool>,
+					usize,
+					&'a AtomicUsize,
+					usize,
+					&'a AtomicBool,
 				);
 				impl<'a, T: 'a> Read for Reader<'a, T> {
 					#[inline(always)]
 					fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+						let depth = stack_depth(self.4);
+						let _ = self.5.fetch_max(depth, Ordering::Relaxed);
+						if depth > self.6 {
+							self.7.store(true, Ordering::Relaxed);
+							return Err(io::Error::other("stack overflow"));
+						}
 						if let Some(killed) = self.3 {
 							return Err(io::Error::new(
 								if killed {
@@ -351,7 +1228,12 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 									match self.0.suspend(Either::Left(false)) {
 										DeserializerMsg::New(t) => Some(Abc::Item(t)),
 										DeserializerMsg::Next => None,
-										DeserializerMsg::Kill if self.2 == 0 => Some(Abc::Kill),
+										// discard a partial frame if killed while unwinding, rather than panicking again
+										DeserializerMsg::Kill
+											if self.2 == 0 || std::thread::panicking() =>
+										{
+											Some(Abc::Kill)
+										}
 										DeserializerMsg::Kill => panic!("{}", self.2),
 										DeserializerMsg::Empty if self.2 > 0 => Some(Abc::Empty),
 										DeserializerMsg::Empty => panic!(),
@@ -394,9 +1276,20 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 						})
 					}
 				}
-				let mut reader = Reader(yielder, t, 0, None);
+				let mut reader = Reader(
+					yielder,
+					t,
+					0,
+					None,
+					base,
+					&high_water_,
+					max_depth(stack_len),
+					&overflowed_,
+				);
 				let mut counter = Counter::new(&mut reader);
-				let ret: Result<T, _> = bincode::deserialize_from(&mut counter);
+				let ret: Result<T, _> = wire::options()
+					.with_limit(size_limit_.load(Ordering::Relaxed))
+					.deserialize_from(&mut counter);
 				if let Err(err) = ret {
 					match *err {
 						bincode::ErrorKind::Io(ref err)
@@ -410,7 +1303,15 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 							x = Some(yielder.suspend(Either::Left(false)));
 							continue;
 						}
-						_ => unreachable!(),
+						// return, as the rest of the frame can't be decoded
+						bincode::ErrorKind::Io(_) if overflowed_.load(Ordering::Relaxed) => break,
+						ref err => fail!(
+							"deserializing failed",
+							"deserializing {} at byte offset {}: {}",
+							any::type_name::<T>(),
+							counter.count().saturating_sub(1),
+							err
+						),
 					}
 				}
 				let ret = ret.unwrap();
@@ -429,24 +1330,31 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 					} {}
 					let x = x.unwrap();
 					if let Some(x) = x {
-						assert_eq!(x, 0);
+						ensure!(x == 0, "the padding byte of a zero-length frame must be 0");
 					} else {
 						break;
 					}
 				}
-				if let DeserializerMsg::Next = yielder.suspend(Either::Left(false)) {
-				} else {
-					panic!()
-				};
-				if let DeserializerMsg::Next = yielder.suspend(Either::Left(true)) {
-				} else {
-					panic!()
-				};
+				match yielder.suspend(Either::Left(false)) {
+					DeserializerMsg::Next => (),
+					// discard the decoded value if killed while unwinding, rather than panicking again
+					DeserializerMsg::Kill if std::thread::panicking() => break,
+					_ => panic!(),
+				}
+				match yielder.suspend(Either::Left(true)) {
+					DeserializerMsg::Next => (),
+					DeserializerMsg::Kill if std::thread::panicking() => break,
+					_ => panic!(),
+				}
 				x = Some(yielder.suspend(Either::Right(ret)));
 			}
 		});
 		Self {
 			generator: Some(generator),
+			stack_len,
+			high_water,
+			overflowed,
+			size_limit,
 			_marker: marker::PhantomData,
 		}
 	}
@@ -457,9 +1365,7 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 			.as_mut()
 			.unwrap()
 			.resume(DeserializerMsg::Next)
-			.unwrap()
-			.left()
-			.unwrap()
+			.map_or(false, |x| x.left().unwrap())
 	}
 
 	#[inline(always)]
@@ -497,13 +1403,14 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 
 	#[inline(always)]
 	fn next(&mut self, x: u8) {
-		let x = self
+		if let Some(x) = self
 			.generator
 			.as_mut()
 			.unwrap()
 			.resume(DeserializerMsg::New(x))
-			.unwrap();
-		assert!(!x.left().unwrap());
+		{
+			assert!(!x.left().unwrap());
+		}
 	}
 
 	#[inline(always)]
@@ -513,13 +1420,41 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInner<T> {
 		assert!(x.is_none());
 		generator.unwrap()
 	}
+
+	/// Whether the frames so far have used more than half the stack.
+	#[inline(always)]
+	fn outgrown(&self) -> bool {
+		self.high_water.load(Ordering::Relaxed) > self.stack_len / 2
+	}
+
+	/// The overflow, if a frame was abandoned as it neared the end of the stack.
+	#[inline(always)]
+	fn overflow(&self) -> Option<StackOverflow> {
+		self.overflowed
+			.load(Ordering::Relaxed)
+			.then(|| StackOverflow {
+				type_name: any::type_name::<T>(),
+				stack_len: self.stack_len,
+			})
+	}
+
+	/// Between frames, respawn onto a stack of twice the size, preferring one from `stacks`.
+	#[inline(always)]
+	fn regrow(self, stacks: &mut Vec<Preallocated>) -> Self {
+		let stack_len = self.stack_len * 2;
+		trace!("growing stack to {} bytes", stack_len);
+		drop(self.into_stack());
+		let stack = take_stack(stacks, stack_len)
+			.unwrap_or_else(|| fringe::OsStack::new(stack_len).unwrap());
+		Self::new(Some(stack))
+	}
 }
 impl<T: serde::de::DeserializeOwned + 'static> Drop for DeserializerInner<T> {
 	#[inline(always)]
 	fn drop(&mut self) {
 		if let Some(mut generator) = self.generator.take() {
 			let x = generator.resume(DeserializerMsg::Kill);
-			assert!(x.is_none());
+			ensure!(x.is_none(), "deserializer coroutine didn't finish when killed");
 			let _ = generator.unwrap();
 		}
 	}
@@ -530,6 +1465,11 @@ trait DeserializerInnerBox: Send + Sync {
 	fn empty_box(&mut self);
 	fn discard_box(&mut self);
 	fn into_stack_box(self: Box<Self>) -> fringe::OsStack;
+	fn outgrown_box(&self) -> bool;
+	fn overflow_box(&self) -> Option<StackOverflow>;
+	fn set_size_limit_box(&self, size_limit: u64);
+	fn regrow_box(self: Box<Self>, stacks: &mut Vec<Preallocated>)
+		-> Box<dyn DeserializerInnerBox>;
 	fn as_any_ref(&self) -> &dyn Any;
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 	fn as_any_box(self: Box<Self>) -> Box<dyn Any>;
@@ -550,6 +1490,20 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInnerBox for Deserial
 	fn into_stack_box(self: Box<Self>) -> fringe::OsStack {
 		self.into_stack()
 	}
+	fn outgrown_box(&self) -> bool {
+		self.outgrown()
+	}
+	fn overflow_box(&self) -> Option<StackOverflow> {
+		self.overflow()
+	}
+	fn set_size_limit_box(&self, size_limit: u64) {
+		self.size_limit.store(size_limit, Ordering::Relaxed);
+	}
+	fn regrow_box(
+		self: Box<Self>, stacks: &mut Vec<Preallocated>,
+	) -> Box<dyn DeserializerInnerBox> {
+		Box::new(self.regrow(stacks))
+	}
 	fn as_any_ref(&self) -> &dyn Any {
 		self as &dyn Any
 	}
@@ -591,25 +1545,100 @@ impl<T: serde::de::DeserializeOwned + 'static> DeserializerInnerBox for Deserial
 ///
 /// # Panics
 ///
-/// Will panic if dropped while non-empty. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Deserializer::empty()`] before dropping it.
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. In practise this almost always signifies a bug. If you do want to drop it when non-empty, call [`Deserializer::empty()`] before dropping it.
+///
+/// With the `fringe` feature, will also panic if pushed to or pulled from while [`poisoned`](Deserializer::poisoned()).
 pub struct Deserializer {
 	deserializer: Option<Box<dyn DeserializerInnerBox>>,
+	/// Idle inner pipelines for other types, least recently used first.
+	cache: Vec<Box<dyn DeserializerInnerBox>>,
+	/// Stacks allocated ahead of time, for new or grown inner pipelines.
+	stacks: Vec<Preallocated>,
 	done: bool,
 	pending: bool,
 	mid: bool,
+	/// The frame decoded, retrieved by [`peek`](Deserializer::peek()).
+	peeked: Option<Box<dyn Any + Send + Sync>>,
+	telemetry: Option<Telemetry>,
+	size_limit: Option<u64>,
+	overflow: Option<StackOverflow>,
+	stalls: Option<StallTimer>,
+	frame: usize,
 }
 impl Deserializer {
 	/// Construct a new Deserializer pipe.
+	///
+	/// This doesn't allocate, and is a `const fn` so it can be used to initialise a `static`.
+	#[must_use]
 	#[inline(always)]
-	pub fn new() -> Self {
+	pub const fn new() -> Self {
 		Self {
 			deserializer: None,
+			cache: Vec::new(),
+			stacks: Vec::new(),
 			done: true,
 			pending: false,
 			mid: false,
+			peeked: None,
+			telemetry: None,
+			size_limit: None,
+			overflow: None,
+			stalls: None,
+			frame: 0,
 		}
 	}
 
+	/// Construct a new Deserializer pipe that expects frames delimited with the given [`Framing`].
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature, as that backend's wire format has no frame header.
+	#[must_use]
+	#[inline(always)]
+	pub const fn with_framing(framing: Framing) -> Self {
+		let _ = framing;
+		fail!("framing isn't supported with the fringe feature, as its wire format has no frame header")
+	}
+
+	/// Set a callback to be invoked as each frame is pulled, or [`None`] to clear it. See [`telemetry`](crate::telemetry).
+	pub fn set_telemetry(&mut self, telemetry: Option<Telemetry>) {
+		self.telemetry = telemetry;
+	}
+
+	/// Enable or disable timing how long this pipe spends awaiting a push versus a pull, returned by [`stalls`](Deserializer::stalls()). Enabling it restarts the timing. See [`telemetry`](crate::telemetry).
+	pub fn set_stall_timing(&mut self, enabled: bool) {
+		self.stalls = enabled.then(|| StallTimer::new(self.pull_avail()));
+	}
+
+	/// The time spent awaiting a push versus a pull since [`set_stall_timing`](Deserializer::set_stall_timing()) was enabled, or [`None`] if it isn't.
+	pub fn stalls(&self) -> Option<Stalls> {
+		self.stalls.as_ref().map(StallTimer::stalls)
+	}
+
+	/// Limit the bytes each frame may decode to `size_limit`, or [`None`] to clear it, so a hostile length can't trigger an allocation larger than it before failing. Frames carry no length header with this backend, so without a limit a claimed length is trusted up to the point of allocation.
+	pub fn set_size_limit(&mut self, size_limit: Option<u64>) {
+		self.size_limit = size_limit;
+	}
+
+	/// Allocate `count` coroutine stacks of `len` bytes now, touching every page, for use as inner pipelines are created for new types or grown, so that latency-critical code doesn't take page faults mid-frame.
+	///
+	/// Every stack, preallocated or not, has a guard page beneath it, so an overflow faults rather than corrupting memory. Without the `fringe` feature there are no coroutines, so this does nothing.
+	///
+	/// # Panics
+	///
+	/// Will panic if the stacks can't be allocated.
+	pub fn preallocate_stacks(&mut self, count: usize, len: usize) {
+		self.stacks
+			.extend((0..count).map(|_| Preallocated::new(len)));
+	}
+
+	/// The number of heartbeat frames, pushed by [`Serializer::push_heartbeat()`], that this Deserializer has absorbed. Always zero with the `fringe` feature, as that backend can't send them.
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub fn heartbeats(&self) -> usize {
+		0
+	}
+
 	#[doc(hidden)]
 	pub fn pull_avail(&self) -> bool {
 		self.pending
@@ -617,9 +1646,11 @@ impl Deserializer {
 	/// Pull a `T` from the Deserializer pipe. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
 	///
 	/// Note that [`push`](Deserializer::push()) will return [`None`] until [`pull`](Deserializer::pull()) has been called, as it's necessary to supply the type of the value being seserialized.
+	#[track_caller]
 	pub fn pull<'a, T: serde::de::DeserializeOwned + 'static>(
 		&'a mut self,
 	) -> Option<impl FnOnce() -> T + 'a> {
+		check_poisoned(self.overflow);
 		if self.done {
 			self.done = false;
 			if self.deserializer.is_none()
@@ -630,14 +1661,27 @@ impl Deserializer {
 					.as_any_ref()
 					.is::<DeserializerInner<T>>()
 			{
-				self.deserializer = Some(Box::new(DeserializerInner::<T>::new(
-					self.deserializer.take().map(|x| x.into_stack_box()),
-				)));
+				let cached = self
+					.cache
+					.iter()
+					.position(|x| x.as_any_ref().is::<DeserializerInner<T>>());
+				let cached = cached.map(|i| self.cache.remove(i));
+				self.cache.extend(self.deserializer.take());
+				self.deserializer = Some(if let Some(cached) = cached {
+					cached
+				} else {
+					// evict the least recently used inner pipeline if the cache is full, reusing its stack, else take a preallocated one
+					let stack = if self.cache.len() > CACHE_LEN {
+						Some(self.cache.remove(0).into_stack_box())
+					} else {
+						take_stack(&mut self.stacks, STACK_LEN)
+					};
+					Box::new(DeserializerInner::<T>::new(stack))
+				});
 			}
-			assert!(!self
-				.deserializer
-				.as_mut()
-				.unwrap()
+			let deserializer = self.deserializer.as_mut().unwrap();
+			deserializer.set_size_limit_box(self.size_limit.unwrap_or(u64::MAX));
+			assert!(!deserializer
 				.as_any_mut()
 				.downcast_mut::<DeserializerInner<T>>()
 				.unwrap()
@@ -647,19 +1691,163 @@ impl Deserializer {
 			Some(move || {
 				self.pending = false;
 				self.done = true;
-				self.deserializer
-					.as_mut()
-					.unwrap()
-					.as_any_mut()
-					.downcast_mut::<DeserializerInner<T>>()
-					.unwrap()
-					.retrieve()
+				self.time_stalls();
+				if let Some(telemetry) = self.telemetry {
+					telemetry(any::type_name::<T>(), self.frame, Direction::Deserialized);
+				}
+				self.frame = 0;
+				let ret = match self.peeked.take() {
+					Some(peeked) => *peeked.downcast().unwrap(),
+					None => self
+						.deserializer
+						.as_mut()
+						.unwrap()
+						.as_any_mut()
+						.downcast_mut::<DeserializerInner<T>>()
+						.unwrap()
+						.retrieve(),
+				};
+				if self.deserializer.as_ref().unwrap().outgrown_box() {
+					self.deserializer = Some(
+						self.deserializer
+							.take()
+							.unwrap()
+							.regrow_box(&mut self.stacks),
+					);
+				}
+				ret
 			})
 		} else {
 			None
 		}
 	}
 
+	/// Take the decoded frame as a `T` without pulling it, so its contents can be inspected before committing to take it. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). The value is kept, and returned by the next [`pull`](Deserializer::pull()).
+	///
+	/// Like [`pull`](Deserializer::pull()), this supplies the type of the value being deserialized, so [`push`](Deserializer::push()) returns [`Some`] once it has been called.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn peek<T: serde::de::DeserializeOwned + Send + Sync + 'static>(&mut self) -> Option<&T> {
+		let _ = self.pull::<T>()?;
+		if self.peeked.is_none() {
+			let t = self
+				.deserializer
+				.as_mut()
+				.unwrap()
+				.as_any_mut()
+				.downcast_mut::<DeserializerInner<T>>()
+				.unwrap()
+				.retrieve();
+			self.peeked = Some(Box::new(t));
+		}
+		self.peeked.as_ref().unwrap().downcast_ref()
+	}
+
+	/// Pull a `T` from the Deserializer pipe into an existing `T`, so that allocations it holds, like the buffers of `Vec`s and `String`s, are reused rather than reallocated. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce(&mut T)` that can be called to perform the `pull`.
+	///
+	/// This uses serde's [`deserialize_in_place`](serde::Deserialize::deserialize_in_place()), which falls back to overwriting the place with a newly deserialized value for types that don't implement it. Types deriving `Deserialize` only implement it if `serde_derive`'s `deserialize_in_place` feature is enabled.
+	///
+	/// Like [`pull`](Deserializer::pull()), this needs to have been called for [`push`](Deserializer::push()) to return [`Some`].
+	///
+	/// With the `fringe` feature the place is always overwritten with a newly deserialized value, as that backend decodes each frame as it's pushed, before the `T` to decode into is supplied.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull_in_place<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce(&mut T) + '_> {
+		let pull = self.pull::<T>()?;
+		Some(move |place: &mut T| *place = pull())
+	}
+
+	/// Pull an `A` or a `B` pushed with [`push_tagged`](Serializer::push_tagged()). [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()). [`Some`] contains an `impl FnOnce() -> Either<A, B>` that can be called to perform the `pull`.
+	pub fn pull_either<
+		A: serde::de::DeserializeOwned + 'static,
+		B: serde::de::DeserializeOwned + 'static,
+	>(
+		&mut self,
+	) -> Option<impl FnOnce() -> Either<A, B> + '_> {
+		self.pull()
+	}
+
+	/// Push `bytes`, pulling each `T` they complete, so that a chunk holding several frames can be decoded in one call rather than alternating between [`push`](Deserializer::push()) and [`pull`](Deserializer::pull()) for each. Returns the values pulled, and how many of `bytes` were accepted, which is fewer than all of them only if the Deserializer stops accepting pushes, as a control frame awaits [`pull_control`](Deserializer::pull_control()), or, with the `fringe` feature, it's [`poisoned`](Deserializer::poisoned()). A frame left incomplete at the end of `bytes` stays buffered, to be completed by those that follow.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull_all<T: serde::de::DeserializeOwned + 'static>(
+		&mut self, bytes: &[u8],
+	) -> (Vec<T>, usize) {
+		let (mut values, mut pushed) = (Vec::new(), 0);
+		while self.overflow.is_none() {
+			// pulling again supplies the type of the next frame
+			if let Some(pull) = self.pull::<T>() {
+				values.push(pull());
+				continue;
+			}
+			let len = self.push_bytes(&bytes[pushed..]);
+			if len == 0 {
+				break;
+			}
+			pushed += len;
+		}
+		(values, pushed)
+	}
+
+	/// Pull a frame, decoding its payload with `f`, for example with a different decoder. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Deserializer::push()), in which case `f` isn't called. [`Some`] contains the result of `f`.
+	///
+	/// `f` is handed a reader scoped to exactly one frame. The whole frame is consumed regardless of how much of it `f` reads, or whether it returns an error, so the stream stays aligned.
+	///
+	/// Like [`pull`](Deserializer::pull()), this needs to have been called for [`push`](Deserializer::push()) to return [`Some`].
+	///
+	/// With the `fringe` feature, whose wire format has no frame header to delimit such a payload, `f` isn't called, and [`Some`] contains an error of kind [`io::ErrorKind::Unsupported`].
+	pub fn pull_with<R, F: FnOnce(&mut dyn Read) -> io::Result<R>>(
+		&mut self, f: F,
+	) -> Option<io::Result<R>> {
+		let _ = (self, f);
+		Some(Err(io::Error::new(
+			io::ErrorKind::Unsupported,
+			"raw frames aren't supported with the fringe feature, as its wire format has no frame header",
+		)))
+	}
+
+	/// Render the buffered frame as JSON-ish text, for logging. [`None`] denotes that no complete frame is buffered, or that its type hasn't been supplied by a [`pull`](Deserializer::pull()), or that it doesn't decode as that type.
+	///
+	/// bincode isn't self-describing, so the frame is decoded as the type supplied, recording the values, field names and variant names it yields. The frame isn't consumed. The alternate [`Debug`](fmt::Debug) format, `{:#?}`, shows this in place of the raw bytes.
+	///
+	/// Always [`None`] with the `fringe` feature, as that backend decodes frames as their bytes arrive rather than buffering them.
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub fn dump(&self) -> Option<String> {
+		None
+	}
+
+	/// The overflow, if a frame nested too deeply for its stack, in which case it was abandoned, and this pipe is poisoned until it's [`empty`](Deserializer::empty())ed: [`push`](Deserializer::push()) and [`pull`](Deserializer::pull()) panic with it, [`fill_from`](Deserializer::fill_from()) returns it as an error, and [`push_bytes`](Deserializer::push_bytes()) and [`pull_all`](Deserializer::pull_all()) stop short. Frames are abandoned once they near the end of their stack, so the process isn't aborted by the guard page, provided they read between each level of nesting, as bincode does. Always [`None`] without the `fringe` feature.
+	#[must_use]
+	pub fn poisoned(&self) -> Option<StackOverflow> {
+		self.overflow
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	#[allow(clippy::unused_self)]
+	pub fn pull_control_avail(&self) -> bool {
+		false
+	}
+	/// Pull a [`Control`] frame from the Deserializer pipe. [`None`] denotes that the next frame isn't a control frame, or hasn't yet been pushed. [`Some`] contains an `impl FnOnce() -> Control` that can be called to perform the `pull`.
+	///
+	/// [`push`](Deserializer::push()) will return [`None`] while a received control frame is awaiting this pull. Always [`None`] with the `fringe` feature, as that backend can't send them.
+	#[allow(clippy::unused_self)]
+	pub fn pull_control(&mut self) -> Option<impl FnOnce() -> Control> {
+		None::<fn() -> Control>
+	}
+
 	#[doc(hidden)]
 	pub fn push_avail(&self) -> bool {
 		!self.done && !self.pending
@@ -667,14 +1855,34 @@ impl Deserializer {
 	/// Push a `u8` to the Deserializer pipe. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](Deserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
 	///
 	/// Note that [`push`](Deserializer::push()) will return [`None`] until [`pull`](Deserializer::pull()) has been called, as it's necessary to supply the type of the value being seserialized.
+	#[track_caller]
 	pub fn push<'a>(&'a mut self) -> Option<impl FnOnce(u8) + 'a> {
-		if !self.done && !self.pending {
+		check_poisoned(self.overflow);
+		if self.push_avail() {
 			Some(move |x| {
+				if !self.mid {
+					trace!("deserializer: frame start");
+				}
 				self.mid = true;
+				self.frame += 1;
 				self.deserializer.as_mut().unwrap().next_box(x);
-				if self.deserializer.as_mut().unwrap().done_box() {
+				if let Some(overflow) = self.deserializer.as_ref().unwrap().overflow_box() {
+					trace!("deserializer: stack overflow");
+					self.overflow = Some(overflow);
+					// its generator has returned, so it's respawned, on a bigger stack
+					self.deserializer = Some(
+						self.deserializer
+							.take()
+							.unwrap()
+							.regrow_box(&mut self.stacks),
+					);
+					self.mid = false;
+					self.frame = 0;
+				} else if self.deserializer.as_mut().unwrap().done_box() {
+					trace!("deserializer: frame end");
 					self.mid = false;
 					self.pending = true;
+					self.time_stalls();
 				}
 			})
 		} else {
@@ -682,33 +1890,127 @@ impl Deserializer {
 		}
 	}
 
+	/// Push as many of `bytes` as the Deserializer pipe accepts before it awaits a [`pull`](Deserializer::pull()), returning how many it accepted. This is equivalent to calling [`push`](Deserializer::push()) for each byte until it returns [`None`], so the bytes accepted never extend beyond the end of a frame, and the rest can be pushed once it's pulled. With the `fringe` feature, it also stops once the Deserializer is [`poisoned`](Deserializer::poisoned()).
+	///
+	/// Each frame's payload is copied in at once. With the `fringe` feature the bytes are still handed to the decoder one at a time, as it decodes them as they arrive.
+	pub fn push_bytes(&mut self, bytes: &[u8]) -> usize {
+		let mut pushed = 0;
+		while pushed < bytes.len() && self.overflow.is_none() {
+			match self.push() {
+				Some(push) => push(bytes[pushed]),
+				None => break,
+			}
+			pushed += 1;
+		}
+		pushed
+	}
+
+	/// Push as much of `chunk` as [`push_bytes`](Deserializer::push_bytes()) would, returning the rest. A frame whose bytes lie wholly within `chunk` is held as a slice of it rather than copied, so frames arriving in [`Bytes`](bytes::Bytes) from the network are decoded straight out of them. Frames straddling chunks are stitched together by copying, as with [`push_bytes`](Deserializer::push_bytes()).
+	///
+	/// A held frame shares the chunk's memory, so it isn't wiped with the `zeroize` feature.
+	///
+	/// Requires the `bytes` feature. With the `fringe` feature every byte is copied, as that backend has no frame buffers to hold a slice in.
+	#[cfg(feature = "bytes")]
+	pub fn push_chunk(&mut self, mut chunk: bytes::Bytes) -> bytes::Bytes {
+		use bytes::Buf;
+		let len = self.push_bytes(&chunk);
+		chunk.advance(len);
+		chunk
+	}
+
+	/// Read bytes from `reader` into the Deserializer pipe until it awaits a [`pull`](Deserializer::pull()), `reader` ends, or a read would block, returning how many were read and whether a value can now be pulled. Bytes are never read beyond the end of a frame, so those of the next frame are left in `reader` for the next call.
+	///
+	/// As with [`push`](Deserializer::push()), [`pull`](Deserializer::pull()) must have been called first, to supply the type of the value being deserialized. With the `fringe` feature the length of a frame isn't known until it's decoded, so bytes are read one at a time; wrap an unbuffered `reader` in a [`BufReader`](io::BufReader) to avoid a system call per byte.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading from `reader` other than [`io::ErrorKind::WouldBlock`], which ends the fill, or [`io::ErrorKind::Interrupted`], which is retried. With the `fringe` feature, returns the [`StackOverflow`] as an error of kind [`io::ErrorKind::Other`] if the Deserializer is, or becomes, [`poisoned`](Deserializer::poisoned()).
+	pub fn fill_from<R: Read>(&mut self, mut reader: R) -> io::Result<(usize, bool)> {
+		let mut buf = [0; 1];
+		let mut filled = 0;
+		while self.push_avail() {
+			if let Some(overflow) = self.overflow {
+				return Err(io::Error::other(overflow));
+			}
+			match reader.read(&mut buf) {
+				Ok(0) => break,
+				Ok(len) => filled += self.push_bytes(&buf[..len]),
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+				Err(err) => return Err(err),
+			}
+		}
+		Ok((filled, self.pull_avail()))
+	}
+
 	#[doc(hidden)]
 	pub fn empty_avail(&self) -> bool {
-		self.mid || self.pending
+		self.mid || self.pending || self.overflow.is_some()
 	}
 	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	///
+	/// This also resets a pipe that's [`poisoned`](Deserializer::poisoned()), so it can be pushed to again.
 	pub fn empty<'a>(&'a mut self) -> Option<impl FnOnce() + 'a> {
-		if self.mid || self.pending {
+		if self.empty_avail() {
 			Some(move || {
+				self.overflow = None;
 				if self.pending {
-					self.deserializer.as_mut().unwrap().discard_box();
+					// a peeked frame has already been taken from the inner pipeline
+					if self.peeked.take().is_none() {
+						self.deserializer.as_mut().unwrap().discard_box();
+					}
 					self.pending = false;
 				}
 				if self.mid {
 					self.deserializer.as_mut().unwrap().empty_box();
 					self.mid = false;
 				}
+				self.frame = 0;
 				self.done = true;
+				self.time_stalls();
 			})
 		} else {
 			None
 		}
 	}
+
+	/// Note a possible transition between awaiting a push and a pull, if stall timing is enabled.
+	#[inline(always)]
+	fn time_stalls(&mut self) {
+		let awaiting_pull = self.pull_avail();
+		if let Some(stalls) = &mut self.stalls {
+			stalls.update(awaiting_pull);
+		}
+	}
+
+	/// Consume this pipe, returning the bytes that have been pushed but not yet pulled, as they were on the wire, and whether they end partway through a frame. Unlike dropping, this doesn't panic if it's non-empty, so a connection can be handed off along with the bytes sitting in the pipe.
+	///
+	/// # Panics
+	///
+	/// Will panic with the `fringe` feature if a frame has been partly or wholly pushed but not pulled, as that backend decodes bytes as they arrive rather than retaining them.
+	#[must_use]
+	#[track_caller]
+	pub fn into_remaining(self) -> (Vec<u8>, bool) {
+		ensure!(
+			!self.mid && !self.pending,
+			"into_remaining() with a partial or unpulled frame isn't supported with the fringe feature",
+			"into_remaining() with a partial or unpulled frame (mid: {}, pending: {}) isn't supported with the fringe feature, as it doesn't retain the bytes pushed",
+			self.mid,
+			self.pending
+		);
+		(Vec::new(), false)
+	}
 }
 impl Drop for Deserializer {
 	#[inline(always)]
 	fn drop(&mut self) {
-		assert!(!self.mid && !self.pending);
+		ensure!(
+			(!self.mid && !self.pending) || std::thread::panicking(),
+			"Deserializer dropped with a partial or unpulled frame; empty() it first to discard it",
+			"Deserializer dropped with a partial or unpulled frame (mid: {}, pending: {}); empty() it first to discard it",
+			self.mid,
+			self.pending
+		);
 	}
 }
 impl Unpin for Deserializer {}
@@ -718,6 +2020,24 @@ impl fmt::Debug for Deserializer {
 			.field("done", &self.done)
 			.field("pending", &self.pending)
 			.field("mid", &self.mid)
+			.field("peeked", &self.peeked.is_some())
+			.field("stacks", &self.stacks.len())
+			.field("telemetry", &self.telemetry)
+			.field("size_limit", &self.size_limit)
+			.field("overflow", &self.overflow)
+			.field("stalls", &self.stalls())
 			.finish()
 	}
 }
+#[cfg(feature = "defmt")]
+impl defmt::Format for Deserializer {
+	fn format(&self, f: defmt::Formatter) {
+		defmt::write!(
+			f,
+			"Deserializer {{ done: {=bool}, pending: {=bool}, mid: {=bool} }}",
+			self.done,
+			self.pending,
+			self.mid
+		);
+	}
+}
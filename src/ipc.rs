@@ -0,0 +1,71 @@
+//! Typed connections over local sockets: named pipes on Windows, and Unix domain sockets elsewhere.
+//!
+//! Built on [interprocess](https://docs.rs/interprocess)'s local sockets, so the same code runs on every platform. Names are namespaced: on Windows `name` becomes the named pipe `\\.\pipe\name`; on Linux it's in the abstract socket namespace; on other Unixes it's a socket file in `/tmp`.
+//!
+//! Requires the `interprocess` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::ipc;
+//! use std::thread;
+//!
+//! let name = format!("serde_pipe-example-{}", std::process::id());
+//! let listener = ipc::Listener::bind(&name).unwrap();
+//! let thread = thread::spawn(move || {
+//!     let mut connection = listener.accept().unwrap();
+//!     let x: String = connection.recv().unwrap();
+//!     connection.send(x.len()).unwrap();
+//! });
+//! let mut connection = ipc::connect(&name).unwrap();
+//! connection.send(String::from("hello")).unwrap();
+//! assert_eq!(connection.recv::<usize>().unwrap(), 5);
+//! thread.join().unwrap();
+//! ```
+
+use crate::stream::Connection;
+use interprocess::local_socket::{prelude::*, GenericNamespaced, ListenerOptions, Stream};
+use std::{fmt, io};
+
+/// Connect to the [`Listener`] bound to `name`.
+///
+/// # Errors
+///
+/// Returns any error connecting, for example if nothing is listening on `name`.
+pub fn connect(name: &str) -> io::Result<Connection<Stream>> {
+	let name = name.to_ns_name::<GenericNamespaced>()?;
+	Ok(Connection::new(Stream::connect(name)?))
+}
+
+/// A listener for typed connections on a local socket.
+pub struct Listener {
+	listener: interprocess::local_socket::Listener,
+}
+impl Listener {
+	/// Listen on `name`.
+	///
+	/// # Errors
+	///
+	/// Returns any error creating the local socket, for example if `name` is already in use.
+	pub fn bind(name: &str) -> io::Result<Self> {
+		let name = name.to_ns_name::<GenericNamespaced>()?;
+		let listener = ListenerOptions::new().name(name).create_sync()?;
+		Ok(Self { listener })
+	}
+
+	/// Block until a client [`connect`]s, returning the connection.
+	///
+	/// # Errors
+	///
+	/// Returns any error accepting the connection.
+	pub fn accept(&self) -> io::Result<Connection<Stream>> {
+		Ok(Connection::new(self.listener.accept()?))
+	}
+}
+impl fmt::Debug for Listener {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Listener")
+			.field("listener", &self.listener)
+			.finish()
+	}
+}
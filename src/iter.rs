@@ -0,0 +1,136 @@
+//! Iterators over the bytes of a [`Serializer`], and the values decoded by a [`Deserializer`], so the pipes can be fed into and from iterator combinators rather than hand-written `while let Some(pull)` loops.
+//!
+//! [`Serializer::iter()`] yields the pending bytes of the frame being pulled, ending with it. Consuming the Serializer with [`into_iter()`](IntoIterator::into_iter()) instead yields the bytes of every frame pushed or queued, as nothing else can pull them. [`Deserializer::iter_from()`] is the dual, pushing bytes from an iterator and yielding each value as its frame completes.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{serialize_to_vec, Deserializer, Serializer};
+//!
+//! let mut serializer = Serializer::new();
+//! serializer.push().unwrap()(String::from("abc"));
+//! serializer.push_or_queue(String::from("de")).unwrap();
+//! let first = serializer.iter().collect::<Vec<_>>();
+//! assert_eq!(first, serialize_to_vec(String::from("abc")));
+//! let rest = serializer.into_iter().collect::<Vec<_>>();
+//! assert_eq!(rest, serialize_to_vec(String::from("de")));
+//!
+//! let mut deserializer = Deserializer::new();
+//! let values = deserializer.iter_from::<String, _>(first.into_iter().chain(rest));
+//! assert_eq!(values.collect::<Vec<_>>(), ["abc", "de"]);
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{fmt, iter::FusedIterator, marker};
+
+impl Serializer {
+	/// An iterator pulling the pending bytes of the frame being pulled, ending at the end of the frame, or once the Serializer is awaiting a [`push`](Serializer::push()).
+	pub fn iter(&mut self) -> Iter<'_> {
+		Iter {
+			serializer: self,
+			ended: false,
+		}
+	}
+}
+
+/// An iterator over the bytes of the frame being pulled from a [`Serializer`], returned by [`Serializer::iter()`].
+#[derive(Debug)]
+pub struct Iter<'a> {
+	serializer: &'a mut Serializer,
+	ended: bool,
+}
+impl Iterator for Iter<'_> {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		if self.ended {
+			return None;
+		}
+		let (byte, last) = self.serializer.pull_byte().unzip();
+		self.ended = last.unwrap_or(true);
+		byte
+	}
+}
+impl FusedIterator for Iter<'_> {}
+
+/// An iterator over the bytes of every frame pushed or queued to a [`Serializer`], returned by its [`into_iter()`](IntoIterator::into_iter()).
+///
+/// As with the Serializer itself, dropping this before it's exhausted will panic; [`into_inner`](IntoIter::into_inner()) returns the Serializer so it can be [`empty`](Serializer::empty())ed.
+#[derive(Debug)]
+pub struct IntoIter(Serializer);
+impl IntoIter {
+	/// Consume this iterator, returning the Serializer with the bytes not yet iterated over.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.0
+	}
+}
+impl Iterator for IntoIter {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		self.0.pull().map(|pull| pull())
+	}
+}
+impl FusedIterator for IntoIter {}
+impl IntoIterator for Serializer {
+	type Item = u8;
+	type IntoIter = IntoIter;
+
+	fn into_iter(self) -> IntoIter {
+		IntoIter(self)
+	}
+}
+
+impl Deserializer {
+	/// An iterator pushing bytes from `bytes` and yielding each `T` as its frame completes, ending once `bytes` runs out. The bytes of a frame left incomplete stay in the Deserializer.
+	///
+	/// # Panics
+	///
+	/// The iterator will panic if a frame holds a value that isn't a valid `T`, as with [`pull`](Deserializer::pull()).
+	pub fn iter_from<T: serde::de::DeserializeOwned + 'static, I: IntoIterator<Item = u8>>(
+		&mut self, bytes: I,
+	) -> IterFrom<'_, T, I::IntoIter> {
+		IterFrom {
+			deserializer: self,
+			bytes: bytes.into_iter(),
+			_marker: marker::PhantomData,
+		}
+	}
+}
+
+/// An iterator over the values decoded from a byte iterator by a [`Deserializer`], returned by [`Deserializer::iter_from()`].
+pub struct IterFrom<'a, T, I> {
+	deserializer: &'a mut Deserializer,
+	bytes: I,
+	_marker: marker::PhantomData<fn() -> T>,
+}
+impl<T, I> IterFrom<'_, T, I> {
+	/// Consume this iterator, returning the bytes not yet pushed.
+	pub fn into_inner(self) -> I {
+		self.bytes
+	}
+}
+impl<T: serde::de::DeserializeOwned + 'static, I: Iterator<Item = u8>> Iterator
+	for IterFrom<'_, T, I>
+{
+	type Item = T;
+
+	fn next(&mut self) -> Option<T> {
+		loop {
+			if let Some(pull) = self.deserializer.pull::<T>() {
+				return Some(pull());
+			}
+			let push = self.deserializer.push()?;
+			push(self.bytes.next()?);
+		}
+	}
+}
+impl<T, I: fmt::Debug> fmt::Debug for IterFrom<'_, T, I> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("IterFrom")
+			.field("deserializer", &self.deserializer)
+			.field("bytes", &self.bytes)
+			.finish()
+	}
+}
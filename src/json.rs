@@ -0,0 +1,278 @@
+//! [JSON Lines](https://jsonlines.org) pipes: each value is one JSON document terminated by `\n`.
+//!
+//! These have the same push/pull discipline as [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer), but the stream can be piped through `jq`, logstash and other line-oriented tooling.
+//!
+//! Requires the `serde_json` feature. The `schemars` feature adds [JSON Schema](https://json-schema.org) generation, so that validators and API gateways downstream can check lines without Rust involvement.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::json::{JsonDeserializer, JsonSerializer};
+//!
+//! let mut serializer = JsonSerializer::new();
+//! serializer.push().unwrap()(vec![1, 2, 3]);
+//!
+//! let mut deserializer = JsonDeserializer::new();
+//! let mut line = Vec::new();
+//! while let Some(pull) = serializer.pull() {
+//!     let byte = pull();
+//!     line.push(byte);
+//!     deserializer.push().unwrap()(byte);
+//! }
+//! assert_eq!(line, b"[1,2,3]\n");
+//! assert_eq!(deserializer.pull::<Vec<u32>>().unwrap()(), [1, 2, 3]);
+//! ```
+
+use std::fmt;
+
+/// The JSON Schema of `T`, generated by [schemars](https://docs.rs/schemars), for publishing alongside a stream.
+///
+/// Requires the `schemars` feature.
+#[cfg(feature = "schemars")]
+#[must_use]
+pub fn schema<T: schemars::JsonSchema>() -> schemars::schema::RootSchema {
+	schemars::schema_for!(T)
+}
+
+/// JSON Lines serializer pipe: push `T`; pull `u8`.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`JsonSerializer::empty()`] before dropping it.
+pub struct JsonSerializer {
+	buffer: Option<(Box<[u8]>, usize)>,
+}
+impl JsonSerializer {
+	/// Construct a new `JsonSerializer` pipe.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self { buffer: None }
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.buffer.is_none()
+	}
+	/// Push a `T`. [`None`] denotes that the `JsonSerializer` is instead awaiting a [`pull`](JsonSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if `T` can't be represented as JSON, for example if it's a map with non-string keys.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.buffer.is_none() {
+			Some(move |t| {
+				let mut vec = serde_json::to_vec(&t).unwrap();
+				vec.push(b'\n');
+				self.buffer = Some((vec.into_boxed_slice(), 0));
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Push the JSON Schema of `T`, as a line of its own, so that the stream describes the lines that follow. [`None`] denotes that the `JsonSerializer` is instead awaiting a [`pull`](JsonSerializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the `push`.
+	///
+	/// The receiving end can [`pull`](JsonDeserializer::pull()) it as a [`serde_json::Value`], or discard it.
+	///
+	/// Requires the `schemars` feature.
+	///
+	/// ```
+	/// use serde_pipe::json::{JsonDeserializer, JsonSerializer};
+	///
+	/// let (mut serializer, mut deserializer) = (JsonSerializer::new(), JsonDeserializer::new());
+	/// serializer.push_schema::<Vec<u32>>().unwrap()();
+	/// while let Some(pull) = serializer.pull() {
+	///     deserializer.push().unwrap()(pull());
+	/// }
+	/// let schema: serde_json::Value = deserializer.pull().unwrap()();
+	/// assert_eq!(schema["type"], "array");
+	/// assert_eq!(schema["items"]["type"], "integer");
+	/// ```
+	#[cfg(feature = "schemars")]
+	pub fn push_schema<T: schemars::JsonSchema>(&mut self) -> Option<impl FnOnce() + '_> {
+		let push = self.push::<schemars::schema::RootSchema>()?;
+		Some(move || push(schema::<T>()))
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Pull a `u8`. [`None`] denotes that the `JsonSerializer` is instead awaiting a [`push`](JsonSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((buffer, index)) = &mut self.buffer else {
+					unreachable!()
+				};
+				let ret = buffer[*index];
+				*index += 1;
+				if *index == buffer.len() {
+					self.buffer = None;
+				}
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				self.buffer = None;
+			})
+		} else {
+			None
+		}
+	}
+}
+impl Drop for JsonSerializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_none() || std::thread::panicking(),
+			"JsonSerializer dropped with a frame unpulled; empty() it first to discard it"
+		);
+	}
+}
+impl fmt::Debug for JsonSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if f.alternate() {
+			// JSON is self-describing, so show the unpulled remainder of the line as text
+			let text = self
+				.buffer
+				.as_ref()
+				.map(|(buffer, index)| String::from_utf8_lossy(&buffer[*index..]));
+			return f
+				.debug_struct("JsonSerializer")
+				.field("buffer", &text)
+				.finish();
+		}
+		f.debug_struct("JsonSerializer")
+			.field("buffer", &self.buffer)
+			.finish()
+	}
+}
+
+/// JSON Lines deserializer pipe: push `u8`; pull `T`.
+///
+/// Unlike [`Deserializer`](crate::Deserializer), bytes can be pushed before the type to be deserialized to has been specified, as lines are delimited independently of their contents. Empty lines are skipped.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`JsonDeserializer::empty()`] before dropping it.
+pub struct JsonDeserializer {
+	buffer: Vec<u8>,
+	done: bool,
+}
+impl JsonDeserializer {
+	/// Construct a new `JsonDeserializer` pipe.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self {
+			buffer: Vec::new(),
+			done: false,
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.done
+	}
+	/// Pull a `T`. [`None`] denotes that the `JsonDeserializer` is instead awaiting a [`push`](JsonDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the line isn't a valid JSON encoding of `T`.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		if self.done {
+			Some(move || {
+				let ret = serde_json::from_slice(&self.buffer).unwrap();
+				self.buffer.clear();
+				self.done = false;
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		!self.done
+	}
+	/// Push a `u8`. [`None`] denotes that the `JsonDeserializer` is instead awaiting a [`pull`](JsonDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.done {
+			None
+		} else {
+			Some(move |x| {
+				if x == b'\n' {
+					self.done = self.buffer.iter().any(|x| !x.is_ascii_whitespace());
+					if !self.done {
+						self.buffer.clear();
+					}
+				} else {
+					self.buffer.push(x);
+				}
+			})
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.buffer.is_empty()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_empty() {
+			None
+		} else {
+			Some(move || {
+				self.buffer.clear();
+				self.done = false;
+			})
+		}
+	}
+}
+impl Drop for JsonDeserializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_empty() || std::thread::panicking(),
+			"JsonDeserializer dropped with {} bytes of a frame buffered; empty() it first to discard them",
+			self.buffer.len()
+		);
+	}
+}
+impl fmt::Debug for JsonDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if f.alternate() {
+			return f
+				.debug_struct("JsonDeserializer")
+				.field("buffer", &String::from_utf8_lossy(&self.buffer))
+				.field("done", &self.done)
+				.finish();
+		}
+		f.debug_struct("JsonDeserializer")
+			.field("buffer", &self.buffer)
+			.field("done", &self.done)
+			.finish()
+	}
+}
@@ -26,7 +26,41 @@
 //! The `fringe` feature depends on [libfringe](https://github.com/edef1c/libfringe), and so enabling it inherits these limitations:
 //!  * Rust nightly is required for the `asm` and `naked_functions` features;
 //!  * The architectures currently supported are: x86, x86_64, aarch64, or1k;
-//!  * The platforms currently supported are: bare metal, Linux (any libc), FreeBSD, DragonFly BSD, macOS. Windows is not supported.
+//!  * The platforms currently supported are: bare metal, Linux (any libc), FreeBSD, `DragonFly` BSD, macOS.
+//!
+//! On Windows, the `fringe` feature instead runs each coroutine on a [fiber](https://learn.microsoft.com/en-us/windows/win32/procthread/fibers), with none of these limitations. Threads driving the pipes are converted to fibers as needed, which can't be undone: they remain fibers for the rest of their lives. So they mustn't already have been converted by other code, nor be converted back with `ConvertFiberToThread`.
+//!
+//! On RISC-V (riscv64, glibc), it likewise runs each coroutine on a `ucontext` with its own mapped stack and guard page, on stable Rust, so the bounded-memory mode is available there too.
+//!
+//! Each type is serialized and deserialized on its own coroutine stack, starting at 64 KiB. Whenever a frame uses more than half of it, the stack is doubled before the next frame, so deeply nested types needn't be tuned for. A single frame that nears the end of the current stack is instead abandoned, poisoning the pipe with a `StackOverflow` returned by `poisoned()` until it's emptied, rather than overflowing into its guard page and aborting the process. Calling `preallocate_stacks()` on either pipe allocates stacks ahead of time and faults in their pages, so latency-critical code doesn't take page faults mid-frame. The serializer's coroutine yields its output in blocks rather than a byte at a time, starting each frame at 32 bytes and doubling up to 64 KiB as it streams, so short frames stay cheap and long ones take few context switches; `Serializer::set_block_len()` pins the size instead.
+//!
+//! The default backend contains no `unsafe` code. The `forbid-unsafe` feature enforces this with `#![forbid(unsafe_code)]`, failing the build if the `fringe` feature is also enabled.
+//!
+//! The `zeroize` feature wipes the default backend's frame buffers as frames are pulled, emptied or dropped, so credentials and other secrets don't persist in freed memory. It doesn't extend to the `fringe` backend's coroutine stacks, nor to allocations outgrown by the writer passed to [`Serializer::push_with()`]. [`crypto::SealingSerializer`] and [`crypto::OpeningDeserializer`] encrypt frames with a pluggable AEAD cipher, managing nonces internally and rotating keys mid-stream. [`codec::Layers`] composes compression, checksum and encryption layers in a fixed order, making both ends from the same configuration.
+//!
+//! For connections carrying two or three types, [`Serializer::push_tagged()`] and [`Deserializer::pull_either()`] tag each value with which of them it is, returning an [`Either`], so no wrapper enum need be declared.
+//!
+//! [`tap::TapSerializer`] and [`tap::TapDeserializer`] pass every `n`th frame to a callback, as raw bytes that can be decoded, for live debugging and metrics sampling of production streams.
+//!
+//! The `defmt` feature implements [`defmt::Format`](https://docs.rs/defmt) for the pipes and emits trace points at frame boundaries, for debugging over RTT. As the crate requires `std`, this is for embedded targets with `std`, such as ESP-IDF, rather than `no_std` ones. The `terse-errors` feature replaces the messages the pipes panic with on malformed frames and misuse with static strings, so the formatting of types, offsets and hex context isn't linked in, for code size on such targets. It doesn't lift the crate's requirement of `std`.
+//!
+//! [`iter`] adapts a [`Serializer`] to an [`Iterator`] of its bytes, and a [`Deserializer`] to an [`Iterator`] of the values decoded from one, for use with iterator combinators. The `embedded-io` feature implements its `Read` trait for [`Serializer`] and `Write` trait for [`Deserializer`], failing with `NotReady` when the pipe can't make progress; the `embedded-io-async` feature implements its `Read` and `Write` traits for [`task::AsyncSerializer`] and [`task::AsyncDeserializer`], awaiting the pipe's being ready. The `bytes` feature implements [`bytes::Buf`](https://docs.rs/bytes/1/bytes/trait.Buf.html) for [`Serializer`], over its frame buffers, or its coroutine's blocks with the `fringe` feature, so it can be handed to tokio and hyper code that writes `Buf`s, and adds `Deserializer::push_chunk()`, which decodes frames straight out of received `Bytes` rather than copying them.
+//!
+//! [`sync::LockPipe`] shares a pipe between threads, blocking until it's ready. The `parking_lot` feature switches it to [parking_lot](https://docs.rs/parking_lot)'s mutex and condvar, for lower wakeup latency and no poisoning. The `tokio` feature provides [`sync::AsyncPipe`], for sharing a pipe between async tasks, with `send` and `recv` that await readiness. [`task::AsyncSerializer`] instead wakes the task awaiting its bytes through its `Waker`, for any executor; the `futures` feature makes it a [`Stream`](https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html) of `Bytes`, for async sinks and hyper bodies, and a [`Sink`](https://docs.rs/futures-sink/0.3/futures_sink/trait.Sink.html) of values; the `tokio` feature makes it an `AsyncRead`, so its bytes can be `tokio::io::copy`'d into a socket. [`task::AsyncDeserializer`] is the dual, waking the task awaiting a value once one can be pulled; the `tokio` feature makes it an `AsyncWrite`, so a socket can be `tokio::io::copy`'d into it. Both offer `push(value).await` and `pull::<T>().await` respectively, which await the pipe's being ready. The `critical-section` feature provides [`sync::CsPipe`], for sharing a pipe between interrupt and thread context where `std`'s mutexes can't be taken. The crate itself requires `std`, so this is for interrupt handlers on targets with `std`, not for `no_std` ones.
+//!
+//! The `serde_json` feature provides [JSON Lines](https://jsonlines.org) pipes in [`json`], for interoperating with line-oriented tooling; similarly the `csv` feature provides CSV pipes in [`csv`](mod@csv). The `schemars` feature adds [JSON Schema](https://json-schema.org) generation for the JSON pipes' types, so downstream validators can check lines without Rust involvement.
+//!
+//! The `prost` feature provides protobuf pipes in [`protobuf`], using protobuf's standard varint length-delimited framing, for interoperating byte-for-byte with existing protobuf stream consumers. The `avro` feature provides [Avro](https://avro.apache.org) object container pipes in [`avro`], with the schema written once at the start of the stream and values encoded against it.
+//!
+//! The bytes these pipes emit are bincode 1's encoding with fixed-width integers. Each of its options is pinned within `serde_pipe` rather than taken from bincode's defaults, and the result is checked against golden bytes in its tests, so stored frames and older peers stay compatible across upgrades of bincode. [`WIRE_VERSION`] is bumped should the format ever change.
+//!
+//! The `serde-reflection` feature exports the wire layout of the types sent, in [`reflection`], so decoders for the exact bytes these pipes emit can be generated for other languages; the `serde-generate` feature generates them, as Python, TypeScript or C++. [`packed::Packed`] wraps a value so its bools, option tags and enum variant indices are packed into bits rather than taking a byte or more each, for types carrying dozens of flags. [`quantize::Quantized`] additionally quantizes floats to fixed point of a chosen width and precision, for telemetry where bandwidth matters more than exact values. [`batch::BatchSerializer`] coalesces small values pushed while a frame is in flight into one frame, amortizing per-frame overhead. [`transaction::TransactionSerializer`] groups values into transactions that the receiving end yields only once committed, discarding them on abort. [`dedup::DedupSerializer`] sends a marker in place of a value equal to the previous one of its type, for status broadcasts that rarely change. [`cache::CacheSerializer`] sends a back-reference in place of a value equal to one recently sent, for configuration and state objects sent repeatedly.
+//!
+//! [`persist::PersistentQueue`] durably appends values to a file in the same framing, redelivering those not acknowledged after a restart. [`record::Recorder`] writes frames to a journal alongside an index of their offsets, types and timestamps, so that [`record::Replay`] can seek straight to a given frame or time.
+//!
+//! [`stream::Connection`] sends and receives values over any blocking byte stream, and [`stream::SerializerReader`] and [`stream::DeserializerWriter`] adapt a [`Serializer`] to [`std::io::Read`] and a [`Deserializer`] to [`std::io::Write`]. [`negotiate`] has the two ends of a stream agree on codecs and other options with an offer and reply at its start, rather than by configuring each out of band. [`udp::Socket`] sends and receives values over UDP, fragmenting frames across datagrams and reassembling them, for unicast or multicast. The `interprocess` feature provides typed connections over local sockets in [`ipc`] – named pipes on Windows, and Unix domain sockets elsewhere – for cross-platform IPC. The `serialport` feature opens serial ports as connections in [`serial`], blocking or poll-driven, for links to microcontrollers and other devices. The `zmq` feature provides [`zmq::Socket`](crate::zmq::Socket), sending one value per [ZeroMQ](https://zeromq.org) message without a frame header, for `REQ`/`REP` and `PUB`/`SUB` services already on a `ZeroMQ` bus.
+//!
+//! The `proptest` feature provides [proptest](https://docs.rs/proptest) strategies in [`proptest`](mod@proptest), generating random interleavings of operations and chunkings of byte delivery, for property-testing code that drives these pipes. The `mock` feature provides simulated transports in [`mock`], with latency, jitter, bandwidth limits and random disconnects, for testing timeout and recovery behaviour without real networks. The `bench` feature enables [criterion](https://docs.rs/criterion) benchmarks of small-message throughput, large-frame streaming and the various push and pull APIs; running them with and without the `fringe` feature, saving a baseline of one, compares the backends.
 
 #![doc(html_root_url = "https://docs.rs/serde_pipe/0.1.3")]
 #![warn(
@@ -46,15 +80,182 @@
 	clippy::new_without_default,
 	clippy::boxed_local
 )]
+#![deny(unsafe_code)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
+
+#[cfg(all(feature = "fringe", feature = "forbid-unsafe"))]
+compile_error!("the `fringe` feature requires `unsafe` code, and so can't be used with the `forbid-unsafe` feature");
+
+/// Emit a `defmt` trace point if the `defmt` feature is enabled; a no-op otherwise.
+macro_rules! trace {
+	($($arg:tt)*) => {
+		#[cfg(feature = "defmt")]
+		defmt::trace!($($arg)*);
+	};
+}
+
+/// Panic with the given formatted message, or with the `terse-errors` feature just the static `$terse` one, so that the formatting of its arguments isn't linked in.
+macro_rules! fail {
+	($terse:literal) => {
+		fail!($terse, $terse)
+	};
+	($terse:literal, $($rich:tt)+) => {{
+		#[cfg(not(feature = "terse-errors"))]
+		panic!($($rich)+);
+		#[cfg(feature = "terse-errors")]
+		panic!($terse);
+	}};
+}
+
+/// [`assert!`], but with the `terse-errors` feature panicking with just the static `$terse` message.
+macro_rules! ensure {
+	($cond:expr, $terse:literal) => {
+		ensure!($cond, $terse, $terse)
+	};
+	($cond:expr, $terse:literal, $($rich:tt)+) => {{
+		#[cfg(not(feature = "terse-errors"))]
+		assert!($cond, $($rich)+);
+		#[cfg(feature = "terse-errors")]
+		if !$cond {
+			panic!($terse);
+		}
+	}};
+}
+
+/// [`assert_eq!`], but with the `terse-errors` feature panicking with just the static `$terse` message, rather than formatting both sides.
+#[cfg(not(feature = "fringe"))]
+macro_rules! ensure_eq {
+	($left:expr, $right:expr, $terse:literal) => {
+		ensure_eq!($left, $right, $terse, $terse)
+	};
+	($left:expr, $right:expr, $terse:literal, $($rich:tt)+) => {{
+		#[cfg(not(feature = "terse-errors"))]
+		assert_eq!($left, $right, $($rich)+);
+		#[cfg(feature = "terse-errors")]
+		if $left != $right {
+			panic!($terse);
+		}
+	}};
+}
 
 #[cfg(not(feature = "fringe"))]
 mod buffer;
 #[cfg(not(feature = "fringe"))]
 pub use crate::buffer::*;
+#[cfg(all(feature = "fringe", any(windows, target_arch = "riscv64")))]
+#[allow(unsafe_code)]
+mod fiber;
 #[cfg(feature = "fringe")]
+#[allow(unsafe_code)]
 mod fringe;
+#[cfg(not(feature = "fringe"))]
+mod pretty;
 #[cfg(feature = "fringe")]
 pub use crate::fringe::*;
+mod wire;
+pub use crate::wire::WIRE_VERSION;
+pub use either::Either;
+
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod batch;
+pub mod cache;
+pub mod codec;
+pub mod crypto;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod dedup;
+#[cfg(feature = "embedded-io")]
+mod embedded;
+#[cfg(feature = "embedded-io")]
+pub use crate::embedded::NotReady;
+pub mod flow;
+#[cfg(feature = "interprocess")]
+pub mod ipc;
+pub mod iter;
+#[cfg(feature = "serde_json")]
+pub mod json;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod negotiate;
+pub mod packed;
+pub mod persist;
+pub mod poll;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod quantize;
+pub mod queue;
+pub mod rate;
+pub mod record;
+#[cfg(feature = "serde-reflection")]
+pub mod reflection;
+#[cfg(feature = "serialport")]
+pub mod serial;
+pub mod spsc;
+pub mod stream;
+pub mod sync;
+pub mod tap;
+pub mod task;
+pub mod telemetry;
+pub mod transaction;
+pub mod typed;
+pub mod udp;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+/// Serialize `t` to a single frame, exactly as a [`Serializer`] would emit it.
+///
+/// # Panics
+///
+/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl returns an error, as with [`Serializer::push()`].
+pub fn serialize_to_vec<T: serde::ser::Serialize + 'static>(t: T) -> Vec<u8> {
+	Serializer::of(t).into_vec()
+}
+
+/// Deserialize a `T` from `slice`, which should hold exactly one frame as emitted by a [`Serializer`] or [`serialize_to_vec()`]. [`None`] denotes that it holds an incomplete frame, or bytes beyond the end of the frame.
+///
+/// A fresh [`Deserializer`] is used each time, so a malformed frame can't affect the next.
+///
+/// # Panics
+///
+/// Will panic if the frame holds a value that isn't a valid `T`.
+#[must_use]
+pub fn deserialize_from_slice<T: serde::de::DeserializeOwned + 'static>(slice: &[u8]) -> Option<T> {
+	let mut deserializer = Deserializer::for_type::<T>();
+	let mut bytes = slice.iter();
+	while let Some(push) = deserializer.push() {
+		match bytes.next() {
+			Some(&byte) => push(byte),
+			None => break,
+		}
+	}
+	let ret = match (bytes.next(), deserializer.pull()) {
+		(None, Some(pull)) => Some(pull()),
+		_ => None,
+	};
+	if let Some(empty) = deserializer.empty() {
+		empty();
+	}
+	ret
+}
+
+/// Move bytes from `serializer` to `deserializer` until either runs out, i.e. the Serializer is awaiting a [`push`](Serializer::push()) or the Deserializer is awaiting a [`pull`](Deserializer::pull()), returning how many were moved. This replaces the `pull`/`push` loop that otherwise bridges the two, for example to connect them in-process or in tests.
+///
+/// The bytes are moved as slices: each frame's with the default backend, and each block its coroutine generates with the `fringe` feature, where the Deserializer's coroutine then decodes them as they arrive.
+pub fn pipe(serializer: &mut Serializer, deserializer: &mut Deserializer) -> usize {
+	let mut moved = 0;
+	while let Some(buf) = serializer.pull_buf() {
+		let len = deserializer.push_bytes(buf);
+		if len == 0 {
+			break;
+		}
+		serializer.consume(len);
+		moved += len;
+	}
+	moved
+}
 
 #[cfg(test)]
 mod tests {
@@ -67,7 +268,7 @@ mod tests {
 	use super::*;
 	use rand::{rngs::SmallRng, Rng, SeedableRng};
 	use std::{
-		collections::VecDeque, io::{self, Write}
+		collections::{BTreeMap, VecDeque}, io::{self, Write}
 	};
 
 	struct VecDequeWriter<'a>(&'a mut VecDeque<u8>);
@@ -93,6 +294,42 @@ mod tests {
 		String(String),
 	}
 
+	/// An allocator that notes whether memory freed held [`SECRET`](wiped::SECRET), to check the `zeroize` feature wipes frames before they're freed. Allocations are zeroed, so the bytes freed are always initialized.
+	#[cfg(all(feature = "zeroize", not(feature = "fringe"), not(feature = "forbid-unsafe")))]
+	#[allow(unsafe_code)]
+	mod wiped {
+		use std::{
+			alloc::{GlobalAlloc, Layout, System}, slice, sync::atomic::{AtomicBool, Ordering}
+		};
+
+		pub const SECRET: &str = "correct horse battery staple";
+		static FREED: AtomicBool = AtomicBool::new(false);
+
+		/// Whether memory holding `SECRET` has been freed.
+		pub fn freed() -> bool {
+			FREED.load(Ordering::SeqCst)
+		}
+
+		struct Wiped;
+		unsafe impl GlobalAlloc for Wiped {
+			unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+				unsafe { System.alloc_zeroed(layout) }
+			}
+			unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+				let bytes = unsafe { slice::from_raw_parts(ptr, layout.size()) };
+				if bytes
+					.windows(SECRET.len())
+					.any(|window| window == SECRET.as_bytes())
+				{
+					FREED.store(true, Ordering::SeqCst);
+				}
+				unsafe { System.dealloc(ptr, layout) }
+			}
+		}
+		#[global_allocator]
+		static WIPED: Wiped = Wiped;
+	}
+
 	#[test]
 	fn serializer() {
 		let mut rng = SmallRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
@@ -112,7 +349,7 @@ mod tests {
 							if let Some(push) = serializer.push() {
 								let mut y = vec![];
 								bincode::serialize_into(&mut y, &()).unwrap();
-								assert_eq!(y, vec![]);
+								assert_eq!(y, Vec::<u8>::new());
 								#[cfg(not(feature = "fringe"))]
 								bincode::serialize_into::<_, usize>(
 									&mut VecDequeWriter(&mut queue),
@@ -206,7 +443,7 @@ mod tests {
 				assert_ne!(queue.len(), 0);
 				empty();
 			} else {
-				assert_eq!(queue, vec![]);
+				assert_eq!(queue, Vec::<u8>::new());
 			};
 		}
 	}
@@ -232,7 +469,7 @@ mod tests {
 								0 => {
 									let mut y = vec![];
 									bincode::serialize_into(&mut y, &()).unwrap();
-									assert_eq!(y, vec![]);
+									assert_eq!(y, Vec::<u8>::new());
 									#[cfg(not(feature = "fringe"))]
 									bincode::serialize_into::<_, usize>(
 										&mut VecDequeWriter(&mut pipe),
@@ -390,7 +627,7 @@ mod tests {
 							if let Some(push) = serializer.push() {
 								let mut y = vec![];
 								bincode::serialize_into(&mut y, &()).unwrap();
-								assert_eq!(y, vec![]);
+								assert_eq!(y, Vec::<u8>::new());
 								queue.push_back(Queue::Unit);
 								push(());
 							}
@@ -495,4 +732,3200 @@ mod tests {
 			};
 		}
 	}
+
+	#[test]
+	fn spsc_ring() {
+		let (mut producer, mut consumer) = spsc::ring(7);
+		let iterations = 1_000_u64;
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(move || {
+				let mut serializer = Serializer::new();
+				for i in 0..iterations {
+					serializer.push().unwrap()((i, i.to_string()));
+					while serializer.pull_avail() {
+						if producer.fill_from(&mut serializer) == 0 {
+							std::thread::yield_now();
+						}
+					}
+				}
+			});
+			let mut deserializer = Deserializer::new();
+			for i in 0..iterations {
+				let x = loop {
+					if let Some(pull) = deserializer.pull::<(u64, String)>() {
+						break pull();
+					}
+					if consumer.drain_into(&mut deserializer) == 0 {
+						std::thread::yield_now();
+					}
+				};
+				assert_eq!(x, (i, i.to_string()));
+			}
+		});
+		assert!(consumer.is_empty());
+	}
+
+	#[test]
+	fn spsc_channel() {
+		let (mut sender, mut receiver) = spsc::channel::<Vec<u64>>(7);
+		let iterations = 1_000_u64;
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(move || {
+				for i in 0..iterations {
+					sender.send((0..i % 20).collect()).unwrap();
+				}
+			});
+			for i in 0..iterations {
+				assert_eq!(receiver.recv().unwrap(), (0..i % 20).collect::<Vec<_>>());
+			}
+			assert_eq!(receiver.recv(), None);
+		});
+		let (mut sender, receiver) = spsc::channel::<u64>(7);
+		drop(receiver);
+		assert_eq!(sender.send(0), Err(0));
+		// a value left partly flushed by a Receiver dropped mid-frame
+		let (mut sender, receiver) = spsc::channel::<Vec<u64>>(7);
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(move || {
+				sender.send(vec![0; 100]).unwrap();
+				assert_eq!(sender.send(vec![1]), Err(vec![1]));
+			});
+			std::thread::sleep(std::time::Duration::from_millis(50));
+			drop(receiver);
+		});
+	}
+
+	#[test]
+	fn lock_pipe() {
+		let serializer = &sync::LockPipe::new(Serializer::new());
+		let deserializer = &sync::LockPipe::new(Deserializer::new());
+		let iterations = 1_000_u64;
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(|| {
+				for i in 0..iterations {
+					serializer
+						.wait(|serializer| serializer.push().map(|push| push((i, i.to_string()))));
+				}
+			});
+			let bytes = (0..iterations)
+				.map(|i| serialize_to_vec((i, i.to_string())).len())
+				.sum::<usize>();
+			let _ = scope.spawn(move || {
+				for _ in 0..bytes {
+					let byte = serializer.wait(|serializer| serializer.pull().map(|pull| pull()));
+					deserializer.wait(|deserializer| deserializer.push().map(|push| push(byte)));
+				}
+			});
+			for i in 0..iterations {
+				let x: (u64, String) =
+					deserializer.wait(|deserializer| deserializer.pull().map(|pull| pull()));
+				assert_eq!(x, (i, i.to_string()));
+			}
+		});
+	}
+
+	#[cfg(feature = "serde-reflection")]
+	#[test]
+	fn reflection() {
+		use serde_reflection::{ContainerFormat, Format};
+		#[derive(serde::Deserialize)]
+		#[allow(dead_code)]
+		enum Side {
+			Buy,
+			Sell(u8),
+		}
+		#[derive(serde::Deserialize)]
+		#[allow(dead_code)]
+		struct Order {
+			id: u64,
+			side: Side,
+		}
+		let mut schema = reflection::Schema::new();
+		schema.add::<Vec<Order>>().unwrap();
+		schema.add::<u16>().unwrap();
+		schema.add::<Side>().unwrap();
+		assert_eq!(
+			schema
+				.frames()
+				.iter()
+				.map(|(_, format)| format)
+				.collect::<Vec<_>>(),
+			[
+				&Format::Seq(Box::new(Format::TypeName(String::from("Order")))),
+				&Format::U16,
+				&Format::TypeName(String::from("Side"))
+			]
+		);
+		let registry = schema.into_registry().unwrap();
+		assert!(
+			matches!(registry["Order"], ContainerFormat::Struct(ref fields) if fields.len() == 2)
+		);
+		assert!(
+			matches!(registry["Side"], ContainerFormat::Enum(ref variants) if variants.len() == 2)
+		);
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn dump() {
+		#[derive(serde::Serialize, serde::Deserialize)]
+		enum Side {
+			Buy,
+			Sell(u8),
+		}
+		#[derive(serde::Serialize, serde::Deserialize)]
+		struct Order {
+			id: u64,
+			symbol: String,
+			side: Side,
+			limit: Option<f64>,
+			fills: Vec<(u32, i16)>,
+		}
+		let order = Order {
+			id: 7,
+			symbol: String::from("ABC"),
+			side: Side::Sell(3),
+			limit: None,
+			fills: vec![(1, -2), (3, 4)],
+		};
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		serializer.push().unwrap()(order);
+		assert_eq!(deserializer.dump(), None);
+		let _ = deserializer.pull::<Order>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		let expected = r#"{"id": 7, "symbol": "ABC", "side": {"Sell": 3}, "limit": null, "fills": [[1, -2], [3, 4]]}"#;
+		assert_eq!(deserializer.dump().unwrap(), expected);
+		assert!(format!("{deserializer:#?}").contains(expected));
+		let _ = deserializer.pull::<Order>().unwrap()();
+		serializer.push().unwrap()(Side::Buy);
+		let _ = deserializer.pull::<Side>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.dump().unwrap(), r#"{"Buy": null}"#);
+		let _ = deserializer.pull::<Side>().unwrap()();
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	fn dump_unsupported() {
+		let mut serializer = Serializer::of(7_u32);
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<u32>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.dump(), None);
+		assert_eq!(deserializer.pull::<u32>().unwrap()(), 7);
+	}
+
+	#[cfg(not(any(feature = "fringe", feature = "terse-errors")))]
+	#[test]
+	fn error_context() {
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<(u8, bool)>();
+		for byte in [2, 0, 0, 0, 0, 0, 0, 0, 7, 2] {
+			deserializer.push().unwrap()(byte);
+		}
+		let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			deserializer.pull::<(u8, bool)>().unwrap()()
+		}))
+		.unwrap_err();
+		let err = err.downcast_ref::<String>().unwrap();
+		assert!(
+			err.starts_with(
+				"deserializing (u8, bool) from a 2 byte frame, at byte offset 1: 07 [02]: "
+			),
+			"{}",
+			err
+		);
+		deserializer.empty().unwrap()();
+	}
+
+	#[cfg(all(not(feature = "fringe"), feature = "terse-errors"))]
+	#[test]
+	fn terse_errors() {
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<(u8, bool)>();
+		for byte in [2, 0, 0, 0, 0, 0, 0, 0, 7, 2] {
+			deserializer.push().unwrap()(byte);
+		}
+		let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			deserializer.pull::<(u8, bool)>().unwrap()()
+		}))
+		.unwrap_err();
+		assert_eq!(err.downcast_ref::<&str>(), Some(&"deserializing failed"));
+		deserializer.empty().unwrap()();
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn size_limit() {
+		// a 16 byte frame holding a String that claims to be 1 TiB
+		let mut frame = 16_u64.to_le_bytes().to_vec();
+		frame.extend_from_slice(&(1_u64 << 40).to_le_bytes());
+		frame.extend_from_slice(b"12345678");
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<String>();
+		for &byte in &frame {
+			deserializer.push().unwrap()(byte);
+		}
+		let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			deserializer.pull::<String>().unwrap()()
+		}))
+		.unwrap_err();
+		if cfg!(not(feature = "terse-errors")) {
+			let err = err.downcast_ref::<String>().unwrap();
+			assert!(err.ends_with("the size limit has been reached"), "{}", err);
+		}
+		deserializer.empty().unwrap()();
+
+		let frame = serialize_to_vec(String::from("hello"));
+		for (size_limit, ok) in [(12, false), (13, true)] {
+			deserializer.set_size_limit(Some(size_limit));
+			let _ = deserializer.pull::<String>();
+			let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+				for &byte in &frame {
+					deserializer.push().unwrap()(byte);
+				}
+				deserializer.pull::<String>().unwrap()()
+			}));
+			assert_eq!(ret.is_ok(), ok);
+			if let Some(empty) = deserializer.empty() {
+				empty();
+			}
+		}
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn size_limit_header() {
+		// a header claiming 32 TiB, which mustn't be allocated up front
+		let header = (1_u64 << 45).to_le_bytes();
+		let mut deserializer = Deserializer::new();
+		deserializer.set_size_limit(Some(1024));
+		let _ = deserializer.pull::<Vec<u8>>();
+		let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			for &byte in &header {
+				deserializer.push().unwrap()(byte);
+			}
+		}))
+		.unwrap_err();
+		if cfg!(not(feature = "terse-errors")) {
+			let err = err.downcast_ref::<String>().unwrap();
+			assert!(err.ends_with("the size limit has been reached"), "{}", err);
+		}
+		deserializer.empty().unwrap()();
+
+		// without a limit it's awaited a byte at a time, rather than reserved
+		assert_eq!(deserialize_from_slice::<Vec<u8>>(&header), None);
+	}
+
+	#[cfg(feature = "interprocess")]
+	#[test]
+	fn ipc() {
+		let name = format!("serde_pipe-test-{}", std::process::id());
+		let listener = ipc::Listener::bind(&name).unwrap();
+		let iterations = 1_000_u64;
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(|| {
+				let mut connection = listener.accept().unwrap();
+				for i in 0..iterations {
+					let x: Vec<u64> = connection.recv().unwrap();
+					connection.send(x.iter().sum::<u64>()).unwrap();
+					assert_eq!(x.len() as u64, i);
+				}
+				assert_eq!(
+					connection.recv::<u64>().unwrap_err().kind(),
+					io::ErrorKind::UnexpectedEof
+				);
+			});
+			let mut connection = ipc::connect(&name).unwrap();
+			for i in 0..iterations {
+				connection.send((0..i).collect::<Vec<_>>()).unwrap();
+				assert_eq!(
+					connection.recv::<u64>().unwrap(),
+					i * i.saturating_sub(1) / 2
+				);
+			}
+		});
+	}
+
+	#[cfg(all(feature = "serialport", unix))]
+	#[test]
+	fn serial() {
+		use serialport::{SerialPort, TTYPort};
+		let (mut a, b) = TTYPort::pair().unwrap();
+		a.set_timeout(std::time::Duration::from_mins(1)).unwrap();
+		let mut a = stream::Connection::new(a);
+		let name = b.name().unwrap();
+		drop(b);
+		let mut b = serial::open_polled(serialport::new(name, 115_200)).unwrap();
+		for i in 0..100_u64 {
+			a.send((0..i).collect::<Vec<_>>()).unwrap();
+			let x = loop {
+				match b.recv::<Vec<u64>>() {
+					Ok(x) => break x,
+					Err(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+				}
+			};
+			while let Err(err) = b.flush() {
+				assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+			}
+			b.send(x.iter().sum::<u64>()).unwrap();
+			assert_eq!(a.recv::<u64>().unwrap(), i * i.saturating_sub(1) / 2);
+		}
+	}
+
+	#[test]
+	fn udp() {
+		use std::{net::UdpSocket, time::Duration};
+		let mut a = udp::Socket::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+		let mut b = udp::Socket::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+		a.set_max_datagram(100);
+		b.set_reassembly_timeout(Duration::from_millis(10));
+		let a_addr = a.get_ref().local_addr().unwrap();
+		let b_addr = b.get_ref().local_addr().unwrap();
+		// the first of two fragments of frame 7, the second of which never arrives
+		let _ = a
+			.get_ref()
+			.send_to(&[7, 0, 0, 0, 0, 0, 2, 0, 1], b_addr)
+			.unwrap();
+		// too short to hold a fragment header
+		let _ = a.get_ref().send_to(&[1, 2, 3], b_addr).unwrap();
+		std::thread::sleep(Duration::from_millis(20));
+		for i in 0..20_u64 {
+			a.send_to((0..i * 10).collect::<Vec<_>>(), b_addr).unwrap();
+			let (x, from) = b.recv_from::<Vec<u64>>().unwrap();
+			assert_eq!((x.len() as u64, from), (i * 10, a_addr));
+			b.send_to(x.iter().sum::<u64>(), a_addr).unwrap();
+			assert_eq!(a.recv_from::<u64>().unwrap().0, (0..i * 10).sum::<u64>());
+		}
+		assert!(format!("{b:?}").contains("partial: 0"));
+
+		let mut c = udp::Socket::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+		c.set_reassembly_limits(2, 3, 4 << 20);
+		let c_addr = c.get_ref().local_addr().unwrap();
+		// a frame that isn't a valid Vec<u64>
+		let mut datagram = vec![9, 0, 0, 0, 0, 0, 1, 0];
+		datagram.extend(serialize_to_vec(5_u64));
+		let _ = a.get_ref().send_to(&datagram, c_addr).unwrap();
+		// the first fragments of frames with the most fragments possible, only two of which are kept at a time
+		for id in 10..20 {
+			let _ = a
+				.get_ref()
+				.send_to(&[id, 0, 0, 0, 0, 0, 255, 255, 1], c_addr)
+				.unwrap();
+		}
+		a.send_to((0..100).collect::<Vec<u64>>(), c_addr).unwrap();
+		let (x, from) = c.recv_from::<Vec<u64>>().unwrap();
+		assert_eq!((x.len(), from), (100, a_addr));
+		assert!(format!("{c:?}").contains("partial: 1,"));
+	}
+
+	#[cfg(feature = "zmq")]
+	#[test]
+	fn zmq() {
+		let context = ::zmq::Context::new();
+		let rep = zmq::Socket::new(context.socket(::zmq::REP).unwrap());
+		rep.get_ref().bind("inproc://test-rep").unwrap();
+		let req = zmq::Socket::new(context.socket(::zmq::REQ).unwrap());
+		req.get_ref().connect("inproc://test-rep").unwrap();
+		let publisher = zmq::Socket::new(context.socket(::zmq::PUB).unwrap());
+		publisher.get_ref().bind("inproc://test-pub").unwrap();
+		let subscriber = zmq::Socket::new(context.socket(::zmq::SUB).unwrap());
+		subscriber.get_ref().connect("inproc://test-pub").unwrap();
+		subscriber.get_ref().set_subscribe(b"odd").unwrap();
+		std::thread::scope(|scope| {
+			let _ = scope.spawn(move || {
+				for _ in 0..100 {
+					let x: Vec<u64> = rep.recv().unwrap();
+					rep.send(x.iter().sum::<u64>()).unwrap();
+				}
+				// subscriptions propagate asynchronously, so publish until one is received
+				while !rep
+					.get_ref()
+					.poll(::zmq::POLLIN, 1)
+					.map(|n| n != 0)
+					.unwrap()
+				{
+					publisher.send_topic(b"even", 0_u8).unwrap();
+					publisher.send_topic(b"odd", String::from("one")).unwrap();
+				}
+				rep.recv::<()>().unwrap();
+				rep.send(()).unwrap();
+			});
+			for i in 0..100_u64 {
+				req.send((0..i).collect::<Vec<_>>()).unwrap();
+				assert_eq!(req.recv::<u64>().unwrap(), i * i.saturating_sub(1) / 2);
+			}
+			let (topic, x) = subscriber.recv_topic::<String>().unwrap();
+			assert_eq!((&*topic, &*x), (&b"odd"[..], "one"));
+			req.send(()).unwrap();
+			req.recv::<()>().unwrap();
+		});
+	}
+
+	#[test]
+	fn telemetry() {
+		use std::sync::Mutex;
+		use telemetry::Direction;
+		static FRAMES: Mutex<Vec<(&'static str, usize, Direction)>> = Mutex::new(Vec::new());
+		let record =
+			|type_name, len, direction| FRAMES.lock().unwrap().push((type_name, len, direction));
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		serializer.set_telemetry(Some(record));
+		deserializer.set_telemetry(Some(record));
+		serializer.push().unwrap()(String::from("abc"));
+		let _ = deserializer.pull::<String>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "abc");
+		serializer.push().unwrap()(());
+		let _ = deserializer.pull::<()>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		deserializer.pull::<()>().unwrap()();
+		let string = std::any::type_name::<String>();
+		assert_eq!(
+			*FRAMES.lock().unwrap(),
+			[
+				(string, 11, Direction::Serialized),
+				(string, 11, Direction::Deserialized),
+				("()", 1, Direction::Serialized),
+				("()", 1, Direction::Deserialized)
+			]
+		);
+	}
+
+	#[test]
+	fn stalls() {
+		use std::{thread, time::Duration};
+		let pause = Duration::from_millis(20);
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		assert_eq!(serializer.stalls(), None);
+		serializer.set_stall_timing(true);
+		deserializer.set_stall_timing(true);
+		thread::sleep(pause);
+		serializer.push().unwrap()(vec![1_u64, 2, 3]);
+		let _ = deserializer.pull::<Vec<u64>>();
+		thread::sleep(pause);
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		thread::sleep(pause);
+		assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), [1, 2, 3]);
+		let (sent, received) = (serializer.stalls().unwrap(), deserializer.stalls().unwrap());
+		assert!(sent.awaiting_push >= pause * 2 && sent.awaiting_pull >= pause);
+		assert!(received.awaiting_push >= pause * 2 && received.awaiting_pull >= pause);
+		serializer.set_stall_timing(false);
+		assert_eq!(serializer.stalls(), None);
+	}
+
+	#[cfg(not(any(feature = "fringe", feature = "terse-errors")))]
+	#[test]
+	fn misuse() {
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<u8>();
+		let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			let _ = deserializer.pull::<u16>();
+		}))
+		.unwrap_err();
+		assert!(err
+			.downcast_ref::<String>()
+			.unwrap()
+			.starts_with("assertion `left == right` failed: pull::<u16>() called on a Deserializer awaiting a different type"));
+	}
+
+	#[test]
+	fn alternating_types() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		for i in 0..100_u64 {
+			if i % 3 == 0 {
+				serializer.push().unwrap()(i.to_string());
+				let _ = deserializer.pull::<String>();
+			} else {
+				serializer.push().unwrap()((i, i % 2 == 0));
+				let _ = deserializer.pull::<(u64, bool)>();
+			}
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			if i % 3 == 0 {
+				assert_eq!(deserializer.pull::<String>().unwrap()(), i.to_string());
+			} else {
+				assert_eq!(
+					deserializer.pull::<(u64, bool)>().unwrap()(),
+					(i, i % 2 == 0)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn deepening() {
+		#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		struct Nested(Option<Box<Nested>>);
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let mut depth = 10;
+		while depth < 1500 {
+			let nested = (0..depth).fold(Nested(None), |x, _| Nested(Some(Box::new(x))));
+			serializer.push().unwrap()(nested);
+			let _ = deserializer.pull::<Nested>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			let mut nested = deserializer.pull::<Nested>().unwrap()();
+			for _ in 0..depth {
+				nested = *nested.0.unwrap();
+			}
+			assert_eq!(nested, Nested(None));
+			depth = depth * 3 / 2;
+		}
+	}
+
+	#[test]
+	fn preallocated_stacks() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		serializer.preallocate_stacks(2, 256 * 1024);
+		deserializer.preallocate_stacks(2, 256 * 1024);
+		for i in 0..10_u64 {
+			serializer.push().unwrap()(vec![i; 100]);
+			let _ = deserializer.pull::<Vec<u64>>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), vec![i; 100]);
+		}
+		// switching between types with cached inner pipelines takes no more stacks
+		serializer.preallocate_stacks(2, 256 * 1024);
+		deserializer.preallocate_stacks(2, 256 * 1024);
+		for i in 0..10_u64 {
+			serializer.push().unwrap()(i);
+			let _ = deserializer.pull::<u64>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull::<u64>().unwrap()(), i);
+			serializer.push().unwrap()(vec![i; 100]);
+			let _ = deserializer.pull::<Vec<u64>>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), vec![i; 100]);
+		}
+		assert!(serializer.poisoned().is_none() && deserializer.poisoned().is_none());
+		// without the fringe feature there are no stacks to take
+		if cfg!(feature = "fringe") {
+			assert!(format!("{serializer:?}").contains("stacks: 2,"));
+			assert!(format!("{deserializer:?}").contains("stacks: 2,"));
+		}
+	}
+
+	#[cfg(all(feature = "fringe", any(windows, target_arch = "riscv64")))]
+	#[test]
+	fn fibers() {
+		use fiber::{generator::Generator, OsStack};
+		use std::panic::{self, AssertUnwindSafe};
+		let mut sum = 0;
+		let mut generator = Generator::new(OsStack::new(64 * 1024).unwrap(), |yielder, mut x: u64| {
+			for _ in 0..3 {
+				sum += x;
+				x = yielder.suspend(x * 2);
+			}
+		});
+		assert_eq!(generator.resume(1), Some(2));
+		assert_eq!(generator.resume(2), Some(4));
+		assert_eq!(generator.resume(3), Some(6));
+		assert_eq!(generator.resume(4), None);
+		let stack = generator.unwrap();
+		assert_eq!(sum, 6);
+		// the stack is reused, and a panic on it is propagated
+		let mut generator: Generator<(), (), _> = Generator::new(stack, |_, ()| panic!("fiber"));
+		assert!(panic::catch_unwind(AssertUnwindSafe(|| generator.resume(()))).is_err());
+		let _ = generator.unwrap();
+		// a frame started on one thread is finished on another, each converted to a fiber as needed
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(vec![1_u64; 1000]);
+		let mut bytes = (0..10).map(|_| serializer.pull().unwrap()()).collect::<Vec<_>>();
+		let (serializer, rest) = std::thread::spawn(move || {
+			let mut rest = Vec::new();
+			while let Some(pull) = serializer.pull() {
+				rest.push(pull());
+			}
+			(serializer, rest)
+		})
+		.join()
+		.unwrap();
+		bytes.extend(rest);
+		drop(serializer);
+		assert_eq!(deserialize_from_slice::<Vec<u64>>(&bytes).unwrap(), vec![1; 1000]);
+	}
+
+	#[test]
+	fn block_len() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		for &block_len in &[Some(1), Some(7), None, Some(4096)] {
+			serializer.set_block_len(block_len);
+			for t in [vec![], vec![1_u64], (0..100_000).collect::<Vec<_>>()] {
+				serializer.push().unwrap()(t.clone());
+				let _ = deserializer.pull::<Vec<u64>>();
+				while let Some(pull) = serializer.pull() {
+					deserializer.push().unwrap()(pull());
+				}
+				assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), t);
+			}
+			// with the fringe feature, a zero-length encoding is padded to a single byte
+			if cfg!(feature = "fringe") {
+				serializer.push().unwrap()(());
+				assert_eq!(serializer.pull().unwrap()(), 0);
+				assert!(serializer.pull().is_none());
+			}
+		}
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	fn stack_overflow() {
+		use std::panic::{catch_unwind, AssertUnwindSafe};
+		#[derive(serde::Serialize, serde::Deserialize)]
+		struct Nested(Option<Box<Nested>>);
+		let depth = 100_000;
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()((0..depth).fold(Nested(None), |x, _| Nested(Some(Box::new(x)))));
+		let mut pulled = 0;
+		while serializer.poisoned().is_none() {
+			match serializer.pull() {
+				Some(pull) => {
+					let _ = pull();
+					pulled += 1;
+				}
+				None => break,
+			}
+		}
+		assert!(pulled < depth);
+		assert_eq!(
+			serializer.poisoned().unwrap().type_name,
+			std::any::type_name::<Nested>()
+		);
+		assert!(catch_unwind(AssertUnwindSafe(|| serializer.push::<Nested>().is_none())).is_err());
+		assert!(catch_unwind(AssertUnwindSafe(|| serializer.pull().is_none())).is_err());
+		let err = serializer.drain_to(io::sink()).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::Other);
+
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<Nested>();
+		let pushed = deserializer.push_bytes(&vec![1; depth]);
+		assert!(pushed < depth && deserializer.poisoned().is_some());
+		assert_eq!(deserializer.push_bytes(&[1]), 0);
+		assert!(catch_unwind(AssertUnwindSafe(|| deserializer.push().is_none())).is_err());
+		assert!(catch_unwind(AssertUnwindSafe(|| deserializer.pull::<Nested>().is_none())).is_err());
+		let err = deserializer.fill_from(&[1_u8][..]).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::Other);
+
+		// emptying resets both, after which shallower frames pass
+		serializer.empty().unwrap()();
+		deserializer.empty().unwrap()();
+		assert!(serializer.poisoned().is_none() && deserializer.poisoned().is_none());
+		assert!(serializer.empty().is_none() && deserializer.empty().is_none());
+		serializer.push().unwrap()((0..10).fold(Nested(None), |x, _| Nested(Some(Box::new(x)))));
+		let _ = deserializer.pull::<Nested>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		let _ = deserializer.pull::<Nested>().unwrap()();
+	}
+
+	#[test]
+	fn of() {
+		let mut serializer = Serializer::of(String::from("one-shot"));
+		let mut deserializer = Deserializer::for_type::<String>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull().unwrap()(), "one-shot");
+	}
+
+	#[test]
+	fn to_vec() {
+		let vec = serialize_to_vec((String::from("whole"), 42_u32));
+		let mut deserializer = Deserializer::for_type::<(String, u32)>();
+		for &byte in &vec {
+			deserializer.push().unwrap()(byte);
+		}
+		assert_eq!(deserializer.pull().unwrap()(), (String::from("whole"), 42));
+		assert_eq!(
+			deserialize_from_slice::<(String, u32)>(&vec),
+			Some((String::from("whole"), 42))
+		);
+		assert_eq!(
+			deserialize_from_slice::<(String, u32)>(&vec[..vec.len() - 1]),
+			None
+		);
+		assert_eq!(
+			deserialize_from_slice::<(String, u32)>(&[vec.clone(), vec].concat()),
+			None
+		);
+	}
+
+	#[test]
+	fn push_or_queue() {
+		let mut serializer = Serializer::new();
+		serializer.set_queue_limit(2);
+		serializer.push_or_queue(1_u16).unwrap();
+		serializer.push_or_queue(String::from("two")).unwrap();
+		serializer.push_or_queue(3_u8).unwrap();
+		assert_eq!(serializer.push_or_queue(4_u8), Err(4));
+		let mut deserializer = Deserializer::for_type::<u16>();
+		while let (Some(push), Some(pull)) = (deserializer.push(), serializer.pull()) {
+			push(pull());
+		}
+		assert_eq!(deserializer.pull().unwrap()(), 1);
+		// the queued frames follow on without being pushed
+		assert!(serializer.push::<u8>().is_none());
+		let mut vec = serializer.into_vec();
+		let last = vec.split_off(vec.len() - serialize_to_vec(3_u8).len());
+		assert_eq!(deserialize_from_slice::<u8>(&last), Some(3));
+		assert_eq!(deserialize_from_slice::<String>(&vec).unwrap(), "two");
+	}
+
+	#[test]
+	fn push_or_queue_with_ttl() {
+		use std::{thread, time::Duration};
+		let mut serializer = Serializer::new();
+		serializer.set_queue_limit(2);
+		let ttl = Duration::from_millis(20);
+		serializer.push_or_queue_with_ttl(1_u8, ttl).unwrap();
+		serializer.push_or_queue_with_ttl(2_u8, ttl).unwrap();
+		serializer.push_or_queue(3_u8).unwrap();
+		assert_eq!(serializer.push_or_queue_with_ttl(4_u8, ttl), Err(4));
+		thread::sleep(ttl);
+		// the stale value no longer takes up room in the queue
+		serializer
+			.push_or_queue_with_ttl(5_u8, Duration::from_mins(1))
+			.unwrap();
+		let mut deserializer = Deserializer::new();
+		let mut received = Vec::new();
+		loop {
+			let _ = deserializer.pull::<u8>();
+			while let (Some(push), Some(pull)) = (deserializer.push(), serializer.pull()) {
+				push(pull());
+			}
+			match deserializer.pull::<u8>() {
+				Some(pull) => received.push(pull()),
+				None => break,
+			}
+		}
+		assert_eq!(received, [1, 3, 5]);
+		assert_eq!(serializer.expired(), 1);
+	}
+
+	#[test]
+	fn queued_with_ttl() {
+		use queue::QueuedSerializer;
+		use std::{thread, time::Duration};
+		let mut serializer = QueuedSerializer::new(Serializer::new(), 2);
+		let ttl = Duration::from_millis(20);
+		serializer.push().unwrap()(1_u8);
+		serializer.push().unwrap()(2_u8);
+		serializer.push_with_ttl(1, ttl).unwrap()(3_u8);
+		assert!(serializer.push::<u8>().is_none());
+		thread::sleep(ttl);
+		// the stale value no longer takes up room in the queue
+		serializer.push_with_priority(2).unwrap()(4_u8);
+		assert_eq!(serializer.len(), 2);
+		let mut deserializer = Deserializer::new();
+		let mut received = Vec::new();
+		loop {
+			let _ = deserializer.pull::<u8>();
+			while let (Some(push), Some(pull)) = (deserializer.push(), serializer.pull()) {
+				push(pull());
+			}
+			match deserializer.pull::<u8>() {
+				Some(pull) => received.push(pull()),
+				None => break,
+			}
+		}
+		assert_eq!(received, [1, 4, 2]);
+		assert_eq!(serializer.expired(), 1);
+	}
+
+	#[test]
+	fn queued_into_frames() {
+		use queue::QueuedSerializer;
+		let mut serializer = QueuedSerializer::new(Serializer::new(), 2);
+		serializer.push().unwrap()(String::from("in flight"));
+		let first = serializer.pull().unwrap()();
+		serializer.push().unwrap()(1_u8);
+		serializer.push_with_priority(1).unwrap()(2_u8);
+		let frames = serializer.into_frames().collect::<Vec<_>>();
+		// the remainder of the frame in flight, then the queued frames in order of priority
+		let mut in_flight = vec![first];
+		in_flight.extend(&frames[0]);
+		assert_eq!(in_flight, serialize_to_vec(String::from("in flight")));
+		assert_eq!(
+			&frames[1..],
+			[serialize_to_vec(2_u8), serialize_to_vec(1_u8)]
+		);
+		// nothing pushed, nothing returned
+		let serializer = QueuedSerializer::new(Serializer::new(), 2);
+		assert_eq!(serializer.into_frames().count(), 0);
+	}
+
+	#[cfg(all(feature = "zeroize", not(feature = "fringe"), not(feature = "forbid-unsafe")))]
+	#[test]
+	fn zeroize() {
+		use wiped::SECRET;
+		use zeroize::Zeroize;
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		// pulled
+		serializer.push().unwrap()(SECRET);
+		let _ = deserializer.pull::<String>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		let mut secret = deserializer.pull::<String>().unwrap()();
+		assert_eq!(secret, SECRET);
+		secret.zeroize();
+		// emptied partway, with another queued behind
+		serializer.push().unwrap()(SECRET);
+		serializer.push_or_queue(SECRET).unwrap();
+		let _ = deserializer.pull::<String>();
+		for _ in 0..10 {
+			deserializer.push().unwrap()(serializer.pull().unwrap()());
+		}
+		serializer.empty().unwrap()();
+		deserializer.empty().unwrap()();
+		// dropped while unwinding
+		let _ = std::panic::catch_unwind(|| {
+			let mut serializer = Serializer::new();
+			serializer.push().unwrap()(SECRET);
+			panic!();
+		});
+		assert!(!wiped::freed());
+		// the check itself
+		drop(String::from(SECRET));
+		assert!(wiped::freed());
+	}
+
+	#[test]
+	fn typed() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let mut typed = deserializer.typed::<(u8, String)>();
+		for i in 0..10_u8 {
+			serializer.push().unwrap()((i, i.to_string()));
+			while let (Some(push), Some(pull)) = (typed.push(), serializer.pull()) {
+				push(pull());
+			}
+			assert_eq!(typed.pull().unwrap()(), (i, i.to_string()));
+		}
+		assert!(typed.empty().is_none());
+	}
+
+	#[test]
+	fn poll() {
+		use poll::PipePoll;
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		assert!(matches!(serializer.poll_pull(), PipePoll::Empty));
+		assert!(matches!(serializer.poll_empty(), PipePoll::Empty));
+		assert!(matches!(deserializer.poll_push(), PipePoll::Empty));
+		serializer.poll_push().ready().unwrap()(String::from("poll"));
+		assert!(matches!(
+			serializer.poll_push::<u8>(),
+			PipePoll::AwaitingPull
+		));
+		assert!(matches!(
+			deserializer.poll_pull::<String>(),
+			PipePoll::AwaitingPush
+		));
+		while let PipePoll::Ready(pull) = serializer.poll_pull() {
+			deserializer.poll_push().ready().unwrap()(pull());
+		}
+		assert!(matches!(deserializer.poll_push(), PipePoll::AwaitingPull));
+		assert_eq!(
+			deserializer.poll_pull::<String>().ready().unwrap()(),
+			"poll"
+		);
+		assert!(matches!(deserializer.poll_empty(), PipePoll::Empty));
+	}
+
+	#[test]
+	fn packed() {
+		use packed::Packed;
+		#[derive(Clone, serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		enum Shape {
+			Dot,
+			Line(f32),
+			Rect { w: u16, h: u16 },
+		}
+		#[derive(Clone, serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		struct Entity {
+			flags: [bool; 19],
+			maybe: Option<char>,
+			none: Option<u64>,
+			shapes: Vec<Shape>,
+			name: String,
+		}
+		let entity = Entity {
+			flags: [
+				true, false, true, true, false, false, false, true, true, true, false, true, false,
+				true, true, true, false, false, true,
+			],
+			maybe: Some('p'),
+			none: None,
+			shapes: vec![Shape::Dot, Shape::Line(1.5), Shape::Rect { w: 3, h: 4 }],
+			name: String::from("packed"),
+		};
+		let vec = serialize_to_vec(Packed(entity.clone()));
+		// 19 flags, 2 option tags, and variant indices of 1, 3 and 3 bits: 4 bytes
+		#[cfg(not(feature = "fringe"))]
+		assert_eq!(vec.len(), 8 + 8 + 1 + 4 + 4 + 8 + 4 + 2 + 2 + 8 + 6);
+		let Packed(entity2) = deserialize_from_slice::<Packed<Entity>>(&vec).unwrap();
+		assert_eq!(entity, entity2);
+		assert!(deserialize_from_slice::<Packed<Entity>>(&vec[..vec.len() - 1]).is_none());
+	}
+
+	#[test]
+	fn quantized() {
+		use quantize::Quantized;
+		let vec = serialize_to_vec(Quantized::<_, 8, 4>((
+			1.0_f32,
+			-2.53_f64,
+			100.0_f32,
+			f32::NAN,
+			true,
+		)));
+		#[cfg(not(feature = "fringe"))]
+		assert_eq!(vec.len(), 8 + 8 + 1 + 1 + 4);
+		let value =
+			deserialize_from_slice::<Quantized<(f32, f64, f32, f32, bool), 8, 4>>(&vec).unwrap();
+		assert_eq!(value.0, (1.0, -2.5, 7.9375, 0.0, true));
+		let vec = serialize_to_vec(Quantized::<_, 32, 16>(vec![0.1_f64; 3]));
+		let Quantized(value) = deserialize_from_slice::<Quantized<Vec<f64>, 32, 16>>(&vec).unwrap();
+		assert!(value.iter().all(|&v| (v - 0.1).abs() < 1e-5));
+	}
+
+	#[test]
+	fn batch() {
+		use batch::{BatchDeserializer, BatchSerializer};
+		let mut rng = SmallRng::from_seed([0; 16]);
+		// three u64s exceed the 20 byte budget
+		let mut serializer = BatchSerializer::new(Serializer::new(), 4, 20);
+		let mut deserializer = BatchDeserializer::<u64>::new(Deserializer::new());
+		let (mut sent, mut received) = (0_u64, Vec::new());
+		while received.len() < 1000 {
+			while sent < 1000 && rng.gen_bool(0.7) {
+				match serializer.push() {
+					Some(push) => push(sent),
+					None => break,
+				}
+				sent += 1;
+				assert!(serializer.len() <= 3);
+			}
+			for _ in 0..rng.gen_range(0, 30) {
+				match (serializer.pull(), deserializer.push()) {
+					(Some(pull), Some(push)) => push(pull()),
+					_ => break,
+				}
+			}
+			while let Some(pull) = deserializer.pull() {
+				received.push(pull());
+			}
+		}
+		assert_eq!(received, (0..1000).collect::<Vec<_>>());
+		assert!(serializer.empty().is_none() && deserializer.empty().is_none());
+	}
+
+	#[test]
+	fn tap() {
+		use std::sync::{Arc, Mutex};
+		use tap::{TapDeserializer, TapSerializer};
+		let sent = Arc::new(Mutex::new(Vec::new()));
+		let received = Arc::new(Mutex::new(Vec::new()));
+		let (sent_sink, received_sink) = (sent.clone(), received.clone());
+		let mut serializer = TapSerializer::new(Serializer::new(), 3, move |sample| {
+			sent_sink.lock().unwrap().push(sample.bytes.to_owned());
+		});
+		let mut deserializer = TapDeserializer::new(Deserializer::new(), 2, move |sample| {
+			assert_eq!(sample.type_name, "alloc::string::String");
+			received_sink
+				.lock()
+				.unwrap()
+				.push(sample.decode::<String>().unwrap());
+		});
+		for i in 0..7 {
+			serializer.push().unwrap()(i.to_string());
+			let _ = deserializer.pull::<String>();
+			while let (Some(pull), Some(push)) = (serializer.pull(), deserializer.push()) {
+				push(pull());
+			}
+			assert_eq!(deserializer.pull::<String>().unwrap()(), i.to_string());
+		}
+		let sent = sent.lock().unwrap();
+		assert_eq!(
+			*sent,
+			["0", "3", "6"].map(|s| serialize_to_vec(String::from(s)))
+		);
+		assert_eq!(*received.lock().unwrap(), ["0", "2", "4", "6"]);
+	}
+
+	#[test]
+	fn persist() {
+		use persist::PersistentQueue;
+		let path = std::env::temp_dir().join(format!("serde_pipe-persist-{}", std::process::id()));
+		let mut queue = PersistentQueue::open(&path).unwrap();
+		for i in 0..5_u32 {
+			queue.push(i).unwrap();
+		}
+		queue.push(()).unwrap();
+		let acks = (0..5)
+			.map(|i| {
+				let (x, ack) = queue.pull::<u32>().unwrap().unwrap();
+				assert_eq!(x, i);
+				ack
+			})
+			.collect::<Vec<_>>();
+		queue.ack(acks[1]).unwrap();
+		queue.ack(acks[3]).unwrap();
+		drop(queue);
+		// a crash part way through a push
+		let mut file = std::fs::OpenOptions::new()
+			.append(true)
+			.open(&path)
+			.unwrap();
+		file.write_all(&[4, 0, 0, 0, 0, 0, 0, 0, 1]).unwrap();
+		drop(file);
+
+		let mut queue = PersistentQueue::open(&path).unwrap();
+		let mut acks = Vec::new();
+		for i in [0, 2, 4] {
+			let (x, ack) = queue.pull::<u32>().unwrap().unwrap();
+			assert_eq!(x, i);
+			acks.push(ack);
+		}
+		assert!(queue.pull::<u32>().is_err());
+		let ((), ack) = queue.pull::<()>().unwrap().unwrap();
+		acks.push(ack);
+		assert!(queue.pull::<u32>().unwrap().is_none());
+		for ack in acks {
+			assert!(!queue.is_empty());
+			queue.ack(ack).unwrap();
+		}
+		assert!(queue.is_empty());
+		drop(queue);
+		assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+		// a crash after truncating the file but before resetting the acks
+		std::fs::write(path.with_extension("ack"), 64_u64.to_le_bytes()).unwrap();
+		let mut queue = PersistentQueue::open(&path).unwrap();
+		for i in 0..10_u32 {
+			queue.push(i).unwrap();
+		}
+		drop(queue);
+		let mut queue = PersistentQueue::open(&path).unwrap();
+		for i in 0..10_u32 {
+			assert_eq!(queue.pull::<u32>().unwrap().unwrap().0, i);
+		}
+		assert!(queue.pull::<u32>().unwrap().is_none());
+		drop(queue);
+		std::fs::remove_file(&path).unwrap();
+		std::fs::remove_file(path.with_extension("ack")).unwrap();
+	}
+
+	#[test]
+	fn record() {
+		use record::{Recorder, Replay};
+		use std::time::{Duration, UNIX_EPOCH};
+		let mut recorder = Recorder::new(Vec::new(), Vec::new());
+		for i in 0..10_u32 {
+			let time = UNIX_EPOCH + Duration::from_secs(u64::from(i / 2));
+			if i % 3 == 0 {
+				recorder.record_at(i.to_string(), time).unwrap();
+			} else {
+				recorder.record_at(i, time).unwrap();
+			}
+		}
+		let (journal, mut index) = recorder.into_inner();
+		// a crash part way through indexing
+		index.extend_from_slice(&[1, 2, 3]);
+
+		let mut replay = Replay::new(io::Cursor::new(journal), &*index).unwrap();
+		assert_eq!(replay.entries().len(), 10);
+		assert_eq!(
+			replay.entries()[3].type_name,
+			std::any::type_name::<String>()
+		);
+		replay.seek(4).unwrap();
+		assert_eq!(replay.pull::<u32>().unwrap(), Some(4));
+		assert_eq!(replay.pull::<u32>().unwrap(), Some(5));
+		assert_eq!(replay.position(), 6);
+		replay.seek_time(UNIX_EPOCH + Duration::from_millis(3500));
+		assert_eq!(replay.pull::<u32>().unwrap(), Some(8));
+		replay.seek_time(UNIX_EPOCH + Duration::from_secs(3));
+		assert_eq!(replay.pull::<String>().unwrap().unwrap(), "6");
+		assert_eq!(replay.pull::<u32>().unwrap(), Some(7));
+		replay.seek_time(UNIX_EPOCH + Duration::from_secs(5));
+		assert_eq!(replay.pull::<u32>().unwrap(), None);
+		assert!(replay.seek(11).is_err());
+	}
+
+	#[test]
+	fn cache() {
+		use cache::{CacheDeserializer, CacheSerializer};
+		let mut serializer = CacheSerializer::new(Serializer::new(), 2);
+		let mut deserializer = CacheDeserializer::new(Deserializer::new(), 2);
+		let configs = ["a", "b", "a", "c", "a", "b"];
+		for config in &configs {
+			serializer.push().unwrap()(String::from(*config));
+			let _ = deserializer.pull::<String>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull::<String>().unwrap()(), *config);
+			// a different type with the same encoding isn't confused for it
+			serializer.push().unwrap()(config.as_bytes().to_vec());
+			let _ = deserializer.pull::<Vec<u8>>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(
+				deserializer.pull::<Vec<u8>>().unwrap()(),
+				config.as_bytes()
+			);
+		}
+		assert_eq!(serializer.hits(), 0);
+		let mut serializer = CacheSerializer::new(Serializer::new(), 2);
+		let mut deserializer = CacheDeserializer::new(Deserializer::new(), 2);
+		for config in &configs {
+			serializer.push().unwrap()(String::from(*config));
+			let _ = deserializer.pull::<String>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull::<String>().unwrap()(), *config);
+		}
+		// only the first repeat of "a" is hit, as "c" then evicts it, and resending it evicts "b"
+		assert_eq!(serializer.hits(), 1);
+	}
+
+	#[test]
+	fn dedup() {
+		use dedup::{DedupDeserializer, DedupSerializer};
+		let mut serializer = DedupSerializer::new(Serializer::new());
+		let mut deserializer = DedupDeserializer::new(Deserializer::new());
+		let statuses = [0_u32, 0, 1, 1, 1, 0];
+		let mut bytes = 0;
+		for (i, &status) in statuses.iter().enumerate() {
+			serializer.push().unwrap()(status);
+			assert!(serializer.push::<u32>().is_none());
+			let _ = deserializer.pull::<u32>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+				bytes += 1;
+			}
+			assert_eq!(deserializer.pull::<u32>().unwrap()(), status);
+			serializer.push().unwrap()(format!("{}", i % 2));
+			let _ = deserializer.pull::<String>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(
+				deserializer.pull::<String>().unwrap()(),
+				format!("{}", i % 2)
+			);
+		}
+		assert_eq!(serializer.repeats(), 3);
+		let header_len = serialize_to_vec(0_u8).len() - 1;
+		assert_eq!(bytes, 3 * (header_len + 1 + 4) + 3 * (header_len + 1));
+	}
+
+	#[test]
+	fn negotiate() {
+		use negotiate::{Mismatch, Offer};
+		let supported = Offer::new()
+			.with("cipher", &["aes-gcm", "chacha20-poly1305"])
+			.with("compression", &["none"])
+			.with("checksum", &["crc32"]);
+		let offer = Offer::new()
+			.with("cipher", &["chacha20-poly1305", "aes-gcm"])
+			.with("compression", &["zstd", "none"])
+			.with("format", &["2"]);
+		let agreement = supported.accept(&offer).unwrap();
+		assert_eq!(
+			agreement.iter().collect::<Vec<_>>(),
+			[("cipher", "chacha20-poly1305"), ("compression", "none")]
+		);
+		assert_eq!(agreement.get("format"), None);
+		let offer = deserialize_from_slice::<Offer>(&serialize_to_vec(
+			offer.with("compression", &["zstd"]),
+		))
+		.unwrap();
+		assert_eq!(
+			supported.accept(&offer),
+			Err(Mismatch {
+				option: String::from("compression")
+			})
+		);
+	}
+
+	#[test]
+	fn codec() {
+		use codec::{
+			Checksum, CodecDeserializer, CodecError, CodecSerializer, Compressor, Crc32, Layers,
+		};
+		use crypto::Cipher;
+		assert_eq!(Crc32.checksum(b"123456789"), 0xcbf4_3926);
+		// run-length encoding, as (count, byte) pairs
+		struct Rle;
+		impl Compressor for Rle {
+			fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+				let mut ret = Vec::new();
+				for &byte in bytes {
+					match ret.len() {
+						len if len >= 2 && ret[len - 1] == byte && ret[len - 2] < u8::MAX => {
+							ret[len - 2] += 1;
+						}
+						_ => ret.extend_from_slice(&[1, byte]),
+					}
+				}
+				ret
+			}
+			fn decompress(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+				if !bytes.len().is_multiple_of(2) {
+					return None;
+				}
+				Some(
+					bytes
+						.chunks(2)
+						.flat_map(|pair| std::iter::repeat_n(pair[1], pair[0].into()))
+						.collect(),
+				)
+			}
+		}
+		// unauthenticated, so tampering is left to the checksum to catch
+		struct Xor;
+		impl Cipher for Xor {
+			fn seal(&self, _nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) {
+				for byte in buffer {
+					*byte ^= 0x5a;
+				}
+			}
+			fn open(&self, _nonce: &[u8; 12], _aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+				for byte in buffer {
+					*byte ^= 0x5a;
+				}
+				true
+			}
+		}
+		fn send(serializer: &mut CodecSerializer, t: Vec<u8>) -> Vec<u8> {
+			serializer.push().unwrap()(t);
+			std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect()
+		}
+		fn receive(
+			deserializer: &mut CodecDeserializer, bytes: &[u8],
+		) -> Result<Vec<u8>, CodecError> {
+			for &byte in bytes {
+				deserializer.push().unwrap()(byte);
+			}
+			deserializer.pull().unwrap()()
+		}
+		let value = vec![7_u8; 200];
+		let mut serializer = Layers::new()
+			.checksum(Crc32)
+			.compress(Rle)
+			.serializer(Serializer::new());
+		let mut deserializer = Layers::new()
+			.compress(Rle)
+			.checksum(Crc32)
+			.deserializer(Deserializer::new());
+		let frame = send(&mut serializer, value.clone());
+		assert!(frame.len() < value.len());
+		assert_eq!(receive(&mut deserializer, &frame), Ok(value.clone()));
+		let mut corrupt = frame.clone();
+		*corrupt.last_mut().unwrap() ^= 1;
+		assert_eq!(
+			receive(&mut deserializer, &corrupt),
+			Err(CodecError::Checksum)
+		);
+		// a mismatched configuration is caught rather than misread
+		let mut deserializer = Layers::new()
+			.checksum(Crc32)
+			.deserializer(Deserializer::new());
+		assert_eq!(
+			receive(&mut deserializer, &frame),
+			Err(CodecError::Malformed)
+		);
+
+		let layers = || Layers::new().encrypt(1, Xor).checksum(Crc32).compress(Rle);
+		let mut serializer = layers().serializer(Serializer::new());
+		let mut deserializer = layers().deserializer(Deserializer::new());
+		let frame = send(&mut serializer, value.clone());
+		assert_eq!(receive(&mut deserializer, &frame), Ok(value.clone()));
+		let mut corrupt = send(&mut serializer, value);
+		let len = corrupt.len();
+		corrupt[len - 3] ^= 1;
+		assert_eq!(
+			receive(&mut deserializer, &corrupt),
+			Err(CodecError::Checksum)
+		);
+	}
+
+	// with the fringe feature, frames can't be delimited to skip a corrupted length
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn codec_corrupt_length() {
+		use codec::{CodecError, Crc32, Layers};
+		let mut serializer = Layers::new().checksum(Crc32).serializer(Serializer::new());
+		let mut deserializer = Layers::new()
+			.checksum(Crc32)
+			.deserializer(Deserializer::new());
+		serializer.push().unwrap()(String::from("abc"));
+		let frame = std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect::<Vec<_>>();
+		let header_len = serialize_to_vec(0_u8).len() - 1;
+		let mut receive = |bytes: &[u8]| {
+			for &byte in bytes {
+				deserializer.push().unwrap()(byte);
+			}
+			deserializer.pull::<String>().unwrap()()
+		};
+		// the length of the bytes, the payload's first field, in its low and high bytes
+		for (offset, bit) in [(0, 1), (7, 0x80)] {
+			let mut corrupt = frame.clone();
+			corrupt[header_len + offset] ^= bit;
+			assert_eq!(receive(&corrupt), Err(CodecError::Checksum));
+		}
+		assert_eq!(receive(&frame), Ok(String::from("abc")));
+	}
+	#[test]
+	fn crypto() {
+		use crypto::{Cipher, OpenError, OpeningDeserializer, SealingSerializer};
+		use std::time::{Duration, Instant};
+		// a stand-in for a real AEAD: a keystream from the key and nonce, and a checksum tag
+		struct Toy(u8);
+		impl Toy {
+			fn xor(&self, nonce: &[u8; 12], buffer: &mut [u8]) {
+				for (byte, n) in buffer.iter_mut().zip(nonce.iter().cycle()) {
+					*byte ^= self.0 ^ n;
+				}
+			}
+			fn tag(&self, nonce: &[u8; 12], aad: &[u8], buffer: &[u8]) -> u8 {
+				nonce
+					.iter()
+					.chain(aad)
+					.chain(buffer)
+					.fold(self.0, |tag, &byte| tag.rotate_left(3) ^ byte)
+			}
+		}
+		impl Cipher for Toy {
+			fn seal(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>) {
+				let tag = self.tag(nonce, aad, buffer);
+				self.xor(nonce, buffer);
+				buffer.push(tag);
+			}
+			fn open(&self, nonce: &[u8; 12], aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+				let tag = buffer.pop();
+				self.xor(nonce, buffer);
+				tag == Some(self.tag(nonce, aad, buffer))
+			}
+		}
+		fn seal(serializer: &mut SealingSerializer, x: u32) -> Vec<u8> {
+			serializer.push().unwrap()(x);
+			std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect()
+		}
+		fn open_at(
+			deserializer: &mut OpeningDeserializer, bytes: &[u8], now: Instant,
+		) -> Result<u32, OpenError> {
+			let _ = deserializer.pull::<u32>();
+			for &byte in bytes {
+				deserializer.push().unwrap()(byte);
+			}
+			deserializer.pull_at(now).unwrap()()
+		}
+		let start = Instant::now();
+		let open = |deserializer: &mut OpeningDeserializer, bytes: &[u8]| {
+			open_at(deserializer, bytes, start)
+		};
+		let mut serializer = SealingSerializer::new(Serializer::new(), 1, Toy(1));
+		let mut deserializer =
+			OpeningDeserializer::new(Deserializer::new(), Duration::from_millis(50));
+		deserializer.add_key(1, Toy(1));
+		let frame = seal(&mut serializer, 10);
+		assert_eq!(open(&mut deserializer, &frame), Ok(10));
+		assert_eq!(open(&mut deserializer, &frame), Err(OpenError::Replayed));
+		let mut frame = seal(&mut serializer, 11);
+		let header_len = serialize_to_vec(0_u8).len() - 1;
+		frame[header_len + 4] += 1; // the nonce
+		assert_eq!(open(&mut deserializer, &frame), Err(OpenError::Forged));
+		let in_flight = seal(&mut serializer, 12);
+
+		serializer.rotate(2, Toy(2));
+		let frame = seal(&mut serializer, 13);
+		assert_eq!(
+			open(&mut deserializer, &frame),
+			Err(OpenError::UnknownKey(2))
+		);
+		deserializer.add_key(2, Toy(2));
+		let frame = seal(&mut serializer, 14);
+		assert_eq!(open(&mut deserializer, &frame), Ok(14));
+		// within the grace period, the old key still opens frames in flight
+		assert_eq!(open(&mut deserializer, &in_flight), Ok(12));
+		let frame = seal(&mut serializer, 15);
+		assert_eq!(
+			open_at(&mut deserializer, &frame, start + Duration::from_millis(60)),
+			Ok(15)
+		);
+		assert_eq!(deserializer.key_ids().collect::<Vec<_>>(), [2]);
+		// an authenticated plaintext must hold exactly the value
+		serializer.push().unwrap()(16_u64);
+		let frame = std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect::<Vec<_>>();
+		assert_eq!(open(&mut deserializer, &frame), Err(OpenError::Malformed));
+		// as must the envelope, which with the fringe feature can't be delimited to skip
+		if cfg!(not(feature = "fringe")) {
+			for frame in [serialize_to_vec([0xff_u8; 3]), serialize_to_vec([0xff_u8; 24])] {
+				assert_eq!(open(&mut deserializer, &frame), Err(OpenError::Malformed));
+			}
+			let frame = seal(&mut serializer, 17);
+			assert_eq!(open(&mut deserializer, &frame), Ok(17));
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "key 1 has already been added")]
+	fn crypto_duplicate_key() {
+		use crypto::{Cipher, OpeningDeserializer};
+		struct Plain;
+		impl Cipher for Plain {
+			fn seal(&self, _nonce: &[u8; 12], _aad: &[u8], _buffer: &mut Vec<u8>) {}
+			fn open(&self, _nonce: &[u8; 12], _aad: &[u8], _buffer: &mut Vec<u8>) -> bool {
+				true
+			}
+		}
+		let mut deserializer =
+			OpeningDeserializer::new(Deserializer::new(), std::time::Duration::from_secs(10));
+		deserializer.add_key(1, Plain);
+		// re-adding would reset the nonces seen, allowing replays
+		deserializer.add_key(1, Plain);
+	}
+
+	#[test]
+	fn transaction() {
+		use transaction::{TransactionDeserializer, TransactionSerializer};
+		let mut serializer = TransactionSerializer::new(Serializer::new());
+		let mut deserializer = TransactionDeserializer::new(Deserializer::new());
+		let mut received = Vec::new();
+		let mut pump = |serializer: &mut TransactionSerializer<String>,
+		                deserializer: &mut TransactionDeserializer<String>| {
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+				while let Some(pull) = deserializer.pull() {
+					received.push(pull());
+				}
+			}
+		};
+		serializer.push().unwrap()(String::from("a"));
+		pump(&mut serializer, &mut deserializer);
+		serializer.begin().unwrap()();
+		assert!(serializer.in_transaction());
+		pump(&mut serializer, &mut deserializer);
+		serializer.push().unwrap()(String::from("b"));
+		pump(&mut serializer, &mut deserializer);
+		assert_eq!(deserializer.pending(), Some(1));
+		for x in ["c", "d"] {
+			// the sender restarts mid-transaction
+			serializer = TransactionSerializer::new(serializer.into_inner());
+			serializer.begin().unwrap()();
+			pump(&mut serializer, &mut deserializer);
+			serializer.push().unwrap()(String::from(x));
+			pump(&mut serializer, &mut deserializer);
+		}
+		serializer.commit().unwrap()();
+		pump(&mut serializer, &mut deserializer);
+		assert_eq!(deserializer.pending(), None);
+		serializer.begin().unwrap()();
+		pump(&mut serializer, &mut deserializer);
+		serializer.push().unwrap()(String::from("e"));
+		pump(&mut serializer, &mut deserializer);
+		serializer.abort().unwrap()();
+		pump(&mut serializer, &mut deserializer);
+		serializer.push().unwrap()(String::from("f"));
+		pump(&mut serializer, &mut deserializer);
+		assert_eq!(received, ["a", "d", "f"]);
+	}
+
+	#[test]
+	fn drop_while_panicking() {
+		let err = std::panic::catch_unwind(|| {
+			let mut serializer = Serializer::new();
+			let mut deserializer = Deserializer::new();
+			serializer.push().unwrap()(0x0102_u16);
+			let _ = deserializer.pull::<u16>();
+			deserializer.push().unwrap()(serializer.pull().unwrap()());
+			panic!("original");
+		})
+		.unwrap_err();
+		assert_eq!(err.downcast_ref::<&str>(), Some(&"original"));
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn async_pipe() {
+		let serializer = sync::AsyncPipe::new(Serializer::new());
+		let deserializer = sync::AsyncPipe::new(Deserializer::new());
+		let (senders, iterations) = (4_u64, 100_u64);
+		let send = |sender| {
+			let serializer = &serializer;
+			async move {
+				for i in 0..iterations {
+					serializer.send(sender * iterations + i).await;
+				}
+			}
+		};
+		let transport = async {
+			for _ in 0..senders * iterations * serialize_to_vec(0_u64).len() as u64 {
+				let byte = serializer
+					.wait(|serializer| serializer.pull().map(|pull| pull()))
+					.await;
+				deserializer
+					.wait(|deserializer| deserializer.push().map(|push| push(byte)))
+					.await;
+			}
+		};
+		let recv = async {
+			let mut received = Vec::new();
+			for _ in 0..senders * iterations {
+				received.push(deserializer.recv::<u64>().await);
+			}
+			received.sort_unstable();
+			assert_eq!(received, (0..senders * iterations).collect::<Vec<_>>());
+		};
+		tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async {
+				let ((), (), (), (), (), ()) =
+					tokio::join!(send(0), send(1), send(2), send(3), transport, recv);
+			});
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn async_serializer_read() {
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let pusher = serializer.clone();
+		let push = async {
+			for i in 0..10_u64 {
+				// yield so that the reader awaits each value
+				tokio::task::yield_now().await;
+				pusher.push_or_queue(vec![i; 1000]).unwrap();
+			}
+			pusher.close();
+		};
+		let copy = async {
+			let mut bytes = Vec::new();
+			let _ = tokio::io::copy(&mut serializer, &mut bytes).await.unwrap();
+			bytes
+		};
+		let ((), bytes) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(push, copy) });
+		assert_eq!(
+			bytes,
+			(0..10_u64)
+				.flat_map(|i| serialize_to_vec(vec![i; 1000]))
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn async_deserializer_write() {
+		use std::future::poll_fn;
+		let mut deserializer = task::AsyncDeserializer::new(Deserializer::new());
+		let puller = deserializer.clone();
+		let bytes = (0..10_u64)
+			.flat_map(|i| serialize_to_vec(vec![i; 1000]))
+			.collect::<Vec<_>>();
+		let copy = async {
+			let len = tokio::io::copy(&mut &*bytes, &mut deserializer).await.unwrap();
+			assert_eq!(len, bytes.len() as u64);
+		};
+		let pull = async {
+			let mut values = Vec::new();
+			for _ in 0..10 {
+				values.push(poll_fn(|cx| puller.poll_pull::<Vec<u64>>(cx)).await);
+			}
+			values
+		};
+		let ((), values) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(copy, pull) });
+		assert_eq!(values, (0..10_u64).map(|i| vec![i; 1000]).collect::<Vec<_>>());
+	}
+
+	#[cfg(feature = "embedded-io")]
+	#[test]
+	fn embedded_io() {
+		use embedded_io::{Read, ReadReady, Write, WriteReady};
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let mut buf = [0; 16];
+		assert!(!serializer.read_ready().unwrap());
+		assert_eq!(serializer.read(&mut buf), Err(NotReady));
+		assert_eq!(serializer.read(&mut []), Ok(0));
+		serializer.push().unwrap()(String::from("hello"));
+		let mut bytes = Vec::new();
+		while serializer.read_ready().unwrap() {
+			let len = serializer.read(&mut buf).unwrap();
+			bytes.extend_from_slice(&buf[..len]);
+		}
+		assert_eq!(bytes, serialize_to_vec(String::from("hello")));
+		assert!(deserializer.pull::<String>().is_none());
+		deserializer.write_all(&bytes).unwrap();
+		assert!(!deserializer.write_ready().unwrap());
+		assert_eq!(deserializer.write(&bytes), Err(NotReady));
+		assert_eq!(deserializer.write(&[]), Ok(0));
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "hello");
+		assert!(deserializer.pull::<String>().is_none());
+		assert!(deserializer.write_ready().unwrap());
+	}
+
+	#[cfg(feature = "embedded-io-async")]
+	#[test]
+	fn embedded_io_async() {
+		use embedded_io_async::{Read, Write};
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let mut deserializer = task::AsyncDeserializer::new(Deserializer::new());
+		let (pusher, puller) = (serializer.clone(), deserializer.clone());
+		let push = async {
+			for i in 0..10_u64 {
+				pusher.push(vec![i; 100]).await;
+			}
+			pusher.close();
+		};
+		let copy = async {
+			let mut buf = [0; 64];
+			loop {
+				let len = serializer.read(&mut buf).await.unwrap();
+				if len == 0 {
+					break;
+				}
+				deserializer.write_all(&buf[..len]).await.unwrap();
+			}
+		};
+		let pull = async {
+			let mut values = Vec::new();
+			for _ in 0..10 {
+				values.push(puller.pull::<Vec<u64>>().await);
+			}
+			values
+		};
+		let ((), (), values) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(push, copy, pull) });
+		assert_eq!(values, (0..10_u64).map(|i| vec![i; 100]).collect::<Vec<_>>());
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn async_push_pull() {
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let mut deserializer = task::AsyncDeserializer::new(Deserializer::new());
+		let (pusher, puller) = (serializer.clone(), deserializer.clone());
+		let push = async {
+			for i in 0..10_u64 {
+				pusher.push(vec![i; 1000]).await;
+			}
+			pusher.close();
+		};
+		let copy = async {
+			let _ = tokio::io::copy(&mut serializer, &mut deserializer)
+				.await
+				.unwrap();
+		};
+		let pull = async {
+			let mut values = Vec::new();
+			for _ in 0..10 {
+				values.push(puller.pull::<Vec<u64>>().await);
+			}
+			values
+		};
+		let ((), (), values) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(push, copy, pull) });
+		assert_eq!(values, (0..10_u64).map(|i| vec![i; 1000]).collect::<Vec<_>>());
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn async_push_pull_shared() {
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let mut deserializer = task::AsyncDeserializer::new(Deserializer::new());
+		let (pusher, puller) = (serializer.clone(), deserializer.clone());
+		// two tasks awaiting each side at once, each of which must be woken
+		let push = |i: u64| {
+			let pusher = pusher.clone();
+			async move {
+				for j in 0..10 {
+					pusher.push(vec![i * 10 + j; 100]).await;
+				}
+			}
+		};
+		let pull = || {
+			let puller = puller.clone();
+			async move {
+				let mut values = Vec::new();
+				for _ in 0..10 {
+					values.push(puller.pull::<Vec<u64>>().await);
+				}
+				values
+			}
+		};
+		let copy = async {
+			let _ = tokio::io::copy(&mut serializer, &mut deserializer)
+				.await
+				.unwrap();
+		};
+		let push_both = async {
+			let ((), ()) = tokio::join!(push(0), push(1));
+			pusher.close();
+		};
+		let ((), (), mut values, rest) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(push_both, copy, pull(), pull()) });
+		values.extend(rest);
+		values.sort();
+		assert_eq!(values, (0..20_u64).map(|i| vec![i; 100]).collect::<Vec<_>>());
+	}
+
+	#[cfg(feature = "futures")]
+	#[test]
+	fn async_serializer_sink() {
+		use futures_core::Stream;
+		use futures_sink::Sink;
+		use std::{future::poll_fn, pin::Pin};
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let mut sink = serializer.clone();
+		let send = async {
+			for i in 0..10_u64 {
+				poll_fn(|cx| Sink::<Vec<u64>>::poll_ready(Pin::new(&mut sink), cx))
+					.await
+					.unwrap();
+				Pin::new(&mut sink).start_send(vec![i; 1000]).unwrap();
+			}
+			// closing awaits the last value's bytes having been pulled
+			poll_fn(|cx| Sink::<Vec<u64>>::poll_close(Pin::new(&mut sink), cx))
+				.await
+				.unwrap();
+			assert!(sink.with(|serializer| serializer.pull().is_none()));
+		};
+		let pull = async {
+			let mut bytes = Vec::new();
+			while let Some(chunk) = poll_fn(|cx| Pin::new(&mut serializer).poll_next(cx)).await {
+				bytes.extend_from_slice(&chunk);
+			}
+			bytes
+		};
+		let ((), bytes) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(send, pull) });
+		assert_eq!(
+			bytes,
+			(0..10_u64)
+				.flat_map(|i| serialize_to_vec(vec![i; 1000]))
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[cfg(feature = "futures")]
+	#[test]
+	fn async_serializer_stream() {
+		use futures_core::Stream;
+		use std::{future::poll_fn, pin::Pin};
+		let mut serializer = task::AsyncSerializer::new(Serializer::new());
+		let pusher = serializer.clone();
+		let push = async {
+			for i in 0..10_u64 {
+				// yield so that the stream awaits each value
+				tokio::task::yield_now().await;
+				pusher.push_or_queue(vec![i; 1000]).unwrap();
+			}
+			pusher.close();
+		};
+		let pull = async {
+			let mut bytes = Vec::new();
+			while let Some(chunk) = poll_fn(|cx| Pin::new(&mut serializer).poll_next(cx)).await {
+				bytes.extend_from_slice(&chunk);
+			}
+			bytes
+		};
+		let ((), bytes) = tokio::runtime::Builder::new_current_thread()
+			.build()
+			.unwrap()
+			.block_on(async { tokio::join!(push, pull) });
+		assert_eq!(
+			bytes,
+			(0..10_u64)
+				.flat_map(|i| serialize_to_vec(vec![i; 1000]))
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[cfg(loom)]
+	#[test]
+	fn loom_ring() {
+		loom::model(|| {
+			let (mut producer, mut consumer) = spsc::ring(1);
+			let thread = loom::thread::spawn(move || {
+				for i in 0..2 {
+					loop {
+						if let Some(push) = producer.push() {
+							break push(i);
+						}
+						loom::thread::yield_now();
+					}
+				}
+			});
+			for i in 0..2 {
+				let x = loop {
+					if let Some(pull) = consumer.pull() {
+						break pull();
+					}
+					loom::thread::yield_now();
+				};
+				assert_eq!(x, i);
+			}
+			thread.join().unwrap();
+		});
+	}
+
+	#[cfg(loom)]
+	#[test]
+	fn loom_channel() {
+		let mut model = loom::model::Builder::new();
+		model.preemption_bound = Some(1);
+		model.check(|| {
+			let (mut sender, mut receiver) = spsc::channel::<()>(16);
+			let thread = loom::thread::spawn(move || sender.send(()).unwrap());
+			assert_eq!(receiver.recv(), Some(()));
+			assert_eq!(receiver.recv(), None);
+			thread.join().unwrap();
+		});
+	}
+
+	#[cfg(loom)]
+	#[test]
+	fn loom_lock_pipe() {
+		let mut model = loom::model::Builder::new();
+		model.preemption_bound = Some(2);
+		model.check(|| {
+			let mut serializer = Serializer::new();
+			serializer.push().unwrap()(());
+			let bytes = serializer.into_vec();
+			let deserializer = loom::sync::Arc::new(sync::LockPipe::new(Deserializer::new()));
+			let thread = loom::thread::spawn({
+				let deserializer = deserializer.clone();
+				move || {
+					for byte in bytes {
+						deserializer
+							.wait(|deserializer| deserializer.push().map(|push| push(byte)));
+					}
+				}
+			});
+			let () = deserializer.wait(|deserializer| deserializer.pull().map(|pull| pull()));
+			thread.join().unwrap();
+		});
+	}
+
+	#[test]
+	fn statics() {
+		use std::sync::Mutex;
+		static SERIALIZER: Mutex<Serializer> = Mutex::new(Serializer::new());
+		static DESERIALIZER: Mutex<Deserializer> = Mutex::new(Deserializer::new());
+		let (mut serializer, mut deserializer) =
+			(SERIALIZER.lock().unwrap(), DESERIALIZER.lock().unwrap());
+		serializer.push().unwrap()(String::from("static"));
+		let _ = deserializer.pull::<String>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "static");
+	}
+
+	#[cfg(feature = "critical-section")]
+	#[test]
+	fn critical_section() {
+		static SERIALIZER: sync::CsPipe<Serializer> = sync::CsPipe::new(Serializer::new());
+		let producer = std::thread::spawn(|| {
+			for i in 0..100_u32 {
+				while !SERIALIZER.with(|serializer| serializer.push().map(|push| push(i)).is_some())
+				{
+					std::thread::yield_now();
+				}
+			}
+		});
+		let mut deserializer = Deserializer::new();
+		for i in 0..100_u32 {
+			let _ = deserializer.pull::<u32>();
+			while !deserializer.pull_avail() {
+				SERIALIZER.with(|serializer| {
+					while let (Some(pull), Some(push)) = (serializer.pull(), deserializer.push()) {
+						push(pull());
+					}
+				});
+			}
+			assert_eq!(deserializer.pull::<u32>().unwrap()(), i);
+		}
+		producer.join().unwrap();
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn heartbeat() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<u8>();
+		for i in 0..3 {
+			assert!(serializer.push_heartbeat().is_some());
+			serializer.push_heartbeat().unwrap()();
+			assert!(serializer.push::<u8>().is_none());
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.heartbeats(), i + 1);
+			assert!(deserializer.pull::<u8>().is_none());
+		}
+		serializer.push().unwrap()(7_u8);
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<u8>().unwrap()(), 7);
+		assert_eq!(deserializer.heartbeats(), 3);
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	#[should_panic(expected = "heartbeats aren't supported with the fringe feature")]
+	fn heartbeat_unsupported() {
+		assert_eq!(Deserializer::new().heartbeats(), 0);
+		let _ = Serializer::new().push_heartbeat();
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn control() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<String>();
+		let frames = vec![
+			Err(Control::Flush),
+			Ok(String::from("a")),
+			Err(Control::Error(404)),
+			Err(Control::App(u32::MAX)),
+			Ok(String::from("b")),
+			Err(Control::Close),
+		];
+		for frame in &frames {
+			match frame {
+				Ok(x) => serializer.push().unwrap()(x.clone()),
+				Err(control) => serializer.push_control().unwrap()(*control),
+			}
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert!(deserializer.push().is_none());
+			match frame {
+				Ok(x) => {
+					assert!(deserializer.pull_control().is_none());
+					assert_eq!(&deserializer.pull::<String>().unwrap()(), x);
+				}
+				Err(control) => {
+					assert!(deserializer.pull::<String>().is_none());
+					assert_eq!(deserializer.pull_control().unwrap()(), *control);
+				}
+			}
+			let _ = deserializer.pull::<String>();
+		}
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	#[should_panic(expected = "control frames aren't supported with the fringe feature")]
+	fn control_unsupported() {
+		assert!(Deserializer::new().pull_control().is_none());
+		let _ = Serializer::new().push_control();
+	}
+
+	#[test]
+	fn flow() {
+		use flow::{Credit, CreditDeserializer, CreditSerializer};
+		fn transfer(
+			serializer: &mut CreditSerializer, deserializer: &mut CreditDeserializer,
+		) -> usize {
+			let mut moved = 0;
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+				moved += 1;
+			}
+			moved
+		}
+		let mut serializer = CreditSerializer::new(
+			Serializer::new(),
+			Credit {
+				bytes: 4,
+				frames: 1,
+			},
+		);
+		let mut deserializer = CreditDeserializer::new(Deserializer::new());
+		let len = serialize_to_vec(String::from("hello")).len();
+		serializer.push().unwrap()(String::from("hello"));
+		assert!(serializer.push::<String>().is_none());
+		let _ = deserializer.pull::<String>();
+		// out of byte credit partway through the frame
+		assert_eq!(transfer(&mut serializer, &mut deserializer), 4);
+		assert_eq!(serializer.credit(), Credit::default());
+		assert!(deserializer.pull::<String>().is_none());
+		// returning the credit freed up resumes it where it stopped
+		serializer.grant(deserializer.take_credit());
+		assert_eq!(transfer(&mut serializer, &mut deserializer), 4);
+		assert!(deserializer.pull::<String>().is_none());
+		serializer.grant(Credit {
+			bytes: len,
+			frames: 0,
+		});
+		assert_eq!(transfer(&mut serializer, &mut deserializer), len - 8);
+		assert_eq!(
+			serializer.credit(),
+			Credit {
+				bytes: 8,
+				frames: 0
+			}
+		);
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "hello");
+		assert_eq!(
+			deserializer.take_credit(),
+			Credit {
+				bytes: len - 4,
+				frames: 1
+			}
+		);
+		assert_eq!(deserializer.take_credit(), Credit::default());
+		// and a frame of credit allows another push
+		assert!(serializer.push::<String>().is_none());
+		serializer.grant(Credit {
+			bytes: 0,
+			frames: 1,
+		});
+		assert!(serializer.push::<String>().is_some());
+	}
+
+	#[test]
+	fn rate() {
+		use rate::{RateLimitedSerializer, TokenBucket};
+		use std::time::{Duration, Instant};
+		let interval = Duration::from_millis(100);
+		let mut bucket = TokenBucket::new(2, interval);
+		// a full bucket holds its capacity however long it's left, so the refill is timed from here
+		let start = Instant::now();
+		assert_eq!(bucket.tokens(start), 2);
+		assert!(bucket.take(start) && bucket.take(start));
+		assert!(!bucket.take(start));
+		// a token is refilled exactly one interval later, not before
+		assert!(!bucket.take(start + interval.checked_sub(Duration::from_nanos(1)).unwrap()));
+		assert!(bucket.take(start + interval));
+		assert_eq!(bucket.tokens(start + interval), 0);
+		// part of an interval carries over to the next token
+		assert_eq!(bucket.tokens(start + interval * 5 / 2), 1);
+		assert_eq!(bucket.tokens(start + interval * 3), 2);
+		// and the tokens never exceed the capacity
+		assert_eq!(bucket.tokens(start + interval * 1000), 2);
+		let mut empty = TokenBucket::new(0, interval);
+		assert!(!empty.take(start + interval * 1000));
+
+		let mut serializer = RateLimitedSerializer::new(Serializer::new());
+		serializer.limit::<String>(TokenBucket::new(1, Duration::from_mins(1)));
+		// a token is only taken once the push is performed
+		assert!(serializer.push::<String>().is_some());
+		serializer.push().unwrap()(String::from("chatty"));
+		while let Some(pull) = serializer.pull() {
+			let _ = pull();
+		}
+		assert!(serializer.push::<String>().is_none());
+		assert!(serializer.push::<u32>().is_some());
+		serializer.unlimit::<String>();
+		assert!(serializer.push::<String>().is_some());
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn big_endian_framing() {
+		let mut serializer = Serializer::with_framing(Framing::BigEndianU32);
+		let mut deserializer = Deserializer::with_framing(Framing::BigEndianU32);
+		serializer.push().unwrap()(0x0102_u16);
+		let mut bytes = vec![];
+		while let Some(pull) = serializer.pull() {
+			bytes.push(pull());
+		}
+		assert_eq!(bytes, [0, 0, 0, 2, 2, 1]);
+		let _ = deserializer.pull::<u16>();
+		for byte in bytes {
+			deserializer.push().unwrap()(byte);
+		}
+		assert_eq!(deserializer.pull::<u16>().unwrap()(), 0x0102);
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	#[should_panic(expected = "framing isn't supported with the fringe feature")]
+	fn framing_unsupported() {
+		let _ = Serializer::with_framing(Framing::Bincode);
+	}
+
+	#[cfg(feature = "prost")]
+	#[test]
+	fn protobuf_malformed() {
+		use super::protobuf::{ProtobufDeserializer, ProtobufSerializer};
+		use std::panic::{catch_unwind, AssertUnwindSafe};
+		let mut deserializer = ProtobufDeserializer::new();
+		// an empty frame is the default message, and needs no bytes beyond its length
+		deserializer.push().unwrap()(0);
+		assert!(deserializer.push().is_none());
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "");
+		// a frame that's truncated, of the wrong wire type, or not UTF-8 panics as it's pulled, and is discarded by empty()
+		for frame in [&[2, 10, 5][..], &[2, 8, 1], &[3, 10, 1, 0xff]] {
+			for &byte in frame {
+				deserializer.push().unwrap()(byte);
+			}
+			assert!(
+				catch_unwind(AssertUnwindSafe(|| deserializer.pull::<String>().unwrap()()))
+					.is_err()
+			);
+			deserializer.empty().unwrap()();
+			assert!(deserializer.empty().is_none());
+		}
+		// a length prefix longer than a varint can be panics as it's pushed, and empty() then leaves the deserializer ready for the next frame
+		for _ in 0..10 {
+			deserializer.push().unwrap()(0x80);
+		}
+		assert!(catch_unwind(AssertUnwindSafe(|| deserializer.push().unwrap()(1))).is_err());
+		deserializer.empty().unwrap()();
+		let mut serializer = ProtobufSerializer::new();
+		serializer.push().unwrap()(String::from("hi"));
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "hi");
+	}
+
+	#[cfg(feature = "serde_json")]
+	#[test]
+	fn json_malformed() {
+		use json::{JsonDeserializer, JsonSerializer};
+		use std::{
+			collections::BTreeMap,
+			panic::{catch_unwind, AssertUnwindSafe},
+		};
+		let mut deserializer = JsonDeserializer::new();
+		// blank lines are skipped, and a \r before the \n is whitespace to JSON
+		for &byte in b"\n  \n[1,2]\r\n" {
+			deserializer.push().unwrap()(byte);
+		}
+		assert!(deserializer.push().is_none());
+		assert_eq!(deserializer.pull::<Vec<u8>>().unwrap()(), [1, 2]);
+		// a malformed line, or one of the wrong type, panics as it's pulled, and is discarded by empty()
+		for line in [&b"{\"unterminated\n"[..], b"[1,2] trailing\n", b"\"1,2\"\n"] {
+			for &byte in line {
+				deserializer.push().unwrap()(byte);
+			}
+			assert!(catch_unwind(AssertUnwindSafe(
+				|| deserializer.pull::<Vec<u8>>().unwrap()()
+			))
+			.is_err());
+			deserializer.empty().unwrap()();
+			assert!(deserializer.empty().is_none());
+		}
+		// as does a value JSON can't represent as it's pushed, leaving the serializer ready for the next
+		let mut serializer = JsonSerializer::new();
+		let map = BTreeMap::from([((1_u8, 2_u8), 3_u8)]);
+		assert!(catch_unwind(AssertUnwindSafe(|| serializer.push().unwrap()(map))).is_err());
+		serializer.push().unwrap()(vec![3_u8]);
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<Vec<u8>>().unwrap()(), [3]);
+	}
+
+	#[cfg(feature = "csv")]
+	#[test]
+	fn csv_malformed() {
+		use super::csv::{CsvDeserializer, CsvSerializer};
+		use std::{
+			collections::BTreeMap,
+			panic::{catch_unwind, AssertUnwindSafe},
+		};
+		let mut deserializer = CsvDeserializer::new(true);
+		// a quoted newline doesn't end the record, and blank lines are skipped
+		for &byte in b"\nname,value\n\n\"a\nb\",1\n" {
+			deserializer.push().unwrap()(byte);
+		}
+		assert!(deserializer.push().is_none());
+		assert_eq!(
+			deserializer.pull::<(String, u8)>().unwrap()(),
+			(String::from("a\nb"), 1)
+		);
+		// a record missing a field, or with a field of the wrong type, panics as it's pulled, and is discarded by empty()
+		for line in [&b"a\n"[..], b"a,x\n", b"a,256\n"] {
+			for &byte in line {
+				deserializer.push().unwrap()(byte);
+			}
+			assert!(catch_unwind(AssertUnwindSafe(|| deserializer
+				.pull::<(String, u8)>()
+				.unwrap()()))
+			.is_err());
+			deserializer.empty().unwrap()();
+			assert!(deserializer.empty().is_none());
+		}
+		// empty() also forgets an unterminated quote
+		for &byte in b"\"a,1\n" {
+			deserializer.push().unwrap()(byte);
+		}
+		assert!(deserializer.pull::<(String, u8)>().is_none());
+		deserializer.empty().unwrap()();
+		// a value CSV can't represent panics as it's pushed, leaving the serializer ready for the next
+		let mut serializer = CsvSerializer::new(false);
+		assert!(
+			catch_unwind(AssertUnwindSafe(|| serializer.push().unwrap()((
+				BTreeMap::from([(1_u8, 2_u8)]),
+				2_u8
+			))))
+			.is_err()
+		);
+		serializer.push().unwrap()((String::from("b"), 2_u8));
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(
+			deserializer.pull::<(String, u8)>().unwrap()(),
+			(String::from("b"), 2)
+		);
+	}
+
+	#[cfg(feature = "avro")]
+	#[test]
+	fn avro() {
+		use super::avro::{AvroDeserializer, AvroSerializer};
+		use std::collections::BTreeMap;
+		#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		enum Value {
+			Unit,
+			Newtype(i64),
+			Tuple(String, bool),
+			Struct { map: BTreeMap<String, Vec<u8>> },
+		}
+		let values = vec![
+			Value::Newtype(-1),
+			Value::Unit,
+			Value::Tuple(String::from("a"), true),
+			Value::Struct {
+				map: vec![(String::from("b"), vec![1, 2]), (String::from("c"), vec![])]
+					.into_iter()
+					.collect(),
+			},
+		];
+		let schema = r#"["null","long",
+			{"type":"record","name":"Tuple","fields":[{"name":"0","type":"string"},{"name":"1","type":"boolean"}]},
+			{"type":"record","name":"Struct","fields":[{"name":"map","type":{"type":"map","values":{"type":"array","items":"int"}}}]}
+		]"#;
+		let mut serializer = AvroSerializer::new(schema).unwrap();
+		let mut deserializer = AvroDeserializer::new();
+		let mut bytes = vec![];
+		for value in values {
+			serializer.push().unwrap()(value);
+			bytes.clear();
+			while let Some(pull) = serializer.pull() {
+				let byte = pull();
+				bytes.push(byte);
+				deserializer.push().unwrap()(byte);
+			}
+			let _ = deserializer.pull::<Value>().unwrap()();
+		}
+		// A block holding two values, as written by other implementations
+		let sync = bytes.split_off(bytes.len() - 16);
+		for &byte in [4, 8, 2, 1, 2, 3].iter().chain(&sync) {
+			deserializer.push().unwrap()(byte);
+		}
+		assert_eq!(deserializer.pull::<Value>().unwrap()(), Value::Newtype(-1));
+		assert!(deserializer.push().is_none());
+		assert_eq!(deserializer.pull::<Value>().unwrap()(), Value::Newtype(-2));
+		assert!(deserializer.pull::<Value>().is_none());
+	}
+
+	#[cfg(feature = "avro")]
+	#[test]
+	fn avro_schema() {
+		use super::avro::{AvroDeserializer, AvroSerializer};
+		use std::panic::{catch_unwind, AssertUnwindSafe};
+		#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		struct Reading {
+			value: f32,
+			count: Option<i32>,
+		}
+		// encoded against the schema: the f32 widened to a double, and the null second in its union
+		let schema = r#"{"type":"record","name":"Reading","doc":"DOC","fields":[
+			{"name":"value","type":"double"},
+			{"name":"count","type":["int","null"]}
+		]}"#;
+		// a long schema, whose header is read in linear time
+		let schema = schema.replace("DOC", &"x".repeat(1 << 20));
+		let mut serializer = AvroSerializer::new(&schema).unwrap();
+		let mut deserializer = AvroDeserializer::new();
+		let mut bytes = Vec::new();
+		for (value, count) in [(1.5, None), (-2.0, Some(3))] {
+			serializer.push().unwrap()(Reading { value, count });
+			bytes = std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect();
+			for &byte in &bytes {
+				deserializer.push().unwrap()(byte);
+			}
+			assert_eq!(
+				deserializer.pull::<(f64, Option<i64>)>().unwrap()(),
+				(f64::from(value), count.map(i64::from))
+			);
+		}
+		assert_eq!(deserializer.schema(), Some(&*schema));
+		// the last block: its count and length, the double, the union index of the int, and the int
+		assert_eq!(bytes[..12], [2, 20, 0, 0, 0, 0, 0, 0, 0, 0xc0, 0, 6]);
+
+		// values that don't fit the schema fail to encode
+		for push in [
+			Box::new(|serializer: &mut AvroSerializer| serializer.push().unwrap()(String::from("a")))
+				as Box<dyn Fn(&mut AvroSerializer)>,
+			Box::new(|serializer| serializer.push().unwrap()((1.5_f64, Some(3_i32)))),
+			Box::new(|serializer| serializer.push().unwrap()((1.5_f32, Some(i64::MAX)))),
+		] {
+			let mut serializer = AvroSerializer::new(r#"{"type":"record","name":"R","fields":[
+				{"name":"value","type":"float"},{"name":"count","type":["null","int"]}
+			]}"#)
+			.unwrap();
+			assert!(catch_unwind(AssertUnwindSafe(|| push(&mut serializer))).is_err());
+		}
+		// as do schemas that aren't valid
+		for schema in ["{", r#""Missing""#, r#"{"type":"array"}"#, r#"[{"type":"fixed","name":"F"}]"#] {
+			assert!(AvroSerializer::new(schema).is_err());
+		}
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn grpc_framing() {
+		let mut serializer = Serializer::with_framing(Framing::Grpc);
+		let mut deserializer = Deserializer::with_framing(Framing::Grpc);
+		serializer.push().unwrap()(0x0102_u16);
+		let mut bytes = vec![];
+		while let Some(pull) = serializer.pull() {
+			bytes.push(pull());
+		}
+		assert_eq!(bytes, [0, 0, 0, 0, 2, 2, 1]);
+		let _ = deserializer.pull::<u16>();
+		for byte in bytes {
+			deserializer.push().unwrap()(byte);
+		}
+		assert_eq!(deserializer.pull::<u16>().unwrap()(), 0x0102);
+
+		// an empty message, as google.protobuf.Empty is encoded, rather than a heartbeat
+		serializer.push().unwrap()(());
+		let bytes = std::iter::from_fn(|| serializer.pull().map(|pull| pull())).collect::<Vec<_>>();
+		assert_eq!(bytes, [0, 0, 0, 0, 0]);
+		let _ = deserializer.pull::<()>();
+		assert_eq!(deserializer.push_bytes(&bytes), 5);
+		assert!(deserializer.push().is_none());
+		deserializer.pull::<()>().unwrap()();
+		assert_eq!(deserializer.heartbeats(), 0);
+		let (remaining, partial) = {
+			let mut deserializer = Deserializer::with_framing(Framing::Grpc);
+			let _ = deserializer.pull::<()>();
+			let _ = deserializer.push_bytes(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+			deserializer.into_remaining()
+		};
+		assert_eq!((remaining, partial), (vec![0, 0, 0, 0, 0], false));
+	}
+
+	#[test]
+	fn tagged() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let values: Vec<Either<u8, Either<String, ()>>> = vec![
+			Either::Left(1),
+			Either::Right(Either::Left(String::from("two"))),
+			Either::Right(Either::Right(())),
+		];
+		for value in values {
+			serializer.push_tagged().unwrap()(value.clone());
+			let _ = deserializer.pull_either::<u8, Either<String, ()>>();
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.pull_either().unwrap()(), value);
+		}
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	fn push_with_unsupported() {
+		let mut serializer = Serializer::new();
+		let err = serializer
+			.push_with(|_| unreachable!())
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+		assert!(serializer.pull().is_none());
+		serializer.push().unwrap()(0_u8);
+		assert!(serializer.push_with(|_| unreachable!()).is_none());
+		serializer.empty().unwrap()();
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn push_with() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let err = serializer
+			.push_with(|_| Err(io::Error::other("failed")))
+			.unwrap();
+		assert!(err.is_err() && serializer.pull().is_none());
+		serializer
+			.push_with(|w| bincode::serialize_into(w, &0x0102_u16).map_err(io::Error::other))
+			.unwrap()
+			.unwrap();
+		assert!(serializer.push_with(|_| Ok(())).is_none());
+		let _ = deserializer.pull::<u16>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.pull::<u16>().unwrap()(), 0x0102);
+	}
+
+	#[test]
+	fn pull_buf() {
+		let mut serializer = Serializer::new();
+		serializer.set_alignment(4096);
+		serializer.push().unwrap()(String::from("abc"));
+		serializer.push_or_queue(7_u8).unwrap();
+		serializer.push_or_queue(String::from("def")).unwrap();
+		let mut frames = Vec::new();
+		while let Some(buf) = serializer.pull_buf() {
+			// the fringe backend's slices are cut from its coroutine's blocks, so aren't aligned
+			#[cfg(not(feature = "fringe"))]
+			assert_eq!(buf.as_ptr() as usize % 4096, 0);
+			let frame = buf.to_vec();
+			// the first byte, then the rest
+			serializer.consume(1);
+			if frame.len() > 1 {
+				assert_eq!(serializer.pull_buf().unwrap(), &frame[1..]);
+				serializer.consume(frame.len() - 1);
+			}
+			frames.push(frame);
+		}
+		assert!(serializer.pull().is_none());
+		assert_eq!(deserialize_from_slice::<String>(&frames[0]).unwrap(), "abc");
+		assert_eq!(deserialize_from_slice::<u8>(&frames[1]), Some(7));
+		assert_eq!(deserialize_from_slice::<String>(&frames[2]).unwrap(), "def");
+	}
+
+	#[test]
+	fn peek() {
+		#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+		struct Message {
+			route: u8,
+			body: String,
+		}
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		for route in 0..3 {
+			serializer.push().unwrap()(Message {
+				route,
+				body: route.to_string(),
+			});
+			assert!(deserializer.peek::<Message>().is_none());
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			assert_eq!(deserializer.peek::<Message>().unwrap().route, route);
+			if route == 1 {
+				// routed elsewhere, so discarded
+				deserializer.empty().unwrap()();
+				continue;
+			}
+			assert_eq!(deserializer.peek::<Message>().unwrap().body, route.to_string());
+			assert_eq!(deserializer.pull::<Message>().unwrap()().route, route);
+		}
+	}
+
+	#[test]
+	fn wire_format() {
+		#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+		struct Unit;
+		#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+		enum Variant {
+			Unit,
+			Newtype(u8),
+			Tuple(u8, u8),
+			Struct { x: u8 },
+		}
+		type Golden = (
+			(bool, i16, u32, i64, u128, f32, f64, char),
+			(String, Option<u8>, Option<u8>, (), Unit, (u8, u16)),
+			(Vec<u16>, BTreeMap<u8, bool>, Vec<Variant>),
+		);
+		let golden: Golden = (
+			(true, -2, 0x0102_0304, -1, 1, 1.0, -0.5, 'é'),
+			(String::from("hi"), Some(7), None, (), Unit, (1, 2)),
+			(
+				vec![3],
+				vec![(5, false)].into_iter().collect(),
+				vec![
+					Variant::Unit,
+					Variant::Newtype(9),
+					Variant::Tuple(1, 2),
+					Variant::Struct { x: 3 },
+				],
+			),
+		);
+		#[rustfmt::skip]
+		let bytes: &[u8] = &[
+			// integers and floats little-endian at full width, chars as UTF-8
+			1, 0xfe, 0xff, 4, 3, 2, 1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+			1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+			0, 0, 0x80, 0x3f, 0, 0, 0, 0, 0, 0, 0xe0, 0xbf, 0xc3, 0xa9,
+			// u64 lengths, option tags as a byte, units as nothing
+			2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i', 1, 7, 0, 1, 2, 0,
+			// u32 variant indices
+			1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 1, 0, 0, 0, 0, 0, 0, 0, 5, 0,
+			4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 9, 2, 0, 0, 0, 1, 2, 3, 0, 0, 0, 3,
+		];
+		assert_eq!(
+			wire::serialize(&golden).unwrap(),
+			bytes,
+			"the wire format has changed, so WIRE_VERSION must be bumped"
+		);
+		assert_eq!(wire::deserialize::<Golden>(bytes).unwrap(), golden);
+		assert_eq!(
+			deserialize_from_slice::<Golden>(&serialize_to_vec(golden.clone())),
+			Some(golden)
+		);
+	}
+
+	#[test]
+	fn pull_in_place() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		let mut received = Vec::<u64>::with_capacity(1024);
+		let buffer = received.as_ptr();
+		for len in [1000, 10, 0] {
+			serializer.push().unwrap()((0..len).collect::<Vec<u64>>());
+			assert!(deserializer.pull_in_place::<Vec<u64>>().is_none());
+			while let Some(pull) = serializer.pull() {
+				deserializer.push().unwrap()(pull());
+			}
+			deserializer.pull_in_place().unwrap()(&mut received);
+			assert_eq!(received, (0..len).collect::<Vec<_>>());
+			// the allocation is reused rather than replaced
+			if cfg!(not(feature = "fringe")) {
+				assert_eq!(received.as_ptr(), buffer);
+			}
+		}
+		// a peeked value is moved in instead
+		serializer.push().unwrap()(vec![1_u64]);
+		let _ = deserializer.peek::<Vec<u64>>();
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		assert_eq!(deserializer.peek::<Vec<u64>>().unwrap(), &[1]);
+		deserializer.pull_in_place().unwrap()(&mut received);
+		assert_eq!(received, [1]);
+	}
+
+	#[test]
+	fn pull_chunk() {
+		let (a, b) = ((0..2000_u64).collect::<Vec<_>>(), String::from("abc"));
+		let (a_bytes, b_bytes) = (serialize_to_vec(a.clone()), serialize_to_vec(b.clone()));
+		let mut serializer = Serializer::new();
+		assert!(serializer.pull_chunk().is_none());
+		serializer.push().unwrap()(a);
+		serializer.push_or_queue(b).unwrap();
+		let mut sent = vec![serializer.pull().unwrap()()];
+		let mut ends = Vec::new();
+		while let Some(pull_chunk) = serializer.pull_chunk() {
+			let chunk = pull_chunk();
+			assert!(!chunk.is_empty());
+			sent.extend_from_slice(chunk);
+			ends.push(sent.len());
+		}
+		// chunks end at frame boundaries
+		assert!(ends.contains(&a_bytes.len()));
+		assert_eq!(sent, [a_bytes, b_bytes].concat());
+	}
+
+	#[test]
+	fn pull_all() {
+		let bytes = (0..5_u32).flat_map(serialize_to_vec).collect::<Vec<_>>();
+		let split = bytes.len() / 2 + 1;
+		let mut deserializer = Deserializer::new();
+		assert_eq!(deserializer.pull_all::<u32>(&bytes[..split]), (vec![0, 1], split));
+		// the frame split across the chunks is completed by the second
+		assert_eq!(
+			deserializer.pull_all::<u32>(&bytes[split..]),
+			(vec![2, 3, 4], bytes.len() - split)
+		);
+		assert!(deserializer.empty().is_none());
+	}
+
+	#[test]
+	fn fill_from() {
+		let a_len = serialize_to_vec(vec![7_u64; 2000]).len();
+		let bytes = [
+			serialize_to_vec(vec![7_u64; 2000]),
+			serialize_to_vec(String::from("de")),
+		]
+		.concat();
+		let mut reader = io::BufReader::new(&bytes[..a_len + 3]);
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<Vec<u64>>();
+		// the read stops at the end of the first frame
+		assert_eq!(deserializer.fill_from(&mut reader).unwrap(), (a_len, true));
+		assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), vec![7_u64; 2000]);
+		let _ = deserializer.pull::<String>();
+		assert_eq!(deserializer.fill_from(&mut reader).unwrap(), (3, false));
+		let mut reader = &bytes[a_len + 3..];
+		assert_eq!(
+			deserializer.fill_from(&mut reader).unwrap(),
+			(bytes.len() - a_len - 3, true)
+		);
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "de");
+	}
+
+	#[test]
+	fn iter() {
+		let a = serialize_to_vec(vec![7_u64; 1000]);
+		let b = serialize_to_vec(String::from("abc"));
+		let mut serializer = Serializer::new();
+		assert_eq!(serializer.iter().next(), None);
+		serializer.push().unwrap()(vec![7_u64; 1000]);
+		serializer.push_or_queue(String::from("abc")).unwrap();
+		serializer.push_or_queue(()).unwrap();
+		// each frame's iterator ends with it
+		assert_eq!(serializer.iter().take(3).collect::<Vec<_>>(), a[..3]);
+		assert_eq!(serializer.iter().collect::<Vec<_>>(), a[3..]);
+		let mut sent = Vec::new();
+		for byte in serializer.iter() {
+			sent.push(byte);
+		}
+		assert_eq!(sent, b);
+		assert_eq!(
+			serializer.into_iter().collect::<Vec<_>>(),
+			serialize_to_vec(())
+		);
+	}
+
+	#[test]
+	fn iter_from() {
+		let bytes = (0..5_u32).flat_map(serialize_to_vec).collect::<Vec<_>>();
+		let split = bytes.len() / 2 + 1;
+		let mut deserializer = Deserializer::new();
+		assert_eq!(
+			deserializer
+				.iter_from::<u32, _>(bytes[..split].iter().copied())
+				.collect::<Vec<_>>(),
+			[0, 1]
+		);
+		// the frame split across the iterators is completed by the second
+		let mut values = deserializer.iter_from::<u32, _>(bytes[split..].iter().copied());
+		assert_eq!(values.next(), Some(2));
+		// bytes beyond the end of the frame yielded aren't pushed
+		let rest = values.into_inner().collect::<Vec<_>>();
+		assert_eq!(rest, bytes[bytes.len() / 5 * 3..]);
+		assert_eq!(
+			deserializer.iter_from::<u32, _>(rest).collect::<Vec<_>>(),
+			[3, 4]
+		);
+		assert!(deserializer.empty().is_none());
+	}
+
+	#[test]
+	fn pipe() {
+		let a_len = serialize_to_vec(vec![7_u64; 1000]).len();
+		let b_len = serialize_to_vec(String::from("abc")).len();
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		assert_eq!(super::pipe(&mut serializer, &mut deserializer), 0);
+		serializer.push().unwrap()(vec![7_u64; 1000]);
+		serializer.push_or_queue(String::from("abc")).unwrap();
+		// nothing moves until the Deserializer knows the type
+		assert_eq!(super::pipe(&mut serializer, &mut deserializer), 0);
+		let _ = deserializer.pull::<Vec<u64>>();
+		assert_eq!(super::pipe(&mut serializer, &mut deserializer), a_len);
+		assert_eq!(deserializer.pull::<Vec<u64>>().unwrap()(), vec![7_u64; 1000]);
+		let _ = deserializer.pull::<String>();
+		assert_eq!(super::pipe(&mut serializer, &mut deserializer), b_len);
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "abc");
+		assert!(serializer.pull().is_none());
+	}
+
+	#[test]
+	fn serializer_reader() {
+		use std::io::Read;
+		use stream::SerializerReader;
+		let bytes = [serialize_to_vec(vec![1_u64; 100]), serialize_to_vec(())].concat();
+		let mut reader = SerializerReader::new(Serializer::new());
+		reader.get_mut().push().unwrap()(vec![1_u64; 100]);
+		reader.get_mut().push_or_queue(()).unwrap();
+		let mut sent = Vec::new();
+		assert_eq!(io::copy(&mut reader, &mut sent).unwrap(), bytes.len() as u64);
+		assert_eq!(sent, bytes);
+		assert_eq!(reader.read(&mut [0; 8]).unwrap(), 0);
+		reader.set_nonblocking(true);
+		assert_eq!(
+			reader.read(&mut [0; 8]).unwrap_err().kind(),
+			io::ErrorKind::WouldBlock
+		);
+		reader.get_mut().push().unwrap()(1_u8);
+		assert_eq!(reader.read(&mut [0; 64]).unwrap(), serialize_to_vec(1_u8).len());
+		assert!(reader.into_inner().empty().is_none());
+	}
+
+	#[test]
+	fn deserializer_writer() {
+		use stream::DeserializerWriter;
+		let a_len = serialize_to_vec(String::from("abc")).len();
+		let bytes = [
+			serialize_to_vec(String::from("abc")),
+			serialize_to_vec(String::from("de")),
+		]
+		.concat();
+		let mut writer = DeserializerWriter::new(Deserializer::new());
+		let _ = writer.get_mut().pull::<String>();
+		// the write stops at the end of the first frame
+		assert_eq!(writer.write(&bytes).unwrap(), a_len);
+		assert_eq!(writer.write(&bytes[a_len..]).unwrap(), 0);
+		writer.set_nonblocking(true);
+		assert_eq!(
+			writer.write(&bytes[a_len..]).unwrap_err().kind(),
+			io::ErrorKind::WouldBlock
+		);
+		assert_eq!(writer.get_mut().pull::<String>().unwrap()(), "abc");
+		let _ = writer.get_mut().pull::<String>();
+		writer.write_all(&bytes[a_len..]).unwrap();
+		writer.flush().unwrap();
+		assert_eq!(writer.get_mut().pull::<String>().unwrap()(), "de");
+		assert!(writer.into_inner().empty().is_none());
+	}
+
+	#[test]
+	fn into_reader_writer() {
+		use std::{io::Read, thread};
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(vec![7_u64; 1000]);
+		let mut reader = serializer.into_reader();
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<Vec<u64>>();
+		let mut writer = deserializer.into_writer();
+		// both move to another thread without the pipes they were made from
+		let (reader, mut writer) = thread::spawn(move || {
+			let mut bytes = Vec::new();
+			let _ = reader.read_to_end(&mut bytes).unwrap();
+			writer.write_all(&bytes).unwrap();
+			(reader, writer)
+		})
+		.join()
+		.unwrap();
+		assert_eq!(
+			writer.get_mut().pull::<Vec<u64>>().unwrap()(),
+			vec![7_u64; 1000]
+		);
+		assert!(reader.into_inner().empty().is_none());
+		assert!(writer.into_inner().empty().is_none());
+	}
+
+	#[test]
+	fn push_batch() {
+		let mut serializer = Serializer::new();
+		serializer.set_queue_limit(3);
+		assert_eq!(serializer.push_batch(0..6_u32), Err(vec![4, 5]));
+		let mut deserializer = Deserializer::new();
+		for i in 0..4_u32 {
+			let _ = deserializer.pull::<u32>();
+			while let Some(push) = deserializer.push() {
+				push(serializer.pull().unwrap()());
+			}
+			assert_eq!(deserializer.pull::<u32>().unwrap()(), i);
+		}
+		assert!(serializer.pull().is_none());
+		assert_eq!(serializer.push_batch(vec![4, 5]), Ok(()));
+		let _ = serializer.into_vec();
+	}
+
+	#[test]
+	fn pull_frame() {
+		let mut serializer = Serializer::new();
+		assert!(serializer.pull_frame().is_none());
+		serializer.push().unwrap()(String::from("abc"));
+		serializer.push_or_queue(vec![1_u16, 2]).unwrap();
+		assert_eq!(
+			serializer.pull_frame().unwrap()(),
+			serialize_to_vec(String::from("abc"))
+		);
+		// a partially pulled frame yields the rest of it
+		let first = serializer.pull().unwrap()();
+		let mut frame = serializer.pull_frame().unwrap()();
+		frame.insert(0, first);
+		assert_eq!(frame, serialize_to_vec(vec![1_u16, 2]));
+		assert!(serializer.pull_frame().is_none());
+	}
+
+	#[test]
+	fn drain_to() {
+		/// A writer accepting at most 100 bytes at a time.
+		struct Trickle(Vec<u8>);
+		impl Write for Trickle {
+			fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+				let len = buf.len().min(100);
+				self.0.extend_from_slice(&buf[..len]);
+				Ok(len)
+			}
+			fn flush(&mut self) -> io::Result<()> {
+				Ok(())
+			}
+		}
+		let a = serialize_to_vec(vec![7_u64; 1000]);
+		let b = serialize_to_vec(String::from("abc"));
+		let mut serializer = Serializer::new();
+		assert_eq!(serializer.drain_to(Trickle(Vec::new())).unwrap(), 0);
+		serializer.push().unwrap()(vec![7_u64; 1000]);
+		serializer.push_or_queue(String::from("abc")).unwrap();
+		let mut sent = Trickle(Vec::new());
+		// each call stops at the end of the frame
+		assert_eq!(serializer.drain_to(&mut sent).unwrap(), a.len());
+		assert_eq!(sent.0, a);
+		assert_eq!(serializer.drain_to(&mut sent).unwrap(), b.len());
+		assert_eq!(sent.0, [a, b].concat());
+		assert!(serializer.pull().is_none());
+	}
+
+	#[test]
+	fn pull_into() {
+		let bytes = [
+			serialize_to_vec(String::from("abc")),
+			serialize_to_vec(String::from("de")),
+		]
+		.concat();
+		let mut serializer = Serializer::new();
+		let mut buf = [0; 7];
+		assert_eq!(serializer.pull_into(&mut buf), 0);
+		serializer.push().unwrap()(String::from("abc"));
+		serializer.push_or_queue(String::from("de")).unwrap();
+		let mut sent = Vec::new();
+		loop {
+			let len = serializer.pull_into(&mut buf);
+			sent.extend_from_slice(&buf[..len]);
+			if len < buf.len() {
+				break;
+			}
+		}
+		// the frames are packed back to back into each buffer
+		assert_eq!(sent, bytes);
+		assert!(serializer.pull().is_none());
+	}
+
+	#[test]
+	fn pull_vectored() {
+		let bytes = serialize_to_vec(String::from("abcdefghij"));
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(String::from("abcdefghij"));
+		let (mut a, mut b, mut c) = ([0; 4], [0; 8], vec![0; 64]);
+		let len = serializer.pull_vectored(&mut [
+			io::IoSliceMut::new(&mut a),
+			io::IoSliceMut::new(&mut b),
+			io::IoSliceMut::new(&mut c),
+		]);
+		assert_eq!(len, bytes.len());
+		assert_eq!([&a[..], &b, &c[..len - 12]].concat(), bytes);
+		assert!(serializer.pull().is_none());
+	}
+
+	#[test]
+	fn push_bytes() {
+		let a_len = serialize_to_vec(String::from("abc")).len();
+		let bytes = [
+			serialize_to_vec(String::from("abc")),
+			serialize_to_vec(String::from("de")),
+		]
+		.concat();
+		let mut deserializer = Deserializer::new();
+		// nothing is accepted until the type is supplied
+		assert_eq!(deserializer.push_bytes(&bytes), 0);
+		assert!(deserializer.pull::<String>().is_none());
+		assert_eq!(deserializer.push_bytes(&bytes[..5]), 5);
+		// only the rest of the first frame is accepted
+		assert_eq!(deserializer.push_bytes(&bytes[5..]), a_len - 5);
+		assert_eq!(deserializer.push_bytes(&bytes[a_len..]), 0);
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "abc");
+		assert!(deserializer.pull::<String>().is_none());
+		assert_eq!(deserializer.push_bytes(&bytes[a_len..]), bytes.len() - a_len);
+		assert_eq!(deserializer.pull::<String>().unwrap()(), "de");
+	}
+
+	#[cfg(feature = "bytes")]
+	#[test]
+	fn bytes_buf() {
+		use bytes::{Buf, BufMut};
+		let mut serializer = Serializer::new();
+		assert!(!serializer.has_remaining());
+		serializer.push().unwrap()(String::from("abc"));
+		serializer.push_or_queue(String::from("de")).unwrap();
+		let mut sent = Vec::new();
+		while serializer.has_remaining() {
+			let len = serializer.remaining().min(5);
+			sent.put(serializer.copy_to_bytes(len));
+		}
+		assert_eq!(
+			sent,
+			[
+				serialize_to_vec(String::from("abc")),
+				serialize_to_vec(String::from("de"))
+			]
+			.concat()
+		);
+	}
+
+	#[cfg(feature = "bytes")]
+	#[test]
+	fn push_chunk() {
+		let values = ["abc", "", "defghijk", "l"];
+		let bytes = values
+			.iter()
+			.flat_map(|&value| serialize_to_vec(String::from(value)))
+			.collect::<Vec<_>>();
+		let frame_len = serialize_to_vec(String::from("abc")).len();
+		// the first two frames lie within the first chunk, the third straddles the second and third
+		let mut chunks = vec![
+			bytes::Bytes::copy_from_slice(&bytes[..frame_len * 2 + 4]),
+			bytes::Bytes::copy_from_slice(&bytes[frame_len * 2 + 4..frame_len * 3]),
+			bytes::Bytes::copy_from_slice(&bytes[frame_len * 3..]),
+		]
+		.into_iter();
+		let mut deserializer = Deserializer::new();
+		let mut chunk = bytes::Bytes::new();
+		for value in values {
+			while deserializer.pull::<String>().is_none() {
+				if chunk.is_empty() {
+					chunk = chunks.next().unwrap();
+				}
+				chunk = deserializer.push_chunk(chunk);
+			}
+			assert_eq!(deserializer.pull::<String>().unwrap()(), value);
+		}
+		assert!(chunk.is_empty() && chunks.next().is_none());
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn mark_rewind() {
+		let mut serializer = Serializer::new();
+		assert!(!serializer.rewind());
+		serializer.push().unwrap()(String::from("abc"));
+		serializer.push_or_queue(String::from("def")).unwrap();
+		let mut sent = Vec::new();
+		for _ in 0..9 {
+			sent.push(serializer.pull().unwrap()());
+		}
+		serializer.mark();
+		// a write of the next two bytes fails, so they're pulled again
+		let _ = (serializer.pull().unwrap()(), serializer.pull().unwrap()());
+		assert!(serializer.rewind());
+		while sent.len() < 8 + 8 + 3 {
+			sent.push(serializer.pull().unwrap()());
+		}
+		assert_eq!(deserialize_from_slice::<String>(&sent).unwrap(), "abc");
+		// the mark is forgotten once the frame ends
+		assert!(!serializer.rewind());
+		let _ = serializer.into_vec();
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	#[should_panic(expected = "mark isn't supported with the fringe feature")]
+	fn mark_unsupported() {
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(String::from("abc"));
+		let _ = serializer.pull().unwrap()();
+		assert!(!serializer.rewind());
+		serializer.mark();
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	fn pull_with_unsupported() {
+		let mut deserializer = Deserializer::new();
+		let err = deserializer
+			.pull_with(|_| -> io::Result<()> { unreachable!() })
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+		assert!(deserializer.push().is_none());
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn pull_with() {
+		let mut serializer = Serializer::new();
+		let mut deserializer = Deserializer::new();
+		assert!(deserializer.pull_with(|_| Ok(())).is_none());
+		serializer.push().unwrap()(String::from("abc"));
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		let mut first = [0; 2];
+		deserializer
+			.pull_with(|r| r.read_exact(&mut first))
+			.unwrap()
+			.unwrap();
+		assert_eq!(first, [3, 0]); // partially read; the rest is discarded
+		serializer.push().unwrap()(String::from("def"));
+		assert!(deserializer.pull_with(|_| Ok(())).is_none());
+		while let Some(pull) = serializer.pull() {
+			deserializer.push().unwrap()(pull());
+		}
+		let x = deserializer
+			.pull_with(|r| bincode::deserialize_from::<_, String>(r).map_err(io::Error::other))
+			.unwrap()
+			.unwrap();
+		assert_eq!(x, "def");
+	}
+
+	#[test]
+	fn into_vec() {
+		let mut serializer = Serializer::new();
+		assert_eq!(Serializer::new().into_vec(), Vec::<u8>::new());
+		serializer.push().unwrap()(String::from("abc"));
+		let mut bytes = vec![serializer.pull().unwrap()()];
+		bytes.extend(serializer.into_vec());
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(String::from("abc"));
+		let mut expected = vec![];
+		while let Some(pull) = serializer.pull() {
+			expected.push(pull());
+		}
+		assert_eq!(bytes, expected);
+	}
+
+	#[cfg(feature = "fringe")]
+	#[test]
+	#[should_panic(expected = "isn't supported with the fringe feature")]
+	fn into_remaining_unsupported() {
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<u64>();
+		assert_eq!(deserializer.into_remaining(), (vec![], false));
+		let mut deserializer = Deserializer::new();
+		let _ = deserializer.pull::<u64>();
+		deserializer.push().unwrap()(1);
+		let _ = deserializer.into_remaining();
+	}
+
+	#[cfg(not(feature = "fringe"))]
+	#[test]
+	fn into_remaining() {
+		let mut serializer = Serializer::new();
+		serializer.push().unwrap()(String::from("abc"));
+		let mut bytes = vec![];
+		while let Some(pull) = serializer.pull() {
+			bytes.push(pull());
+		}
+		for i in 0..=bytes.len() {
+			let mut deserializer = Deserializer::new();
+			let _ = deserializer.pull::<String>();
+			for &byte in &bytes[..i] {
+				deserializer.push().unwrap()(byte);
+			}
+			let (remaining, partial) = deserializer.into_remaining();
+			assert_eq!(remaining, &bytes[..i]);
+			assert_eq!(partial, i != 0 && i != bytes.len());
+		}
+	}
+
+	#[cfg(feature = "proptest")]
+	#[test]
+	fn chunking() {
+		use ::proptest::{
+			strategy::{Just, Strategy}, test_runner::TestRunner
+		};
+		let strategy = (0..64_usize, 1..8_usize).prop_flat_map(|(len, max_chunk)| {
+			(
+				proptest::chunking(len, max_chunk),
+				Just(len),
+				Just(max_chunk),
+			)
+		});
+		TestRunner::default()
+			.run(&strategy, |(chunks, len, max_chunk)| {
+				assert!(chunks.iter().all(|&chunk| chunk != 0 && chunk <= max_chunk));
+				assert_eq!(chunks.iter().sum::<usize>(), len);
+				Ok(())
+			})
+			.unwrap();
+	}
+
+	#[cfg(feature = "mock")]
+	#[test]
+	fn mock_transport() {
+		use super::mock::MockTransport;
+		use std::time::Duration;
+		let mut transport = MockTransport::new(1)
+			.latency(Duration::from_millis(10))
+			.jitter(Duration::from_millis(10))
+			.bandwidth(10_000)
+			.disconnect_probability(0.001);
+		let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+		let (mut sent, mut received, mut disconnects) = (0, 0, 0);
+		let mut now = Duration::from_millis(0);
+		while received < 20 {
+			now += Duration::from_millis(1);
+			if let Some(push) = serializer.push() {
+				push(vec![sent; 10]);
+				sent += 1;
+			}
+			let _ = transport.fill_from(now, &mut serializer);
+			let _ = transport.drain_into(now, &mut deserializer);
+			if let Some(pull) = deserializer.pull::<Vec<usize>>() {
+				assert_eq!(pull(), vec![received; 10]);
+				received += 1;
+			}
+			if !transport.is_connected() {
+				disconnects += 1;
+				sent = received;
+				let _ = serializer.empty().map(|empty| empty());
+				let _ = deserializer.empty().map(|empty| empty());
+				transport.reconnect();
+			}
+		}
+		assert_ne!(disconnects, 0);
+		let _ = serializer.empty().map(|empty| empty());
+		let _ = deserializer.empty().map(|empty| empty());
+	}
 }
@@ -0,0 +1,186 @@
+//! Simulated transports for testing.
+//!
+//! [`MockTransport`] sits between a [`Serializer`] and a [`Deserializer`], delivering bytes with configurable latency, jitter and bandwidth, and optionally disconnecting at random. Time is simulated – it's supplied by the caller – so tests of timeout and recovery behaviour are fast and deterministic for a given seed.
+//!
+//! Requires the `mock` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{mock::MockTransport, Deserializer, Serializer};
+//! use std::time::Duration;
+//!
+//! let mut transport = MockTransport::new(0)
+//!     .latency(Duration::from_millis(50))
+//!     .bandwidth(1000);
+//! let mut serializer = Serializer::new();
+//! let mut deserializer = Deserializer::new();
+//! serializer.push().unwrap()(String::from("hello"));
+//! let _ = deserializer.pull::<String>();
+//!
+//! let mut now = Duration::from_millis(0);
+//! while deserializer.pull::<String>().is_none() {
+//!     now += Duration::from_millis(1);
+//!     let _ = transport.fill_from(now, &mut serializer);
+//!     let _ = transport.drain_into(now, &mut deserializer);
+//! }
+//! assert_eq!(deserializer.pull::<String>().unwrap()(), "hello");
+//! assert!(now >= Duration::from_millis(50 + 13)); // latency + 13 bytes at 1000 bytes/s
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{collections::VecDeque, convert::TryFrom, time::Duration};
+
+/// A simulated transport, delivering bytes from a [`Serializer`] to a [`Deserializer`].
+///
+/// Bytes are delivered in order, after a fixed latency plus a uniformly random jitter, at no more than the configured bandwidth. With a disconnect probability set, each byte sent may cause a disconnect, upon which bytes in flight are lost and nothing more is sent or delivered until [`reconnect`](MockTransport::reconnect()) is called.
+#[derive(Clone, Debug)]
+pub struct MockTransport {
+	latency: Duration,
+	jitter: Duration,
+	bandwidth: Option<u32>,
+	disconnect: f64,
+	rng: u64,
+	in_flight: VecDeque<(Duration, u8)>,
+	last_fill: Duration,
+	next_send: Duration,
+	connected: bool,
+}
+impl MockTransport {
+	/// Construct a new `MockTransport`, with no latency, jitter, bandwidth limit or disconnects. `seed` seeds the random number generator used for jitter and disconnects.
+	#[must_use]
+	pub fn new(seed: u64) -> Self {
+		Self {
+			latency: Duration::from_secs(0),
+			jitter: Duration::from_secs(0),
+			bandwidth: None,
+			disconnect: 0.0,
+			rng: seed ^ 0x9e37_79b9_7f4a_7c15,
+			in_flight: VecDeque::new(),
+			last_fill: Duration::from_secs(0),
+			next_send: Duration::from_secs(0),
+			connected: true,
+		}
+	}
+
+	/// Delay each byte by a fixed `latency`.
+	#[must_use]
+	pub fn latency(mut self, latency: Duration) -> Self {
+		self.latency = latency;
+		self
+	}
+
+	/// Delay each byte by an additional uniformly random duration of up to `jitter`. Bytes are still delivered in order.
+	#[must_use]
+	pub fn jitter(mut self, jitter: Duration) -> Self {
+		self.jitter = jitter;
+		self
+	}
+
+	/// Send no more than `bytes_per_sec` bytes per second.
+	///
+	/// # Panics
+	///
+	/// Will panic if `bytes_per_sec` is zero.
+	#[must_use]
+	#[track_caller]
+	pub fn bandwidth(mut self, bytes_per_sec: u32) -> Self {
+		assert_ne!(bytes_per_sec, 0);
+		self.bandwidth = Some(bytes_per_sec);
+		self
+	}
+
+	/// Disconnect with probability `probability` upon each byte sent.
+	///
+	/// # Panics
+	///
+	/// Will panic if `probability` isn't in `0.0..=1.0`.
+	#[must_use]
+	#[track_caller]
+	pub fn disconnect_probability(mut self, probability: f64) -> Self {
+		assert!((0.0..=1.0).contains(&probability));
+		self.disconnect = probability;
+		self
+	}
+
+	/// Whether the transport is connected.
+	#[must_use]
+	pub fn is_connected(&self) -> bool {
+		self.connected
+	}
+
+	/// The number of bytes sent but not yet delivered.
+	#[must_use]
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/// Disconnect, losing any bytes in flight.
+	pub fn disconnect(&mut self) {
+		self.connected = false;
+		self.in_flight.clear();
+	}
+
+	/// Reconnect after a disconnect.
+	pub fn reconnect(&mut self) {
+		self.connected = true;
+	}
+
+	/// Send as many bytes from `serializer` as are available and the bandwidth allows at time `now`, returning the number sent.
+	pub fn fill_from(&mut self, now: Duration, serializer: &mut Serializer) -> usize {
+		let mut count = 0;
+		self.next_send = self.next_send.max(self.last_fill);
+		self.last_fill = now;
+		while self.connected && self.next_send <= now {
+			let byte = match serializer.pull() {
+				Some(pull) => pull(),
+				None => break,
+			};
+			count += 1;
+			let sent = if let Some(bandwidth) = self.bandwidth {
+				let sent = self.next_send;
+				self.next_send += Duration::from_secs(1) / bandwidth;
+				sent
+			} else {
+				now
+			};
+			let jitter = self.jitter.mul_f64(self.random());
+			let deliver = (sent + self.latency + jitter).max(
+				self.in_flight
+					.back()
+					.map_or(Duration::from_secs(0), |&(deliver, _)| deliver),
+			);
+			self.in_flight.push_back((deliver, byte));
+			if self.disconnect != 0.0 && self.random() < self.disconnect {
+				self.disconnect();
+			}
+		}
+		count
+	}
+
+	/// Deliver as many bytes to `deserializer` as have arrived by time `now` and it accepts, returning the number delivered.
+	pub fn drain_into(&mut self, now: Duration, deserializer: &mut Deserializer) -> usize {
+		let mut count = 0;
+		while let Some(&(deliver, byte)) = self.in_flight.front() {
+			if deliver > now {
+				break;
+			}
+			match deserializer.push() {
+				Some(push) => push(byte),
+				None => break,
+			}
+			let _ = self.in_flight.pop_front();
+			count += 1;
+		}
+		count
+	}
+
+	/// A uniformly random number in `0.0..=1.0`, by xorshift64*.
+	fn random(&mut self) -> f64 {
+		self.rng ^= self.rng >> 12;
+		self.rng ^= self.rng << 25;
+		self.rng ^= self.rng >> 27;
+		let x = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+		f64::from(u32::try_from(x >> 32).unwrap()) / f64::from(u32::MAX)
+	}
+}
@@ -0,0 +1,164 @@
+//! Negotiating options at the start of a stream.
+//!
+//! When the two ends of a stream must agree on a compression codec, a cipher suite, a format version and so on, configuring each end out of band inevitably drifts. Instead one end sends an [`Offer`] of the values it supports for each option, in order of preference. The other [`accept`](Offer::accept())s it against the values it supports, choosing for each option the first value offered that it also supports, and replies with the resulting [`Agreement`], or the option on which they couldn't agree.
+//!
+//! [`offer()`] and [`accept()`] perform this exchange over a [`Connection`]. Offers and agreements are also plain serializable values, so can equally be pushed and pulled through any pipe.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{negotiate::{self, Offer}, stream::Connection};
+//! use std::{net::{TcpListener, TcpStream}, thread};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let addr = listener.local_addr().unwrap();
+//! let thread = thread::spawn(move || {
+//!     let supported = Offer::new()
+//!         .with("compression", &["lz4", "none"])
+//!         .with("version", &["1", "2"]);
+//!     let mut connection = Connection::new(listener.accept().unwrap().0);
+//!     negotiate::accept(&mut connection, &supported).unwrap()
+//! });
+//! let offer = Offer::new()
+//!     .with("compression", &["zstd", "lz4", "none"])
+//!     .with("version", &["2"]);
+//! let mut connection = Connection::new(TcpStream::connect(addr).unwrap());
+//! let agreement = negotiate::offer(&mut connection, &offer).unwrap();
+//! assert_eq!(agreement.get("compression"), Some("lz4"));
+//! assert_eq!(agreement.get("version"), Some("2"));
+//! assert_eq!(thread.join().unwrap(), agreement);
+//! ```
+
+use crate::stream::Connection;
+use serde::{de, ser};
+use std::{
+	collections::BTreeMap, error, fmt, io::{self, Read, Write}
+};
+
+/// The values supported for each option, in order of preference.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Offer(BTreeMap<String, Vec<String>>);
+impl Offer {
+	/// An offer of no options.
+	#[must_use]
+	pub fn new() -> Self {
+		Self(BTreeMap::new())
+	}
+
+	/// Offer `values` for `option`, most preferred first, replacing any already offered.
+	#[must_use]
+	pub fn with<V: AsRef<str>>(mut self, option: &str, values: &[V]) -> Self {
+		let _ = self.0.insert(
+			option.to_owned(),
+			values
+				.iter()
+				.map(|value| value.as_ref().to_owned())
+				.collect(),
+		);
+		self
+	}
+
+	/// The values offered for `option`, most preferred first, or [`None`] if it isn't offered.
+	pub fn get(&self, option: &str) -> Option<&[String]> {
+		self.0.get(option).map(Vec::as_slice)
+	}
+
+	/// Accept `offer`, choosing for each option the first value in it that `self` supports. Options that only one of the two includes are left out of the [`Agreement`].
+	///
+	/// # Errors
+	///
+	/// Returns a [`Mismatch`] if an option is included by both, but none of the values offered for it are supported.
+	pub fn accept(&self, offer: &Self) -> Result<Agreement, Mismatch> {
+		let mut agreement = BTreeMap::new();
+		for (option, offered) in &offer.0 {
+			if let Some(supported) = self.0.get(option) {
+				let value = offered
+					.iter()
+					.find(|value| supported.contains(value))
+					.ok_or_else(|| Mismatch {
+						option: option.clone(),
+					})?;
+				let _ = agreement.insert(option.clone(), value.clone());
+			}
+		}
+		Ok(Agreement(agreement))
+	}
+}
+impl ser::Serialize for Offer {
+	fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.0.serialize(serializer)
+	}
+}
+impl<'de> de::Deserialize<'de> for Offer {
+	fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		BTreeMap::deserialize(deserializer).map(Self)
+	}
+}
+
+/// The value agreed for each option.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Agreement(BTreeMap<String, String>);
+impl Agreement {
+	/// The value agreed for `option`, or [`None`] if it wasn't negotiated.
+	pub fn get(&self, option: &str) -> Option<&str> {
+		self.0.get(option).map(String::as_str)
+	}
+
+	/// Iterate over the options agreed, and their values.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.0
+			.iter()
+			.map(|(option, value)| (option.as_str(), value.as_str()))
+	}
+}
+impl ser::Serialize for Agreement {
+	fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.0.serialize(serializer)
+	}
+}
+impl<'de> de::Deserialize<'de> for Agreement {
+	fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		BTreeMap::deserialize(deserializer).map(Self)
+	}
+}
+
+/// The two ends support no value in common for an option.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Mismatch {
+	/// The option on which the two ends couldn't agree.
+	pub option: String,
+}
+impl fmt::Display for Mismatch {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "no supported value in common for {:?}", self.option)
+	}
+}
+impl error::Error for Mismatch {}
+
+/// Send `offer`, returning the [`Agreement`] the other end replies with. It should call [`accept()`].
+///
+/// # Errors
+///
+/// Returns any error sending or receiving, or [`io::ErrorKind::InvalidData`] wrapping a [`Mismatch`] if the other end couldn't agree.
+pub fn offer<S: Read + Write>(
+	connection: &mut Connection<S>, offer: &Offer,
+) -> io::Result<Agreement> {
+	connection.send(offer.clone())?;
+	connection
+		.recv::<Result<Agreement, String>>()?
+		.map_err(|option| io::Error::new(io::ErrorKind::InvalidData, Mismatch { option }))
+}
+
+/// Receive the other end's [`Offer`], accepting it against `supported`, and reply with the [`Agreement`]. It should call [`offer()`].
+///
+/// # Errors
+///
+/// Returns any error sending or receiving, or [`io::ErrorKind::InvalidData`] wrapping a [`Mismatch`] if the two ends couldn't agree. The other end is notified of the mismatch before it's returned.
+pub fn accept<S: Read + Write>(
+	connection: &mut Connection<S>, supported: &Offer,
+) -> io::Result<Agreement> {
+	let offer = connection.recv::<Offer>()?;
+	let agreement = supported.accept(&offer);
+	connection.send(agreement.clone().map_err(|mismatch| mismatch.option))?;
+	agreement.map_err(|mismatch| io::Error::new(io::ErrorKind::InvalidData, mismatch))
+}
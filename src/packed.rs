@@ -0,0 +1,723 @@
+//! A compact encoding that packs bools, option tags and enum variant indices into bits, for values with many flags.
+//!
+//! bincode spends a byte on each `bool` and `Option` tag, and four on each enum variant index. Wrapping a value in [`Packed`], or annotating a field with `#[serde(with = "serde_pipe::packed")]`, instead encodes it with these packed into a bit section at the start, followed by its other fields as bincode would encode them. Variant indices are exponential-Golomb coded, so the first variant takes one bit, the next two three bits, and so on.
+//!
+//! The packed value is itself encoded as bytes, so bincode prefixes it with an 8 byte length. This pays off once a value holds more than a handful of flags.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{packed::Packed, Deserializer, Serializer};
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! enum Stance {
+//!     Standing,
+//!     Crouching,
+//!     Prone,
+//! }
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Entity {
+//!     id: u32,
+//!     flags: [bool; 24],
+//!     stance: Stance,
+//! }
+//!
+//! let entity = Entity { id: 7, flags: [true; 24], stance: Stance::Prone };
+//! let vec = serde_pipe::serialize_to_vec(Packed(entity));
+//! assert!(vec.len() < serde_pipe::serialize_to_vec(Entity { id: 7, flags: [true; 24], stance: Stance::Prone }).len());
+//! let Packed(entity) = serde_pipe::deserialize_from_slice::<Packed<Entity>>(&vec).unwrap();
+//! assert_eq!(entity.stance, Stance::Prone);
+//! ```
+
+use serde::{de, ser};
+use std::{
+	convert::{TryFrom, TryInto}, error, fmt
+};
+
+/// A `T` that serializes in the packed encoding.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Packed<T>(pub T);
+impl<T: ser::Serialize> ser::Serialize for Packed<T> {
+	fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize(&self.0, serializer)
+	}
+}
+impl<'de, T: de::DeserializeOwned> de::Deserialize<'de> for Packed<T> {
+	fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserialize(deserializer).map(Packed)
+	}
+}
+
+/// Serialize `t` in the packed encoding, for `#[serde(with = "serde_pipe::packed")]`.
+///
+/// # Errors
+///
+/// Returns an error if `t` contains a sequence or map of unknown length, or its [`Serialize`](serde::ser::Serialize) impl fails.
+pub fn serialize<T: ser::Serialize + ?Sized, S: ser::Serializer>(
+	t: &T, serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serialize_with(t, serializer, None)
+}
+
+/// Deserialize a `T` from the packed encoding, for `#[serde(with = "serde_pipe::packed")]`.
+///
+/// # Errors
+///
+/// Returns an error if the packed bytes aren't exactly a valid `T`.
+pub fn deserialize<'de, T: de::DeserializeOwned, D: de::Deserializer<'de>>(
+	deserializer: D,
+) -> Result<T, D::Error> {
+	deserialize_with(deserializer, None)
+}
+
+/// As [`serialize()`], encoding floats as `fixed` if [`Some`].
+pub(crate) fn serialize_with<T: ser::Serialize + ?Sized, S: ser::Serializer>(
+	t: &T, serializer: S, fixed: Option<Fixed>,
+) -> Result<S::Ok, S::Error> {
+	let mut encoder = Encoder {
+		bits: Vec::new(),
+		len: 0,
+		out: Vec::new(),
+		fixed,
+	};
+	t.serialize(&mut encoder).map_err(ser::Error::custom)?;
+	let mut vec = Vec::with_capacity(10 + encoder.bits.len() + encoder.out.len());
+	write_varint(&mut vec, encoder.bits.len() as u64);
+	vec.extend(encoder.bits);
+	vec.extend(encoder.out);
+	serializer.serialize_bytes(&vec)
+}
+
+/// As [`deserialize()`], decoding floats as `fixed` if [`Some`].
+pub(crate) fn deserialize_with<'de, T: de::DeserializeOwned, D: de::Deserializer<'de>>(
+	deserializer: D, fixed: Option<Fixed>,
+) -> Result<T, D::Error> {
+	let vec = deserializer.deserialize_byte_buf(BytesVisitor)?;
+	decode(&vec, fixed).map_err(de::Error::custom)
+}
+
+fn decode<T: de::DeserializeOwned>(input: &[u8], fixed: Option<Fixed>) -> Result<T, Error> {
+	let (len, offset) = read_varint(input).ok_or_else(eof)?;
+	let len = usize::try_from(len).map_err(de::Error::custom)?;
+	let input = &input[offset..];
+	if len > input.len() {
+		return Err(eof());
+	}
+	let (bits, input) = input.split_at(len);
+	let mut decoder = Decoder {
+		bits,
+		pos: 0,
+		input,
+		fixed,
+	};
+	let t = T::deserialize(&mut decoder)?;
+	if !decoder.input.is_empty() || decoder.pos.div_ceil(8) != decoder.bits.len() {
+		return Err(de::Error::custom("trailing bytes after packed value"));
+	}
+	Ok(t)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+	while n >= 0x80 {
+		#[allow(clippy::cast_possible_truncation)]
+		out.push(n as u8 | 0x80);
+		n >>= 7;
+	}
+	#[allow(clippy::cast_possible_truncation)]
+	out.push(n as u8);
+}
+
+/// Read a varint, returning it and its length, or [`None`] if `input` is incomplete or it overflows.
+fn read_varint(input: &[u8]) -> Option<(u64, usize)> {
+	let mut n = 0_u64;
+	for (i, &byte) in input.iter().enumerate().take(10) {
+		n |= u64::from(byte & 0x7f).checked_shl(u32::try_from(i).unwrap() * 7)?;
+		if byte & 0x80 == 0 {
+			return Some((n, i + 1));
+		}
+	}
+	None
+}
+
+struct BytesVisitor;
+impl<'de> de::Visitor<'de> for BytesVisitor {
+	type Value = Vec<u8>;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("packed bytes")
+	}
+	fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+		Ok(v.to_owned())
+	}
+	fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+		Ok(v)
+	}
+	fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+		let mut vec = Vec::new();
+		while let Some(byte) = seq.next_element()? {
+			vec.push(byte);
+		}
+		Ok(vec)
+	}
+}
+
+#[derive(Debug)]
+struct Error(String);
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+impl error::Error for Error {}
+impl ser::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self(msg.to_string())
+	}
+}
+impl de::Error for Error {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self(msg.to_string())
+	}
+}
+fn eof() -> Error {
+	Error(String::from("unexpected end of packed value"))
+}
+
+/// A signed fixed-point encoding of floats, `bits` wide with `frac` fractional bits.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Fixed {
+	pub(crate) bits: u32,
+	pub(crate) frac: u32,
+}
+impl Fixed {
+	/// Round `v` to the nearest representable value, saturating at the ends of the range, with NaN as zero.
+	fn encode(self, v: f64, out: &mut Vec<u8>) {
+		let max = i64::MAX >> (64 - self.bits);
+		#[allow(clippy::cast_possible_truncation)]
+		let n = ((v * f64::from(self.frac).exp2()).round() as i64).clamp(-max - 1, max);
+		out.extend_from_slice(&n.to_le_bytes()[..self.bits as usize / 8]);
+	}
+	fn decode(self, decoder: &mut Decoder) -> Result<f64, Error> {
+		let bytes = decoder.take(self.bits as usize / 8)?;
+		let fill = if bytes.last().is_some_and(|&byte| byte & 0x80 != 0) {
+			0xff
+		} else {
+			0
+		};
+		let mut array = [fill; 8];
+		array[..bytes.len()].copy_from_slice(bytes);
+		#[allow(clippy::cast_precision_loss)]
+		Ok(i64::from_le_bytes(array) as f64 / f64::from(self.frac).exp2())
+	}
+}
+
+struct Encoder {
+	/// The bit section, least significant bit first.
+	bits: Vec<u8>,
+	/// The number of bits written to `bits`.
+	len: usize,
+	out: Vec<u8>,
+	fixed: Option<Fixed>,
+}
+impl Encoder {
+	fn bit(&mut self, bit: bool) {
+		if self.len / 8 == self.bits.len() {
+			self.bits.push(0);
+		}
+		*self.bits.last_mut().unwrap() |= u8::from(bit) << (self.len % 8);
+		self.len += 1;
+	}
+	/// Write a variant index as an exponential-Golomb code: the number of bits following the leading one, in zeros, then `index + 1` most significant bit first.
+	fn variant(&mut self, index: u32) {
+		let x = u64::from(index) + 1;
+		let width = x.ilog2();
+		for _ in 0..width {
+			self.bit(false);
+		}
+		for i in (0..=width).rev() {
+			self.bit(x >> i & 1 == 1);
+		}
+	}
+	fn len(&mut self, len: Option<usize>) -> Result<(), Error> {
+		let len = len.ok_or_else(|| Error(String::from("sequences must have a known length")))?;
+		self.out.extend_from_slice(&(len as u64).to_le_bytes());
+		Ok(())
+	}
+}
+impl ser::Serializer for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = Self;
+	type SerializeTuple = Self;
+	type SerializeTupleStruct = Self;
+	type SerializeTupleVariant = Self;
+	type SerializeMap = Self;
+	type SerializeStruct = Self;
+	type SerializeStructVariant = Self;
+
+	fn serialize_bool(self, v: bool) -> Result<(), Error> {
+		self.bit(v);
+		Ok(())
+	}
+	fn serialize_i8(self, v: i8) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_i16(self, v: i16) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_i32(self, v: i32) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_i64(self, v: i64) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_i128(self, v: i128) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_u8(self, v: u8) -> Result<(), Error> {
+		self.out.push(v);
+		Ok(())
+	}
+	fn serialize_u16(self, v: u16) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_u32(self, v: u32) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_u64(self, v: u64) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_u128(self, v: u128) -> Result<(), Error> {
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_f32(self, v: f32) -> Result<(), Error> {
+		if self.fixed.is_some() {
+			return self.serialize_f64(v.into());
+		}
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_f64(self, v: f64) -> Result<(), Error> {
+		if let Some(fixed) = self.fixed {
+			fixed.encode(v, &mut self.out);
+			return Ok(());
+		}
+		self.out.extend_from_slice(&v.to_le_bytes());
+		Ok(())
+	}
+	fn serialize_char(self, v: char) -> Result<(), Error> {
+		self.serialize_u32(v.into())
+	}
+	fn serialize_str(self, v: &str) -> Result<(), Error> {
+		self.serialize_bytes(v.as_bytes())
+	}
+	fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+		self.len(Some(v.len()))?;
+		self.out.extend_from_slice(v);
+		Ok(())
+	}
+	fn serialize_none(self) -> Result<(), Error> {
+		self.bit(false);
+		Ok(())
+	}
+	fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<(), Error> {
+		self.bit(true);
+		value.serialize(self)
+	}
+	fn serialize_unit(self) -> Result<(), Error> {
+		Ok(())
+	}
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+		Ok(())
+	}
+	fn serialize_unit_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str,
+	) -> Result<(), Error> {
+		self.variant(variant_index);
+		Ok(())
+	}
+	fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+		self, _name: &'static str, value: &T,
+	) -> Result<(), Error> {
+		value.serialize(self)
+	}
+	fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T,
+	) -> Result<(), Error> {
+		self.variant(variant_index);
+		value.serialize(self)
+	}
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+		self.len(len)?;
+		Ok(self)
+	}
+	fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+		Ok(self)
+	}
+	fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+		Ok(self)
+	}
+	fn serialize_tuple_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize,
+	) -> Result<Self, Error> {
+		self.variant(variant_index);
+		Ok(self)
+	}
+	fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+		self.len(len)?;
+		Ok(self)
+	}
+	fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+		Ok(self)
+	}
+	fn serialize_struct_variant(
+		self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize,
+	) -> Result<Self, Error> {
+		self.variant(variant_index);
+		Ok(self)
+	}
+	fn is_human_readable(&self) -> bool {
+		false
+	}
+}
+impl ser::SerializeSeq for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeTuple for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeTupleStruct for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeTupleVariant for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeMap for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<(), Error> {
+		key.serialize(&mut **self)
+	}
+	fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeStruct for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(
+		&mut self, _key: &'static str, value: &T,
+	) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+impl ser::SerializeStructVariant for &mut Encoder {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: ?Sized + ser::Serialize>(
+		&mut self, _key: &'static str, value: &T,
+	) -> Result<(), Error> {
+		value.serialize(&mut **self)
+	}
+	fn end(self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+struct Decoder<'de> {
+	bits: &'de [u8],
+	/// The number of bits read from `bits`.
+	pos: usize,
+	input: &'de [u8],
+	fixed: Option<Fixed>,
+}
+impl<'de> Decoder<'de> {
+	fn bit(&mut self) -> Result<bool, Error> {
+		let byte = self.bits.get(self.pos / 8).ok_or_else(eof)?;
+		let bit = byte >> (self.pos % 8) & 1 == 1;
+		self.pos += 1;
+		Ok(bit)
+	}
+	fn variant(&mut self) -> Result<u32, Error> {
+		let mut width = 0;
+		while !self.bit()? {
+			width += 1;
+			if width > 32 {
+				return Err(de::Error::custom("invalid packed variant index"));
+			}
+		}
+		let mut x = 1_u64;
+		for _ in 0..width {
+			x = x << 1 | u64::from(self.bit()?);
+		}
+		u32::try_from(x - 1).map_err(de::Error::custom)
+	}
+	fn take(&mut self, len: usize) -> Result<&'de [u8], Error> {
+		if len > self.input.len() {
+			return Err(eof());
+		}
+		let (taken, rest) = self.input.split_at(len);
+		self.input = rest;
+		Ok(taken)
+	}
+	fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+		Ok(self.take(N)?.try_into().unwrap())
+	}
+	fn len(&mut self) -> Result<usize, Error> {
+		usize::try_from(u64::from_le_bytes(self.array()?)).map_err(de::Error::custom)
+	}
+	fn bytes(&mut self) -> Result<&'de [u8], Error> {
+		let len = self.len()?;
+		self.take(len)
+	}
+}
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+	type Error = Error;
+
+	fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+		Err(de::Error::custom(
+			"the packed encoding is not self-describing",
+		))
+	}
+	fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_bool(self.bit()?)
+	}
+	fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i8(i8::from_le_bytes(self.array()?))
+	}
+	fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i16(i16::from_le_bytes(self.array()?))
+	}
+	fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i32(i32::from_le_bytes(self.array()?))
+	}
+	fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i64(i64::from_le_bytes(self.array()?))
+	}
+	fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_i128(i128::from_le_bytes(self.array()?))
+	}
+	fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u8(u8::from_le_bytes(self.array()?))
+	}
+	fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u16(u16::from_le_bytes(self.array()?))
+	}
+	fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u32(u32::from_le_bytes(self.array()?))
+	}
+	fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u64(u64::from_le_bytes(self.array()?))
+	}
+	fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_u128(u128::from_le_bytes(self.array()?))
+	}
+	fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		if let Some(fixed) = self.fixed {
+			#[allow(clippy::cast_possible_truncation)]
+			return visitor.visit_f32(fixed.decode(self)? as f32);
+		}
+		visitor.visit_f32(f32::from_le_bytes(self.array()?))
+	}
+	fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		if let Some(fixed) = self.fixed {
+			return visitor.visit_f64(fixed.decode(self)?);
+		}
+		visitor.visit_f64(f64::from_le_bytes(self.array()?))
+	}
+	fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let c = u32::from_le_bytes(self.array()?);
+		visitor.visit_char(char::try_from(c).map_err(de::Error::custom)?)
+	}
+	fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_borrowed_str(std::str::from_utf8(self.bytes()?).map_err(de::Error::custom)?)
+	}
+	fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_str(visitor)
+	}
+	fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_borrowed_bytes(self.bytes()?)
+	}
+	fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_bytes(visitor)
+	}
+	fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		if self.bit()? {
+			visitor.visit_some(self)
+		} else {
+			visitor.visit_none()
+		}
+	}
+	fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		visitor.visit_unit()
+	}
+	fn deserialize_unit_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_unit()
+	}
+	fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_newtype_struct(self)
+	}
+	fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let len = self.len()?;
+		visitor.visit_seq(Fields { decoder: self, len })
+	}
+	fn deserialize_tuple<V: de::Visitor<'de>>(
+		self, len: usize, visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_seq(Fields { decoder: self, len })
+	}
+	fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, len: usize, visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_tuple(len, visitor)
+	}
+	fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		let len = self.len()?;
+		visitor.visit_map(Fields { decoder: self, len })
+	}
+	fn deserialize_struct<V: de::Visitor<'de>>(
+		self, _name: &'static str, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		self.deserialize_tuple(fields.len(), visitor)
+	}
+	fn deserialize_enum<V: de::Visitor<'de>>(
+		self, _name: &'static str, _variants: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		visitor.visit_enum(self)
+	}
+	fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_any(visitor)
+	}
+	fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+		self.deserialize_any(visitor)
+	}
+	fn is_human_readable(&self) -> bool {
+		false
+	}
+}
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+	type Error = Error;
+	type Variant = Self;
+
+	fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+		let index = self.variant()?;
+		let value = seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(index))?;
+		Ok((value, self))
+	}
+}
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+	type Error = Error;
+
+	fn unit_variant(self) -> Result<(), Error> {
+		Ok(())
+	}
+	fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+		seed.deserialize(self)
+	}
+	fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_tuple(self, len, visitor)
+	}
+	fn struct_variant<V: de::Visitor<'de>>(
+		self, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, Error> {
+		de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+	}
+}
+
+/// The elements of a sequence, tuple or struct, or the entries of a map.
+struct Fields<'a, 'de> {
+	decoder: &'a mut Decoder<'de>,
+	len: usize,
+}
+impl<'de> de::SeqAccess<'de> for Fields<'_, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T: de::DeserializeSeed<'de>>(
+		&mut self, seed: T,
+	) -> Result<Option<T::Value>, Error> {
+		if self.len != 0 {
+			self.len -= 1;
+			seed.deserialize(&mut *self.decoder).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
+impl<'de> de::MapAccess<'de> for Fields<'_, 'de> {
+	type Error = Error;
+
+	fn next_key_seed<K: de::DeserializeSeed<'de>>(
+		&mut self, seed: K,
+	) -> Result<Option<K::Value>, Error> {
+		if self.len != 0 {
+			self.len -= 1;
+			seed.deserialize(&mut *self.decoder).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+	fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+		seed.deserialize(&mut *self.decoder)
+	}
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
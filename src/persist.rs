@@ -0,0 +1,274 @@
+//! A durable queue of frames on disk, with acknowledgements.
+//!
+//! [`PersistentQueue`] appends each pushed value to a file, syncing it to disk before returning. Pulled values must be [`ack`](PersistentQueue::ack())ed once processed; those that aren't are delivered again when the queue is next opened, so each value is processed at least once across crashes and restarts.
+//!
+//! Values are laid out in the file as the default backend frames them: an 8-byte little-endian length, followed by the bincode encoding, with a zero-length encoding padded to a single zero byte. Acknowledgements are kept alongside, in a file with `.ack` appended to the name, replaced atomically on each ack. Once every value has been acked, the file is truncated.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::persist::PersistentQueue;
+//!
+//! let path = std::env::temp_dir().join(format!("serde_pipe-doc-{}", std::process::id()));
+//! let mut queue = PersistentQueue::open(&path).unwrap();
+//! queue.push(String::from("first")).unwrap();
+//! queue.push(String::from("second")).unwrap();
+//! let (first, ack) = queue.pull::<String>().unwrap().unwrap();
+//! assert_eq!(first, "first");
+//! queue.ack(ack).unwrap();
+//! let _ = queue.pull::<String>().unwrap().unwrap(); // not acked
+//! drop(queue);
+//!
+//! // the unacked value is delivered again
+//! let mut queue = PersistentQueue::open(&path).unwrap();
+//! let (second, ack) = queue.pull::<String>().unwrap().unwrap();
+//! assert_eq!(second, "second");
+//! queue.ack(ack).unwrap();
+//! # drop(queue);
+//! # std::fs::remove_file(&path).unwrap();
+//! # std::fs::remove_file(path.with_extension("ack")).unwrap();
+//! ```
+
+use crate::wire;
+use std::{
+	collections::BTreeSet, convert::{TryFrom, TryInto}, ffi::OsString, fmt, fs::{self, File, OpenOptions}, io::{self, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}
+};
+
+/// The receipt for a pulled value, to be passed to [`PersistentQueue::ack()`] once it's processed.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[must_use = "unacked values are delivered again when the queue is next opened"]
+pub struct Ack {
+	/// The offset of the frame in the file.
+	start: u64,
+	/// The offset of the frame following it.
+	end: u64,
+}
+
+/// A queue of values durably stored in a file.
+pub struct PersistentQueue {
+	file: File,
+	ack_path: PathBuf,
+	/// The length of the file's complete frames.
+	len: u64,
+	/// Every frame before this offset has been acked.
+	acked: u64,
+	/// The frames beyond `acked` that have been acked, ahead of those before them.
+	ahead: BTreeSet<Ack>,
+	/// The offset of the next frame to deliver.
+	next: u64,
+}
+impl PersistentQueue {
+	/// Open the queue stored at `path`, creating it if it doesn't exist. Values that were pushed but not acked are delivered again, in the order pushed. A frame left incomplete by a crash mid-push is discarded.
+	///
+	/// # Errors
+	///
+	/// Returns any error opening or reading the files, or [`io::ErrorKind::InvalidData`] if the acknowledgements are corrupt.
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let path = path.as_ref();
+		let mut file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(path)?;
+		let mut ack_path = OsString::from(path);
+		ack_path.push(".ack");
+		let ack_path = PathBuf::from(ack_path);
+		let (mut acked, mut ahead) = match fs::read(&ack_path) {
+			Ok(bytes) => parse_acks(&bytes)
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "corrupt ack file"))?,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => (0, BTreeSet::new()),
+			Err(err) => return Err(err),
+		};
+		let file_len = file.metadata()?.len();
+		let mut stale = acked > file_len;
+		if stale {
+			// the file was truncated once everything was acked, but the acks weren't yet reset
+			acked = 0;
+			ahead.clear();
+		}
+		// find the end of the last complete frame
+		let mut len = acked;
+		let _ = file.seek(SeekFrom::Start(len))?;
+		let mut reader = io::BufReader::new(&mut file);
+		let mut header = [0; 8];
+		while reader.read_exact(&mut header).is_ok() {
+			let end = len + 8 + u64::from_le_bytes(header);
+			if end > file_len {
+				break;
+			}
+			let _ = reader.seek(SeekFrom::Start(end))?;
+			len = end;
+		}
+		let ahead_len = ahead.len();
+		ahead.retain(|ack| ack.end <= len);
+		stale |= ahead.len() != ahead_len;
+		let queue = Self {
+			file,
+			ack_path,
+			len,
+			acked,
+			ahead,
+			next: acked,
+		};
+		// persist reset acks before anything is appended past them, lest they skip the new frames after another crash
+		if stale {
+			queue.write_acks()?;
+		}
+		if len != file_len {
+			queue.file.set_len(len)?;
+			queue.file.sync_data()?;
+		}
+		Ok(queue)
+	}
+
+	/// Durably append a `T`, returning once it's synced to disk.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to or syncing the file. The queue should then be dropped and reopened, which discards any partially written frame.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T`'s [`Serialize`](serde::ser::Serialize) impl fails.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self, t: T) -> io::Result<()> {
+		let mut frame = 0_u64.to_le_bytes().to_vec();
+		wire::serialize_into(&mut frame, &t).unwrap();
+		if frame.len() == 8 {
+			frame.push(0);
+		}
+		let len = frame.len() as u64 - 8;
+		frame[..8].copy_from_slice(&len.to_le_bytes());
+		let _ = self.file.seek(SeekFrom::Start(self.len))?;
+		self.file.write_all(&frame)?;
+		self.file.sync_data()?;
+		self.len += frame.len() as u64;
+		Ok(())
+	}
+
+	/// Pull the next undelivered `T`, along with the [`Ack`] to pass to [`ack`](PersistentQueue::ack()) once it's processed. [`None`] denotes that every value has been delivered.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading the file, or [`io::ErrorKind::InvalidData`] if the frame isn't a `T`, in which case it isn't delivered, and can be pulled as another type.
+	///
+	/// # Panics
+	///
+	/// Will panic if the frame is too long to fit in memory.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> io::Result<Option<(T, Ack)>> {
+		// skip frames acked before the queue was last opened
+		while let Some(&ack) = self
+			.ahead
+			.range(
+				Ack {
+					start: self.next,
+					end: 0,
+				}..,
+			)
+			.next()
+		{
+			if ack.start != self.next {
+				break;
+			}
+			self.next = ack.end;
+		}
+		if self.next == self.len {
+			return Ok(None);
+		}
+		let _ = self.file.seek(SeekFrom::Start(self.next))?;
+		let mut header = [0; 8];
+		self.file.read_exact(&mut header)?;
+		let len = u64::from_le_bytes(header);
+		let mut payload = vec![0; usize::try_from(len).unwrap()];
+		self.file.read_exact(&mut payload)?;
+		let t = wire::deserialize(&payload)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		let ack = Ack {
+			start: self.next,
+			end: self.next + 8 + len,
+		};
+		self.next = ack.end;
+		Ok(Some((t, ack)))
+	}
+
+	/// Acknowledge that a pulled value has been processed, so it isn't delivered again. Once every value has been acked, the file is truncated.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing the acknowledgements or truncating the file. The queue should then be dropped and reopened, which delivers again any values whose acks weren't written.
+	pub fn ack(&mut self, ack: Ack) -> io::Result<()> {
+		if ack.start < self.acked {
+			return Ok(());
+		}
+		let _ = self.ahead.insert(ack);
+		while let Some(&first) = self.ahead.iter().next() {
+			if first.start != self.acked {
+				break;
+			}
+			let _ = self.ahead.remove(&first);
+			self.acked = first.end;
+		}
+		if self.acked == self.len {
+			// everything is acked: start afresh, resetting the acks before truncating, so that a crash in between delivers the values again rather than skipping those pushed after
+			self.acked = 0;
+			self.ahead.clear();
+			self.write_acks()?;
+			self.file.set_len(0)?;
+			self.file.sync_data()?;
+			self.len = 0;
+			self.next = 0;
+			return Ok(());
+		}
+		self.write_acks()
+	}
+
+	/// Whether every value pushed has been acked.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.acked == self.len
+	}
+
+	fn write_acks(&self) -> io::Result<()> {
+		let mut bytes = Vec::with_capacity(8 * (1 + 2 * self.ahead.len()));
+		bytes.extend_from_slice(&self.acked.to_le_bytes());
+		for ack in &self.ahead {
+			bytes.extend_from_slice(&ack.start.to_le_bytes());
+			bytes.extend_from_slice(&ack.end.to_le_bytes());
+		}
+		let mut tmp = self.ack_path.clone().into_os_string();
+		tmp.push(".tmp");
+		let mut file = File::create(&tmp)?;
+		file.write_all(&bytes)?;
+		file.sync_data()?;
+		fs::rename(&tmp, &self.ack_path)
+	}
+}
+impl fmt::Debug for PersistentQueue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("PersistentQueue")
+			.field("ack_path", &self.ack_path)
+			.field("len", &self.len)
+			.field("acked", &self.acked)
+			.field("ahead", &self.ahead.len())
+			.field("next", &self.next)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Parse the acked offset followed by the `(start, end)` of each frame acked ahead of it.
+fn parse_acks(bytes: &[u8]) -> Option<(u64, BTreeSet<Ack>)> {
+	if bytes.len() % 16 != 8 {
+		return None;
+	}
+	let mut words = bytes
+		.chunks_exact(8)
+		.map(|word| u64::from_le_bytes(word.try_into().unwrap()));
+	let acked = words.next()?;
+	let mut ahead = BTreeSet::new();
+	while let (Some(start), Some(end)) = (words.next(), words.next()) {
+		let _ = ahead.insert(Ack { start, end });
+	}
+	Some((acked, ahead))
+}
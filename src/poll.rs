@@ -0,0 +1,107 @@
+//! Poll-style alternatives to the `Option<impl FnOnce>` operations, saying why an operation isn't available.
+//!
+//! Each `poll_*` method returns a [`PipePoll`], which is [`Ready`](PipePoll::Ready) where the corresponding method would return [`Some`], and otherwise says what the pipe is waiting on.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{poll::PipePoll, Deserializer};
+//!
+//! let mut deserializer = Deserializer::new();
+//! assert!(matches!(deserializer.poll_push(), PipePoll::Empty));
+//! assert!(matches!(deserializer.poll_pull::<u64>(), PipePoll::AwaitingPush));
+//! match deserializer.poll_push() {
+//!     PipePoll::Ready(push) => push(1),
+//!     _ => unreachable!(),
+//! }
+//! # deserializer.empty().unwrap()();
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::fmt;
+
+/// The readiness of an operation on a pipe.
+pub enum PipePoll<F> {
+	/// The operation is available: the `impl FnOnce` can be called to perform it.
+	Ready(F),
+	/// The pipe is part way through a frame, awaiting a push.
+	AwaitingPush,
+	/// The pipe holds a frame, or on a [`Deserializer`] a control frame, awaiting a pull.
+	AwaitingPull,
+	/// The pipe holds nothing: for [`Serializer::poll_pull()`] nothing has been pushed, and for [`Deserializer::poll_push()`] no type has been supplied by a pull.
+	Empty,
+}
+impl<F> PipePoll<F> {
+	/// The `impl FnOnce` if [`Ready`](PipePoll::Ready), discarding the reason otherwise.
+	pub fn ready(self) -> Option<F> {
+		match self {
+			PipePoll::Ready(f) => Some(f),
+			_ => None,
+		}
+	}
+}
+impl<F> fmt::Debug for PipePoll<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PipePoll::Ready(_) => f.write_str("Ready(..)"),
+			PipePoll::AwaitingPush => f.write_str("AwaitingPush"),
+			PipePoll::AwaitingPull => f.write_str("AwaitingPull"),
+			PipePoll::Empty => f.write_str("Empty"),
+		}
+	}
+}
+
+impl Serializer {
+	/// As [`push`](Serializer::push()), but [`AwaitingPull`](PipePoll::AwaitingPull) rather than [`None`].
+	pub fn poll_push<T: serde::ser::Serialize + 'static>(
+		&mut self,
+	) -> PipePoll<impl FnOnce(T) + '_> {
+		self.push().map_or(PipePoll::AwaitingPull, PipePoll::Ready)
+	}
+
+	/// As [`pull`](Serializer::pull()), but [`Empty`](PipePoll::Empty) rather than [`None`].
+	pub fn poll_pull(&mut self) -> PipePoll<impl FnOnce() -> u8 + '_> {
+		self.pull().map_or(PipePoll::Empty, PipePoll::Ready)
+	}
+
+	/// As [`empty`](Serializer::empty()), but [`Empty`](PipePoll::Empty) rather than [`None`].
+	pub fn poll_empty(&mut self) -> PipePoll<impl FnOnce() + '_> {
+		self.empty().map_or(PipePoll::Empty, PipePoll::Ready)
+	}
+}
+
+impl Deserializer {
+	/// As [`pull`](Deserializer::pull()), but rather than [`None`], [`AwaitingPush`](PipePoll::AwaitingPush) if the frame is incomplete, or [`AwaitingPull`](PipePoll::AwaitingPull) if a control frame is awaiting a pull first.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn poll_pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> PipePoll<impl FnOnce() -> T + '_> {
+		// supply the type first, so push_avail() reflects it
+		let _ = self.pull::<T>();
+		let state = if self.push_avail() {
+			PipePoll::AwaitingPush
+		} else {
+			PipePoll::AwaitingPull
+		};
+		self.pull().map_or(state, PipePoll::Ready)
+	}
+
+	/// As [`push`](Deserializer::push()), but rather than [`None`], [`AwaitingPull`](PipePoll::AwaitingPull) if a frame is awaiting a pull, or [`Empty`](PipePoll::Empty) if no type has yet been supplied by a pull.
+	pub fn poll_push(&mut self) -> PipePoll<impl FnOnce(u8) + '_> {
+		let state = if self.empty_avail() {
+			PipePoll::AwaitingPull
+		} else {
+			PipePoll::Empty
+		};
+		self.push().map_or(state, PipePoll::Ready)
+	}
+
+	/// As [`empty`](Deserializer::empty()), but [`Empty`](PipePoll::Empty) rather than [`None`].
+	pub fn poll_empty(&mut self) -> PipePoll<impl FnOnce() + '_> {
+		self.empty().map_or(PipePoll::Empty, PipePoll::Ready)
+	}
+}
@@ -0,0 +1,357 @@
+//! Rendering of buffered bincode frames as JSON-ish text, for [`Deserializer::dump()`](crate::Deserializer::dump()).
+//!
+//! bincode isn't self-describing, so the registered type's [`Deserialize`](serde::de::Deserialize) impl drives decoding, through a proxy that records each value it's handed, along with the field and variant names it supplies.
+
+use crate::wire;
+use serde::de::{self, DeserializeSeed};
+use std::{cell::RefCell, convert::TryFrom, fmt, fmt::Write};
+
+/// A monomorphised [`render()`], recorded when the type of the next frame is supplied.
+pub(crate) type Render = fn(&[u8]) -> Option<String>;
+
+/// Render `bytes`, a bincode-encoded `T`, as JSON-ish text. [`None`] if they don't decode.
+pub(crate) fn render<T: de::DeserializeOwned>(bytes: &[u8]) -> Option<String> {
+	let out = RefCell::new(String::new());
+	let options = wire::options();
+	let mut deserializer = bincode::Deserializer::from_slice(bytes, options);
+	let _: T = de::Deserialize::deserialize(Proxy {
+		de: &mut deserializer,
+		out: &out,
+		variants: None,
+	})
+	.ok()?;
+	Some(out.into_inner())
+}
+
+fn write(out: &RefCell<String>, args: fmt::Arguments) {
+	out.borrow_mut().write_fmt(args).unwrap();
+}
+
+struct Proxy<'a, D> {
+	de: D,
+	out: &'a RefCell<String>,
+	variants: Option<&'static [&'static str]>,
+}
+impl<'a, D> Proxy<'a, D> {
+	fn visit<V>(&self, visitor: V, fields: Option<&'static [&'static str]>) -> Visit<'a, V> {
+		Visit {
+			visitor,
+			out: self.out,
+			fields,
+			variants: self.variants,
+		}
+	}
+}
+macro_rules! forward {
+	($($method:ident($($arg:ident: $ty:ty),*))*) => {$(
+		fn $method<V: de::Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+			let visitor = self.visit(visitor, None);
+			self.de.$method($($arg,)* visitor)
+		}
+	)*};
+}
+impl<'de, D: de::Deserializer<'de>> de::Deserializer<'de> for Proxy<'_, D> {
+	type Error = D::Error;
+
+	forward! {
+		deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32()
+		deserialize_i64() deserialize_i128() deserialize_u8() deserialize_u16() deserialize_u32()
+		deserialize_u64() deserialize_u128() deserialize_f32() deserialize_f64() deserialize_char()
+		deserialize_str() deserialize_string() deserialize_bytes() deserialize_byte_buf()
+		deserialize_option() deserialize_unit() deserialize_unit_struct(name: &'static str)
+		deserialize_newtype_struct(name: &'static str) deserialize_seq() deserialize_tuple(len: usize)
+		deserialize_tuple_struct(name: &'static str, len: usize) deserialize_map()
+		deserialize_identifier() deserialize_ignored_any()
+	}
+
+	fn deserialize_struct<V: de::Visitor<'de>>(
+		self, name: &'static str, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, D::Error> {
+		let visitor = self.visit(visitor, Some(fields));
+		self.de.deserialize_struct(name, fields, visitor)
+	}
+
+	fn deserialize_enum<V: de::Visitor<'de>>(
+		self, name: &'static str, variants: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, D::Error> {
+		let mut visitor = self.visit(visitor, None);
+		visitor.variants = Some(variants);
+		self.de.deserialize_enum(name, variants, visitor)
+	}
+
+	fn is_human_readable(&self) -> bool {
+		self.de.is_human_readable()
+	}
+}
+
+struct Visit<'a, V> {
+	visitor: V,
+	out: &'a RefCell<String>,
+	fields: Option<&'static [&'static str]>,
+	variants: Option<&'static [&'static str]>,
+}
+macro_rules! record {
+	($($method:ident($ty:ty) $fmt:literal)*) => {$(
+		fn $method<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+			write(self.out, format_args!($fmt, v));
+			self.visitor.$method(v)
+		}
+	)*};
+}
+impl<'de, V: de::Visitor<'de>> de::Visitor<'de> for Visit<'_, V> {
+	type Value = V::Value;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.visitor.expecting(f)
+	}
+
+	record! {
+		visit_bool(bool) "{}" visit_i8(i8) "{}" visit_i16(i16) "{}" visit_i32(i32) "{}"
+		visit_i64(i64) "{}" visit_i128(i128) "{}" visit_u8(u8) "{}" visit_u16(u16) "{}"
+		visit_f32(f32) "{}" visit_f64(f64) "{}" visit_char(char) "{:?}" visit_str(&str) "{:?}"
+		visit_bytes(&[u8]) "{:?}" visit_u128(u128) "{}"
+	}
+
+	fn visit_u32<E: de::Error>(self, v: u32) -> Result<V::Value, E> {
+		self.visit_u64(v.into())
+	}
+	fn visit_u64<E: de::Error>(self, v: u64) -> Result<V::Value, E> {
+		match self
+			.variants
+			.and_then(|variants| variants.get(usize::try_from(v).ok()?))
+		{
+			Some(variant) => write(self.out, format_args!("{variant:?}: ")),
+			None => write(self.out, format_args!("{v}")),
+		}
+		self.visitor.visit_u64(v)
+	}
+	fn visit_string<E: de::Error>(self, v: String) -> Result<V::Value, E> {
+		write(self.out, format_args!("{v:?}"));
+		self.visitor.visit_string(v)
+	}
+	fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<V::Value, E> {
+		write(self.out, format_args!("{v:?}"));
+		self.visitor.visit_byte_buf(v)
+	}
+	fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+		write(self.out, format_args!("null"));
+		self.visitor.visit_none()
+	}
+	fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+		write(self.out, format_args!("null"));
+		self.visitor.visit_unit()
+	}
+	fn visit_some<D: de::Deserializer<'de>>(self, de: D) -> Result<V::Value, D::Error> {
+		self.visitor.visit_some(Proxy {
+			de,
+			out: self.out,
+			variants: None,
+		})
+	}
+	fn visit_newtype_struct<D: de::Deserializer<'de>>(self, de: D) -> Result<V::Value, D::Error> {
+		self.visitor.visit_newtype_struct(Proxy {
+			de,
+			out: self.out,
+			variants: None,
+		})
+	}
+	fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+		let (open, close) = if self.fields.is_some() {
+			("{", "}")
+		} else {
+			("[", "]")
+		};
+		write(self.out, format_args!("{open}"));
+		let ret = self.visitor.visit_seq(Seq {
+			inner: seq,
+			out: self.out,
+			fields: self.fields,
+			index: 0,
+		})?;
+		write(self.out, format_args!("{close}"));
+		Ok(ret)
+	}
+	fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+		write(self.out, format_args!("{{"));
+		let ret = self.visitor.visit_map(Map {
+			inner: map,
+			out: self.out,
+			index: 0,
+		})?;
+		write(self.out, format_args!("}}"));
+		Ok(ret)
+	}
+	fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+		write(self.out, format_args!("{{"));
+		let ret = self.visitor.visit_enum(Enum {
+			data,
+			out: self.out,
+			variants: self.variants,
+		})?;
+		write(self.out, format_args!("}}"));
+		Ok(ret)
+	}
+}
+
+/// Writes `prefix` before the value, if there is one.
+struct Seed<'a, S> {
+	inner: S,
+	out: &'a RefCell<String>,
+	prefix: String,
+	variants: Option<&'static [&'static str]>,
+}
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Seed<'_, S> {
+	type Value = S::Value;
+
+	fn deserialize<D: de::Deserializer<'de>>(self, de: D) -> Result<S::Value, D::Error> {
+		write(self.out, format_args!("{}", self.prefix));
+		self.inner.deserialize(Proxy {
+			de,
+			out: self.out,
+			variants: self.variants,
+		})
+	}
+}
+
+struct Seq<'a, A> {
+	inner: A,
+	out: &'a RefCell<String>,
+	fields: Option<&'static [&'static str]>,
+	index: usize,
+}
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for Seq<'_, A> {
+	type Error = A::Error;
+
+	fn next_element_seed<S: DeserializeSeed<'de>>(
+		&mut self, seed: S,
+	) -> Result<Option<S::Value>, A::Error> {
+		let mut prefix = String::from(if self.index != 0 { ", " } else { "" });
+		if let Some(field) = self.fields.and_then(|fields| fields.get(self.index)) {
+			write!(prefix, "{field:?}: ").unwrap();
+		}
+		self.index += 1;
+		self.inner.next_element_seed(Seed {
+			inner: seed,
+			out: self.out,
+			prefix,
+			variants: None,
+		})
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		self.inner.size_hint()
+	}
+}
+
+struct Map<'a, A> {
+	inner: A,
+	out: &'a RefCell<String>,
+	index: usize,
+}
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for Map<'_, A> {
+	type Error = A::Error;
+
+	fn next_key_seed<S: DeserializeSeed<'de>>(
+		&mut self, seed: S,
+	) -> Result<Option<S::Value>, A::Error> {
+		let prefix = String::from(if self.index != 0 { ", " } else { "" });
+		self.index += 1;
+		self.inner.next_key_seed(Seed {
+			inner: seed,
+			out: self.out,
+			prefix,
+			variants: None,
+		})
+	}
+
+	fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, A::Error> {
+		self.inner.next_value_seed(Seed {
+			inner: seed,
+			out: self.out,
+			prefix: String::from(": "),
+			variants: None,
+		})
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		self.inner.size_hint()
+	}
+}
+
+struct Enum<'a, A> {
+	data: A,
+	out: &'a RefCell<String>,
+	variants: Option<&'static [&'static str]>,
+}
+impl<'a, 'de, A: de::EnumAccess<'de>> de::EnumAccess<'de> for Enum<'a, A> {
+	type Error = A::Error;
+	type Variant = Variant<'a, A::Variant>;
+
+	fn variant_seed<S: DeserializeSeed<'de>>(
+		self, seed: S,
+	) -> Result<(S::Value, Self::Variant), A::Error> {
+		let (value, variant) = self.data.variant_seed(Seed {
+			inner: seed,
+			out: self.out,
+			prefix: String::new(),
+			variants: self.variants,
+		})?;
+		Ok((
+			value,
+			Variant {
+				inner: variant,
+				out: self.out,
+			},
+		))
+	}
+}
+
+struct Variant<'a, A> {
+	inner: A,
+	out: &'a RefCell<String>,
+}
+impl<'de, A: de::VariantAccess<'de>> de::VariantAccess<'de> for Variant<'_, A> {
+	type Error = A::Error;
+
+	fn unit_variant(self) -> Result<(), A::Error> {
+		write(self.out, format_args!("null"));
+		self.inner.unit_variant()
+	}
+
+	fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, A::Error> {
+		self.inner.newtype_variant_seed(Seed {
+			inner: seed,
+			out: self.out,
+			prefix: String::new(),
+			variants: None,
+		})
+	}
+
+	fn tuple_variant<V: de::Visitor<'de>>(
+		self, len: usize, visitor: V,
+	) -> Result<V::Value, A::Error> {
+		self.inner.tuple_variant(
+			len,
+			Visit {
+				visitor,
+				out: self.out,
+				fields: None,
+				variants: None,
+			},
+		)
+	}
+
+	fn struct_variant<V: de::Visitor<'de>>(
+		self, fields: &'static [&'static str], visitor: V,
+	) -> Result<V::Value, A::Error> {
+		self.inner.struct_variant(
+			fields,
+			Visit {
+				visitor,
+				out: self.out,
+				fields: Some(fields),
+				variants: None,
+			},
+		)
+	}
+}
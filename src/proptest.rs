@@ -0,0 +1,121 @@
+//! [proptest](https://docs.rs/proptest) strategies for property-testing code that drives a [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer).
+//!
+//! [`schedule()`] generates random interleavings of pushes, pulls, byte transfers of random sizes, and empties, while [`chunking()`] generates random splittings of a byte stream, for exercising a driver against realistic delivery patterns.
+//!
+//! Requires the `proptest` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use proptest::test_runner::TestRunner;
+//! use serde_pipe::{proptest::{schedule, Op}, Deserializer, Serializer};
+//!
+//! TestRunner::default()
+//!     .run(&schedule(0..100, 8), |ops| {
+//!         let (mut serializer, mut deserializer) = (Serializer::new(), Deserializer::new());
+//!         let (mut pushed, mut pulled) = (0_u32, 0_u32);
+//!         for op in ops {
+//!             match op {
+//!                 Op::Push => {
+//!                     if let Some(push) = serializer.push() {
+//!                         push(pushed);
+//!                         pushed += 1;
+//!                     }
+//!                 }
+//!                 Op::Transfer(n) => {
+//!                     let _ = deserializer.pull::<u32>();
+//!                     for _ in 0..n {
+//!                         match (serializer.pull(), deserializer.push()) {
+//!                             (Some(pull), Some(push)) => push(pull()),
+//!                             _ => break,
+//!                         }
+//!                     }
+//!                 }
+//!                 Op::Pull => {
+//!                     if let Some(pull) = deserializer.pull::<u32>() {
+//!                         assert_eq!(pull(), pulled);
+//!                         pulled += 1;
+//!                     }
+//!                 }
+//!                 Op::Empty => {
+//!                     if let Some(empty) = serializer.empty() {
+//!                         empty();
+//!                     }
+//!                     if let Some(empty) = deserializer.empty() {
+//!                         empty();
+//!                     }
+//!                     pulled = pushed;
+//!                 }
+//!             }
+//!         }
+//!         let _ = (serializer.empty().map(|empty| empty()), deserializer.empty().map(|empty| empty()));
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use ::proptest::{collection::SizeRange, prelude::*};
+
+/// An operation in a [`schedule()`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Op {
+	/// Push a value to the Serializer, if it's ready.
+	Push,
+	/// Transfer up to this many bytes from the Serializer to the Deserializer, as far as both are ready.
+	Transfer(usize),
+	/// Pull a value from the Deserializer, if it's ready.
+	Pull,
+	/// Empty both pipes, as when a connection is reset.
+	Empty,
+}
+
+/// A strategy for a single [`Op`], with transfers of between 1 and `max_chunk` bytes. Empties are rare relative to the other operations.
+///
+/// # Panics
+///
+/// Will panic if `max_chunk` is zero.
+#[track_caller]
+pub fn op(max_chunk: usize) -> impl Strategy<Value = Op> {
+	assert_ne!(max_chunk, 0);
+	prop_oneof![
+		10 => Just(Op::Push),
+		10 => (1..=max_chunk).prop_map(Op::Transfer),
+		10 => Just(Op::Pull),
+		1 => Just(Op::Empty),
+	]
+}
+
+/// A strategy for a sequence of [`Op`]s, with length in `len` and transfers of between 1 and `max_chunk` bytes.
+///
+/// # Panics
+///
+/// Will panic if `max_chunk` is zero.
+#[track_caller]
+pub fn schedule(len: impl Into<SizeRange>, max_chunk: usize) -> impl Strategy<Value = Vec<Op>> {
+	::proptest::collection::vec(op(max_chunk), len)
+}
+
+/// A strategy for splitting `len` bytes into chunks of between 1 and `max_chunk` bytes, summing to `len`.
+///
+/// # Panics
+///
+/// Will panic if `max_chunk` is zero.
+#[track_caller]
+pub fn chunking(len: usize, max_chunk: usize) -> impl Strategy<Value = Vec<usize>> {
+	assert_ne!(max_chunk, 0);
+	::proptest::collection::vec(1..=max_chunk, len).prop_map(move |sizes| {
+		let mut remaining = len;
+		sizes
+			.into_iter()
+			.map_while(|size| {
+				let size = size.min(remaining);
+				remaining -= size;
+				if size != 0 {
+					Some(size)
+				} else {
+					None
+				}
+			})
+			.collect()
+	})
+}
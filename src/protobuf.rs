@@ -0,0 +1,223 @@
+//! Protobuf pipes: each value is a [`prost`] message with protobuf's standard varint length-delimited framing.
+//!
+//! These have the same push/pull discipline as [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer), but the stream is byte-for-byte compatible with `writeDelimitedTo`/`parseDelimitedFrom` and other protobuf stream consumers.
+//!
+//! Requires the `prost` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::protobuf::{ProtobufDeserializer, ProtobufSerializer};
+//!
+//! let mut serializer = ProtobufSerializer::new();
+//! serializer.push().unwrap()(String::from("hi"));
+//!
+//! let mut deserializer = ProtobufDeserializer::new();
+//! let mut frame = Vec::new();
+//! while let Some(pull) = serializer.pull() {
+//!     let byte = pull();
+//!     frame.push(byte);
+//!     deserializer.push().unwrap()(byte);
+//! }
+//! assert_eq!(frame, [4, 10, 2, b'h', b'i']);
+//! assert_eq!(deserializer.pull::<String>().unwrap()(), "hi");
+//! ```
+
+use std::fmt;
+
+/// Protobuf serializer pipe: push `T`; pull `u8`.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`ProtobufSerializer::empty()`] before dropping it.
+pub struct ProtobufSerializer {
+	buffer: Option<(Box<[u8]>, usize)>,
+}
+impl ProtobufSerializer {
+	/// Construct a new `ProtobufSerializer` pipe.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self { buffer: None }
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.buffer.is_none()
+	}
+	/// Push a `T`. [`None`] denotes that the `ProtobufSerializer` is instead awaiting a [`pull`](ProtobufSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push<T: prost::Message + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.buffer.is_none() {
+			Some(move |t: T| {
+				let vec = t.encode_length_delimited_to_vec();
+				self.buffer = Some((vec.into_boxed_slice(), 0));
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Pull a `u8`. [`None`] denotes that the `ProtobufSerializer` is instead awaiting a [`push`](ProtobufSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				let Some((buffer, index)) = &mut self.buffer else {
+					unreachable!()
+				};
+				let ret = buffer[*index];
+				*index += 1;
+				if *index == buffer.len() {
+					self.buffer = None;
+				}
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.buffer.is_some()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.buffer.is_some() {
+			Some(move || {
+				self.buffer = None;
+			})
+		} else {
+			None
+		}
+	}
+}
+impl Drop for ProtobufSerializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.buffer.is_none() || std::thread::panicking(),
+			"ProtobufSerializer dropped with a frame unpulled; empty() it first to discard it"
+		);
+	}
+}
+impl fmt::Debug for ProtobufSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProtobufSerializer")
+			.field("buffer", &self.buffer)
+			.finish()
+	}
+}
+
+/// Protobuf deserializer pipe: push `u8`; pull `T`.
+///
+/// As frames are delimited by their varint length prefix, bytes can be pushed before the type to be deserialized to has been specified.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`ProtobufDeserializer::empty()`] before dropping it.
+pub struct ProtobufDeserializer {
+	buffer: Vec<u8>,
+	len: Option<usize>,
+}
+impl ProtobufDeserializer {
+	/// Construct a new `ProtobufDeserializer` pipe.
+	#[must_use]
+	#[inline(always)]
+	pub const fn new() -> Self {
+		Self {
+			buffer: Vec::new(),
+			len: None,
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.len == Some(self.buffer.len())
+	}
+	/// Pull a `T`. [`None`] denotes that the `ProtobufDeserializer` is instead awaiting a [`push`](ProtobufDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the frame isn't a valid encoding of `T`.
+	pub fn pull<T: prost::Message + Default + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let ret = T::decode(&*self.buffer).unwrap();
+				self.buffer.clear();
+				self.len = None;
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		!self.pull_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that the `ProtobufDeserializer` is instead awaiting a [`pull`](ProtobufDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// The returned closure will panic if the length prefix is invalid.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.push_avail() {
+			Some(move |x| {
+				self.buffer.push(x);
+				if self.len.is_none() && x & 0x80 == 0 {
+					self.len = Some(prost::decode_length_delimiter(&*self.buffer).unwrap());
+					self.buffer.clear();
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.buffer.is_empty() || self.len.is_some()
+	}
+	/// Empty this pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				self.buffer.clear();
+				self.len = None;
+			})
+		} else {
+			None
+		}
+	}
+}
+impl Drop for ProtobufDeserializer {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			!self.empty_avail() || std::thread::panicking(),
+			"ProtobufDeserializer dropped with {} bytes of a frame buffered; empty() it first to discard them",
+			self.buffer.len()
+		);
+	}
+}
+impl fmt::Debug for ProtobufDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProtobufDeserializer")
+			.field("buffer", &self.buffer)
+			.field("len", &self.len)
+			.finish()
+	}
+}
@@ -0,0 +1,59 @@
+//! Lossy quantization of floats to fixed point, for telemetry where bandwidth matters more than exact values.
+//!
+//! Wrapping a value in [`Quantized<T, BITS, FRAC>`](Quantized) encodes every `f32` and `f64` it contains as a signed `BITS`-bit fixed-point number with `FRAC` fractional bits, rounding to the nearest multiple of 2<sup>-`FRAC`</sup>, and saturating outside ±2<sup>`BITS`-`FRAC`-1</sup>. NaN is encoded as zero. `BITS` must be 8, 16, 32 or 64, and `FRAC` less than 64.
+//!
+//! The value is otherwise encoded as with [`packed::Packed`](crate::packed::Packed), so its bools, option tags and enum variant indices are also packed into bits.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::quantize::Quantized;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Telemetry {
+//!     altitude: f32,
+//!     heading: f64,
+//! }
+//!
+//! let telemetry = Telemetry { altitude: 12.34, heading: -90.0 };
+//! let vec = serde_pipe::serialize_to_vec(Quantized::<_, 16, 6>(telemetry));
+//! let Quantized(telemetry) = serde_pipe::deserialize_from_slice::<Quantized<Telemetry, 16, 6>>(&vec).unwrap();
+//! assert_eq!(telemetry, Telemetry { altitude: 12.34375, heading: -90.0 });
+//! ```
+
+use crate::packed::{self, Fixed};
+use serde::{de, ser};
+
+/// A `T` whose floats serialize as `BITS`-bit fixed point with `FRAC` fractional bits.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Quantized<T, const BITS: u32 = 16, const FRAC: u32 = 8>(pub T);
+impl<T, const BITS: u32, const FRAC: u32> Quantized<T, BITS, FRAC> {
+	fn fixed() -> Result<Fixed, &'static str> {
+		if !matches!(BITS, 8 | 16 | 32 | 64) {
+			return Err("Quantized BITS must be 8, 16, 32 or 64");
+		}
+		if FRAC >= 64 {
+			return Err("Quantized FRAC must be less than 64");
+		}
+		Ok(Fixed {
+			bits: BITS,
+			frac: FRAC,
+		})
+	}
+}
+impl<T: ser::Serialize, const BITS: u32, const FRAC: u32> ser::Serialize
+	for Quantized<T, BITS, FRAC>
+{
+	fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let fixed = Self::fixed().map_err(ser::Error::custom)?;
+		packed::serialize_with(&self.0, serializer, Some(fixed))
+	}
+}
+impl<'de, T: de::DeserializeOwned, const BITS: u32, const FRAC: u32> de::Deserialize<'de>
+	for Quantized<T, BITS, FRAC>
+{
+	fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let fixed = Self::fixed().map_err(de::Error::custom)?;
+		packed::deserialize_with(deserializer, Some(fixed)).map(Quantized)
+	}
+}
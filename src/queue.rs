@@ -0,0 +1,154 @@
+//! Queueing multiple frames on a [`Serializer`].
+//!
+//! [`QueuedSerializer`] accepts pushes while a frame is still being pulled, holding the values until the [`Serializer`] is ready for them. Pushes can carry a priority, so that urgent messages jump ahead of bulk data that hasn't yet begun transmission.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{queue::QueuedSerializer, Deserializer, Serializer};
+//!
+//! let mut serializer = QueuedSerializer::new(Serializer::new(), 16);
+//! serializer.push().unwrap()(String::from("bulk 1"));
+//! serializer.push().unwrap()(String::from("bulk 2"));
+//! serializer.push_with_priority(1).unwrap()(String::from("urgent"));
+//!
+//! let mut deserializer = Deserializer::new();
+//! let mut received = Vec::new();
+//! while received.len() < 3 {
+//!     let _ = deserializer.pull::<String>();
+//!     while let (Some(pull), Some(push)) = (serializer.pull(), deserializer.push()) {
+//!         push(pull());
+//!     }
+//!     received.push(deserializer.pull::<String>().unwrap()());
+//! }
+//! assert_eq!(received, ["bulk 1", "urgent", "bulk 2"]);
+//! ```
+
+use crate::Serializer;
+use std::{
+	fmt,
+	time::{Duration, Instant},
+};
+
+/// A [`Serializer`] that queues up to `capacity` pushed values while a frame is in flight.
+///
+/// Values are serialized in order of priority, highest first, and in the order pushed for equal priorities. A frame that has begun transmission is never interrupted. The values are held in the [`Serializer`]'s own queue, as used by [`Serializer::push_or_queue()`], so they're serialized as they're queued, or with the `fringe` feature, held unserialized until their turn.
+///
+/// # Panics
+///
+/// Will panic if dropped while non-empty, as with [`Serializer`]. If you do want to drop it when non-empty, call [`QueuedSerializer::empty()`] before dropping it.
+pub struct QueuedSerializer {
+	serializer: Serializer,
+}
+impl QueuedSerializer {
+	/// Wrap `serializer`, queueing up to `capacity` values. This sets its [`queue_limit`](Serializer::set_queue_limit()).
+	#[must_use]
+	pub fn new(mut serializer: Serializer, capacity: usize) -> Self {
+		serializer.set_queue_limit(capacity);
+		Self { serializer }
+	}
+
+	/// The number of values queued, not including any frame in flight.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.serializer.queued()
+	}
+
+	/// Whether no values are queued, not including any frame in flight.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.queue_avail()
+	}
+	/// Push a `T` with priority `0`. [`None`] denotes that the queue is full, and is instead awaiting a [`pull`](QueuedSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push<T: serde::ser::Serialize + Send + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce(T) + '_> {
+		self.push_with_priority(0)
+	}
+	/// Push a `T` with the given priority; higher priorities are serialized first. [`None`] denotes that the queue is full, and is instead awaiting a [`pull`](QueuedSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push_with_priority<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, priority: i32,
+	) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				// there's room, as checked by push_avail
+				let _ = self.serializer.push_or_queue_until(t, priority, None);
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Push a `T` with the given priority, as with [`push_with_priority`](QueuedSerializer::push_with_priority()), but if it's queued, and hasn't started to be pulled within `ttl`, drop it unsent, counting it in [`expired`](QueuedSerializer::expired()), as with [`Serializer::push_or_queue_with_ttl()`]. [`None`] denotes that the queue is full of unexpired values, and is instead awaiting a [`pull`](QueuedSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push_with_ttl<T: serde::ser::Serialize + Send + 'static>(
+		&mut self, priority: i32, ttl: Duration,
+	) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				// there's room, as checked by push_avail
+				let _ = self
+					.serializer
+					.push_or_queue_until(t, priority, Some(Instant::now() + ttl));
+			})
+		} else {
+			None
+		}
+	}
+
+	/// The number of values queued by [`push_with_ttl`](QueuedSerializer::push_with_ttl()) that were dropped unsent as their TTL passed.
+	#[must_use]
+	pub fn expired(&self) -> usize {
+		self.serializer.expired()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that nothing is queued, and it's instead awaiting a [`push`](QueuedSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the queue and the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.serializer.empty()
+	}
+
+	/// Consume this pipe, returning an iterator over the remaining frames, each as a `Vec<u8>`. If a frame is in flight, the first item is the remainder of it.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use serde_pipe::{queue::QueuedSerializer, Serializer};
+	///
+	/// let mut serializer = QueuedSerializer::new(Serializer::new(), usize::MAX);
+	/// for x in 0..3_u8 {
+	///     serializer.push().unwrap()(x);
+	/// }
+	/// let frames = serializer.into_frames().collect::<Vec<_>>();
+	/// assert_eq!(frames.len(), 3);
+	/// ```
+	pub fn into_frames(mut self) -> impl Iterator<Item = Vec<u8>> {
+		std::iter::from_fn(move || self.serializer.pull_frame().map(|pull| pull()))
+	}
+}
+impl fmt::Debug for QueuedSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("QueuedSerializer")
+			.field("serializer", &self.serializer)
+			.finish()
+	}
+}
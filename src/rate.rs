@@ -0,0 +1,158 @@
+//! Per-type rate limiting of pushes to a [`Serializer`].
+//!
+//! [`RateLimitedSerializer`] consults a [`TokenBucket`] for each type that has been [`limit`](RateLimitedSerializer::limit())ed, reporting not-ready for pushes of that type while its bucket is empty. This stops chatty messages from starving other traffic sharing the same pipe.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{rate::{RateLimitedSerializer, TokenBucket}, Serializer};
+//! use std::time::Duration;
+//!
+//! let mut serializer = RateLimitedSerializer::new(Serializer::new());
+//! serializer.limit::<String>(TokenBucket::new(1, Duration::from_secs(60)));
+//!
+//! serializer.push().unwrap()(String::from("chatty"));
+//! while let Some(pull) = serializer.pull() {
+//!     let _ = pull();
+//! }
+//! assert!(serializer.push::<String>().is_none()); // rate limited
+//! assert!(serializer.push::<u32>().is_some()); // not limited
+//! ```
+
+use crate::Serializer;
+use std::{
+	any::TypeId, collections::HashMap, convert::TryFrom, fmt, time::{Duration, Instant}
+};
+
+/// A token bucket: holding up to `capacity` tokens, refilled at one token per `interval`.
+#[derive(Copy, Clone, Debug)]
+pub struct TokenBucket {
+	capacity: u32,
+	interval: Duration,
+	tokens: u32,
+	last: Instant,
+}
+impl TokenBucket {
+	/// Construct a new, full, `TokenBucket` holding up to `capacity` tokens and refilled at one token per `interval`.
+	#[must_use]
+	pub fn new(capacity: u32, interval: Duration) -> Self {
+		Self {
+			capacity,
+			interval,
+			tokens: capacity,
+			last: Instant::now(),
+		}
+	}
+
+	/// The number of tokens available at `now`.
+	pub fn tokens(&mut self, now: Instant) -> u32 {
+		self.refill(now);
+		self.tokens
+	}
+
+	/// Take a token, if one is available at `now`.
+	pub fn take(&mut self, now: Instant) -> bool {
+		self.refill(now);
+		if self.tokens != 0 {
+			self.tokens -= 1;
+			true
+		} else {
+			false
+		}
+	}
+
+	fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.last);
+		let refill = elapsed.as_nanos() / self.interval.as_nanos().max(1);
+		match u32::try_from(refill) {
+			Ok(refill) if refill < self.capacity - self.tokens => {
+				self.tokens += refill;
+				self.last += self.interval * refill;
+			}
+			_ => {
+				self.tokens = self.capacity;
+				self.last = now;
+			}
+		}
+	}
+}
+
+/// A [`Serializer`] that rate limits pushes of particular types with a [`TokenBucket`] each.
+pub struct RateLimitedSerializer {
+	serializer: Serializer,
+	buckets: HashMap<TypeId, TokenBucket>,
+}
+impl RateLimitedSerializer {
+	/// Wrap `serializer`, initially with no types limited.
+	#[must_use]
+	pub fn new(serializer: Serializer) -> Self {
+		Self {
+			serializer,
+			buckets: HashMap::new(),
+		}
+	}
+
+	/// Limit pushes of `T` with `bucket`, replacing any existing limit.
+	pub fn limit<T: 'static>(&mut self, bucket: TokenBucket) {
+		let _ = self.buckets.insert(TypeId::of::<T>(), bucket);
+	}
+
+	/// Remove any limit on pushes of `T`.
+	pub fn unlimit<T: 'static>(&mut self) {
+		let _ = self.buckets.remove(&TypeId::of::<T>());
+	}
+
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](RateLimitedSerializer::pull()), or that `T` is rate limited and its bucket is empty. [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// A token is only taken when the push is performed.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		let now = Instant::now();
+		let mut bucket = self.buckets.get_mut(&TypeId::of::<T>());
+		let limited = match bucket {
+			Some(ref mut bucket) => bucket.tokens(now) == 0,
+			None => false,
+		};
+		if limited {
+			None
+		} else {
+			self.serializer.push().map(move |push| {
+				move |t| {
+					if let Some(bucket) = bucket {
+						// there's a token, as checked above
+						let _ = bucket.take(now);
+					}
+					push(t);
+				}
+			})
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](RateLimitedSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.serializer.empty()
+	}
+
+	/// Unwrap, returning the underlying [`Serializer`].
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl fmt::Debug for RateLimitedSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("RateLimitedSerializer")
+			.field("serializer", &self.serializer)
+			.field("buckets", &self.buckets.len())
+			.finish()
+	}
+}
@@ -0,0 +1,238 @@
+//! Recording streams of frames with a sidecar index, for random access on replay.
+//!
+//! [`Recorder`] writes each value to a journal as a frame, and appends an [`Entry`] to a separate index: the frame's offset and length in the journal, its type, and when it was recorded. [`Replay`] reads the index back, so a replay can [`seek`](Replay::seek()) straight to the `n`th frame, or to the first recorded at or after a given time, and resume deserializing from there, rather than scanning the journal from the start.
+//!
+//! Frames in the journal are exactly as a [`Serializer`](crate::Serializer) emits them, so a journal can also be read sequentially with a [`Deserializer`](crate::Deserializer). An index entry left incomplete by a crash is ignored.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::record::{Recorder, Replay};
+//! use std::{io::Cursor, time::{Duration, UNIX_EPOCH}};
+//!
+//! let mut recorder = Recorder::new(Vec::new(), Vec::new());
+//! for i in 0..100_u64 {
+//!     recorder.record_at(i, UNIX_EPOCH + Duration::from_secs(i)).unwrap();
+//! }
+//! let (journal, index) = recorder.into_inner();
+//!
+//! let mut replay = Replay::new(Cursor::new(journal), &*index).unwrap();
+//! replay.seek(40).unwrap();
+//! assert_eq!(replay.pull::<u64>().unwrap(), Some(40));
+//! replay.seek_time(UNIX_EPOCH + Duration::from_millis(72_500));
+//! assert_eq!(replay.pull::<u64>().unwrap(), Some(73));
+//! ```
+
+use crate::wire;
+use std::{
+	any, convert::TryFrom, fmt, io::{self, Read, Seek, SeekFrom, Write}, time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+/// The index entry for a recorded frame.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Entry {
+	/// The offset of the frame in the journal.
+	pub offset: u64,
+	/// The length of the frame, header included.
+	pub len: u64,
+	/// The name of the frame's type, as returned by [`std::any::type_name()`].
+	pub type_name: String,
+	/// When the frame was recorded.
+	pub time: SystemTime,
+}
+
+/// Records values to a journal `W`, indexing them in `I`.
+pub struct Recorder<W, I> {
+	journal: W,
+	index: I,
+	offset: u64,
+}
+impl<W: Write, I: Write> Recorder<W, I> {
+	/// Record to the start of `journal`, indexing in `index`.
+	pub fn new(journal: W, index: I) -> Self {
+		Self::with_offset(journal, index, 0)
+	}
+
+	/// Record to `journal`, which already holds `offset` bytes, indexing in `index`. For appending to an existing recording.
+	pub fn with_offset(journal: W, index: I, offset: u64) -> Self {
+		Self {
+			journal,
+			index,
+			offset,
+		}
+	}
+
+	/// Record a `T`, timestamped now.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to the journal or index.
+	pub fn record<T: serde::ser::Serialize + 'static>(&mut self, t: T) -> io::Result<()> {
+		self.record_at(t, SystemTime::now())
+	}
+
+	/// Record a `T`, timestamped `time`. Timestamps should be non-decreasing, for [`Replay::seek_time()`].
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to the journal or index.
+	///
+	/// # Panics
+	///
+	/// Will panic if `time` is before the Unix epoch.
+	pub fn record_at<T: serde::ser::Serialize + 'static>(
+		&mut self, t: T, time: SystemTime,
+	) -> io::Result<()> {
+		let frame = crate::serialize_to_vec(t);
+		self.journal.write_all(&frame)?;
+		let nanos = time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+		let entry = (
+			self.offset,
+			frame.len() as u64,
+			any::type_name::<T>(),
+			u64::try_from(nanos).unwrap(),
+		);
+		self.index.write_all(&wire::serialize(&entry).unwrap())?;
+		self.offset += frame.len() as u64;
+		Ok(())
+	}
+
+	/// Flush the journal and index.
+	///
+	/// # Errors
+	///
+	/// Returns any error flushing either.
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.journal.flush()?;
+		self.index.flush()
+	}
+
+	/// Unwrap, returning the journal and index.
+	pub fn into_inner(self) -> (W, I) {
+		(self.journal, self.index)
+	}
+}
+impl<W, I> fmt::Debug for Recorder<W, I> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Recorder")
+			.field("offset", &self.offset)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Replays the frames of a journal `R`, seeking by its index.
+pub struct Replay<R> {
+	journal: R,
+	entries: Vec<Entry>,
+	next: usize,
+}
+impl<R: Read + Seek> Replay<R> {
+	/// Replay `journal` from its first frame, reading its entries from `index`.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading the index.
+	pub fn new<I: Read>(journal: R, index: I) -> io::Result<Self> {
+		let mut index = io::BufReader::new(index);
+		let mut entries = Vec::new();
+		loop {
+			match wire::deserialize_from::<_, (u64, u64, String, u64)>(&mut index) {
+				Ok((offset, len, type_name, nanos)) => entries.push(Entry {
+					offset,
+					len,
+					type_name,
+					time: UNIX_EPOCH + Duration::from_nanos(nanos),
+				}),
+				Err(err) => match *err {
+					bincode::ErrorKind::Io(ref err)
+						if err.kind() == io::ErrorKind::UnexpectedEof =>
+					{
+						break
+					}
+					bincode::ErrorKind::Io(err) => return Err(err),
+					err => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+				},
+			}
+		}
+		Ok(Self {
+			journal,
+			entries,
+			next: 0,
+		})
+	}
+
+	/// The index entries, one per recorded frame.
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// The position of the next frame to be replayed.
+	pub fn position(&self) -> usize {
+		self.next
+	}
+
+	/// Seek to the `n`th frame, counting from zero, so that it's replayed next. Seeking to the end is allowed, after which nothing remains to be replayed.
+	///
+	/// # Errors
+	///
+	/// Returns [`io::ErrorKind::InvalidInput`] if `n` is beyond the end.
+	pub fn seek(&mut self, n: usize) -> io::Result<()> {
+		if n > self.entries.len() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"seek beyond the last frame",
+			));
+		}
+		self.next = n;
+		Ok(())
+	}
+
+	/// Seek to the first frame recorded at or after `time`, so that it's replayed next, or to the end if there's none.
+	pub fn seek_time(&mut self, time: SystemTime) {
+		self.next = self.entries.partition_point(|entry| entry.time < time);
+	}
+
+	/// Pull the next frame as a `T`. [`None`] denotes that every frame has been replayed.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading the journal, or [`io::ErrorKind::InvalidData`] if the journal doesn't hold a complete frame where indexed.
+	///
+	/// # Panics
+	///
+	/// Will panic if the frame is too long to fit in memory, or can't be decoded as a `T`.
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(&mut self) -> io::Result<Option<T>> {
+		if self.next == self.entries.len() {
+			return Ok(None);
+		}
+		let entry = &self.entries[self.next];
+		let _ = self.journal.seek(SeekFrom::Start(entry.offset))?;
+		let mut frame = vec![0; usize::try_from(entry.len).unwrap()];
+		self.journal.read_exact(&mut frame)?;
+		let t = crate::deserialize_from_slice(&frame).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"frame {} can't be decoded as a {}",
+					self.next,
+					any::type_name::<T>()
+				),
+			)
+		})?;
+		self.next += 1;
+		Ok(Some(t))
+	}
+
+	/// Unwrap, returning the journal.
+	pub fn into_inner(self) -> R {
+		self.journal
+	}
+}
+impl<R> fmt::Debug for Replay<R> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Replay")
+			.field("entries", &self.entries.len())
+			.field("next", &self.next)
+			.finish_non_exhaustive()
+	}
+}
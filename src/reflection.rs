@@ -0,0 +1,191 @@
+//! Describe the wire layout of the types sent through these pipes, for decoding them in other languages.
+//!
+//! [`Schema`] is a registry of the types sent over a connection. [`Schema::into_registry()`] traces them with [serde-reflection](https://docs.rs/serde-reflection), producing a [`Registry`] of every struct and enum they contain, from which decoders can be generated with, for example, [serde-generate](https://docs.rs/serde-generate).
+//!
+//! Each frame's payload is encoded with bincode's default options: little-endian fixed-width integers, `u64` lengths for sequences, maps and strings, and `u32` variant indices. With the default [`Framing::Bincode`](crate::Framing::Bincode), each payload is preceded by its length as a little-endian `u64`, and a zero-length payload is padded to a single zero byte.
+//!
+//! The `serde-generate` feature adds [`generate()`], writing Python, TypeScript or C++ definitions of these types, along with a helper for reading frames, so other languages can decode what these pipes emit.
+//!
+//! Requires the `serde-reflection` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::Deserialize;
+//! use serde_pipe::reflection::Schema;
+//!
+//! #[derive(Deserialize)]
+//! enum Command {
+//!     Move { x: f32, y: f32 },
+//!     Stop,
+//! }
+//!
+//! let mut schema = Schema::new();
+//! schema.add::<Command>().unwrap();
+//! schema.add::<Vec<u64>>().unwrap();
+//! let registry = schema.into_registry().unwrap();
+//! assert!(registry.contains_key("Command"));
+//! ```
+
+use serde_reflection::{Format, Registry, Tracer, TracerConfig};
+#[cfg(feature = "serde-generate")]
+use std::io;
+use std::any;
+
+/// A registry of the types sent over a connection, whose wire layout can be exported.
+#[derive(Debug)]
+pub struct Schema {
+	tracer: Tracer,
+	frames: Vec<(&'static str, Format)>,
+}
+impl Schema {
+	/// Construct an empty registry.
+	#[must_use]
+	pub fn new() -> Self {
+		Self {
+			tracer: Tracer::new(TracerConfig::default()),
+			frames: Vec::new(),
+		}
+	}
+
+	/// Register `T` as a type sent over the connection, tracing its layout and that of every type it contains.
+	///
+	/// Only the first variant of enums nested within `T` is traced, so they must be added too, otherwise [`into_registry`](Schema::into_registry()) reports them as missing variants.
+	///
+	/// # Errors
+	///
+	/// Returns any error tracing `T`, for example if a hand-written [`Deserialize`](serde::de::Deserialize) impl rejects the placeholder values serde-reflection supplies.
+	pub fn add<T: serde::de::DeserializeOwned>(&mut self) -> serde_reflection::Result<()> {
+		let (format, _) = self.tracer.trace_simple_type::<T>()?;
+		self.frames.push((any::type_name::<T>(), format));
+		Ok(())
+	}
+
+	/// The layout of each registered type, in order of registration, named by [`type_name`](std::any::type_name). Structs and enums are referred to by name, and described in the [`Registry`].
+	#[must_use]
+	pub fn frames(&self) -> &[(&'static str, Format)] {
+		&self.frames
+	}
+
+	/// Consume this registry, returning the layout of every struct and enum contained in the registered types.
+	///
+	/// # Errors
+	///
+	/// Returns an error if a type's layout couldn't be fully determined, for example an enum variant that was never traced.
+	pub fn into_registry(self) -> serde_reflection::Result<Registry> {
+		self.tracer.registry()
+	}
+}
+
+/// A language for [`generate()`] to write definitions in.
+#[cfg(feature = "serde-generate")]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Language {
+	/// Python 3, with a `read_frame(stream)` helper.
+	Python3,
+	/// TypeScript, with a `splitFrame(buffer)` helper.
+	TypeScript,
+	/// C++17, with a `read_frame(istream)` helper.
+	Cpp,
+}
+
+/// Write definitions in `language` of the types in `registry`, with bincode decoding and encoding, followed by a helper that reads a frame's payload, skipping heartbeat and control frames. `module_name` names the generated module or namespace.
+///
+/// The definitions depend on serde-generate's runtime for `language`, which can be written out with its [`Installer`](serde_generate::SourceInstaller)s. Frames are assumed to use the default [`Framing::Bincode`](crate::Framing::Bincode).
+///
+/// # Errors
+///
+/// Returns any error writing to `out`.
+#[cfg(feature = "serde-generate")]
+pub fn generate(
+	registry: &Registry, language: Language, module_name: &str, out: &mut dyn io::Write,
+) -> io::Result<()> {
+	use serde_generate::{cpp, python3, typescript, CodeGeneratorConfig, Encoding};
+	let config =
+		CodeGeneratorConfig::new(module_name.to_owned()).with_encodings(vec![Encoding::Bincode]);
+	match language {
+		Language::Python3 => {
+			python3::CodeGenerator::new(&config).output(out, registry)?;
+			out.write_all(PYTHON3_FRAME.as_bytes())
+		}
+		Language::TypeScript => {
+			typescript::CodeGenerator::new(&config).output(out, registry)?;
+			out.write_all(TYPESCRIPT_FRAME.as_bytes())
+		}
+		Language::Cpp => {
+			cpp::CodeGenerator::new(&config)
+				.output(out, registry)
+				.map_err(|err| io::Error::other(err.to_string()))?;
+			out.write_all(CPP_FRAME.replace("{module_name}", module_name).as_bytes())
+		}
+	}
+}
+
+#[cfg(feature = "serde-generate")]
+const PYTHON3_FRAME: &str = r#"
+def read_frame(stream: typing.BinaryIO) -> typing.Optional[bytes]:
+    """Read the payload of the next serde_pipe frame from `stream`, skipping heartbeat and control frames. Returns None at the end of the stream."""
+    while True:
+        header = stream.read(8)
+        if len(header) < 8:
+            return None
+        length = int.from_bytes(header, "little")
+        if length != 0 and length < 1 << 63:
+            payload = stream.read(length)
+            return payload if len(payload) == length else None
+"#;
+
+#[cfg(feature = "serde-generate")]
+const TYPESCRIPT_FRAME: &str = r"
+/** Split the payload of the first serde_pipe frame from `buffer`, skipping heartbeat and control frames. Returns the payload and the bytes after it, or null if `buffer` doesn't yet hold a whole frame. */
+export function splitFrame(buffer: Uint8Array): [Uint8Array, Uint8Array] | null {
+  const view = new DataView(buffer.buffer, buffer.byteOffset, buffer.byteLength);
+  let offset = 0;
+  while (buffer.length - offset >= 8) {
+    const length = view.getBigUint64(offset, true);
+    offset += 8;
+    if (length === BigInt(0) || length >= BigInt(1) << BigInt(63)) {
+      continue;
+    }
+    if (BigInt(buffer.length - offset) < length) {
+      return null;
+    }
+    const end = offset + Number(length);
+    return [buffer.subarray(offset, end), buffer.subarray(end)];
+  }
+  return null;
+}
+";
+
+#[cfg(feature = "serde-generate")]
+const CPP_FRAME: &str = r"
+#include <cstdint>
+#include <istream>
+#include <optional>
+#include <vector>
+
+namespace {module_name} {
+
+    /// Read the payload of the next serde_pipe frame from `in`, skipping heartbeat and control frames. Returns std::nullopt at the end of the stream.
+    inline std::optional<std::vector<uint8_t>> read_frame(std::istream &in) {
+        for (;;) {
+            uint8_t header[8];
+            if (!in.read(reinterpret_cast<char *>(header), 8)) {
+                return std::nullopt;
+            }
+            uint64_t length = 0;
+            for (int i = 7; i >= 0; i--) {
+                length = length << 8 | header[i];
+            }
+            if (length != 0 && length < uint64_t(1) << 63) {
+                std::vector<uint8_t> payload(length);
+                if (!in.read(reinterpret_cast<char *>(payload.data()), length)) {
+                    return std::nullopt;
+                }
+                return payload;
+            }
+        }
+    }
+
+} // end of namespace {module_name}
+";
@@ -0,0 +1,44 @@
+//! Typed connections over serial ports.
+//!
+//! Serial ports are opened with [serialport](https://docs.rs/serialport) and wrapped in a [`Connection`], either for blocking use with [`open`], or for poll-driven use with [`open_polled`], where calls return [`io::ErrorKind::TimedOut`](std::io::ErrorKind::TimedOut) rather than wait, so a control loop can service the link without a dedicated thread.
+//!
+//! Requires the `serialport` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use serde_pipe::serial;
+//!
+//! let mut connection = serial::open(serialport::new("/dev/ttyUSB0", 115_200)).unwrap();
+//! connection.send((1.5_f32, -0.5_f32)).unwrap();
+//! let odometry: (f64, f64, f64) = connection.recv().unwrap();
+//! ```
+
+use crate::stream::Connection;
+use serialport::{SerialPort, SerialPortBuilder};
+use std::time::Duration;
+
+/// serialport has no infinite timeout, and adds the timeout to [`Instant::now()`](std::time::Instant::now()), so [`Duration::MAX`] would overflow.
+const FOREVER: Duration = Duration::from_secs(u32::MAX as u64);
+
+/// Open a serial port for blocking use: [`send`](Connection::send()) and [`recv`](Connection::recv()) wait as long as necessary.
+///
+/// # Errors
+///
+/// Returns any error opening the port, for example if it doesn't exist or is in use.
+pub fn open(builder: SerialPortBuilder) -> serialport::Result<Connection<Box<dyn SerialPort>>> {
+	Ok(Connection::new(builder.timeout(FOREVER).open()?))
+}
+
+/// Open a serial port for poll-driven use: [`recv`](Connection::recv()) and [`flush`](Connection::flush()) return [`io::ErrorKind::TimedOut`](std::io::ErrorKind::TimedOut) rather than wait for the port, and resume where they left off when next called. Flushing still waits for written bytes to finish transmitting.
+///
+/// As [`send`](Connection::send()) first finishes sending the previous value, and drops its argument if that times out, call [`flush`](Connection::flush()) until it succeeds before each `send`.
+///
+/// # Errors
+///
+/// Returns any error opening the port, for example if it doesn't exist or is in use.
+pub fn open_polled(
+	builder: SerialPortBuilder,
+) -> serialport::Result<Connection<Box<dyn SerialPort>>> {
+	Ok(Connection::new(builder.timeout(Duration::ZERO).open()?))
+}
@@ -0,0 +1,463 @@
+//! Single-producer single-consumer primitives for connecting pipes across contexts.
+//!
+//! [`ring()`] creates a lock-free byte ring, intended to sit between a pipe driven from an interrupt handler and a pipe driven from the main loop (or between two threads). It allocates only on creation, and the hot path is a handful of atomic loads and stores – there are no locks or critical sections.
+//!
+//! [`channel()`] builds a bounded typed channel on a ring, with a [`Serializer`] at one end and a [`Deserializer`] at the other. Its capacity is measured in bytes rather than elements, which suits variable-size messages.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{spsc, Deserializer, Serializer};
+//!
+//! let (mut producer, mut consumer) = spsc::ring(64);
+//!
+//! let mut serializer = Serializer::new();
+//! serializer.push().unwrap()(String::from("hello"));
+//! let mut deserializer = Deserializer::new();
+//! let _ = deserializer.pull::<String>();
+//!
+//! while deserializer.pull::<String>().is_none() {
+//!     let _ = producer.fill_from(&mut serializer);
+//!     let _ = consumer.drain_into(&mut deserializer);
+//! }
+//! assert_eq!(deserializer.pull::<String>().unwrap()(), "hello");
+//! ```
+
+use crate::{Deserializer, Serializer};
+#[cfg(loom)]
+use loom::{
+	sync::{
+		atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering}, Arc, Mutex
+	}, thread::{self, Thread}
+};
+use std::{fmt, marker};
+#[cfg(not(loom))]
+use std::{
+	sync::{
+		atomic::{self, AtomicBool, AtomicU8, AtomicUsize, Ordering}, Arc, Mutex
+	}, thread::{self, Thread}
+};
+
+/// Create a lock-free byte ring able to hold `capacity` bytes, returning its producing and consuming halves.
+///
+/// # Panics
+///
+/// Will panic if `capacity` is zero.
+#[must_use]
+#[track_caller]
+pub fn ring(capacity: usize) -> (Producer, Consumer) {
+	assert_ne!(capacity, 0);
+	let ring = Arc::new(Ring {
+		buffer: (0..capacity).map(|_| AtomicU8::new(0)).collect(),
+		head: AtomicUsize::new(0),
+		tail: AtomicUsize::new(0),
+	});
+	(Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+struct Ring {
+	buffer: Box<[AtomicU8]>,
+	/// Index of the next byte to be written, in `0..2*capacity`.
+	head: AtomicUsize,
+	/// Index of the next byte to be read, in `0..2*capacity`.
+	tail: AtomicUsize,
+}
+impl Ring {
+	#[inline(always)]
+	fn capacity(&self) -> usize {
+		self.buffer.len()
+	}
+
+	#[inline(always)]
+	fn len(&self) -> usize {
+		let head = self.head.load(Ordering::Acquire);
+		let tail = self.tail.load(Ordering::Acquire);
+		(head + 2 * self.capacity() - tail) % (2 * self.capacity())
+	}
+
+	#[inline(always)]
+	fn advance(&self, index: usize) -> usize {
+		(index + 1) % (2 * self.capacity())
+	}
+}
+impl fmt::Debug for Ring {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Ring")
+			.field("capacity", &self.capacity())
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+/// The producing half of a [`ring()`].
+pub struct Producer {
+	ring: Arc<Ring>,
+}
+impl Producer {
+	/// The number of bytes the ring can hold.
+	#[must_use]
+	pub fn capacity(&self) -> usize {
+		self.ring.capacity()
+	}
+	/// The number of bytes currently held in the ring.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.ring.len()
+	}
+	/// Whether the ring currently holds no bytes.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.ring.len() == 0
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.ring.len() != self.ring.capacity()
+	}
+	/// Push a `u8` to the ring. [`None`] denotes that the ring is full, and is instead awaiting a [`pull`](Consumer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.push_avail() {
+			Some(move |x| {
+				let ring = &self.ring;
+				let head = ring.head.load(Ordering::Relaxed);
+				ring.buffer[head % ring.capacity()].store(x, Ordering::Relaxed);
+				ring.head.store(ring.advance(head), Ordering::Release);
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Move as many bytes as are available and fit from `serializer` into the ring, returning the number moved.
+	pub fn fill_from(&mut self, serializer: &mut Serializer) -> usize {
+		let mut len = 0;
+		while let (Some(pull), Some(push)) = (serializer.pull(), self.push()) {
+			push(pull());
+			len += 1;
+		}
+		len
+	}
+}
+impl fmt::Debug for Producer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Producer")
+			.field("ring", &self.ring)
+			.finish()
+	}
+}
+
+/// The consuming half of a [`ring()`].
+pub struct Consumer {
+	ring: Arc<Ring>,
+}
+impl Consumer {
+	/// The number of bytes the ring can hold.
+	#[must_use]
+	pub fn capacity(&self) -> usize {
+		self.ring.capacity()
+	}
+	/// The number of bytes currently held in the ring.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.ring.len()
+	}
+	/// Whether the ring currently holds no bytes.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.ring.len() == 0
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.ring.len() != 0
+	}
+	/// Pull a `u8` from the ring. [`None`] denotes that the ring is empty, and is instead awaiting a [`push`](Producer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let ring = &self.ring;
+				let tail = ring.tail.load(Ordering::Relaxed);
+				let ret = ring.buffer[tail % ring.capacity()].load(Ordering::Relaxed);
+				ring.tail.store(ring.advance(tail), Ordering::Release);
+				ret
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Move as many bytes as are available and accepted from the ring into `deserializer`, returning the number moved.
+	pub fn drain_into(&mut self, deserializer: &mut Deserializer) -> usize {
+		let mut len = 0;
+		while let (Some(push), Some(pull)) = (deserializer.push(), self.pull()) {
+			push(pull());
+			len += 1;
+		}
+		len
+	}
+}
+impl fmt::Debug for Consumer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Consumer")
+			.field("ring", &self.ring)
+			.finish()
+	}
+}
+
+/// Create a bounded channel of `T`s, holding up to `capacity` bytes of serialized values in flight, returning its sending and receiving halves.
+///
+/// In addition to the `capacity` bytes of the ring, each half buffers at most one serialized value.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::spsc;
+/// use std::thread;
+///
+/// let (mut sender, mut receiver) = spsc::channel::<String>(16);
+/// let thread = thread::spawn(move || {
+///     for i in 0..100 {
+///         sender.send("x".repeat(i)).unwrap();
+///     }
+/// });
+/// for i in 0..100 {
+///     assert_eq!(receiver.recv().unwrap(), "x".repeat(i));
+/// }
+/// assert_eq!(receiver.recv(), None);
+/// thread.join().unwrap();
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `capacity` is zero.
+#[must_use]
+#[track_caller]
+pub fn channel<T: serde::ser::Serialize + serde::de::DeserializeOwned + 'static>(
+	capacity: usize,
+) -> (Sender<T>, Receiver<T>) {
+	let (producer, consumer) = ring(capacity);
+	let parked = Arc::new(Parked {
+		sender: Waiter::new(),
+		receiver: Waiter::new(),
+		closed: AtomicBool::new(false),
+	});
+	(
+		Sender {
+			producer,
+			serializer: Serializer::new(),
+			parked: parked.clone(),
+			_marker: marker::PhantomData,
+		},
+		Receiver {
+			consumer,
+			deserializer: Deserializer::new(),
+			parked,
+			_marker: marker::PhantomData,
+		},
+	)
+}
+
+/// The threads blocked in [`Sender::send()`] and [`Receiver::recv()`], and whether either half has been dropped.
+struct Parked {
+	sender: Waiter,
+	receiver: Waiter,
+	closed: AtomicBool,
+}
+
+/// A thread that may be parked until the other half of a [`channel()`] makes progress.
+struct Waiter {
+	parked: AtomicBool,
+	thread: Mutex<Option<Thread>>,
+}
+impl Waiter {
+	fn new() -> Self {
+		Self {
+			parked: AtomicBool::new(false),
+			thread: Mutex::new(None),
+		}
+	}
+
+	/// Park the current thread until [`unpark`](Waiter::unpark()) is called, unless `ready` returns true once it's registered to be unparked. As with [`thread::park()`], this can return spuriously.
+	fn park(&self, ready: impl FnOnce() -> bool) {
+		*self.thread.lock().unwrap() = Some(thread::current());
+		self.parked.store(true, Ordering::SeqCst);
+		// pairs with the fence in unpark, so either ready sees the progress, or unpark sees parked
+		atomic::fence(Ordering::SeqCst);
+		if !ready() {
+			thread::park();
+		}
+		self.parked.store(false, Ordering::Relaxed);
+	}
+
+	/// Unpark the thread, if it's parked. Called after making progress it may be waiting on.
+	fn unpark(&self) {
+		atomic::fence(Ordering::SeqCst);
+		if self.parked.load(Ordering::SeqCst) {
+			if let Some(thread) = &*self.thread.lock().unwrap() {
+				thread.unpark();
+			}
+		}
+	}
+}
+
+/// The sending half of a [`channel()`].
+pub struct Sender<T> {
+	producer: Producer,
+	serializer: Serializer,
+	parked: Arc<Parked>,
+	_marker: marker::PhantomData<fn(T)>,
+}
+impl<T: serde::ser::Serialize + 'static> Sender<T> {
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T` to the channel. [`None`] denotes that the previous value hasn't yet fit into the ring, and it's instead awaiting the [`Receiver`]. [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	///
+	/// As much of the value as fits is moved into the ring immediately; call [`flush`](Sender::flush()) to move the rest as space frees up.
+	pub fn push(&mut self) -> Option<impl FnOnce(T) + '_> {
+		let _ = self.flush();
+		if self.serializer.push_avail() {
+			Some(move |t| {
+				let Some(push) = self.serializer.push() else {
+					unreachable!()
+				};
+				push(t);
+				let _ = self.flush();
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Move as much of the value being sent into the ring as fits, returning the number of bytes moved.
+	pub fn flush(&mut self) -> usize {
+		let len = self.producer.fill_from(&mut self.serializer);
+		if len != 0 {
+			self.parked.receiver.unpark();
+		}
+		len
+	}
+
+	/// Send a `T`, blocking until it has been moved entirely into the ring. While the ring is full, the thread is parked until the [`Receiver`] makes room.
+	///
+	/// # Errors
+	///
+	/// Returns the `T` back if the [`Receiver`] has been dropped.
+	pub fn send(&mut self, t: T) -> Result<(), T> {
+		// the previous value may be only partly flushed, and never will be if the Receiver's gone
+		while !self.serializer.push_avail() && !self.is_disconnected() {
+			self.flush_or_park();
+		}
+		if self.is_disconnected() {
+			return Err(t);
+		}
+		let Some(push) = self.serializer.push() else {
+			unreachable!()
+		};
+		push(t);
+		while self.serializer.pull_avail() && !self.is_disconnected() {
+			self.flush_or_park();
+		}
+		Ok(())
+	}
+
+	/// Flush, or if the ring is full, park until the [`Receiver`] makes room or is dropped.
+	fn flush_or_park(&mut self) {
+		if self.flush() == 0 {
+			self.parked
+				.sender
+				.park(|| self.producer.push_avail() || self.is_disconnected());
+		}
+	}
+
+	/// Whether the [`Receiver`] has been dropped.
+	#[must_use]
+	pub fn is_disconnected(&self) -> bool {
+		self.parked.closed.load(Ordering::Acquire)
+	}
+}
+impl<T> Drop for Sender<T> {
+	fn drop(&mut self) {
+		if let Some(empty) = self.serializer.empty() {
+			empty();
+		}
+		self.parked.closed.store(true, Ordering::Release);
+		self.parked.receiver.unpark();
+	}
+}
+impl<T> fmt::Debug for Sender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Sender")
+			.field("producer", &self.producer)
+			.field("serializer", &self.serializer)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The receiving half of a [`channel()`].
+pub struct Receiver<T> {
+	consumer: Consumer,
+	deserializer: Deserializer,
+	parked: Arc<Parked>,
+	_marker: marker::PhantomData<fn() -> T>,
+}
+impl<T: serde::de::DeserializeOwned + 'static> Receiver<T> {
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T` from the channel. [`None`] denotes that no complete value has been received, and it's instead awaiting the [`Sender`]. [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> T + '_> {
+		self.drain();
+		self.deserializer.pull::<T>()
+	}
+
+	/// Receive a `T`, blocking until one is available. While the ring is empty, the thread is parked until the [`Sender`] fills it. Returns [`None`] if the [`Sender`] has been dropped and every value it sent has been received.
+	pub fn recv(&mut self) -> Option<T> {
+		loop {
+			self.drain();
+			if let Some(pull) = self.deserializer.pull() {
+				return Some(pull());
+			}
+			if self.parked.closed.load(Ordering::Acquire) {
+				if self.consumer.is_empty() {
+					return None;
+				}
+			} else {
+				self.parked.receiver.park(|| {
+					!self.consumer.is_empty() || self.parked.closed.load(Ordering::Acquire)
+				});
+			}
+		}
+	}
+
+	/// Move as many bytes as are available from the ring into the deserializer, unparking the [`Sender`] if that made room.
+	fn drain(&mut self) {
+		let _ = self.deserializer.pull::<T>();
+		if self.consumer.drain_into(&mut self.deserializer) != 0 {
+			self.parked.sender.unpark();
+		}
+	}
+}
+impl<T> Drop for Receiver<T> {
+	fn drop(&mut self) {
+		if let Some(empty) = self.deserializer.empty() {
+			empty();
+		}
+		self.parked.closed.store(true, Ordering::Release);
+		self.parked.sender.unpark();
+	}
+}
+impl<T> fmt::Debug for Receiver<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Receiver")
+			.field("consumer", &self.consumer)
+			.field("deserializer", &self.deserializer)
+			.finish_non_exhaustive()
+	}
+}
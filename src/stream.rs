@@ -0,0 +1,313 @@
+//! Blocking typed connections over byte streams.
+//!
+//! [`Connection`] pairs a [`Serializer`] and a [`Deserializer`] with any [`Read`] + [`Write`] stream – a TCP stream, a local socket, a serial port – so that values can be sent and received without hand-writing the pump.
+//!
+//! [`SerializerReader`] and [`DeserializerWriter`] instead adapt a lone [`Serializer`] to [`Read`] and a lone [`Deserializer`] to [`Write`], for code that drives a reader or writer itself. Each owns its pipe, as returned by [`Serializer::into_reader()`] and [`Deserializer::into_writer()`], so it can be stored in a struct or moved to another thread.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::stream::Connection;
+//! use std::{net::{TcpListener, TcpStream}, thread};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let addr = listener.local_addr().unwrap();
+//! let thread = thread::spawn(move || {
+//!     let mut connection = Connection::new(listener.accept().unwrap().0);
+//!     let x: String = connection.recv().unwrap();
+//!     connection.send(x.len()).unwrap();
+//! });
+//! let mut connection = Connection::new(TcpStream::connect(addr).unwrap());
+//! connection.send(String::from("hello")).unwrap();
+//! assert_eq!(connection.recv::<usize>().unwrap(), 5);
+//! thread.join().unwrap();
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{
+	fmt, io::{self, Read, Write}
+};
+
+const BUF_LEN: usize = 8 * 1024;
+
+/// A typed connection over a byte stream `S`, sending and receiving one frame per value.
+///
+/// Calls that return an error, for example on a read or write timeout, can be retried, and resume where they left off. Bytes read beyond the end of a frame are kept for the next [`recv`](Connection::recv()). Partially sent or received frames are discarded on drop, rather than panicking, as a stream can fail at any point.
+pub struct Connection<S> {
+	stream: S,
+	pipes: Pipes,
+}
+impl<S: Read + Write> Connection<S> {
+	/// Wrap `stream`.
+	pub fn new(stream: S) -> Self {
+		Self {
+			stream,
+			pipes: Pipes {
+				serializer: Serializer::new(),
+				deserializer: Deserializer::new(),
+				read: (vec![0; BUF_LEN].into_boxed_slice(), 0, 0),
+				write: (Vec::with_capacity(BUF_LEN), 0),
+			},
+		}
+	}
+
+	/// Send a `T`, blocking until it has been written to the stream and flushed. The remainder of a frame from a previous failed call is sent first.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to or flushing the stream.
+	pub fn send<T: serde::ser::Serialize + 'static>(&mut self, t: T) -> io::Result<()> {
+		self.flush()?;
+		// the flush drained the serializer, so it's ready for a push
+		let Some(push) = self.pipes.serializer.push() else {
+			unreachable!()
+		};
+		push(t);
+		self.flush()
+	}
+
+	/// Write the remainder of a frame from a previous failed [`send`](Connection::send()), and flush the stream.
+	///
+	/// # Errors
+	///
+	/// Returns any error writing to or flushing the stream.
+	pub fn flush(&mut self) -> io::Result<()> {
+		let (buf, pos) = &mut self.pipes.write;
+		loop {
+			if *pos == buf.len() {
+				buf.clear();
+				*pos = 0;
+				while let (true, Some(pull)) = (buf.len() < BUF_LEN, self.pipes.serializer.pull()) {
+					buf.push(pull());
+				}
+				if buf.is_empty() {
+					break;
+				}
+			}
+			match self.stream.write(&buf[*pos..]) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(len) => *pos += len,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err),
+			}
+		}
+		self.stream.flush()
+	}
+
+	/// Receive a `T`, blocking until one has been read from the stream.
+	///
+	/// # Errors
+	///
+	/// Returns any error reading from the stream, or [`io::ErrorKind::UnexpectedEof`] if it ends before a `T` is received.
+	pub fn recv<T: serde::de::DeserializeOwned + 'static>(&mut self) -> io::Result<T> {
+		let (buf, pos, len) = &mut self.pipes.read;
+		loop {
+			if let Some(pull) = self.pipes.deserializer.pull::<T>() {
+				return Ok(pull());
+			}
+			if *pos == *len {
+				*pos = 0;
+				*len = 0;
+				*len = match self.stream.read(buf) {
+					Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+					Ok(len) => len,
+					Err(err) if err.kind() == io::ErrorKind::Interrupted => 0,
+					Err(err) => return Err(err),
+				};
+			}
+			while let (Some(&byte), Some(push)) =
+				(buf[..*len].get(*pos), self.pipes.deserializer.push())
+			{
+				push(byte);
+				*pos += 1;
+			}
+		}
+	}
+}
+impl<S> Connection<S> {
+	/// A reference to the underlying stream.
+	pub fn get_ref(&self) -> &S {
+		&self.stream
+	}
+
+	/// A mutable reference to the underlying stream. Reading from or writing to it directly will corrupt the connection.
+	pub fn get_mut(&mut self) -> &mut S {
+		&mut self.stream
+	}
+
+	/// Consume this connection, returning the underlying stream. Any partially sent or received frame, and any bytes read beyond the last frame received, are lost.
+	pub fn into_inner(self) -> S {
+		self.stream
+	}
+}
+impl<S: fmt::Debug> fmt::Debug for Connection<S> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Connection")
+			.field("stream", &self.stream)
+			.field("serializer", &self.pipes.serializer)
+			.field("deserializer", &self.pipes.deserializer)
+			.finish()
+	}
+}
+
+impl Serializer {
+	/// Consume this Serializer, returning a [`SerializerReader`] over its bytes, which can be stored or moved to another thread on its own.
+	#[must_use]
+	pub fn into_reader(self) -> SerializerReader {
+		SerializerReader::new(self)
+	}
+}
+impl Deserializer {
+	/// Consume this Deserializer, returning a [`DeserializerWriter`] into it, which can be stored or moved to another thread on its own.
+	#[must_use]
+	pub fn into_writer(self) -> DeserializerWriter {
+		DeserializerWriter::new(self)
+	}
+}
+
+/// An [`io::Read`] over the bytes of a [`Serializer`], so frames can be handed to anything expecting a reader, for example [`io::copy`] into a file or socket.
+///
+/// A read while the serializer is awaiting a push returns `Ok(0)`, so [`io::copy`] and [`read_to_end`](io::Read::read_to_end()) stop cleanly after the frames pushed so far; or, once [`set_nonblocking`](SerializerReader::set_nonblocking()), an [`io::ErrorKind::WouldBlock`] error, as a nonblocking socket would.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::{deserialize_from_slice, stream::SerializerReader, Serializer};
+/// use std::io;
+///
+/// let mut reader = SerializerReader::new(Serializer::new());
+/// reader.get_mut().push().unwrap()(String::from("hello"));
+/// let mut file = Vec::new();
+/// let _ = io::copy(&mut reader, &mut file).unwrap();
+/// assert_eq!(deserialize_from_slice::<String>(&file).unwrap(), "hello");
+/// ```
+#[derive(Debug)]
+pub struct SerializerReader {
+	serializer: Serializer,
+	nonblocking: bool,
+}
+impl SerializerReader {
+	/// Wrap `serializer`.
+	#[must_use]
+	pub fn new(serializer: Serializer) -> Self {
+		Self {
+			serializer,
+			nonblocking: false,
+		}
+	}
+
+	/// Whether a read while the serializer is awaiting a push returns an [`io::ErrorKind::WouldBlock`] error rather than `Ok(0)`.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) {
+		self.nonblocking = nonblocking;
+	}
+
+	/// A reference to the underlying serializer.
+	#[must_use]
+	pub fn get_ref(&self) -> &Serializer {
+		&self.serializer
+	}
+
+	/// A mutable reference to the underlying serializer, to push values to it.
+	pub fn get_mut(&mut self) -> &mut Serializer {
+		&mut self.serializer
+	}
+
+	/// Consume this reader, returning the underlying serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl Read for SerializerReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self.serializer.pull_into(buf) {
+			0 if self.nonblocking && !buf.is_empty() => Err(io::ErrorKind::WouldBlock.into()),
+			len => Ok(len),
+		}
+	}
+}
+
+/// An [`io::Write`] into a [`Deserializer`], so bytes arriving from a [`read`](io::Read::read()) loop or [`io::copy`] can be written straight into the pipe.
+///
+/// Each write accepts as many bytes as the current frame can, never beyond its end, and a write while the deserializer is awaiting a pull returns `Ok(0)`; or, once [`set_nonblocking`](DeserializerWriter::set_nonblocking()), an [`io::ErrorKind::WouldBlock`] error, as a nonblocking socket would. [`write_all`](io::Write::write_all()) therefore only succeeds if the bytes end at or before the end of a frame.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::{serialize_to_vec, stream::DeserializerWriter, Deserializer};
+/// use std::io::Write;
+///
+/// let mut writer = DeserializerWriter::new(Deserializer::new());
+/// let _ = writer.get_mut().pull::<String>();
+/// writer.write_all(&serialize_to_vec(String::from("hello"))).unwrap();
+/// assert_eq!(writer.get_mut().pull::<String>().unwrap()(), "hello");
+/// ```
+#[derive(Debug)]
+pub struct DeserializerWriter {
+	deserializer: Deserializer,
+	nonblocking: bool,
+}
+impl DeserializerWriter {
+	/// Wrap `deserializer`.
+	#[must_use]
+	pub fn new(deserializer: Deserializer) -> Self {
+		Self {
+			deserializer,
+			nonblocking: false,
+		}
+	}
+
+	/// Whether a write while the deserializer is awaiting a pull returns an [`io::ErrorKind::WouldBlock`] error rather than `Ok(0)`.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) {
+		self.nonblocking = nonblocking;
+	}
+
+	/// A reference to the underlying deserializer.
+	#[must_use]
+	pub fn get_ref(&self) -> &Deserializer {
+		&self.deserializer
+	}
+
+	/// A mutable reference to the underlying deserializer, to pull values from it.
+	pub fn get_mut(&mut self) -> &mut Deserializer {
+		&mut self.deserializer
+	}
+
+	/// Consume this writer, returning the underlying deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
+impl Write for DeserializerWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self.deserializer.push_bytes(buf) {
+			0 if self.nonblocking && !buf.is_empty() => Err(io::ErrorKind::WouldBlock.into()),
+			len => Ok(len),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// The pipes and buffers of a [`Connection`], split out so that the stream can be moved out of it while these are emptied on drop.
+struct Pipes {
+	serializer: Serializer,
+	deserializer: Deserializer,
+	/// Buffer, position, and length of bytes read.
+	read: (Box<[u8]>, usize, usize),
+	/// Buffer, and position, of bytes to be written.
+	write: (Vec<u8>, usize),
+}
+impl Drop for Pipes {
+	fn drop(&mut self) {
+		if let Some(empty) = self.serializer.empty() {
+			empty();
+		}
+		if let Some(empty) = self.deserializer.empty() {
+			empty();
+		}
+	}
+}
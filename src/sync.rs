@@ -0,0 +1,304 @@
+//! Wrappers for sharing a pipe between contexts.
+//!
+//! The lock-free and blocking primitives here and in [`spsc`](crate::spsc) are built on [loom](https://docs.rs/loom)'s `Mutex`, `Condvar` and atomics when compiled with `--cfg loom`, so that their protocols can be model-checked. The models live alongside the crate's tests, and are run with `RUSTFLAGS="--cfg loom" cargo test --release loom`.
+
+#[cfg(feature = "tokio")]
+use crate::{Deserializer, Serializer};
+#[cfg(loom)]
+use loom::sync::{Condvar, Mutex, MutexGuard};
+#[cfg(all(feature = "parking_lot", not(loom)))]
+use parking_lot::{Condvar, Mutex, MutexGuard};
+#[cfg(feature = "critical-section")]
+use std::cell::RefCell;
+use std::fmt;
+#[cfg(not(any(feature = "parking_lot", loom)))]
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// A pipe that can be shared between threads, with access mediated by a mutex, and a condvar for blocking until the pipe is ready.
+///
+/// With the `parking_lot` feature, this uses [parking_lot](https://docs.rs/parking_lot)'s mutex and condvar, for lower wakeup latency and no poisoning.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::{sync::LockPipe, Deserializer, Serializer};
+/// use std::{sync::Arc, thread};
+///
+/// let serializer = Arc::new(LockPipe::new(Serializer::new()));
+///
+/// let thread = thread::spawn({
+///     let serializer = serializer.clone();
+///     move || {
+///         for i in 0..10_u32 {
+///             serializer.wait(|serializer| serializer.push().map(|push| push(i)));
+///         }
+///     }
+/// });
+///
+/// let mut deserializer = Deserializer::new();
+/// for i in 0..10_u32 {
+///     while deserializer.pull::<u32>().is_none() {
+///         let byte = serializer.wait(|serializer| serializer.pull().map(|pull| pull()));
+///         deserializer.push().unwrap()(byte);
+///     }
+///     assert_eq!(deserializer.pull::<u32>().unwrap()(), i);
+/// }
+/// thread.join().unwrap();
+/// ```
+pub struct LockPipe<P> {
+	pipe: Mutex<P>,
+	ready: Condvar,
+}
+impl<P> LockPipe<P> {
+	/// Wrap `pipe` for sharing.
+	pub fn new(pipe: P) -> Self {
+		Self {
+			pipe: Mutex::new(pipe),
+			ready: Condvar::new(),
+		}
+	}
+
+	/// Lock the pipe and call `f` with exclusive access to it, waking any threads blocked in [`wait`](LockPipe::wait()).
+	pub fn with<R>(&self, f: impl FnOnce(&mut P) -> R) -> R {
+		let ret = f(&mut self.lock());
+		self.notify();
+		ret
+	}
+
+	/// Lock the pipe and call `f` with exclusive access to it, blocking and calling it again each time another thread accesses the pipe, until it returns [`Some`].
+	///
+	/// `f` typically tries a `push` or `pull`, returning [`None`] if it's not ready. As the first such `pull` on a [`Deserializer`](crate::Deserializer) readies it for a `push`, the first call of `f` wakes other threads regardless.
+	pub fn wait<R>(&self, mut f: impl FnMut(&mut P) -> Option<R>) -> R {
+		let mut pipe = self.lock();
+		let mut first = true;
+		loop {
+			if let Some(ret) = f(&mut pipe) {
+				drop(pipe);
+				self.notify();
+				break ret;
+			}
+			if first {
+				self.notify();
+				first = false;
+			}
+			pipe = self.block(pipe);
+		}
+	}
+
+	/// Consume this wrapper, returning the pipe.
+	///
+	/// # Panics
+	///
+	/// Will panic if the mutex was poisoned by a thread panicking while holding it. [parking_lot](https://docs.rs/parking_lot)'s mutex isn't poisoned, so with the `parking_lot` feature this won't panic.
+	pub fn into_inner(self) -> P {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		let pipe = self.pipe.into_inner();
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		let pipe = self.pipe.into_inner().unwrap();
+		pipe
+	}
+
+	fn lock(&self) -> MutexGuard<'_, P> {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		let pipe = self.pipe.lock();
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		let pipe = self.pipe.lock().unwrap();
+		pipe
+	}
+
+	fn notify(&self) {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		let _ = self.ready.notify_all();
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		self.ready.notify_all();
+	}
+
+	#[allow(unused_mut)]
+	fn block<'a>(&self, mut pipe: MutexGuard<'a, P>) -> MutexGuard<'a, P> {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		self.ready.wait(&mut pipe);
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		let pipe = self.ready.wait(pipe).unwrap();
+		pipe
+	}
+}
+impl<P: fmt::Debug> fmt::Debug for LockPipe<P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("LockPipe")
+			.field("pipe", &self.pipe)
+			.field("ready", &self.ready)
+			.finish()
+	}
+}
+
+/// A pipe that can be shared between async tasks, with access mediated by an async mutex, and a notification for awaiting until the pipe is ready.
+///
+/// Requires the `tokio` feature, though it's usable from any executor. Wrapped in an [`Arc`](std::sync::Arc), many tasks can [`send`](AsyncPipe::send()) on or [`recv`](AsyncPipe::recv()) from one connection, while another task moves bytes to or from the transport.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::{serialize_to_vec, sync::AsyncPipe, Deserializer, Serializer};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let serializer = AsyncPipe::new(Serializer::new());
+/// let deserializer = AsyncPipe::new(Deserializer::new());
+///
+/// let transport = async {
+///     for _ in 0..serialize_to_vec(String::from("hello")).len() {
+///         let byte = serializer.wait(|serializer| serializer.pull().map(|pull| pull())).await;
+///         deserializer.wait(|deserializer| deserializer.push().map(|push| push(byte))).await;
+///     }
+/// };
+/// let ((), (), x) = tokio::join!(
+///     transport,
+///     serializer.send(String::from("hello")),
+///     deserializer.recv::<String>()
+/// );
+/// assert_eq!(x, "hello");
+/// # });
+/// ```
+#[cfg(feature = "tokio")]
+pub struct AsyncPipe<P> {
+	pipe: tokio::sync::Mutex<P>,
+	ready: tokio::sync::Notify,
+}
+#[cfg(feature = "tokio")]
+impl<P> AsyncPipe<P> {
+	/// Wrap `pipe` for sharing.
+	pub fn new(pipe: P) -> Self {
+		Self {
+			pipe: tokio::sync::Mutex::new(pipe),
+			ready: tokio::sync::Notify::new(),
+		}
+	}
+
+	/// Lock the pipe and call `f` with exclusive access to it, waking any tasks awaiting in [`wait`](AsyncPipe::wait()).
+	pub async fn with<R>(&self, f: impl FnOnce(&mut P) -> R) -> R {
+		let ret = f(&mut *self.pipe.lock().await);
+		self.ready.notify_waiters();
+		ret
+	}
+
+	/// Lock the pipe and call `f` with exclusive access to it, awaiting and calling it again each time another task accesses the pipe, until it returns [`Some`].
+	///
+	/// As with [`LockPipe::wait()`], the first call of `f` wakes other tasks regardless.
+	pub async fn wait<R>(&self, mut f: impl FnMut(&mut P) -> Option<R>) -> R {
+		let mut first = true;
+		loop {
+			let ready = self.ready.notified();
+			tokio::pin!(ready);
+			let _ = ready.as_mut().enable();
+			if let Some(ret) = f(&mut *self.pipe.lock().await) {
+				self.ready.notify_waiters();
+				break ret;
+			}
+			if first {
+				self.ready.notify_waiters();
+				first = false;
+			}
+			ready.await;
+		}
+	}
+
+	/// Consume this wrapper, returning the pipe.
+	pub fn into_inner(self) -> P {
+		self.pipe.into_inner()
+	}
+}
+#[cfg(feature = "tokio")]
+impl AsyncPipe<Serializer> {
+	/// Push a `T`, awaiting until the Serializer is ready for it.
+	pub async fn send<T: serde::ser::Serialize + 'static>(&self, t: T) {
+		let mut t = Some(t);
+		// wait stops polling as soon as the push is made, so t is only taken once
+		self.wait(|serializer| {
+			serializer.push().map(|push| {
+				let Some(t) = t.take() else { unreachable!() };
+				push(t);
+			})
+		})
+		.await;
+	}
+}
+#[cfg(feature = "tokio")]
+impl AsyncPipe<Deserializer> {
+	/// Pull a `T`, awaiting until one has been received.
+	pub async fn recv<T: serde::de::DeserializeOwned + 'static>(&self) -> T {
+		self.wait(|deserializer| deserializer.pull().map(|pull| pull()))
+			.await
+	}
+}
+#[cfg(feature = "tokio")]
+impl<P: fmt::Debug> fmt::Debug for AsyncPipe<P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AsyncPipe")
+			.field("pipe", &self.pipe)
+			.field("ready", &self.ready)
+			.finish()
+	}
+}
+
+/// A pipe that can be shared between interrupt and thread context, with access mediated by [`critical_section::with()`](https://docs.rs/critical-section).
+///
+/// Requires the `critical-section` feature. As [`CsPipe::new()`] is a `const fn`, it can be used to initialise a `static`.
+///
+/// The crate requires `std`, as the pipes allocate and serialize with bincode's `std` API, so this is usable only on targets with `std`, for handlers that mustn't block on a `std` mutex; it doesn't make the pipes available to `no_std` targets.
+///
+/// # Example
+///
+/// ```
+/// use serde_pipe::{sync::CsPipe, Serializer};
+///
+/// static TELEMETRY: CsPipe<Serializer> = CsPipe::new(Serializer::new());
+///
+/// // in the interrupt handler
+/// TELEMETRY.with(|serializer| {
+///     if let Some(push) = serializer.push() {
+///         push(42_u32);
+///     }
+/// });
+///
+/// // in the main loop
+/// while let Some(byte) = TELEMETRY.with(|serializer| serializer.pull().map(|pull| pull())) {
+///     println!("byte! {}", byte);
+/// }
+/// ```
+#[cfg(feature = "critical-section")]
+pub struct CsPipe<P> {
+	pipe: critical_section::Mutex<RefCell<P>>,
+}
+#[cfg(feature = "critical-section")]
+impl<P> CsPipe<P> {
+	/// Wrap `pipe` for sharing.
+	#[inline(always)]
+	pub const fn new(pipe: P) -> Self {
+		Self {
+			pipe: critical_section::Mutex::new(RefCell::new(pipe)),
+		}
+	}
+
+	/// Enter a critical section and call `f` with exclusive access to the pipe.
+	///
+	/// # Panics
+	///
+	/// Will panic if called reentrantly, i.e. from within `f`.
+	pub fn with<R>(&self, f: impl FnOnce(&mut P) -> R) -> R {
+		critical_section::with(|cs| f(&mut self.pipe.borrow_ref_mut(cs)))
+	}
+
+	/// Consume this wrapper, returning the pipe.
+	pub fn into_inner(self) -> P {
+		self.pipe.into_inner().into_inner()
+	}
+}
+#[cfg(feature = "critical-section")]
+impl<P: fmt::Debug> fmt::Debug for CsPipe<P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		critical_section::with(|cs| {
+			f.debug_struct("CsPipe")
+				.field("pipe", &self.pipe.borrow(cs).try_borrow())
+				.finish()
+		})
+	}
+}
@@ -0,0 +1,285 @@
+//! Sampling the frames passing through a pipe, without disturbing its consumer.
+//!
+//! [`TapSerializer`] and [`TapDeserializer`] wrap a pipe, passing every `every`th frame to a callback as a [`Sample`]: the name of its type, and its bytes as they appear on the wire. [`Sample::decode()`] decodes it, for live debugging or metrics sampling of production streams. Frames not sampled aren't copied.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{tap::TapSerializer, Serializer};
+//! use std::sync::{Arc, Mutex};
+//!
+//! let sampled = Arc::new(Mutex::new(Vec::new()));
+//! let sampled_ = sampled.clone();
+//! let mut serializer = TapSerializer::new(Serializer::new(), 10, move |sample| {
+//!     sampled_.lock().unwrap().push(sample.decode::<u32>().unwrap());
+//! });
+//! for i in 0..30_u32 {
+//!     serializer.push().unwrap()(i);
+//!     while let Some(pull) = serializer.pull() {
+//!         let _byte = pull();
+//!     }
+//! }
+//! assert_eq!(*sampled.lock().unwrap(), [0, 10, 20]);
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{any, fmt};
+
+/// A frame sampled by a [`TapSerializer`] or [`TapDeserializer`].
+#[derive(Copy, Clone, Debug)]
+pub struct Sample<'a> {
+	/// The name of the frame's type, as returned by [`std::any::type_name()`].
+	pub type_name: &'static str,
+	/// The frame, header included, as it appears on the wire.
+	pub bytes: &'a [u8],
+}
+impl Sample<'_> {
+	/// Decode the frame as a `T`, returning [`None`] if it isn't one. The pipe must use the default [`Framing`](crate::Framing).
+	#[must_use]
+	pub fn decode<T: serde::de::DeserializeOwned + 'static>(&self) -> Option<T> {
+		crate::deserialize_from_slice(self.bytes)
+	}
+}
+
+type Tap = Box<dyn FnMut(Sample) + Send>;
+
+/// A [`Serializer`] that passes every `every`th frame it serializes to a callback.
+pub struct TapSerializer {
+	serializer: Serializer,
+	tap: Tap,
+	every: u64,
+	/// The number of frames to pass before sampling the next.
+	skip: u64,
+	/// The type of the frame being sampled, if any.
+	sampling: Option<&'static str>,
+	recording: Vec<u8>,
+}
+impl TapSerializer {
+	/// Wrap `serializer`, passing the first frame and every `every`th thereafter to `tap`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `every` is zero.
+	pub fn new<F: FnMut(Sample) + Send + 'static>(
+		serializer: Serializer, every: u64, tap: F,
+	) -> Self {
+		assert_ne!(every, 0, "every must be non-zero");
+		Self {
+			serializer,
+			tap: Box::new(tap),
+			every,
+			skip: 0,
+			sampling: None,
+			recording: Vec::new(),
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T`. [`None`] denotes that the Serializer is instead awaiting a [`pull`](TapSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push<T: serde::ser::Serialize + 'static>(&mut self) -> Option<impl FnOnce(T) + '_> {
+		if self.push_avail() {
+			Some(move |t: T| {
+				if self.skip == 0 {
+					self.sampling = Some(any::type_name::<T>());
+					self.skip = self.every;
+				}
+				self.skip -= 1;
+				let Some(push) = self.serializer.push() else {
+					unreachable!()
+				};
+				push(t);
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](TapSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let Some(pull) = self.serializer.pull() else {
+					unreachable!()
+				};
+				let byte = pull();
+				if let Some(type_name) = self.sampling {
+					self.recording.push(byte);
+					if self.serializer.push_avail() {
+						(self.tap)(Sample {
+							type_name,
+							bytes: &self.recording,
+						});
+						self.recording.clear();
+						self.sampling = None;
+					}
+				}
+				byte
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the underlying pipe, discarding any partially sampled frame. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				self.recording.clear();
+				self.sampling = None;
+				let Some(empty) = self.serializer.empty() else {
+					unreachable!()
+				};
+				empty();
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Unwrap, returning the underlying Serializer.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl fmt::Debug for TapSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TapSerializer")
+			.field("serializer", &self.serializer)
+			.field("every", &self.every)
+			.field("skip", &self.skip)
+			.field("sampling", &self.sampling)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A [`Deserializer`] that passes every `every`th frame it deserializes to a callback.
+pub struct TapDeserializer {
+	deserializer: Deserializer,
+	tap: Tap,
+	every: u64,
+	/// The number of frames to pass before sampling the next.
+	skip: u64,
+	recording: Vec<u8>,
+}
+impl TapDeserializer {
+	/// Wrap `deserializer`, passing the first frame and every `every`th thereafter to `tap`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `every` is zero.
+	pub fn new<F: FnMut(Sample) + Send + 'static>(
+		deserializer: Deserializer, every: u64, tap: F,
+	) -> Self {
+		assert_ne!(every, 0, "every must be non-zero");
+		Self {
+			deserializer,
+			tap: Box::new(tap),
+			every,
+			skip: 0,
+			recording: Vec::new(),
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](TapDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `T` differs from the type supplied to a previous call for the same frame.
+	#[track_caller]
+	pub fn pull<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> Option<impl FnOnce() -> T + '_> {
+		let pull = self.deserializer.pull::<T>();
+		let (tap, recording, skip, every) = (
+			&mut self.tap,
+			&mut self.recording,
+			&mut self.skip,
+			self.every,
+		);
+		pull.map(move |pull| {
+			move || {
+				let t = pull();
+				if *skip == 0 {
+					tap(Sample {
+						type_name: any::type_name::<T>(),
+						bytes: recording,
+					});
+					recording.clear();
+					*skip = every;
+				}
+				*skip -= 1;
+				t
+			}
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](TapDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		let sampling = self.skip == 0;
+		let recording = &mut self.recording;
+		self.deserializer.push().map(move |push| {
+			move |x| {
+				if sampling {
+					recording.push(x);
+				}
+				push(x);
+			}
+		})
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.deserializer.empty_avail()
+	}
+	/// Empty the underlying pipe, discarding any partially sampled frame. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		let recording = &mut self.recording;
+		self.deserializer.empty().map(move |empty| {
+			move || {
+				recording.clear();
+				empty();
+			}
+		})
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	#[must_use]
+	pub fn into_inner(self) -> Deserializer {
+		self.deserializer
+	}
+}
+impl fmt::Debug for TapDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TapDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("every", &self.every)
+			.field("skip", &self.skip)
+			.finish_non_exhaustive()
+	}
+}
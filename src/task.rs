@@ -0,0 +1,402 @@
+//! Pipes shared between async tasks, waking them through their [`Waker`]s rather than a runtime's primitives, so they work with any executor.
+//!
+//! [`AsyncSerializer`] is a cloneable handle to a [`Serializer`]: one task pushes values through it while another consumes their bytes. With the `futures` feature it's a [`Stream`](futures_core::Stream) of [`Bytes`](bytes::Bytes), so it can be handed to async sinks and hyper bodies directly, and a [`Sink`](futures_sink::Sink) of values, so async code can `send(value).await` into it. With the `tokio` feature it's a [`tokio::io::AsyncRead`], so its bytes can be [`tokio::io::copy`](https://docs.rs/tokio/1/tokio/io/fn.copy.html)'d straight into a `TcpStream`. With the `embedded-io-async` feature it's an `embedded_io_async::Read`.
+//!
+//! [`AsyncDeserializer`] is the dual, a cloneable handle to a [`Deserializer`]: one task writes the incoming bytes into it while another awaits the values, through [`poll_pull`](AsyncDeserializer::poll_pull()). With the `tokio` feature it's a [`tokio::io::AsyncWrite`], so a `TcpStream` can be [`tokio::io::copy`](https://docs.rs/tokio/1/tokio/io/fn.copy.html)'d straight into it. With the `embedded-io-async` feature it's an `embedded_io_async::Write`.
+//!
+//! Values can be pushed and pulled with [`push(value).await`](AsyncSerializer::push()) and [`pull::<T>().await`](AsyncDeserializer::pull()), which await the pipe's being ready rather than returning [`None`], on any executor.
+//!
+//! With the `parking_lot` feature, the handles lock their pipe with [parking_lot](https://docs.rs/parking_lot)'s mutex, as [`LockPipe`](crate::sync::LockPipe) does.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "futures")] {
+//! use futures_core::Stream;
+//! use serde_pipe::{deserialize_from_slice, task::AsyncSerializer, Serializer};
+//! use std::{future::poll_fn, pin::Pin};
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let mut serializer = AsyncSerializer::new(Serializer::new());
+//! let pusher = serializer.clone();
+//! pusher.push_or_queue(String::from("hello")).unwrap();
+//! pusher.close();
+//! let mut bytes = Vec::new();
+//! while let Some(chunk) = poll_fn(|cx| Pin::new(&mut serializer).poll_next(cx)).await {
+//!     bytes.extend_from_slice(&chunk);
+//! }
+//! assert_eq!(deserialize_from_slice::<String>(&bytes).unwrap(), "hello");
+//! # });
+//! # }
+//! ```
+
+use crate::{Deserializer, Serializer};
+#[cfg(loom)]
+use loom::sync::{Mutex, MutexGuard};
+#[cfg(all(feature = "parking_lot", not(loom)))]
+use parking_lot::{Mutex, MutexGuard};
+#[cfg(any(feature = "futures", feature = "embedded-io-async"))]
+use std::convert::Infallible;
+#[cfg(not(any(feature = "parking_lot", loom)))]
+use std::sync::{Mutex, MutexGuard};
+use std::{
+	fmt, future::poll_fn, mem, sync::Arc, task::{Context, Poll, Waker}
+};
+#[cfg(feature = "tokio")]
+use std::io;
+#[cfg(any(feature = "futures", feature = "tokio"))]
+use std::pin::Pin;
+
+/// Register the task of `cx` to be woken, alongside any others awaiting the same, unless it already is.
+fn register(wakers: &mut Vec<Waker>, cx: &Context<'_>) {
+	if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+		wakers.push(cx.waker().clone());
+	}
+}
+
+/// Wake every task registered in `wakers`.
+fn wake(wakers: Vec<Waker>) {
+	for waker in wakers {
+		waker.wake();
+	}
+}
+
+/// A [`Serializer`] shared between async tasks, with the tasks awaiting its bytes woken as values are pushed.
+///
+/// Clones are handles to the same Serializer. Values pushed through [`push_or_queue`](AsyncSerializer::push_or_queue()) or [`with`](AsyncSerializer::with()) wake the tasks awaiting bytes; once [`close`](AsyncSerializer::close())d, the bytes end after those of the values already pushed. Any number of tasks can await either side at once, each being woken.
+#[derive(Clone)]
+pub struct AsyncSerializer(Arc<Mutex<SerializerState>>);
+struct SerializerState {
+	serializer: Serializer,
+	/// The tasks awaiting bytes.
+	pullers: Vec<Waker>,
+	/// The tasks awaiting the bytes' being pulled, to push or flush.
+	pushers: Vec<Waker>,
+	closed: bool,
+}
+#[cfg(any(feature = "futures", feature = "tokio", feature = "embedded-io-async"))]
+impl SerializerState {
+	/// Call `f` to pull bytes if there are any, waking the tasks awaiting their being pulled. Otherwise register `cx` to be woken once there are, or return [`None`] if closed.
+	fn poll_pull<R>(
+		&mut self, cx: &mut Context<'_>, f: impl FnOnce(&mut Serializer) -> R,
+	) -> Poll<Option<R>> {
+		if self.serializer.pull_avail() {
+			let ret = f(&mut self.serializer);
+			// the frame has been pulled, so a push or flush may now succeed
+			if self.serializer.push_avail() {
+				wake(mem::take(&mut self.pushers));
+			}
+			Poll::Ready(Some(ret))
+		} else if self.closed {
+			Poll::Ready(None)
+		} else {
+			register(&mut self.pullers, cx);
+			Poll::Pending
+		}
+	}
+}
+impl AsyncSerializer {
+	/// Wrap `serializer` for sharing.
+	#[must_use]
+	pub fn new(serializer: Serializer) -> Self {
+		Self(Arc::new(Mutex::new(SerializerState {
+			serializer,
+			pullers: Vec::new(),
+			pushers: Vec::new(),
+			closed: false,
+		})))
+	}
+
+	/// Push a `T` if the Serializer is awaiting a push, otherwise queue it, as with [`Serializer::push_or_queue()`], waking the tasks awaiting bytes.
+	///
+	/// # Errors
+	///
+	/// Returns `t` if the queue is full.
+	pub fn push_or_queue<T: serde::ser::Serialize + Send + 'static>(&self, t: T) -> Result<(), T> {
+		let mut state = self.lock();
+		let ret = state.serializer.push_or_queue(t);
+		let pullers = mem::take(&mut state.pullers);
+		drop(state);
+		wake(pullers);
+		ret
+	}
+
+	/// Push a `T`, awaiting until the Serializer is awaiting a push, and waking the tasks awaiting bytes.
+	pub async fn push<T: serde::ser::Serialize + 'static>(&self, t: T) {
+		let mut t = Some(t);
+		poll_fn(|cx| {
+			let mut state = self.lock();
+			if state.serializer.push_avail() {
+				// t is only taken once, as the future then completes
+				let (Some(push), Some(t)) = (state.serializer.push(), t.take()) else {
+					unreachable!()
+				};
+				push(t);
+				let pullers = mem::take(&mut state.pullers);
+				drop(state);
+				wake(pullers);
+				Poll::Ready(())
+			} else {
+				register(&mut state.pushers, cx);
+				Poll::Pending
+			}
+		})
+		.await;
+	}
+
+	/// Lock the Serializer and call `f` with exclusive access to it, waking the tasks awaiting bytes, or their being pulled.
+	pub fn with<R>(&self, f: impl FnOnce(&mut Serializer) -> R) -> R {
+		let mut state = self.lock();
+		let ret = f(&mut state.serializer);
+		let (pullers, pushers) = (mem::take(&mut state.pullers), mem::take(&mut state.pushers));
+		drop(state);
+		wake(pullers);
+		wake(pushers);
+		ret
+	}
+
+	/// Mark the end of the values to be pushed, so the bytes end once those already pushed have been pulled.
+	pub fn close(&self) {
+		let mut state = self.lock();
+		state.closed = true;
+		let pullers = mem::take(&mut state.pullers);
+		drop(state);
+		wake(pullers);
+	}
+
+	fn lock(&self) -> MutexGuard<'_, SerializerState> {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		let state = self.0.lock();
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		let state = self.0.lock().unwrap();
+		state
+	}
+}
+#[cfg(feature = "futures")]
+impl futures_core::Stream for AsyncSerializer {
+	type Item = bytes::Bytes;
+
+	/// Yield the bytes pulled from the Serializer, a chunk at a time, each within a frame, as with [`Serializer::pull_chunk()`].
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<bytes::Bytes>> {
+		self.lock().poll_pull(cx, |serializer| {
+			bytes::Bytes::copy_from_slice(serializer.pull_chunk().unwrap()())
+		})
+	}
+}
+/// Push each value sent, once the Serializer is awaiting a push. Flushing awaits every byte pushed having been pulled; closing also [`close`](AsyncSerializer::close())s it.
+///
+/// Values mustn't be pushed through other handles between [`poll_ready`](futures_sink::Sink::poll_ready()) and [`start_send`](futures_sink::Sink::start_send()), which panics if the Serializer isn't awaiting a push.
+#[cfg(feature = "futures")]
+impl<T: serde::ser::Serialize + 'static> futures_sink::Sink<T> for AsyncSerializer {
+	type Error = Infallible;
+
+	fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+		let mut state = self.lock();
+		if state.serializer.push_avail() {
+			Poll::Ready(Ok(()))
+		} else {
+			register(&mut state.pushers, cx);
+			Poll::Pending
+		}
+	}
+
+	fn start_send(self: Pin<&mut Self>, t: T) -> Result<(), Infallible> {
+		let mut state = self.lock();
+		state
+			.serializer
+			.push()
+			.expect("start_send() called without poll_ready()")(t);
+		let pullers = mem::take(&mut state.pullers);
+		drop(state);
+		wake(pullers);
+		Ok(())
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+		let mut state = self.lock();
+		if state.serializer.pull_avail() {
+			register(&mut state.pushers, cx);
+			Poll::Pending
+		} else {
+			Poll::Ready(Ok(()))
+		}
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+		self.close();
+		<Self as futures_sink::Sink<T>>::poll_flush(self, cx)
+	}
+}
+/// Read the bytes pulled from the Serializer, which can span frames, as with [`Serializer::pull_into()`]. Reads while it's awaiting a push wait for one, until it's [`close`](AsyncSerializer::close())d, after which they return end-of-file.
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for AsyncSerializer {
+	fn poll_read(
+		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut tokio::io::ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		let mut state = self.lock();
+		state
+			.poll_pull(cx, |serializer| {
+				let len = serializer.pull_into(buf.initialize_unfilled());
+				buf.advance(len);
+			})
+			.map(|_| Ok(()))
+	}
+}
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::ErrorType for AsyncSerializer {
+	type Error = Infallible;
+}
+/// Read the bytes pulled from the Serializer, which can span frames, as with [`Serializer::pull_into()`]. Reads while it's awaiting a push wait for one, until it's [`close`](AsyncSerializer::close())d, after which they return end-of-file.
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::Read for AsyncSerializer {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let len = poll_fn(|cx| {
+			self.lock()
+				.poll_pull(cx, |serializer| serializer.pull_into(buf))
+		})
+		.await;
+		Ok(len.unwrap_or(0))
+	}
+}
+impl fmt::Debug for AsyncSerializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let state = self.lock();
+		f.debug_struct("AsyncSerializer")
+			.field("serializer", &state.serializer)
+			.field("closed", &state.closed)
+			.finish()
+	}
+}
+
+/// A [`Deserializer`] shared between async tasks, with the tasks awaiting a value woken once one can be pulled.
+///
+/// Clones are handles to the same Deserializer. Tasks awaiting a value register through [`poll_pull`](AsyncDeserializer::poll_pull()); bytes pushed through [`with`](AsyncDeserializer::with()), or written with the `tokio` or `embedded-io-async` feature, wake them once a frame completes. Any number of tasks can await either side at once, each being woken.
+#[derive(Clone)]
+pub struct AsyncDeserializer(Arc<Mutex<DeserializerState>>);
+struct DeserializerState {
+	deserializer: Deserializer,
+	/// The tasks awaiting a value.
+	pullers: Vec<Waker>,
+	/// The tasks awaiting a value's being pulled, to push bytes.
+	pushers: Vec<Waker>,
+}
+impl AsyncDeserializer {
+	/// Wrap `deserializer` for sharing.
+	#[must_use]
+	pub fn new(deserializer: Deserializer) -> Self {
+		Self(Arc::new(Mutex::new(DeserializerState {
+			deserializer,
+			pullers: Vec::new(),
+			pushers: Vec::new(),
+		})))
+	}
+
+	/// Pull a `T` if one can be pulled, waking the tasks awaiting room to push bytes. Otherwise register `cx` to be woken once one can.
+	///
+	/// As with [`Deserializer::pull()`], this supplies the type of the value being deserialized, so bytes can't be pushed until it's been called.
+	///
+	/// # Panics
+	///
+	/// Will panic if the frame holds a value that isn't a valid `T`, as with [`Deserializer::pull()`].
+	pub fn poll_pull<T: serde::de::DeserializeOwned + 'static>(&self, cx: &mut Context<'_>) -> Poll<T> {
+		let mut guard = self.lock();
+		let state = &mut *guard;
+		let ret = if let Some(pull) = state.deserializer.pull::<T>() {
+			Poll::Ready(pull())
+		} else {
+			register(&mut state.pullers, cx);
+			Poll::Pending
+		};
+		// either a value has been pulled or the type has been supplied, so a push may now succeed
+		let pushers = mem::take(&mut state.pushers);
+		drop(guard);
+		wake(pushers);
+		ret
+	}
+
+	/// Pull a `T`, awaiting until one can be pulled, as with [`poll_pull`](AsyncDeserializer::poll_pull()).
+	///
+	/// # Panics
+	///
+	/// Will panic if the frame holds a value that isn't a valid `T`, as with [`Deserializer::pull()`].
+	pub async fn pull<T: serde::de::DeserializeOwned + 'static>(&self) -> T {
+		poll_fn(|cx| self.poll_pull(cx)).await
+	}
+
+	/// Lock the Deserializer and call `f` with exclusive access to it, waking the tasks awaiting a value, or room to push bytes.
+	pub fn with<R>(&self, f: impl FnOnce(&mut Deserializer) -> R) -> R {
+		let mut state = self.lock();
+		let ret = f(&mut state.deserializer);
+		let (pullers, pushers) = (mem::take(&mut state.pullers), mem::take(&mut state.pushers));
+		drop(state);
+		wake(pullers);
+		wake(pushers);
+		ret
+	}
+
+	/// Push the bytes of `buf` that the Deserializer will take, waking the tasks awaiting a value once one can be pulled. Otherwise, if it's awaiting a pull, register `cx` to be woken once it's pulled.
+	#[cfg(any(feature = "tokio", feature = "embedded-io-async"))]
+	fn poll_push(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<usize> {
+		let mut state = self.lock();
+		let len = state.deserializer.push_bytes(buf);
+		if len == 0 && !buf.is_empty() {
+			register(&mut state.pushers, cx);
+			return Poll::Pending;
+		}
+		let pullers = if state.deserializer.pull_avail() {
+			mem::take(&mut state.pullers)
+		} else {
+			Vec::new()
+		};
+		drop(state);
+		wake(pullers);
+		Poll::Ready(len)
+	}
+
+	fn lock(&self) -> MutexGuard<'_, DeserializerState> {
+		#[cfg(all(feature = "parking_lot", not(loom)))]
+		let state = self.0.lock();
+		#[cfg(not(all(feature = "parking_lot", not(loom))))]
+		let state = self.0.lock().unwrap();
+		state
+	}
+}
+/// Push the bytes written, as with [`Deserializer::push_bytes()`], waking the tasks awaiting a value once one can be pulled. Writes while it's awaiting a pull wait for one.
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for AsyncDeserializer {
+	fn poll_write(
+		self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		self.poll_push(cx, buf).map(Ok)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+}
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::ErrorType for AsyncDeserializer {
+	type Error = Infallible;
+}
+/// Push the bytes written, as with [`Deserializer::push_bytes()`], waking the tasks awaiting a value once one can be pulled. Writes while it's awaiting a pull wait for one.
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::Write for AsyncDeserializer {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+		Ok(poll_fn(|cx| self.poll_push(cx, buf)).await)
+	}
+}
+impl fmt::Debug for AsyncDeserializer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let state = self.lock();
+		f.debug_struct("AsyncDeserializer")
+			.field("deserializer", &state.deserializer)
+			.finish()
+	}
+}
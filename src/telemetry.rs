@@ -0,0 +1,97 @@
+//! Per-frame telemetry, for maintaining per-message-type metrics without wrapping the pipes.
+//!
+//! A [`Telemetry`] callback set with [`Serializer::set_telemetry()`](crate::Serializer::set_telemetry()) or [`Deserializer::set_telemetry()`](crate::Deserializer::set_telemetry()) is invoked on every completed frame with the name of the type, the length of the frame's payload in bytes, and the [`Direction`] it passed through the pipe.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{telemetry::Direction, Serializer};
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! static SENT: AtomicUsize = AtomicUsize::new(0);
+//!
+//! let mut serializer = Serializer::new();
+//! serializer.set_telemetry(Some(|type_name, len, direction| {
+//!     assert_eq!((type_name, direction), ("u64", Direction::Serialized));
+//!     let _ = SENT.fetch_add(len, Ordering::Relaxed);
+//! }));
+//! serializer.push().unwrap()(123_u64);
+//! while let Some(pull) = serializer.pull() {
+//!     let _byte = pull();
+//! }
+//! assert_eq!(SENT.load(Ordering::Relaxed), 8);
+//! ```
+//!
+//! With [`Serializer::set_stall_timing()`](crate::Serializer::set_stall_timing()) or [`Deserializer::set_stall_timing()`](crate::Deserializer::set_stall_timing()) enabled, a pipe also tracks how long it has spent awaiting a push versus awaiting a pull, returned as [`Stalls`] by `stalls()`. For a Serializer, time awaiting a push is time spent waiting on the producer, and time awaiting a pull is time spent waiting on whatever is writing its bytes out; for a Deserializer, the reverse. Comparing them shows which side is the bottleneck.
+
+use std::time::{Duration, Instant};
+
+/// Which way a frame passed through a pipe.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+	/// A `T` was pushed to a [`Serializer`](crate::Serializer) and serialized.
+	Serialized,
+	/// A `T` was deserialized and pulled from a [`Deserializer`](crate::Deserializer).
+	Deserialized,
+}
+
+/// A callback invoked on every completed frame with `(type_name, frame_len, direction)`.
+///
+/// `type_name` is as returned by [`std::any::type_name()`]; frames pushed by `push_with` or pulled by `pull_with` report [`RAW_FRAME`]. `frame_len` excludes any frame header. Heartbeat and control frames aren't reported.
+pub type Telemetry = fn(&'static str, usize, Direction);
+
+/// The `type_name` reported for frames pushed by `push_with` or pulled by `pull_with`, whose payloads aren't of any Rust type.
+pub const RAW_FRAME: &str = "<raw frame>";
+
+/// The time a pipe has spent in each state, since stall timing was enabled.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Stalls {
+	/// Time spent awaiting a push.
+	pub awaiting_push: Duration,
+	/// Time spent awaiting a pull.
+	pub awaiting_pull: Duration,
+}
+
+/// Accumulates [`Stalls`] by timing each transition between awaiting a push and awaiting a pull.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StallTimer {
+	stalls: Stalls,
+	awaiting_pull: bool,
+	since: Instant,
+}
+impl StallTimer {
+	pub(crate) fn new(awaiting_pull: bool) -> Self {
+		Self {
+			stalls: Stalls::default(),
+			awaiting_pull,
+			since: Instant::now(),
+		}
+	}
+
+	/// Note the pipe's state, accounting the time since the last transition if it's changed.
+	#[inline(always)]
+	pub(crate) fn update(&mut self, awaiting_pull: bool) {
+		if awaiting_pull != self.awaiting_pull {
+			let now = Instant::now();
+			let elapsed = now - self.since;
+			*self.state() += elapsed;
+			self.awaiting_pull = awaiting_pull;
+			self.since = now;
+		}
+	}
+
+	/// The time spent in each state, including the current one so far.
+	pub(crate) fn stalls(&self) -> Stalls {
+		let mut ret = *self;
+		*ret.state() += self.since.elapsed();
+		ret.stalls
+	}
+
+	fn state(&mut self) -> &mut Duration {
+		if self.awaiting_pull {
+			&mut self.stalls.awaiting_pull
+		} else {
+			&mut self.stalls.awaiting_push
+		}
+	}
+}
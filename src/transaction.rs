@@ -0,0 +1,289 @@
+//! Grouping values into transactions that are received all or nothing.
+//!
+//! [`TransactionSerializer`] sends values either individually, or grouped between [`begin`](TransactionSerializer::begin()) and [`commit`](TransactionSerializer::commit()) or [`abort`](TransactionSerializer::abort()). [`TransactionDeserializer`] holds back the values of a transaction until its commit arrives, and discards them on abort, so a sender crashing part way through a logically atomic update can't leave the receiver with half of it.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{transaction::{TransactionDeserializer, TransactionSerializer}, Deserializer, Serializer};
+//!
+//! let mut serializer = TransactionSerializer::new(Serializer::new());
+//! let mut deserializer = TransactionDeserializer::<u32>::new(Deserializer::new());
+//! let mut received = Vec::new();
+//! let mut pump = |serializer: &mut TransactionSerializer<u32>| {
+//!     while let Some(pull) = serializer.pull() {
+//!         deserializer.push().unwrap()(pull());
+//!         while let Some(pull) = deserializer.pull() {
+//!             received.push(pull());
+//!         }
+//!     }
+//! };
+//! serializer.begin().unwrap()();
+//! pump(&mut serializer);
+//! for i in 0..3 {
+//!     serializer.push().unwrap()(i);
+//!     pump(&mut serializer);
+//! }
+//! serializer.abort().unwrap()();
+//! pump(&mut serializer);
+//! serializer.begin().unwrap()();
+//! pump(&mut serializer);
+//! for i in 3..6 {
+//!     serializer.push().unwrap()(i);
+//!     pump(&mut serializer);
+//! }
+//! serializer.commit().unwrap()();
+//! pump(&mut serializer);
+//! assert_eq!(received, [3, 4, 5]);
+//! ```
+
+use crate::{Deserializer, Serializer};
+use std::{collections::VecDeque, fmt, marker::PhantomData};
+
+/// Each frame is either a value, or one of the markers.
+type Frame<T> = Result<T, u8>;
+const BEGIN: u8 = 0;
+const COMMIT: u8 = 1;
+const ABORT: u8 = 2;
+
+/// A [`Serializer`] that sends values of type `T`, optionally grouped into transactions.
+pub struct TransactionSerializer<T> {
+	serializer: Serializer,
+	in_transaction: bool,
+	marker: PhantomData<fn(T)>,
+}
+impl<T: serde::ser::Serialize + 'static> TransactionSerializer<T> {
+	/// Wrap `serializer`.
+	#[must_use]
+	pub fn new(serializer: Serializer) -> Self {
+		Self {
+			serializer,
+			in_transaction: false,
+			marker: PhantomData,
+		}
+	}
+
+	/// Whether a transaction has begun, and not yet been committed or aborted.
+	#[must_use]
+	pub fn in_transaction(&self) -> bool {
+		self.in_transaction
+	}
+
+	/// Begin a transaction. Values pushed until it's committed are received only once it is. [`None`] denotes that the Serializer is instead awaiting a [`pull`](TransactionSerializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the begin.
+	///
+	/// # Panics
+	///
+	/// Will panic if a transaction has already begun.
+	#[track_caller]
+	pub fn begin(&mut self) -> Option<impl FnOnce() + '_> {
+		assert!(!self.in_transaction, "transaction already begun");
+		self.marker(BEGIN, true)
+	}
+
+	/// Commit the transaction, so its values are received. [`None`] denotes that the Serializer is instead awaiting a [`pull`](TransactionSerializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the commit.
+	///
+	/// # Panics
+	///
+	/// Will panic if no transaction has begun.
+	#[track_caller]
+	pub fn commit(&mut self) -> Option<impl FnOnce() + '_> {
+		assert!(self.in_transaction, "no transaction to commit");
+		self.marker(COMMIT, false)
+	}
+
+	/// Abort the transaction, so its values are discarded. [`None`] denotes that the Serializer is instead awaiting a [`pull`](TransactionSerializer::pull()). [`Some`] contains an `impl FnOnce()` that can be called to perform the abort.
+	///
+	/// # Panics
+	///
+	/// Will panic if no transaction has begun.
+	#[track_caller]
+	pub fn abort(&mut self) -> Option<impl FnOnce() + '_> {
+		assert!(self.in_transaction, "no transaction to abort");
+		self.marker(ABORT, false)
+	}
+
+	fn marker(&mut self, marker: u8, in_transaction: bool) -> Option<impl FnOnce() + '_> {
+		if self.push_avail() {
+			Some(move || {
+				self.serializer.push().unwrap()(Frame::<T>::Err(marker));
+				self.in_transaction = in_transaction;
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.serializer.push_avail()
+	}
+	/// Push a `T`, as part of the transaction if one has begun. [`None`] denotes that the Serializer is instead awaiting a [`pull`](TransactionSerializer::pull()). [`Some`] contains an `impl FnOnce(T)` that can be called to perform the `push`.
+	pub fn push(&mut self) -> Option<impl FnOnce(T) + '_> {
+		self.serializer
+			.push::<Frame<T>>()
+			.map(|push| move |t: T| push(Frame::<T>::Ok(t)))
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		self.serializer.pull_avail()
+	}
+	/// Pull a `u8`. [`None`] denotes that the Serializer is instead awaiting a [`push`](TransactionSerializer::push()). [`Some`] contains an `impl FnOnce() -> u8` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> u8 + '_> {
+		self.serializer.pull()
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		self.serializer.empty_avail()
+	}
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.serializer.empty()
+	}
+
+	/// Unwrap, returning the underlying Serializer. A transaction in progress is left uncommitted.
+	#[must_use]
+	pub fn into_inner(self) -> Serializer {
+		self.serializer
+	}
+}
+impl<T> fmt::Debug for TransactionSerializer<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TransactionSerializer")
+			.field("serializer", &self.serializer)
+			.field("in_transaction", &self.in_transaction)
+			.finish()
+	}
+}
+
+/// A [`Deserializer`] that receives the values sent by a [`TransactionSerializer`], yielding those of a transaction only once it's committed.
+///
+/// # Panics
+///
+/// Will panic if dropped while committed values remain to be pulled, unless already unwinding from another panic. If you do want to drop it when non-empty, call [`TransactionDeserializer::empty()`] before dropping it.
+pub struct TransactionDeserializer<T> {
+	deserializer: Deserializer,
+	/// The values of the transaction in progress, if one is.
+	pending: Option<Vec<T>>,
+	/// Values received, or committed, awaiting a pull.
+	ready: VecDeque<T>,
+}
+impl<T: serde::de::DeserializeOwned + 'static> TransactionDeserializer<T> {
+	/// Wrap `deserializer`.
+	///
+	/// # Panics
+	///
+	/// Will panic if `deserializer` is part way through a frame of another type.
+	#[must_use]
+	pub fn new(mut deserializer: Deserializer) -> Self {
+		let _ = deserializer.pull::<Frame<T>>();
+		Self {
+			deserializer,
+			pending: None,
+			ready: VecDeque::new(),
+		}
+	}
+
+	/// The number of values received as part of the transaction in progress, or [`None`] if none is.
+	pub fn pending(&self) -> Option<usize> {
+		self.pending.as_ref().map(Vec::len)
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn push_avail(&self) -> bool {
+		self.ready.is_empty() && self.deserializer.push_avail()
+	}
+	/// Push a `u8`. [`None`] denotes that values are awaiting a [`pull`](TransactionDeserializer::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if a marker received is out of place, or unknown.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		if self.push_avail() {
+			Some(move |x| {
+				self.deserializer.push().unwrap()(x);
+				let frame = self.deserializer.pull::<Frame<T>>().map(|pull| pull());
+				if let Some(frame) = frame {
+					let _ = self.deserializer.pull::<Frame<T>>();
+					match (frame, &mut self.pending) {
+						(Ok(t), Some(pending)) => pending.push(t),
+						(Ok(t), None) => self.ready.push_back(t),
+						// a sender that restarted mid-transaction, abandoning it
+						(Err(BEGIN), pending) => *pending = Some(Vec::new()),
+						(Err(COMMIT), pending @ Some(_)) => {
+							self.ready.extend(pending.take().unwrap());
+						}
+						(Err(ABORT), pending @ Some(_)) => *pending = None,
+						(Err(marker), _) => panic!("unexpected transaction marker {}", marker),
+					}
+				}
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn pull_avail(&self) -> bool {
+		!self.ready.is_empty()
+	}
+	/// Pull a `T`. [`None`] denotes that no values are yet available, and it's instead awaiting a [`push`](TransactionDeserializer::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> T + '_> {
+		if self.pull_avail() {
+			Some(move || {
+				let Some(t) = self.ready.pop_front() else {
+					unreachable!()
+				};
+				t
+			})
+		} else {
+			None
+		}
+	}
+
+	#[doc(hidden)]
+	#[must_use]
+	pub fn empty_avail(&self) -> bool {
+		!self.ready.is_empty() || self.pending.is_some() || self.deserializer.empty_avail()
+	}
+	/// Empty the underlying pipe, discarding the transaction in progress and any values awaiting a pull. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		if self.empty_avail() {
+			Some(move || {
+				self.ready.clear();
+				self.pending = None;
+				if let Some(empty) = self.deserializer.empty() {
+					empty();
+				}
+				let _ = self.deserializer.pull::<Frame<T>>();
+			})
+		} else {
+			None
+		}
+	}
+}
+impl<T> Drop for TransactionDeserializer<T> {
+	#[inline(always)]
+	fn drop(&mut self) {
+		assert!(
+			self.ready.is_empty() || std::thread::panicking(),
+			"TransactionDeserializer dropped with {} values unpulled; empty() it first to discard them",
+			self.ready.len()
+		);
+	}
+}
+impl<T> fmt::Debug for TransactionDeserializer<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("TransactionDeserializer")
+			.field("deserializer", &self.deserializer)
+			.field("pending", &self.pending.as_ref().map(Vec::len))
+			.field("ready", &self.ready.len())
+			.finish()
+	}
+}
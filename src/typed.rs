@@ -0,0 +1,95 @@
+//! Deserializer handles that remember the type being deserialized.
+//!
+//! A [`Deserializer`] forgets the type it's deserializing at the end of each frame, so [`pull::<T>()`](Deserializer::pull()) must be called, naming `T`, before each frame can be pushed. [`Typed`] remembers `T`, so neither [`push`](Typed::push()) nor [`pull`](Typed::pull()) need it spelled out, and it can't be gotten wrong.
+//!
+//! [`Deserializer::for_type()`] constructs an owned `Typed`, while [`Deserializer::typed()`] borrows an existing Deserializer, for example one alternating between types.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::{Deserializer, Serializer};
+//!
+//! let mut serializer = Serializer::new();
+//! let mut deserializer = Deserializer::new();
+//! let mut typed = deserializer.typed::<Vec<u64>>();
+//! for i in 0..3 {
+//!     serializer.push().unwrap()(vec![i; 3]);
+//!     while let (Some(push), Some(pull)) = (typed.push(), serializer.pull()) {
+//!         push(pull());
+//!     }
+//!     assert_eq!(typed.pull().unwrap()(), vec![i; 3]);
+//! }
+//! ```
+
+use crate::Deserializer;
+use std::{any, borrow::BorrowMut, fmt, marker::PhantomData};
+
+impl Deserializer {
+	/// Construct a new Deserializer pipe for `T`s, into which bytes can be pushed straight away, and from which `T`s are pulled without naming `T`.
+	#[must_use]
+	pub fn for_type<T: serde::de::DeserializeOwned + 'static>() -> Typed<Self, T> {
+		Typed::new(Self::new())
+	}
+
+	/// A handle through which `T`s can be pushed and pulled without naming `T` each time.
+	pub fn typed<T: serde::de::DeserializeOwned + 'static>(&mut self) -> Typed<&mut Self, T> {
+		Typed::new(self)
+	}
+}
+
+/// A [`Deserializer`], or a mutable reference to one, that deserializes `T`s.
+pub struct Typed<D, T> {
+	deserializer: D,
+	marker: PhantomData<fn() -> T>,
+}
+impl<D: BorrowMut<Deserializer>, T: serde::de::DeserializeOwned + 'static> Typed<D, T> {
+	/// Wrap `deserializer`.
+	pub fn new(deserializer: D) -> Self {
+		Self {
+			deserializer,
+			marker: PhantomData,
+		}
+	}
+
+	#[doc(hidden)]
+	pub fn pull_avail(&self) -> bool {
+		self.deserializer.borrow().pull_avail()
+	}
+	/// Pull a `T`. [`None`] denotes that the Deserializer is instead awaiting a [`push`](Typed::push()). [`Some`] contains an `impl FnOnce() -> T` that can be called to perform the `pull`.
+	///
+	/// # Panics
+	///
+	/// Will panic if the underlying Deserializer was part way through a frame of a different type.
+	pub fn pull(&mut self) -> Option<impl FnOnce() -> T + '_> {
+		self.deserializer.borrow_mut().pull()
+	}
+
+	/// Push a `u8`. Unlike [`Deserializer::push()`], there's no need to call [`pull`](Typed::pull()) first. [`None`] denotes that the Deserializer is instead awaiting a [`pull`](Typed::pull()). [`Some`] contains an `impl FnOnce(u8)` that can be called to perform the `push`.
+	///
+	/// # Panics
+	///
+	/// Will panic if the underlying Deserializer was part way through a frame of a different type.
+	pub fn push(&mut self) -> Option<impl FnOnce(u8) + '_> {
+		let deserializer = self.deserializer.borrow_mut();
+		let _ = deserializer.pull::<T>();
+		deserializer.push()
+	}
+
+	/// Empty the underlying pipe. [`None`] denotes it's already empty. [`Some`] contains an `impl FnOnce()` that can be called to perform the empty.
+	pub fn empty(&mut self) -> Option<impl FnOnce() + '_> {
+		self.deserializer.borrow_mut().empty()
+	}
+
+	/// Unwrap, returning the underlying Deserializer.
+	pub fn into_inner(self) -> D {
+		self.deserializer
+	}
+}
+impl<D: BorrowMut<Deserializer>, T> fmt::Debug for Typed<D, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Typed")
+			.field("type", &any::type_name::<T>())
+			.field("deserializer", self.deserializer.borrow())
+			.finish()
+	}
+}
@@ -0,0 +1,325 @@
+//! Typed messaging over UDP, fragmenting frames larger than a datagram.
+//!
+//! [`Socket`] serializes each value to a frame, splits it into datagrams of at most [`max_datagram`](Socket::set_max_datagram()) bytes, each prefixed with an 8 byte fragment header, and reassembles them on the receiving side. Fragments may arrive out of order; if any are lost, the partial frame is discarded once the [reassembly timeout](Socket::set_reassembly_timeout()) has passed. Frames are reassembled per sender, so this works with multicast.
+//!
+//! As any host can send datagrams to the socket, the partial frames kept are [limited](Socket::set_reassembly_limits()) per sender, in total and in bytes, the oldest being evicted to make room for new ones; and frames that don't hold a valid value are discarded rather than panicking.
+//!
+//! The fragment header is a `u32` frame id, a `u16` fragment index and a `u16` fragment count, all little-endian.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::udp::Socket;
+//! use std::net::UdpSocket;
+//!
+//! let mut a = Socket::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+//! let mut b = Socket::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+//! let addr = b.get_ref().local_addr().unwrap();
+//! a.send_to(vec![0_u8; 10_000], addr).unwrap();
+//! let (x, from) = b.recv_from::<Vec<u8>>().unwrap();
+//! assert_eq!((x.len(), from), (10_000, a.get_ref().local_addr().unwrap()));
+//! ```
+
+use crate::{serialize_to_vec, wire};
+use std::{
+	collections::HashMap, convert::TryFrom, fmt, io, net::{SocketAddr, ToSocketAddrs, UdpSocket}, time::{Duration, Instant}
+};
+
+const HEADER_LEN: usize = 8;
+/// The default limit on partial frames kept per sender. See [`Socket::set_reassembly_limits()`].
+const PARTIAL_PER_SENDER: usize = 16;
+/// The default limit on partial frames kept in total.
+const PARTIAL: usize = 256;
+/// The default limit on bytes held by partial frames.
+const PARTIAL_BYTES: usize = 16 * 1024 * 1024;
+
+/// A UDP socket that sends and receives one frame per value, fragmenting frames across datagrams.
+pub struct Socket {
+	udp: UdpSocket,
+	max_datagram: usize,
+	reassembly_timeout: Duration,
+	next_id: u32,
+	partial: HashMap<(SocketAddr, u32), Partial>,
+	/// The bytes held by `partial`, as counted by [`Partial::bytes`].
+	partial_bytes: usize,
+	max_partial_per_sender: usize,
+	max_partial: usize,
+	max_partial_bytes: usize,
+}
+impl Socket {
+	/// Wrap `socket`, with a maximum datagram size of 1472 bytes – an Ethernet MTU less IPv4 and UDP headers – a reassembly timeout of 1 second, and up to 16 partial frames kept per sender, 256 in total, holding up to 16 MiB.
+	#[must_use]
+	pub fn new(socket: UdpSocket) -> Self {
+		Self {
+			udp: socket,
+			max_datagram: 1472,
+			reassembly_timeout: Duration::from_secs(1),
+			next_id: 0,
+			partial: HashMap::new(),
+			partial_bytes: 0,
+			max_partial_per_sender: PARTIAL_PER_SENDER,
+			max_partial: PARTIAL,
+			max_partial_bytes: PARTIAL_BYTES,
+		}
+	}
+
+	/// Set the maximum size of datagrams sent, including the fragment header.
+	///
+	/// # Panics
+	///
+	/// Will panic if `max_datagram` doesn't leave room for at least one byte after the fragment header.
+	pub fn set_max_datagram(&mut self, max_datagram: usize) {
+		assert!(
+			max_datagram > HEADER_LEN,
+			"max_datagram of {} bytes leaves no room after the {} byte fragment header",
+			max_datagram,
+			HEADER_LEN
+		);
+		self.max_datagram = max_datagram;
+	}
+
+	/// Set how long a partially received frame is kept awaiting its remaining fragments.
+	pub fn set_reassembly_timeout(&mut self, reassembly_timeout: Duration) {
+		self.reassembly_timeout = reassembly_timeout;
+	}
+
+	/// Limit the partial frames kept awaiting their remaining fragments to `per_sender` from any one sender and `total` in all, holding at most `bytes` between them. Once a limit is reached, the oldest partial frames are evicted to make room for new ones, so a host sending spurious fragments can't exhaust memory, though it can cause frames to be lost. Frames too large to fit in `bytes` can't be received.
+	///
+	/// # Panics
+	///
+	/// Will panic if `per_sender` or `total` is zero.
+	pub fn set_reassembly_limits(&mut self, per_sender: usize, total: usize, bytes: usize) {
+		assert!(
+			per_sender >= 1 && total >= 1,
+			"at least one partial frame must be kept"
+		);
+		self.max_partial_per_sender = per_sender;
+		self.max_partial = total;
+		self.max_partial_bytes = bytes;
+	}
+
+	/// Send a `T` to `addr`, as one or more datagrams.
+	///
+	/// # Errors
+	///
+	/// Returns any error resolving `addr` or sending a datagram.
+	///
+	/// # Panics
+	///
+	/// Will panic if the frame would need more than [`u16::MAX`] fragments.
+	pub fn send_to<T: serde::ser::Serialize + 'static, A: ToSocketAddrs>(
+		&mut self, t: T, addr: A,
+	) -> io::Result<()> {
+		let addr = addr
+			.to_socket_addrs()?
+			.next()
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+		let frame = serialize_to_vec(t);
+		let chunks = frame.chunks(self.max_datagram - HEADER_LEN);
+		let count = u16::try_from(chunks.len()).expect("frame too long to fragment");
+		let id = self.next_id;
+		self.next_id = self.next_id.wrapping_add(1);
+		let mut datagram = Vec::with_capacity(self.max_datagram);
+		for (index, chunk) in (0..count).zip(chunks) {
+			datagram.clear();
+			datagram.extend_from_slice(&id.to_le_bytes());
+			datagram.extend_from_slice(&index.to_le_bytes());
+			datagram.extend_from_slice(&count.to_le_bytes());
+			datagram.extend_from_slice(chunk);
+			let _ = self.udp.send_to(&datagram, addr)?;
+		}
+		Ok(())
+	}
+
+	/// Receive a `T`, blocking until all of a frame's fragments have arrived, returning it and the address it was sent from.
+	///
+	/// Datagrams too short to hold a fragment header, and reassembled frames that aren't exactly one frame holding a valid `T`, are discarded.
+	///
+	/// # Errors
+	///
+	/// Returns any error receiving a datagram, for example on a read timeout. Partially received frames are kept, so this can be retried.
+	pub fn recv_from<T: serde::de::DeserializeOwned + 'static>(
+		&mut self,
+	) -> io::Result<(T, SocketAddr)> {
+		let mut datagram = vec![0; 65536];
+		loop {
+			let (len, from) = self.udp.recv_from(&mut datagram)?;
+			let now = Instant::now();
+			let reassembly_timeout = self.reassembly_timeout;
+			let partial_bytes = &mut self.partial_bytes;
+			self.partial.retain(|_, partial| {
+				let keep = now.duration_since(partial.started) < reassembly_timeout;
+				if !keep {
+					*partial_bytes -= partial.bytes;
+				}
+				keep
+			});
+			let Some((id, index, count, chunk)) = parse_fragment(&datagram[..len]) else {
+				continue;
+			};
+			let frame = if count == 1 {
+				chunk.to_owned()
+			} else {
+				match self.reassemble((from, id), index, count, chunk, now) {
+					Some(frame) => frame,
+					None => continue,
+				}
+			};
+			if let Some(t) = decode(&frame) {
+				return Ok((t, from));
+			}
+		}
+	}
+
+	/// Add a fragment to the partial frame `key`, returning the frame once every fragment has arrived.
+	fn reassemble(
+		&mut self, key: (SocketAddr, u32), index: u16, count: u16, chunk: &[u8], now: Instant,
+	) -> Option<Vec<u8>> {
+		let (from, index, count) = (key.0, usize::from(index), usize::from(count));
+		if self
+			.partial
+			.get(&key)
+			.is_none_or(|partial| partial.fragments.len() != count)
+		{
+			// a new frame, or a stale one whose id has been reused
+			let _ = self.remove_partial(key);
+			let partial = Partial {
+				started: now,
+				fragments: vec![None; count],
+				received: 0,
+				bytes: count * size_of::<Option<Box<[u8]>>>(),
+			};
+			if !self.make_room(from, partial.bytes, None) {
+				return None;
+			}
+			self.partial_bytes += partial.bytes;
+			let _ = self.partial.insert(key, partial);
+		}
+		if self.partial[&key].fragments[index].is_some() {
+			return None;
+		}
+		if !self.make_room(from, chunk.len(), Some(key)) {
+			// too large to reassemble within the limit
+			let _ = self.remove_partial(key);
+			return None;
+		}
+		self.partial_bytes += chunk.len();
+		let partial = self.partial.get_mut(&key).unwrap();
+		partial.fragments[index] = Some(chunk.into());
+		partial.received += 1;
+		partial.bytes += chunk.len();
+		if partial.received != partial.fragments.len() {
+			return None;
+		}
+		let partial = self.remove_partial(key).unwrap();
+		Some(
+			partial
+				.fragments
+				.into_iter()
+				.flat_map(Option::unwrap)
+				.collect(),
+		)
+	}
+
+	/// Evict the oldest partial frames until there's room for `bytes` more, and, unless they're for the partial frame `keep`, for another partial frame from `from`. Returns `false` if there isn't room even once every other partial frame is evicted.
+	fn make_room(
+		&mut self, from: SocketAddr, bytes: usize, keep: Option<(SocketAddr, u32)>,
+	) -> bool {
+		let new = usize::from(keep.is_none());
+		loop {
+			let from_sender = self.partial.keys().filter(|key| key.0 == from).count();
+			let sender_full = from_sender + new > self.max_partial_per_sender;
+			if !sender_full
+				&& self.partial.len() + new <= self.max_partial
+				&& self.partial_bytes + bytes <= self.max_partial_bytes
+			{
+				return true;
+			}
+			let oldest = self
+				.partial
+				.iter()
+				.filter(|&(key, _)| Some(*key) != keep && (!sender_full || key.0 == from))
+				.min_by_key(|(_, partial)| partial.started)
+				.map(|(&key, _)| key);
+			match oldest {
+				Some(oldest) => {
+					let _ = self.remove_partial(oldest);
+				}
+				None => return false,
+			}
+		}
+	}
+
+	fn remove_partial(&mut self, key: (SocketAddr, u32)) -> Option<Partial> {
+		let partial = self.partial.remove(&key)?;
+		self.partial_bytes -= partial.bytes;
+		Some(partial)
+	}
+
+	/// A reference to the underlying socket, for setting timeouts, joining multicast groups and so on.
+	#[must_use]
+	pub fn get_ref(&self) -> &UdpSocket {
+		&self.udp
+	}
+
+	/// Consume this wrapper, returning the underlying socket. Any partially received frames are lost.
+	#[must_use]
+	pub fn into_inner(self) -> UdpSocket {
+		self.udp
+	}
+}
+impl fmt::Debug for Socket {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Socket")
+			.field("udp", &self.udp)
+			.field("max_datagram", &self.max_datagram)
+			.field("reassembly_timeout", &self.reassembly_timeout)
+			.field("partial", &self.partial.len())
+			.field("partial_bytes", &self.partial_bytes)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A frame whose fragments are still arriving.
+struct Partial {
+	started: Instant,
+	fragments: Vec<Option<Box<[u8]>>>,
+	received: usize,
+	/// The bytes held, counting the fragments received and the slots for them.
+	bytes: usize,
+}
+
+/// Split a datagram into its fragment header's id, index and count, and its chunk of the frame. [`None`] denotes that it's too short to hold a header, or that the index is out of range.
+fn parse_fragment(datagram: &[u8]) -> Option<(u32, u16, u16, &[u8])> {
+	if datagram.len() < HEADER_LEN {
+		return None;
+	}
+	let id = u32::from_le_bytes(<[u8; 4]>::try_from(&datagram[..4]).unwrap());
+	let index = u16::from_le_bytes(<[u8; 2]>::try_from(&datagram[4..6]).unwrap());
+	let count = u16::from_le_bytes(<[u8; 2]>::try_from(&datagram[6..8]).unwrap());
+	(index < count).then(|| (id, index, count, &datagram[HEADER_LEN..]))
+}
+
+/// Decode a frame as [`serialize_to_vec()`] emits it. [`None`] denotes that it's incomplete, continues beyond the value, or doesn't hold a valid `T`.
+fn decode<T: serde::de::DeserializeOwned>(frame: &[u8]) -> Option<T> {
+	// the default backend prefixes the payload with its length, and pads a zero-length payload to a single zero byte
+	#[cfg(not(feature = "fringe"))]
+	let payload = {
+		if frame.len() < 8 {
+			return None;
+		}
+		let (header, payload) = frame.split_at(8);
+		if u64::from_le_bytes(<[u8; 8]>::try_from(header).unwrap()) != payload.len() as u64 {
+			return None;
+		}
+		if payload == [0] {
+			if let Ok(t) = wire::deserialize_exact(&[]) {
+				return Some(t);
+			}
+		}
+		payload
+	};
+	#[cfg(feature = "fringe")]
+	let payload = frame;
+	wire::deserialize_exact(payload).ok()
+}
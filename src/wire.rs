@@ -0,0 +1,67 @@
+//! The encoding of each frame's payload, pinned here rather than left to whichever defaults the linked bincode has, so the bytes these pipes emit stay stable across bincode upgrades.
+//!
+//! Payloads are encoded as bincode 1 does with fixed-width integers: integers and floats little-endian at their full width, `bool`s and `Option` tags as a byte, `char`s as UTF-8, `u64` lengths before sequences, maps, strings and byte arrays, and `u32` variant indices. Every payload, whether of a frame or nested within one by a wrapper pipe, goes through the functions here; the golden bytes in the tests pin the format, so an upgrade that would change it fails them rather than silently breaking compatibility with stored frames and older peers.
+
+use crate::Deserializer;
+use bincode::Options;
+use serde::{de, ser};
+use std::io;
+
+/// The version of the wire format, bumped whenever the bytes emitted for any value change. Peers can agree on it with [`negotiate`](crate::negotiate), and stored frames can record it.
+pub const WIRE_VERSION: u32 = 1;
+
+/// The options every payload is encoded and decoded with. Each is given explicitly, so a change to bincode's defaults can't alter the format.
+pub(crate) fn options() -> impl Options + Copy {
+	bincode::DefaultOptions::new()
+		.with_little_endian()
+		.with_fixint_encoding()
+		.allow_trailing_bytes()
+		.with_no_limit()
+}
+
+pub(crate) fn serialize<T: ser::Serialize + ?Sized>(t: &T) -> bincode::Result<Vec<u8>> {
+	options().serialize(t)
+}
+
+pub(crate) fn serialize_into<W: io::Write, T: ser::Serialize + ?Sized>(
+	writer: W, t: &T,
+) -> bincode::Result<()> {
+	options().serialize_into(writer, t)
+}
+
+pub(crate) fn serialized_size<T: ser::Serialize + ?Sized>(t: &T) -> bincode::Result<u64> {
+	options().serialized_size(t)
+}
+
+pub(crate) fn deserialize<'a, T: de::Deserialize<'a>>(bytes: &'a [u8]) -> bincode::Result<T> {
+	options().deserialize(bytes)
+}
+
+/// As [`deserialize`], but failing if `bytes` continue beyond the value, for payloads whose length is authenticated and so must be exact.
+pub(crate) fn deserialize_exact<'a, T: de::Deserialize<'a>>(bytes: &'a [u8]) -> bincode::Result<T> {
+	options().reject_trailing_bytes().deserialize(bytes)
+}
+
+pub(crate) fn deserialize_from<R: io::Read, T: de::DeserializeOwned>(
+	reader: R,
+) -> bincode::Result<T> {
+	options().deserialize_from(reader)
+}
+
+/// Pull the next frame's payload undecoded, for wrapper pipes that decode it themselves, so that a malformed one is an error rather than a panic. [`None`] denotes that `deserializer` is instead awaiting a push. With the `fringe` feature, whose frames aren't delimited, it's decoded as a `T` as it arrives, and re-encoded, so a malformed one still panics.
+pub(crate) fn pull_payload<T: de::DeserializeOwned + ser::Serialize + 'static>(
+	deserializer: &mut Deserializer,
+) -> Option<Vec<u8>> {
+	if cfg!(feature = "fringe") {
+		return deserializer
+			.pull::<T>()
+			.map(|pull| serialize(&pull()).unwrap());
+	}
+	deserializer
+		.pull_with(|frame| {
+			let mut payload = Vec::new();
+			let _ = frame.read_to_end(&mut payload)?;
+			Ok(payload)
+		})
+		.map(|payload| payload.unwrap())
+}
@@ -0,0 +1,142 @@
+//! Typed messaging over [ZeroMQ](https://zeromq.org) sockets.
+//!
+//! `ZeroMQ` messages are already delimited, so rather than streaming length-prefixed frames through a socket, [`Socket`] sends each value as one message holding just the frame payload – the same bytes a [`Serializer`](crate::Serializer) emits after the header. This suits the request-reply (`REQ`/`REP`) and publish-subscribe (`PUB`/`SUB`) patterns, where per-message boundaries matter.
+//!
+//! Requires the `zmq` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_pipe::zmq::Socket;
+//! use std::thread;
+//!
+//! let context = zmq::Context::new();
+//! let rep = Socket::new(context.socket(zmq::REP).unwrap());
+//! rep.get_ref().bind("inproc://example").unwrap();
+//! let thread = thread::spawn(move || {
+//!     let x: String = rep.recv().unwrap();
+//!     rep.send(x.len()).unwrap();
+//! });
+//! let req = Socket::new(context.socket(zmq::REQ).unwrap());
+//! req.get_ref().connect("inproc://example").unwrap();
+//! req.send(String::from("hello")).unwrap();
+//! assert_eq!(req.recv::<usize>().unwrap(), 5);
+//! thread.join().unwrap();
+//! ```
+
+use crate::wire;
+use std::{any, fmt};
+
+/// A `ZeroMQ` socket that sends and receives one value per message.
+pub struct Socket {
+	socket: zmq::Socket,
+}
+impl Socket {
+	/// Wrap `socket`, which should already be, or subsequently be, bound or connected via [`get_ref`](Socket::get_ref()).
+	#[must_use]
+	pub fn new(socket: zmq::Socket) -> Self {
+		Self { socket }
+	}
+
+	/// Send a `T` as one message.
+	///
+	/// # Errors
+	///
+	/// Returns any error sending the message, for example [`zmq::Error::EFSM`] if a `REQ` socket is awaiting a reply.
+	pub fn send<T: serde::ser::Serialize>(&self, t: T) -> zmq::Result<()> {
+		self.socket.send(payload(&t), 0)
+	}
+
+	/// Send a `T` as the second part of a two-part message, after `topic`, for `SUB` sockets to filter on.
+	///
+	/// # Errors
+	///
+	/// Returns any error sending the message.
+	pub fn send_topic<T: serde::ser::Serialize>(&self, topic: &[u8], t: T) -> zmq::Result<()> {
+		self.socket.send_multipart([topic, &payload(&t)], 0)
+	}
+
+	/// Receive a `T`, blocking until a message arrives.
+	///
+	/// # Errors
+	///
+	/// Returns any error receiving the message.
+	///
+	/// # Panics
+	///
+	/// Will panic if the message isn't exactly a valid `T`.
+	pub fn recv<T: serde::de::DeserializeOwned>(&self) -> zmq::Result<T> {
+		let message = self.socket.recv_msg(0)?;
+		Ok(decode(&message))
+	}
+
+	/// Receive a `T` sent with [`send_topic`](Socket::send_topic()), blocking until a message arrives, returning its topic too.
+	///
+	/// # Errors
+	///
+	/// Returns any error receiving the message.
+	///
+	/// # Panics
+	///
+	/// Will panic if the message doesn't have two parts, or its second isn't a valid `T`.
+	pub fn recv_topic<T: serde::de::DeserializeOwned>(&self) -> zmq::Result<(Vec<u8>, T)> {
+		let mut parts = self.socket.recv_multipart(0)?;
+		assert_eq!(
+			parts.len(),
+			2,
+			"recv_topic::<{}>() received a {} part message",
+			any::type_name::<T>(),
+			parts.len()
+		);
+		let t = decode(&parts.pop().unwrap());
+		Ok((parts.pop().unwrap(), t))
+	}
+
+	/// A reference to the underlying socket, for binding, connecting, subscribing and setting options.
+	#[must_use]
+	pub fn get_ref(&self) -> &zmq::Socket {
+		&self.socket
+	}
+
+	/// Consume this wrapper, returning the underlying socket.
+	#[must_use]
+	pub fn into_inner(self) -> zmq::Socket {
+		self.socket
+	}
+}
+impl fmt::Debug for Socket {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Socket")
+			.field("type", &self.socket.get_socket_type())
+			.finish()
+	}
+}
+
+/// The frame payload of `t`, padded to one byte if empty as with [`Serializer`](crate::Serializer).
+fn payload<T: serde::ser::Serialize>(t: &T) -> Vec<u8> {
+	let mut vec = wire::serialize(t).unwrap();
+	if vec.is_empty() {
+		vec.push(0);
+	}
+	vec
+}
+
+/// Decode a frame payload, checking it's consumed entirely.
+fn decode<T: serde::de::DeserializeOwned>(payload: &[u8]) -> T {
+	let error = || {
+		format!(
+			"deserializing {} from a {} byte message",
+			any::type_name::<T>(),
+			payload.len()
+		)
+	};
+	let mut remaining = payload;
+	let ret = wire::deserialize_from(&mut remaining)
+		.unwrap_or_else(|err| panic!("{}: {err}", error()));
+	if remaining.len() == payload.len() {
+		assert_eq!(remaining, [0], "{}: expected padding", error());
+	} else {
+		assert!(remaining.is_empty(), "{}: trailing bytes", error());
+	}
+	ret
+}